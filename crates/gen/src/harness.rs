@@ -0,0 +1,154 @@
+//! Running a program through every execution path we have, and comparing
+//! the results.
+//!
+//! A program is run with:
+//!
+//! 1. The AST interpreter, as the reference result;
+//! 2. The VM, on TAC generated without optimization;
+//! 3. The VM, on TAC optimized with the default pipeline.
+//!
+//! TAC programs without a source are checked with the last two paths only.
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use azuki_syntax::{ast::Program, parse};
+use azuki_tacvm::{
+    err::{Fault, RuntimeError},
+    Vm,
+};
+
+/// The entry point of programs checked by the harness.
+pub const ENTRY: &str = "main";
+
+/// The maximum number of steps each execution path may take. Programs
+/// exceeding it are treated as not terminating.
+pub const STEP_LIMIT: u64 = 1_000_000;
+
+/// How a program fails the check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Failure {
+    /// The program does not parse.
+    Parse(String),
+    /// The program is rejected by code generation.
+    Compile(String),
+    /// The AST interpreter fails to run the program.
+    Interpret(azuki_interp::err::Error),
+    /// The VM faults when running the program.
+    Fault {
+        stage: &'static str,
+        error: RuntimeError,
+    },
+    /// Some stage panicked.
+    Panic {
+        stage: &'static str,
+        message: String,
+    },
+    /// The results of execution paths disagree.
+    Mismatch {
+        interpreted: Option<i64>,
+        unoptimized: Option<i64>,
+        optimized: Option<i64>,
+    },
+}
+
+impl Failure {
+    /// Returns whether this failure is a bug of the compiler, rather than an
+    /// invalid program.
+    pub fn is_bug(&self) -> bool {
+        match self {
+            Failure::Fault { error, .. } => error.fault != Fault::StepLimitExceeded,
+            Failure::Panic { .. } => true,
+            Failure::Mismatch { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+fn catch<T>(stage: &'static str, f: impl FnOnce() -> T) -> Result<T, Failure> {
+    catch_unwind(AssertUnwindSafe(f)).map_err(|e| {
+        let message = e
+            .downcast_ref::<&str>()
+            .map(|x| x.to_string())
+            .or_else(|| e.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        Failure::Panic { stage, message }
+    })
+}
+
+/// Print `program` into source code and check it. See [`check_source`].
+pub fn check_program(program: &Program) -> Result<Option<i64>, Failure> {
+    check_source(&program.to_string())
+}
+
+/// Run the program in `source` through every execution path. Returns the
+/// result of the program if all paths agree.
+pub fn check_source(source: &str) -> Result<Option<i64>, Failure> {
+    let program =
+        catch("parse", || parse(source))?.map_err(|e| Failure::Parse(format!("{:?}", e)))?;
+
+    let interpreted = catch("interpret", || {
+        let mut interp = azuki_interp::Interpreter::new(&program);
+        interp.set_step_limit(Some(STEP_LIMIT));
+        interp.run_func(ENTRY, vec![])
+    })?
+    .map_err(Failure::Interpret)?;
+
+    let tac = catch("tacgen", || azuki_tacgen::compile(&program))?
+        .map_err(|e| Failure::Compile(format!("{:?}", e)))?;
+    if !tac.functions.contains_key(ENTRY) {
+        return Err(Failure::Compile(format!("no function named `{}`", ENTRY)));
+    }
+
+    let (unoptimized, optimized) = run_tac(&tac)?;
+    if interpreted == unoptimized && unoptimized == optimized {
+        Ok(interpreted)
+    } else {
+        Err(Failure::Mismatch {
+            interpreted,
+            unoptimized,
+            optimized,
+        })
+    }
+}
+
+/// Run the TAC program `tac` in the VM, both before and after optimization.
+/// Returns the result of the program if both runs agree.
+pub fn check_tac(tac: &azuki_tac::Program) -> Result<Option<i64>, Failure> {
+    if !tac.functions.contains_key(ENTRY) {
+        return Err(Failure::Compile(format!("no function named `{}`", ENTRY)));
+    }
+    let (unoptimized, optimized) = run_tac(tac)?;
+    if unoptimized == optimized {
+        Ok(unoptimized)
+    } else {
+        Err(Failure::Mismatch {
+            interpreted: None,
+            unoptimized,
+            optimized,
+        })
+    }
+}
+
+/// Run `tac` in the VM without and with optimization.
+fn run_tac(tac: &azuki_tac::Program) -> Result<(Option<i64>, Option<i64>), Failure> {
+    let unoptimized = catch("vm", || run_vm(tac, "vm"))??;
+
+    let mut optimized_tac = tac.clone();
+    catch("optimize", || {
+        let mut pipeline = azuki_opt::default_pipeline();
+        let schedule = azuki_opt::OptLevel::default().schedule();
+        pipeline
+            .run(&mut optimized_tac, &schedule)
+            .expect("Default passes should exist");
+    })?;
+    let optimized = catch("vm-optimized", || run_vm(&optimized_tac, "vm-optimized"))??;
+
+    Ok((unoptimized, optimized))
+}
+
+fn run_vm(tac: &azuki_tac::Program, stage: &'static str) -> Result<Option<i64>, Failure> {
+    let mut vm = Vm::new(tac);
+    vm.set_step_limit(Some(STEP_LIMIT));
+    vm.try_run_func(ENTRY, vec![])
+        .map_err(|error| Failure::Fault { stage, error })
+}
@@ -0,0 +1,772 @@
+//! Random C0 program generator, used for fuzzing the compiler.
+//!
+//! Programs are generated from a seed, and are guaranteed to:
+//!
+//! - Pass type checking and flow analysis in `azuki_tacgen`;
+//! - Terminate: every loop has a bounded counter, and recursive calls are
+//!   guarded by a depth parameter that decreases on every call;
+//! - Never divide by zero.
+//!
+//! The entry point of generated programs is `main`, which takes no parameters
+//! and returns `int`. See [`harness`] for running generated programs.
+
+pub mod harness;
+
+use azuki_syntax::{ast::*, span::DUMMY_SPAN, util::P};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use smol_str::SmolStr;
+
+/// Limits of generated programs.
+#[derive(Debug, Clone)]
+pub struct GenConfig {
+    /// Maximum number of functions apart from `main`.
+    pub max_funcs: usize,
+    /// Maximum number of statements generated in a block.
+    pub max_stmts: usize,
+    /// Maximum nesting of blocks inside a function.
+    pub max_block_depth: usize,
+    /// Maximum depth of expression trees.
+    pub max_expr_depth: usize,
+    /// Maximum nesting of loops.
+    pub max_loop_depth: usize,
+    /// Maximum number of iterations of every loop.
+    pub max_loop_iters: u64,
+    /// Maximum depth of recursive calls.
+    pub max_recursion: u64,
+    /// Maximum number of call sites inside a function.
+    pub max_calls: usize,
+}
+
+impl Default for GenConfig {
+    fn default() -> Self {
+        GenConfig {
+            max_funcs: 4,
+            max_stmts: 6,
+            max_block_depth: 4,
+            max_expr_depth: 4,
+            max_loop_depth: 2,
+            max_loop_iters: 5,
+            max_recursion: 3,
+            max_calls: 3,
+        }
+    }
+}
+
+/// Generate a random program from `seed`.
+pub fn generate(seed: u64, config: &GenConfig) -> Program {
+    Generator::new(seed, config.clone()).program()
+}
+
+/// The depth parameter each generated function takes as its first parameter.
+const DEPTH_PARAM: &str = "d";
+
+/// Signature of a generated function. Every function takes the depth
+/// parameter, followed by `n_params` integers.
+struct FuncSig {
+    name: SmolStr,
+    n_params: usize,
+    returns_int: bool,
+}
+
+#[derive(Clone)]
+enum VarKind {
+    /// An integer that can be assigned to.
+    Mutable,
+    /// An integer that must not be assigned to, like parameters and loop
+    /// counters.
+    ReadOnly,
+    /// A constant, which may be used in initializers of other constants.
+    Const,
+    /// A variable holding the function of the given index.
+    Func(usize),
+}
+
+#[derive(Clone)]
+struct Var {
+    name: SmolStr,
+    kind: VarKind,
+}
+
+/// State of the function being generated.
+struct FuncCtx {
+    /// Index of this function, if it's not `main`.
+    idx: Option<usize>,
+    returns_int: bool,
+    scopes: Vec<Vec<Var>>,
+    block_depth: usize,
+    loop_depth: usize,
+    /// Labels of the enclosing labelled loops.
+    labels: Vec<SmolStr>,
+    calls_left: usize,
+}
+
+impl FuncCtx {
+    fn vars(&self) -> impl Iterator<Item = &Var> {
+        self.scopes.iter().flatten()
+    }
+
+    fn int_vars(&self) -> Vec<&Var> {
+        self.vars()
+            .filter(|v| !matches!(v.kind, VarKind::Func(_)))
+            .collect()
+    }
+
+    fn declare(&mut self, name: SmolStr, kind: VarKind) {
+        self.scopes.last_mut().unwrap().push(Var { name, kind });
+    }
+
+    /// Calls are not generated inside loops to keep the running time small.
+    fn can_call(&self) -> bool {
+        self.calls_left > 0 && self.loop_depth == 0
+    }
+}
+
+struct Generator {
+    rng: StdRng,
+    config: GenConfig,
+    funcs: Vec<FuncSig>,
+    name_counter: usize,
+}
+
+fn ident(name: impl Into<SmolStr>) -> Ident {
+    Ident {
+        span: DUMMY_SPAN,
+        name: name.into(),
+    }
+}
+
+fn ty(name: &str) -> TyDef {
+    TyDef {
+        span: DUMMY_SPAN,
+        name: name.into(),
+        params: None,
+        ret: None,
+    }
+}
+
+fn func_ty(n_params: usize) -> TyDef {
+    TyDef {
+        span: DUMMY_SPAN,
+        name: "fn".into(),
+        params: Some(vec![ty("int"); n_params + 1]),
+        ret: Some(P::new(ty("int"))),
+    }
+}
+
+fn int_lit(val: u64) -> Expr {
+    Expr::Literal(LiteralExpr {
+        span: DUMMY_SPAN,
+        kind: LiteralKind::Integer(val),
+    })
+}
+
+fn var_expr(name: &SmolStr) -> Expr {
+    Expr::Ident(ident(name.clone()))
+}
+
+fn binary(op: BinaryOp, lhs: Expr, rhs: Expr) -> Expr {
+    Expr::Binary(BinaryExpr {
+        span: DUMMY_SPAN,
+        op,
+        lhs: P::new(lhs),
+        rhs: P::new(rhs),
+    })
+}
+
+fn assign(name: &SmolStr, val: Expr) -> Stmt {
+    Stmt::Expr(Expr::Assign(AssignExpr {
+        span: DUMMY_SPAN,
+        allow_assign_const: false,
+        lhs: P::new(var_expr(name)),
+        rhs: P::new(val),
+    }))
+}
+
+fn call(name: &SmolStr, params: Vec<Expr>) -> Expr {
+    Expr::Call(CallExpr {
+        span: DUMMY_SPAN,
+        func: ident(name.clone()),
+        params,
+    })
+}
+
+fn decl(is_const: bool, name: &SmolStr, ty: TyDef, val: Option<Expr>) -> Stmt {
+    Stmt::Decl(DeclStmt {
+        is_const,
+        name: ident(name.clone()),
+        ty,
+        val: val.map(P::new),
+        span: DUMMY_SPAN,
+    })
+}
+
+fn block(stmts: Vec<Stmt>) -> BlockStmt {
+    BlockStmt {
+        span: DUMMY_SPAN,
+        stmts,
+    }
+}
+
+fn ret(val: Option<Expr>) -> Stmt {
+    Stmt::Return(ReturnStmt {
+        val: val.map(P::new),
+        span: DUMMY_SPAN,
+    })
+}
+
+/// Returns whether control never flows out of `stmt` normally.
+fn diverges(stmt: &Stmt) -> bool {
+    match stmt {
+        Stmt::Return(_) | Stmt::Break(_) | Stmt::Continue(_) => true,
+        Stmt::Block(b) => block_diverges(b),
+        Stmt::If(i) => if_diverges(i),
+        _ => false,
+    }
+}
+
+fn block_diverges(block: &BlockStmt) -> bool {
+    matches!(block.stmts.last(), Some(stmt) if diverges(stmt))
+}
+
+fn if_diverges(stmt: &IfStmt) -> bool {
+    let if_block = block_diverges(&stmt.if_block);
+    let else_block = match &stmt.else_block {
+        IfElseBlock::None => false,
+        IfElseBlock::If(i) => if_diverges(i),
+        IfElseBlock::Block(b) => block_diverges(b),
+    };
+    if_block && else_block
+}
+
+impl Generator {
+    fn new(seed: u64, config: GenConfig) -> Generator {
+        Generator {
+            rng: StdRng::seed_from_u64(seed),
+            config,
+            funcs: vec![],
+            name_counter: 0,
+        }
+    }
+
+    fn fresh_name(&mut self, prefix: &str) -> SmolStr {
+        self.name_counter += 1;
+        format!("{}{}", prefix, self.name_counter).into()
+    }
+
+    fn program(&mut self) -> Program {
+        let n_funcs = self.rng.gen_range(1..=self.config.max_funcs);
+        let mut funcs = vec![];
+        for idx in 0..n_funcs {
+            let sig = FuncSig {
+                name: format!("f{}", idx).into(),
+                n_params: self.rng.gen_range(0..=3),
+                // The first function always returns a value, so indirect
+                // calls always have a target
+                returns_int: idx == 0 || self.rng.gen_bool(0.8),
+            };
+            self.funcs.push(sig);
+            funcs.push(self.func(idx));
+        }
+        funcs.push(self.main());
+        Program {
+            imports: vec![],
+            decls: vec![],
+            funcs,
+        }
+    }
+
+    fn new_ctx(&self, idx: Option<usize>, returns_int: bool) -> FuncCtx {
+        FuncCtx {
+            idx,
+            returns_int,
+            scopes: vec![vec![]],
+            block_depth: 0,
+            loop_depth: 0,
+            labels: vec![],
+            calls_left: self.config.max_calls,
+        }
+    }
+
+    fn func(&mut self, idx: usize) -> FuncStmt {
+        let sig = &self.funcs[idx];
+        let mut ctx = self.new_ctx(Some(idx), sig.returns_int);
+        let ret_ty = if sig.returns_int { "int" } else { "void" };
+
+        let mut params = vec![FuncParam {
+            is_const: false,
+            name: ident(DEPTH_PARAM),
+            ty: ty("int"),
+        }];
+        ctx.declare(DEPTH_PARAM.into(), VarKind::ReadOnly);
+        for i in 0..sig.n_params {
+            let name: SmolStr = format!("p{}", i).into();
+            params.push(FuncParam {
+                is_const: false,
+                name: ident(name.clone()),
+                ty: ty("int"),
+            });
+            ctx.declare(name, VarKind::Mutable);
+        }
+
+        let mut body = self.block_body(&mut ctx);
+        if !block_diverges(&body) && ctx.returns_int {
+            let val = self.expr(&mut ctx, self.config.max_expr_depth);
+            body.stmts.push(ret(Some(val)));
+        }
+
+        // Attributes are only hints, so they don't change the result
+        let attrs = match self.rng.gen_range(0..10) {
+            0 => vec![ident("inline")],
+            1 => vec![ident("noinline")],
+            2 => vec![ident("cold")],
+            _ => vec![],
+        };
+
+        FuncStmt {
+            span: DUMMY_SPAN,
+            attrs,
+            name: ident(format!("f{}", idx)),
+            params,
+            ret_ty: ty(ret_ty),
+            body,
+        }
+    }
+
+    /// `main` calls every function with the maximum recursion depth, and
+    /// returns the sum of their results.
+    fn main(&mut self) -> FuncStmt {
+        let mut ctx = self.new_ctx(None, true);
+        let result: SmolStr = "result".into();
+        let mut stmts = vec![decl(false, &result, ty("int"), Some(int_lit(0)))];
+        ctx.declare(result.clone(), VarKind::Mutable);
+
+        for idx in 0..self.funcs.len() {
+            let params = self.call_params(&mut ctx, idx, int_lit(self.config.max_recursion));
+            let name = self.funcs[idx].name.clone();
+            let call = call(&name, params);
+            if self.funcs[idx].returns_int {
+                let sum = binary(BinaryOp::Add, var_expr(&result), call);
+                stmts.push(assign(&result, sum));
+            } else {
+                stmts.push(Stmt::Expr(call));
+            }
+        }
+        stmts.push(ret(Some(var_expr(&result))));
+
+        FuncStmt {
+            span: DUMMY_SPAN,
+            attrs: vec![],
+            name: ident("main"),
+            params: vec![],
+            ret_ty: ty("int"),
+            body: block(stmts),
+        }
+    }
+
+    /// Parameters for calling function `idx`, with `depth` as its depth.
+    fn call_params(&mut self, ctx: &mut FuncCtx, idx: usize, depth: Expr) -> Vec<Expr> {
+        let mut params = vec![depth];
+        for _ in 0..self.funcs[idx].n_params {
+            params.push(self.expr(ctx, self.config.max_expr_depth - 1));
+        }
+        params
+    }
+
+    fn block_body(&mut self, ctx: &mut FuncCtx) -> BlockStmt {
+        ctx.block_depth += 1;
+        let n_stmts = self.rng.gen_range(0..=self.config.max_stmts);
+        let mut stmts = vec![];
+        for _ in 0..n_stmts {
+            self.stmt(ctx, &mut stmts);
+            // Statements after a diverging one are unreachable
+            if matches!(stmts.last(), Some(stmt) if diverges(stmt)) {
+                break;
+            }
+        }
+        ctx.block_depth -= 1;
+        block(stmts)
+    }
+
+    fn scoped_block(&mut self, ctx: &mut FuncCtx) -> BlockStmt {
+        ctx.scopes.push(vec![]);
+        let res = self.block_body(ctx);
+        ctx.scopes.pop();
+        res
+    }
+
+    /// Generate one or more statements into `stmts`.
+    fn stmt(&mut self, ctx: &mut FuncCtx, stmts: &mut Vec<Stmt>) {
+        let can_nest = ctx.block_depth < self.config.max_block_depth;
+        loop {
+            match self.rng.gen_range(0..14) {
+                0 | 1 => {
+                    let name = self.fresh_name("v");
+                    let val = self.expr(ctx, self.config.max_expr_depth);
+                    stmts.push(decl(false, &name, ty("int"), Some(val)));
+                    ctx.declare(name, VarKind::Mutable);
+                }
+                2 => {
+                    // Declaration without initialization, assigned later
+                    let name = self.fresh_name("v");
+                    let val = self.expr(ctx, self.config.max_expr_depth);
+                    stmts.push(decl(false, &name, ty("int"), None));
+                    stmts.push(assign(&name, val));
+                    ctx.declare(name, VarKind::Mutable);
+                }
+                3 => {
+                    let name = self.fresh_name("c");
+                    let val = self.const_expr(ctx, self.config.max_expr_depth);
+                    stmts.push(decl(true, &name, ty("int"), Some(val)));
+                    ctx.declare(name, VarKind::Const);
+                }
+                4 => {
+                    let targets = self.int_funcs_before(ctx);
+                    let target = match targets.choose(&mut self.rng) {
+                        Some(&target) => target,
+                        None => continue,
+                    };
+                    let name = self.fresh_name("g");
+                    let ty = func_ty(self.funcs[target].n_params);
+                    let target_name = self.funcs[target].name.clone();
+                    stmts.push(decl(false, &name, ty, Some(var_expr(&target_name))));
+                    ctx.declare(name, VarKind::Func(target));
+                }
+                5 | 6 => {
+                    let vars = ctx
+                        .vars()
+                        .filter(|x| matches!(x.kind, VarKind::Mutable))
+                        .map(|x| x.name.clone())
+                        .collect::<Vec<_>>();
+                    let name = match vars.choose(&mut self.rng) {
+                        Some(name) => name.clone(),
+                        None => continue,
+                    };
+                    let val = self.expr(ctx, self.config.max_expr_depth);
+                    stmts.push(assign(&name, val));
+                }
+                7 => {
+                    // A call as a statement, to a function returning nothing
+                    let targets = self.funcs_before(ctx, false);
+                    let target = match targets.choose(&mut self.rng) {
+                        Some(&target) if ctx.can_call() => target,
+                        _ => continue,
+                    };
+                    ctx.calls_left -= 1;
+                    let params = self.call_params(ctx, target, var_expr(&DEPTH_PARAM.into()));
+                    let name = self.funcs[target].name.clone();
+                    stmts.push(Stmt::Expr(call(&name, params)));
+                }
+                8 if can_nest => {
+                    let stmt = self.if_stmt(ctx, 2);
+                    stmts.push(Stmt::If(stmt));
+                }
+                9 if can_nest && ctx.loop_depth < self.config.max_loop_depth => {
+                    self.while_stmt(ctx, stmts);
+                }
+                10 if can_nest => {
+                    let block = self.scoped_block(ctx);
+                    stmts.push(Stmt::Block(block));
+                }
+                11 if can_nest && ctx.idx.is_some() && ctx.can_call() => {
+                    self.recursive_call(ctx, stmts);
+                }
+                12 => {
+                    if ctx.loop_depth > 0 && self.rng.gen_bool(0.5) {
+                        let jump = JumpStmt {
+                            span: DUMMY_SPAN,
+                            label: match ctx.labels.choose(&mut self.rng) {
+                                Some(label) if self.rng.gen_bool(0.5) => Some(ident(label.clone())),
+                                _ => None,
+                            },
+                        };
+                        if self.rng.gen_bool(0.5) {
+                            stmts.push(Stmt::Break(jump));
+                        } else {
+                            stmts.push(Stmt::Continue(jump));
+                        }
+                    } else if self.rng.gen_bool(0.3) {
+                        let val = if ctx.returns_int {
+                            Some(self.expr(ctx, self.config.max_expr_depth))
+                        } else {
+                            None
+                        };
+                        stmts.push(ret(val));
+                    } else {
+                        continue;
+                    }
+                }
+                13 => stmts.push(Stmt::Empty(DUMMY_SPAN)),
+                _ => continue,
+            }
+            return;
+        }
+    }
+
+    fn if_stmt(&mut self, ctx: &mut FuncCtx, max_else_if: usize) -> IfStmt {
+        let cond = self.cond(ctx);
+        let if_block = self.scoped_block(ctx);
+        let else_block = match self.rng.gen_range(0..3) {
+            0 => IfElseBlock::None,
+            1 if max_else_if > 0 => IfElseBlock::If(P::new(self.if_stmt(ctx, max_else_if - 1))),
+            _ => IfElseBlock::Block(P::new(self.scoped_block(ctx))),
+        };
+        IfStmt {
+            span: DUMMY_SPAN,
+            cond: P::new(cond),
+            if_block: P::new(if_block),
+            else_block,
+        }
+    }
+
+    /// A loop with a counter, sometimes labelled:
+    ///
+    /// ```plain
+    /// let i: int = 0;
+    /// 'l: while i < N {
+    ///     i = i + 1;
+    ///     ...
+    /// }
+    /// ```
+    fn while_stmt(&mut self, ctx: &mut FuncCtx, stmts: &mut Vec<Stmt>) {
+        let counter = self.fresh_name("i");
+        let iters = self.rng.gen_range(0..=self.config.max_loop_iters);
+        stmts.push(decl(false, &counter, ty("int"), Some(int_lit(0))));
+        ctx.declare(counter.clone(), VarKind::ReadOnly);
+
+        let label = if self.rng.gen_bool(0.3) {
+            Some(self.fresh_name("l"))
+        } else {
+            None
+        };
+        ctx.loop_depth += 1;
+        ctx.labels.extend(label.clone());
+        let mut body = self.scoped_block(ctx);
+        if label.is_some() {
+            ctx.labels.pop();
+        }
+        ctx.loop_depth -= 1;
+        let inc = binary(BinaryOp::Add, var_expr(&counter), int_lit(1));
+        body.stmts.insert(0, assign(&counter, inc));
+
+        let cond = if self.rng.gen_bool(0.5) {
+            binary(BinaryOp::Lt, var_expr(&counter), int_lit(iters))
+        } else {
+            binary(BinaryOp::Gt, int_lit(iters), var_expr(&counter))
+        };
+        stmts.push(Stmt::While(WhileStmt {
+            span: DUMMY_SPAN,
+            label: label.map(ident),
+            cond: P::new(cond),
+            body: P::new(body),
+        }));
+    }
+
+    /// A recursive call guarded by the depth parameter:
+    ///
+    /// ```plain
+    /// if d > 0 {
+    ///     v = f(d - 1, ...);
+    /// }
+    /// ```
+    fn recursive_call(&mut self, ctx: &mut FuncCtx, stmts: &mut Vec<Stmt>) {
+        let idx = ctx.idx.unwrap();
+        ctx.calls_left -= 1;
+        let depth = binary(BinaryOp::Sub, var_expr(&DEPTH_PARAM.into()), int_lit(1));
+        let params = self.call_params(ctx, idx, depth);
+        let name = self.funcs[idx].name.clone();
+        let call = call(&name, params);
+
+        let vars = ctx
+            .vars()
+            .filter(|x| matches!(x.kind, VarKind::Mutable))
+            .map(|x| x.name.clone())
+            .collect::<Vec<_>>();
+        let stmt = match vars.choose(&mut self.rng) {
+            Some(var) if self.funcs[idx].returns_int => assign(var, call),
+            _ if self.funcs[idx].returns_int => {
+                let name = self.fresh_name("r");
+                decl(false, &name, ty("int"), Some(call))
+            }
+            _ => Stmt::Expr(call),
+        };
+
+        let cond = binary(BinaryOp::Gt, var_expr(&DEPTH_PARAM.into()), int_lit(0));
+        stmts.push(Stmt::If(IfStmt {
+            span: DUMMY_SPAN,
+            cond: P::new(cond),
+            if_block: P::new(block(vec![stmt])),
+            else_block: IfElseBlock::None,
+        }));
+    }
+
+    /// Functions that can be called from the current function without
+    /// recursion.
+    fn funcs_before(&self, ctx: &FuncCtx, returns_int: bool) -> Vec<usize> {
+        let end = ctx.idx.unwrap_or(self.funcs.len());
+        (0..end)
+            .filter(|&i| self.funcs[i].returns_int == returns_int)
+            .collect()
+    }
+
+    fn int_funcs_before(&self, ctx: &FuncCtx) -> Vec<usize> {
+        self.funcs_before(ctx, true)
+    }
+
+    fn cond(&mut self, ctx: &mut FuncCtx) -> Expr {
+        let op = *[
+            BinaryOp::Gt,
+            BinaryOp::Lt,
+            BinaryOp::Ge,
+            BinaryOp::Le,
+            BinaryOp::Eq,
+            BinaryOp::Neq,
+        ]
+        .choose(&mut self.rng)
+        .unwrap();
+        if self.rng.gen_bool(0.8) {
+            let lhs = self.expr(ctx, self.config.max_expr_depth - 1);
+            let rhs = self.expr(ctx, self.config.max_expr_depth - 1);
+            binary(op, lhs, rhs)
+        } else {
+            self.expr(ctx, self.config.max_expr_depth)
+        }
+    }
+
+    fn literal(&mut self) -> Expr {
+        let val = match self.rng.gen_range(0..10) {
+            0 => self.rng.gen_range(0..=u32::MAX as u64),
+            // Literals larger than the largest `int` are rejected
+            1 => self.rng.gen_range(0..=i64::MAX as u64),
+            _ => self.rng.gen_range(0..100),
+        };
+        int_lit(val)
+    }
+
+    /// A constant expression. Other constants are only combined with small
+    /// literals, so constant values grow slowly and never overflow.
+    fn const_expr(&mut self, ctx: &FuncCtx, depth: usize) -> Expr {
+        if depth == 0 || self.rng.gen_bool(0.3) {
+            let consts = ctx
+                .vars()
+                .filter(|x| matches!(x.kind, VarKind::Const))
+                .collect::<Vec<_>>();
+            return match consts.choose(&mut self.rng) {
+                Some(c) if self.rng.gen_bool(0.5) => var_expr(&c.name),
+                _ => int_lit(self.rng.gen_range(0..100)),
+            };
+        }
+        let op = *[BinaryOp::Add, BinaryOp::Sub, BinaryOp::Lt, BinaryOp::Eq]
+            .choose(&mut self.rng)
+            .unwrap();
+        let lhs = self.const_expr(ctx, depth - 1);
+        binary(op, lhs, int_lit(self.rng.gen_range(0..100)))
+    }
+
+    fn leaf(&mut self, ctx: &FuncCtx) -> Expr {
+        let vars = ctx.int_vars();
+        match vars.choose(&mut self.rng) {
+            Some(var) if self.rng.gen_bool(0.7) => var_expr(&var.name),
+            _ => self.literal(),
+        }
+    }
+
+    /// An expression whose value is never zero.
+    fn non_zero(&mut self, ctx: &FuncCtx) -> Expr {
+        let vars = ctx.int_vars();
+        match vars.choose(&mut self.rng) {
+            // v * v + 1 is never zero even when wrapping, because squares of
+            // odd numbers are 1 modulo 8
+            Some(var) if self.rng.gen_bool(0.5) => {
+                let v = var_expr(&var.name);
+                let square = binary(BinaryOp::Mul, v.clone(), v);
+                binary(BinaryOp::Add, square, int_lit(1))
+            }
+            _ => int_lit(self.rng.gen_range(1..20)),
+        }
+    }
+
+    fn expr(&mut self, ctx: &mut FuncCtx, depth: usize) -> Expr {
+        if depth == 0 {
+            return self.leaf(ctx);
+        }
+        match self.rng.gen_range(0..10) {
+            0 => self.literal(),
+            1 => self.leaf(ctx),
+            2 => {
+                let expr = self.expr(ctx, depth - 1);
+                Expr::Unary(UnaryExpr {
+                    span: DUMMY_SPAN,
+                    op: UnaryOp::Neg,
+                    expr: P::new(expr),
+                })
+            }
+            3 => {
+                let expr = self.expr(ctx, depth - 1);
+                Expr::As(AsExpr {
+                    span: DUMMY_SPAN,
+                    val: P::new(expr),
+                    ty: ty("int"),
+                })
+            }
+            4 => {
+                let lhs = self.expr(ctx, depth - 1);
+                let rhs = self.non_zero(ctx);
+                binary(BinaryOp::Div, lhs, rhs)
+            }
+            5 => self.call_expr(ctx, depth),
+            _ => {
+                let op = *[
+                    BinaryOp::Add,
+                    BinaryOp::Sub,
+                    BinaryOp::Mul,
+                    BinaryOp::Gt,
+                    BinaryOp::Lt,
+                    BinaryOp::Ge,
+                    BinaryOp::Le,
+                    BinaryOp::Eq,
+                    BinaryOp::Neq,
+                ]
+                .choose(&mut self.rng)
+                .unwrap();
+                let lhs = self.expr(ctx, depth - 1);
+                let rhs = self.expr(ctx, depth - 1);
+                binary(op, lhs, rhs)
+            }
+        }
+    }
+
+    /// A direct or indirect call to a function returning `int`.
+    fn call_expr(&mut self, ctx: &mut FuncCtx, depth: usize) -> Expr {
+        if !ctx.can_call() {
+            return self.leaf(ctx);
+        }
+        let func_vars = ctx
+            .vars()
+            .filter_map(|x| match x.kind {
+                VarKind::Func(target) => Some((x.name.clone(), target)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let (name, target) = if !func_vars.is_empty() && self.rng.gen_bool(0.5) {
+            func_vars.choose(&mut self.rng).unwrap().clone()
+        } else {
+            match self.int_funcs_before(ctx).choose(&mut self.rng) {
+                Some(&target) => (self.funcs[target].name.clone(), target),
+                None => return self.leaf(ctx),
+            }
+        };
+        ctx.calls_left -= 1;
+
+        let mut params = vec![var_expr(&DEPTH_PARAM.into())];
+        if ctx.idx.is_none() {
+            params[0] = int_lit(self.config.max_recursion);
+        }
+        for _ in 0..self.funcs[target].n_params {
+            params.push(self.expr(ctx, depth - 1));
+        }
+        call(&name, params)
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,124 @@
+use azuki_interp::Interpreter;
+use azuki_syntax::parse;
+use azuki_tacvm::{err::Fault, Vm};
+
+use crate::{
+    generate,
+    harness::{check_program, check_source, check_tac, Failure, ENTRY},
+    GenConfig,
+};
+
+#[test]
+fn test_generation_is_deterministic() {
+    let config = GenConfig::default();
+    for seed in 0..10 {
+        let a = generate(seed, &config).to_string();
+        let b = generate(seed, &config).to_string();
+        assert_eq!(a, b);
+    }
+}
+
+#[test]
+fn test_generated_programs_are_valid() {
+    let config = GenConfig::default();
+    for seed in 0..50 {
+        let source = generate(seed, &config).to_string();
+        let program = parse(&source).unwrap_or_else(|e| panic!("{:?}\n{}", e, source));
+        // Printing is stable after a round trip
+        assert_eq!(program.to_string(), source);
+
+        let interpreted = Interpreter::new(&program)
+            .run_func(ENTRY, vec![])
+            .unwrap_or_else(|e| panic!("{:?}\n{}", e, source));
+        let tac = azuki_tacgen::compile(&program).unwrap_or_else(|e| panic!("{:?}\n{}", e, source));
+        let vm_result = Vm::new(&tac).run_func(ENTRY, vec![]);
+        assert_eq!(interpreted, vm_result, "seed {}:\n{}", seed, source);
+    }
+}
+
+#[test]
+fn test_generated_programs_pass_the_harness() {
+    let config = GenConfig::default();
+    for seed in 0..200 {
+        let program = generate(seed, &config);
+        if let Err(failure) = check_program(&program) {
+            assert!(
+                !failure.is_bug(),
+                "seed {}: {:?}\n{}",
+                seed,
+                failure,
+                program
+            );
+        }
+    }
+}
+
+#[test]
+fn test_step_limit_is_not_a_bug() {
+    let input = r"
+    (fn main () i32
+        (bb0 ()
+            (br bb0)))
+    ";
+    let tac = azuki_tac::parser::parse_program_from_string(input).unwrap();
+    let failure = check_tac(&tac).unwrap_err();
+    assert!(
+        matches!(&failure, Failure::Fault { stage: "vm", error } if error.fault == Fault::StepLimitExceeded),
+        "{:?}",
+        failure
+    );
+    assert!(!failure.is_bug());
+}
+
+#[test]
+fn test_negation_is_not_miscompiled() {
+    // Const folding used to turn `sub 0 %0` into `%0`, and `(1 - a) + (b - 1)`
+    // into `a - b`
+    let cases = [
+        ("fn f1(d: int) -> int { return -d; }", "f1(5)", -5),
+        (
+            "fn f1(a: int, b: int) -> int { return (1 - a) + (b - 1); }",
+            "f1(1, 10)",
+            9,
+        ),
+    ];
+    for &(func, call, expected) in cases.iter() {
+        let source = format!("{}\nfn main() -> int {{ return {}; }}", func, call);
+        assert_eq!(check_source(&source), Ok(Some(expected)), "{}", source);
+    }
+}
+
+#[test]
+fn test_branch_simplification_is_not_miscompiled() {
+    // Programs reduced from failures found by `azfuzz`
+    let cases = [
+        // Phis inside a block connected into its only predecessor
+        (
+            "fn f(p: int) -> int { if 72 { p = 92; } return p; }",
+            "f(0)",
+            92,
+        ),
+        // Phis whose source block is collapsed, and then connected
+        (
+            "fn f(d: int, p: int) -> int { if p {} if 44 {} else { if 0 {} } return d; }",
+            "f(3, 0)",
+            3,
+        ),
+        // Empty blocks jumping into a phi its predecessor also jumps into
+        (
+            "fn f(p: int) -> int { p = 22; let v: int = 0; if 83 < 0 { p = v; } return p; }",
+            "f(0)",
+            22,
+        ),
+        // Empty blocks reached again after being collapsed
+        (
+            "fn f(d: int) -> int { let v: int = 11; if d {} else if v {} else {} return 58; }",
+            "f(3)",
+            58,
+        ),
+    ];
+    for &(func, call, expected) in cases.iter() {
+        let source = format!("{}\nfn main() -> int {{ return {}; }}", func, call);
+        assert_eq!(check_source(&source), Ok(Some(expected)), "{}", source);
+    }
+}
@@ -0,0 +1,154 @@
+//! Desugaring of the syntax tree into HIR.
+//!
+//! The rewrites performed are:
+//!
+//! - `while cond { body }` becomes `loop { if cond {} else { break } body }`;
+//! - `if a {} else if b {} ...` becomes `if a {} else { if b {} ... }`;
+//! - `break` and `continue` are resolved to the innermost loop, or to the
+//!   enclosing loop with the given label;
+//! - Empty statements are removed.
+
+use azuki_syntax::ast;
+
+use crate::{err::Error, Block, Func, IfStmt, LoopId, LoopStmt, Stmt};
+
+/// Desugar the function `func`.
+pub fn desugar_func(func: &ast::FuncStmt) -> Result<Func, Error> {
+    let mut desugarer = Desugarer::default();
+    Ok(Func {
+        attrs: func.attrs.clone(),
+        name: func.name.clone(),
+        params: func.params.clone(),
+        ret_ty: func.ret_ty.clone(),
+        body: desugarer.block(&func.body)?,
+        span: func.span,
+    })
+}
+
+#[derive(Default)]
+struct Desugarer {
+    /// Loops we are currently inside with their labels, innermost last.
+    loops: Vec<(LoopId, Option<ast::Ident>)>,
+    next_loop: u32,
+}
+
+impl Desugarer {
+    fn new_loop(&mut self) -> LoopId {
+        let id = LoopId(self.next_loop);
+        self.next_loop += 1;
+        id
+    }
+
+    fn block(&mut self, block: &ast::BlockStmt) -> Result<Block, Error> {
+        let mut stmts = vec![];
+        for stmt in &block.stmts {
+            if let Some(stmt) = self.stmt(stmt)? {
+                stmts.push(stmt);
+            }
+        }
+        Ok(Block {
+            stmts,
+            span: block.span,
+        })
+    }
+
+    fn stmt(&mut self, stmt: &ast::Stmt) -> Result<Option<Stmt>, Error> {
+        let stmt = match stmt {
+            ast::Stmt::Block(b) => Stmt::Block(self.block(b)?),
+            ast::Stmt::While(w) => Stmt::Loop(self.while_stmt(w)?),
+            ast::Stmt::If(i) => Stmt::If(self.if_stmt(i)?),
+            ast::Stmt::Expr(e) => Stmt::Expr(e.clone()),
+            ast::Stmt::Decl(d) => Stmt::Decl(d.clone()),
+            ast::Stmt::Return(r) => Stmt::Return(r.clone()),
+            ast::Stmt::Break(j) => Stmt::Break(
+                self.jump_target(j, Error::BreakOutsideLoop(j.span))?,
+                j.span,
+            ),
+            ast::Stmt::Continue(j) => Stmt::Continue(
+                self.jump_target(j, Error::ContinueOutsideLoop(j.span))?,
+                j.span,
+            ),
+            ast::Stmt::Empty(_) => return Ok(None),
+        };
+        Ok(Some(stmt))
+    }
+
+    /// Resolves the loop a `break` or `continue` jumps out of. `outside` is
+    /// the error to report when there is no enclosing loop at all.
+    fn jump_target(&self, stmt: &ast::JumpStmt, outside: Error) -> Result<LoopId, Error> {
+        match &stmt.label {
+            None => self.loops.last().map(|(id, _)| *id).ok_or(outside),
+            Some(label) => self
+                .find_label(&label.name)
+                .ok_or_else(|| Error::UnknownLabel {
+                    name: label.name.to_string(),
+                    span: label.span,
+                }),
+        }
+    }
+
+    fn find_label(&self, name: &str) -> Option<LoopId> {
+        self.loops
+            .iter()
+            .rev()
+            .find(|(_, label)| matches!(label, Some(l) if l.name == name))
+            .map(|(id, _)| *id)
+    }
+
+    fn while_stmt(&mut self, stmt: &ast::WhileStmt) -> Result<LoopStmt, Error> {
+        if let Some(label) = &stmt.label {
+            if self.find_label(&label.name).is_some() {
+                return Err(Error::ShadowedLabel {
+                    name: label.name.to_string(),
+                    span: label.span,
+                });
+            }
+        }
+        let id = self.new_loop();
+        self.loops.push((id, stmt.label.clone()));
+        let body = self.block(&stmt.body);
+        self.loops.pop();
+        let mut body = body?;
+
+        let cond_span = stmt.cond.span();
+        let exit = Stmt::If(IfStmt {
+            cond: (*stmt.cond).clone(),
+            then_block: Block {
+                stmts: vec![],
+                span: cond_span,
+            },
+            else_block: Some(Block {
+                stmts: vec![Stmt::Break(id, cond_span)],
+                span: cond_span,
+            }),
+            span: cond_span,
+        });
+        body.stmts.insert(0, exit);
+        Ok(LoopStmt {
+            id,
+            body,
+            span: stmt.span,
+        })
+    }
+
+    fn if_stmt(&mut self, stmt: &ast::IfStmt) -> Result<IfStmt, Error> {
+        let then_block = self.block(&stmt.if_block)?;
+        let else_block = match &stmt.else_block {
+            ast::IfElseBlock::None => None,
+            ast::IfElseBlock::If(i) => {
+                let else_if = self.if_stmt(i)?;
+                Some(Block {
+                    span: else_if.span,
+                    stmts: vec![Stmt::If(else_if)],
+                })
+            }
+            ast::IfElseBlock::Block(b) => Some(self.block(b)?),
+        };
+        Ok(IfStmt {
+            cond: (*stmt.cond).clone(),
+            then_block,
+            else_block,
+            span: stmt.span,
+        })
+    }
+}
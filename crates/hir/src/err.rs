@@ -0,0 +1,41 @@
+use std::fmt::Display;
+
+use azuki_syntax::span::Span;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    BreakOutsideLoop(Span),
+    ContinueOutsideLoop(Span),
+    /// A `break` or `continue` to a label that no enclosing loop has.
+    UnknownLabel {
+        name: String,
+        span: Span,
+    },
+    /// A loop label that is already used by an enclosing loop.
+    ShadowedLabel {
+        name: String,
+        span: Span,
+    },
+}
+
+impl Error {
+    pub fn span(&self) -> Span {
+        match self {
+            Error::BreakOutsideLoop(span) | Error::ContinueOutsideLoop(span) => *span,
+            Error::UnknownLabel { span, .. } | Error::ShadowedLabel { span, .. } => *span,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::BreakOutsideLoop(_) => write!(f, "`break` outside of a loop"),
+            Error::ContinueOutsideLoop(_) => write!(f, "`continue` outside of a loop"),
+            Error::UnknownLabel { name, .. } => write!(f, "use of undeclared label `'{}`", name),
+            Error::ShadowedLabel { name, .. } => {
+                write!(f, "label `'{}` shadows a label of an enclosing loop", name)
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! The high-level IR of C0 functions.
+//!
+//! The HIR is a desugared form of the syntax tree with a minimal set of
+//! statements: blocks, infinite loops, conditionals, `break` and `continue`
+//! out of a specific loop, declarations, expressions and returns. Every other
+//! statement (e.g. `while`, `else if`) is rewritten into these by
+//! [`desugar`], so code generation only needs to handle the core statements.
+//!
+//! Expressions, declarations and types are shared with the syntax tree.
+
+pub mod desugar;
+pub mod err;
+mod test;
+
+use azuki_syntax::{
+    ast::{DeclStmt, Expr, FuncParam, Ident, ReturnStmt, TyDef},
+    span::Span,
+};
+
+pub use desugar::desugar_func;
+
+/// Identifies a loop inside a function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LoopId(pub u32);
+
+#[derive(Debug, Clone)]
+pub struct Func {
+    pub attrs: Vec<Ident>,
+    pub name: Ident,
+    pub params: Vec<FuncParam>,
+    pub ret_ty: TyDef,
+    pub body: Block,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct Block {
+    pub stmts: Vec<Stmt>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum Stmt {
+    Block(Block),
+    Loop(LoopStmt),
+    If(IfStmt),
+    /// Leave the given loop.
+    Break(LoopId, Span),
+    /// Jump to the start of the given loop.
+    Continue(LoopId, Span),
+    Decl(DeclStmt),
+    Expr(Expr),
+    Return(ReturnStmt),
+}
+
+impl Stmt {
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Block(b) => b.span,
+            Stmt::Loop(l) => l.span,
+            Stmt::If(i) => i.span,
+            Stmt::Break(_, span) | Stmt::Continue(_, span) => *span,
+            Stmt::Decl(d) => d.span,
+            Stmt::Expr(e) => e.span(),
+            Stmt::Return(r) => r.span,
+        }
+    }
+}
+
+/// Runs `body` repeatedly, until a `break` out of this loop.
+#[derive(Debug, Clone)]
+pub struct LoopStmt {
+    pub id: LoopId,
+    pub body: Block,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct IfStmt {
+    pub cond: Expr,
+    pub then_block: Block,
+    pub else_block: Option<Block>,
+    pub span: Span,
+}
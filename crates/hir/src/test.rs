@@ -0,0 +1,140 @@
+#![cfg(test)]
+
+use azuki_syntax::parse;
+
+use crate::{desugar_func, err::Error, Func, LoopId, Stmt};
+
+fn desugar(input: &str) -> Result<Func, Error> {
+    let program = parse(input).unwrap();
+    desugar_func(&program.funcs[0])
+}
+
+#[test]
+fn test_desugar_loops() {
+    let func = desugar(
+        r"
+    fn f(n: int) -> int {
+        while n > 0 {
+            while 1 {
+                break;
+            }
+            if n == 5 {
+                continue;
+            } else if n == 6 {
+                break;
+            };
+            n = n - 1;
+        }
+        return n;
+    }
+    ",
+    )
+    .unwrap();
+    let stmts = &func.body.stmts;
+    assert_eq!(stmts.len(), 2);
+    let outer = match &stmts[0] {
+        Stmt::Loop(l) => l,
+        s => panic!("expected a loop, found {:?}", s),
+    };
+    assert_eq!(outer.id, LoopId(0));
+
+    // The exit condition, the inner loop, the `if` and the assignment, with
+    // the empty statement removed
+    let body = &outer.body.stmts;
+    assert_eq!(body.len(), 4);
+    match &body[0] {
+        Stmt::If(exit) => {
+            assert!(exit.then_block.stmts.is_empty());
+            let else_block = exit.else_block.as_ref().unwrap();
+            assert!(matches!(else_block.stmts[..], [Stmt::Break(LoopId(0), _)]));
+        }
+        s => panic!("expected the exit condition, found {:?}", s),
+    }
+    match &body[1] {
+        Stmt::Loop(inner) => {
+            assert_eq!(inner.id, LoopId(1));
+            assert!(matches!(inner.body.stmts[1], Stmt::Break(LoopId(1), _)));
+        }
+        s => panic!("expected a loop, found {:?}", s),
+    }
+    match &body[2] {
+        Stmt::If(i) => {
+            assert!(matches!(
+                i.then_block.stmts[..],
+                [Stmt::Continue(LoopId(0), _)]
+            ));
+            let else_if = match &i.else_block.as_ref().unwrap().stmts[..] {
+                [Stmt::If(else_if)] => else_if,
+                s => panic!("expected an else-if, found {:?}", s),
+            };
+            assert!(matches!(
+                else_if.then_block.stmts[..],
+                [Stmt::Break(LoopId(0), _)]
+            ));
+            assert!(else_if.else_block.is_none());
+        }
+        s => panic!("expected an if statement, found {:?}", s),
+    }
+}
+
+#[test]
+fn test_desugar_errors() {
+    let res = desugar("fn f() -> void { if 1 { break; } }");
+    assert!(matches!(res, Err(Error::BreakOutsideLoop(_))));
+    let res = desugar("fn f() -> void { { continue; } }");
+    assert!(matches!(res, Err(Error::ContinueOutsideLoop(_))));
+}
+
+#[test]
+fn test_desugar_labels() {
+    let func = desugar(
+        r"
+    fn f() -> void {
+        'outer: while 1 {
+            while 1 {
+                'inner: while 1 {
+                    break 'outer;
+                    continue;
+                    continue 'inner;
+                }
+            }
+        }
+    }
+    ",
+    )
+    .unwrap();
+    let outer = match &func.body.stmts[..] {
+        [Stmt::Loop(l)] => l,
+        s => panic!("expected a loop, found {:?}", s),
+    };
+    let middle = match &outer.body.stmts[1] {
+        Stmt::Loop(l) => l,
+        s => panic!("expected a loop, found {:?}", s),
+    };
+    let inner = match &middle.body.stmts[1] {
+        Stmt::Loop(l) => l,
+        s => panic!("expected a loop, found {:?}", s),
+    };
+    assert_eq!(inner.id, LoopId(2));
+    assert!(matches!(
+        inner.body.stmts[1..],
+        [
+            Stmt::Break(LoopId(0), _),
+            Stmt::Continue(LoopId(2), _),
+            Stmt::Continue(LoopId(2), _)
+        ]
+    ));
+}
+
+#[test]
+fn test_desugar_label_errors() {
+    let res = desugar("fn f() -> void { 'a: while 1 { break 'b; } }");
+    assert!(matches!(res, Err(Error::UnknownLabel { name, .. }) if name == "b"));
+    // Labels are only visible inside their own loop
+    let res = desugar("fn f() -> void { 'a: while 1 {} while 1 { continue 'a; } }");
+    assert!(matches!(res, Err(Error::UnknownLabel { .. })));
+    let res = desugar("fn f() -> void { 'a: while 1 { while 1 { 'a: while 1 {} } } }");
+    assert!(matches!(res, Err(Error::ShadowedLabel { name, .. }) if name == "a"));
+    // Sibling loops can reuse a label
+    assert!(desugar("fn f() -> void { 'a: while 1 {} 'a: while 1 {} }").is_ok());
+}
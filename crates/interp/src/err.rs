@@ -0,0 +1,49 @@
+use std::fmt::Display;
+
+use smol_str::SmolStr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    UnknownVar(SmolStr),
+    UnknownFunc(SmolStr),
+    UseBeforeInit(SmolStr),
+    InvalidLExpr,
+    NotCallable(SmolStr),
+    WrongParamLength {
+        expected: usize,
+        found: usize,
+    },
+    /// The value is of the wrong kind, e.g. adding a function to an integer.
+    TypeMismatch,
+    DivideByZero,
+    /// An integer literal is larger than the largest `int`.
+    LiteralOutOfRange,
+    /// The expression uses a feature not supported by the interpreter.
+    Unsupported(&'static str),
+    /// The program runs more statements than allowed.
+    StepLimitExceeded,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::UnknownVar(name) => write!(f, "cannot find `{}` in this scope", name),
+            Error::UnknownFunc(name) => write!(f, "function `{}` does not exist", name),
+            Error::UseBeforeInit(name) => {
+                write!(f, "`{}` is used before being initialized", name)
+            }
+            Error::InvalidLExpr => write!(f, "invalid left-hand side of assignment"),
+            Error::NotCallable(name) => write!(f, "`{}` is not a function", name),
+            Error::WrongParamLength { expected, found } => write!(
+                f,
+                "expected {} parameter(s), found {} parameter(s)",
+                expected, found
+            ),
+            Error::TypeMismatch => write!(f, "mismatched types"),
+            Error::DivideByZero => write!(f, "division by zero"),
+            Error::LiteralOutOfRange => write!(f, "integer literal is too large"),
+            Error::Unsupported(what) => write!(f, "{} is not supported", what),
+            Error::StepLimitExceeded => write!(f, "step limit exceeded"),
+        }
+    }
+}
@@ -0,0 +1,325 @@
+//! A tree-walking interpreter running [`ast::Program`]s directly.
+//!
+//! This interpreter is intended as a reference implementation of the
+//! language. Results of running a program in it should be identical to
+//! compiling the program into TAC and running it in the VM, so bugs in code
+//! generation and in the VM can be told apart.
+
+pub mod err;
+
+use std::{collections::HashMap, convert::TryFrom};
+
+use azuki_syntax::ast::*;
+use err::Error;
+use smol_str::SmolStr;
+
+/// A value during interpretation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    /// The result of expressions with no value, like assignments.
+    Unit,
+    Int(i64),
+    /// A reference to a function.
+    Func(SmolStr),
+}
+
+impl Value {
+    pub fn as_int(&self) -> Result<i64, Error> {
+        match self {
+            Value::Int(i) => Ok(*i),
+            _ => Err(Error::TypeMismatch),
+        }
+    }
+}
+
+/// How control leaves a statement.
+enum ControlFlow {
+    Normal,
+    /// Leave the loop with the given label, or the innermost loop.
+    Break(Option<SmolStr>),
+    /// Continue the loop with the given label, or the innermost loop.
+    Continue(Option<SmolStr>),
+    Return(Option<Value>),
+}
+
+type Scope = HashMap<SmolStr, Option<Value>>;
+
+pub struct Interpreter<'src> {
+    functions: HashMap<&'src str, &'src FuncStmt>,
+    globals: Scope,
+    /// Scopes of the function currently running, innermost last.
+    scopes: Vec<Scope>,
+    global_decls: Vec<&'src DeclStmt>,
+    globals_initialized: bool,
+    /// The maximum number of statements to execute, if any.
+    step_limit: Option<u64>,
+    steps: u64,
+}
+
+impl<'src> Interpreter<'src> {
+    pub fn new(program: &'src Program) -> Interpreter<'src> {
+        Self::with_programs(std::iter::once(program))
+    }
+
+    /// Create an interpreter running the functions of all `programs`, like
+    /// the programs are linked together.
+    pub fn with_programs(programs: impl IntoIterator<Item = &'src Program>) -> Interpreter<'src> {
+        let mut functions = HashMap::new();
+        let mut global_decls = vec![];
+        for program in programs {
+            for func in &program.funcs {
+                functions.insert(func.name.name.as_str(), func);
+            }
+            global_decls.extend(program.decls.iter());
+        }
+        Interpreter {
+            functions,
+            globals: Scope::new(),
+            scopes: vec![],
+            global_decls,
+            globals_initialized: false,
+            step_limit: None,
+            steps: 0,
+        }
+    }
+
+    /// Limit the number of statements executed by this interpreter. Exceeding
+    /// the limit fails with [`Error::StepLimitExceeded`].
+    pub fn set_step_limit(&mut self, limit: Option<u64>) {
+        self.step_limit = limit;
+    }
+
+    /// Run the function with the given name, returning its return value.
+    /// Functions returning nothing return `None`.
+    pub fn run_func(&mut self, name: &str, params: Vec<i64>) -> Result<Option<i64>, Error> {
+        if !self.globals_initialized {
+            self.globals_initialized = true;
+            for decl in self.global_decls.clone() {
+                let val = self.eval_decl_value(decl)?;
+                self.globals.insert(decl.name.name.clone(), val);
+            }
+        }
+        let params = params.into_iter().map(Value::Int).collect();
+        match self.call(name, params)? {
+            Value::Unit => Ok(None),
+            val => val.as_int().map(Some),
+        }
+    }
+
+    fn call(&mut self, name: &str, params: Vec<Value>) -> Result<Value, Error> {
+        let func = *self
+            .functions
+            .get(name)
+            .ok_or_else(|| Error::UnknownFunc(name.into()))?;
+        if func.params.len() != params.len() {
+            return Err(Error::WrongParamLength {
+                expected: func.params.len(),
+                found: params.len(),
+            });
+        }
+
+        let frame = func
+            .params
+            .iter()
+            .zip(params)
+            .map(|(param, val)| (param.name.name.clone(), Some(val)))
+            .collect();
+        let saved_scopes = std::mem::replace(&mut self.scopes, vec![frame]);
+        let res = self.exec_block(&func.body);
+        self.scopes = saved_scopes;
+
+        match res? {
+            ControlFlow::Return(Some(val)) => Ok(val),
+            _ => Ok(Value::Unit),
+        }
+    }
+
+    fn exec_block(&mut self, block: &BlockStmt) -> Result<ControlFlow, Error> {
+        self.scopes.push(Scope::new());
+        let mut res = Ok(ControlFlow::Normal);
+        for stmt in &block.stmts {
+            res = self.exec_stmt(stmt);
+            if !matches!(res, Ok(ControlFlow::Normal)) {
+                break;
+            }
+        }
+        self.scopes.pop();
+        res
+    }
+
+    fn step(&mut self) -> Result<(), Error> {
+        self.steps += 1;
+        if matches!(self.step_limit, Some(limit) if self.steps > limit) {
+            return Err(Error::StepLimitExceeded);
+        }
+        Ok(())
+    }
+
+    fn exec_stmt(&mut self, stmt: &Stmt) -> Result<ControlFlow, Error> {
+        self.step()?;
+        match stmt {
+            Stmt::Block(b) => self.exec_block(b),
+            Stmt::While(w) => {
+                while self.eval_cond(&w.cond)? {
+                    // Loops with empty bodies still count as steps
+                    self.step()?;
+                    let targets_this = |label: &Option<SmolStr>| match label {
+                        None => true,
+                        Some(label) => matches!(&w.label, Some(l) if l.name == *label),
+                    };
+                    match self.exec_block(&w.body)? {
+                        ControlFlow::Break(label) if targets_this(&label) => break,
+                        ControlFlow::Continue(label) if targets_this(&label) => {}
+                        ControlFlow::Normal => {}
+                        // Jumps out of an outer loop, and returns
+                        flow => return Ok(flow),
+                    }
+                }
+                Ok(ControlFlow::Normal)
+            }
+            Stmt::If(i) => self.exec_if(i),
+            Stmt::Expr(e) => {
+                self.eval_expr(e)?;
+                Ok(ControlFlow::Normal)
+            }
+            Stmt::Decl(d) => {
+                let val = self.eval_decl_value(d)?;
+                self.scopes
+                    .last_mut()
+                    .unwrap()
+                    .insert(d.name.name.clone(), val);
+                Ok(ControlFlow::Normal)
+            }
+            Stmt::Return(r) => {
+                let val = match &r.val {
+                    Some(val) => Some(self.eval_expr(val)?),
+                    None => None,
+                };
+                Ok(ControlFlow::Return(val))
+            }
+            Stmt::Break(j) => Ok(ControlFlow::Break(j.label.as_ref().map(|l| l.name.clone()))),
+            Stmt::Continue(j) => Ok(ControlFlow::Continue(
+                j.label.as_ref().map(|l| l.name.clone()),
+            )),
+            Stmt::Empty(_) => Ok(ControlFlow::Normal),
+        }
+    }
+
+    fn exec_if(&mut self, stmt: &IfStmt) -> Result<ControlFlow, Error> {
+        if self.eval_cond(&stmt.cond)? {
+            self.exec_block(&stmt.if_block)
+        } else {
+            match &stmt.else_block {
+                IfElseBlock::None => Ok(ControlFlow::Normal),
+                IfElseBlock::If(i) => self.exec_if(i),
+                IfElseBlock::Block(b) => self.exec_block(b),
+            }
+        }
+    }
+
+    fn eval_decl_value(&mut self, decl: &DeclStmt) -> Result<Option<Value>, Error> {
+        match &decl.val {
+            Some(val) => Ok(Some(self.eval_expr(val)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn eval_cond(&mut self, expr: &Expr) -> Result<bool, Error> {
+        Ok(self.eval_expr(expr)?.as_int()? != 0)
+    }
+
+    /// Find the slot of the variable named `name`, looking from the innermost
+    /// scope to the global scope.
+    fn find_var(&mut self, name: &str) -> Option<&mut Option<Value>> {
+        let scope = match self.scopes.iter().rposition(|s| s.contains_key(name)) {
+            Some(idx) => &mut self.scopes[idx],
+            None => &mut self.globals,
+        };
+        scope.get_mut(name)
+    }
+
+    fn eval_ident(&mut self, ident: &Ident) -> Result<Value, Error> {
+        if let Some(slot) = self.find_var(&ident.name) {
+            return slot
+                .clone()
+                .ok_or_else(|| Error::UseBeforeInit(ident.name.clone()));
+        }
+        if self.functions.contains_key(ident.name.as_str()) {
+            Ok(Value::Func(ident.name.clone()))
+        } else {
+            Err(Error::UnknownVar(ident.name.clone()))
+        }
+    }
+
+    fn eval_expr(&mut self, expr: &Expr) -> Result<Value, Error> {
+        match expr {
+            Expr::Ident(i) => self.eval_ident(i),
+            Expr::Assign(e) => {
+                let name = match &*e.lhs {
+                    Expr::Ident(i) => &i.name,
+                    _ => return Err(Error::InvalidLExpr),
+                };
+                let val = self.eval_expr(&e.rhs)?;
+                let slot = self
+                    .find_var(name)
+                    .ok_or_else(|| Error::UnknownVar(name.clone()))?;
+                *slot = Some(val);
+                Ok(Value::Unit)
+            }
+            Expr::As(e) => self.eval_expr(&e.val),
+            Expr::Literal(l) => match &l.kind {
+                LiteralKind::Integer(i) => i64::try_from(*i)
+                    .map(Value::Int)
+                    .map_err(|_| Error::LiteralOutOfRange),
+                LiteralKind::Char(c) => Ok(Value::Int(*c as i64)),
+                LiteralKind::Float(_) => Err(Error::Unsupported("float literal")),
+                LiteralKind::String(_) => Err(Error::Unsupported("string literal")),
+            },
+            Expr::Unary(e) => {
+                let val = self.eval_expr(&e.expr)?.as_int()?;
+                match e.op {
+                    UnaryOp::Neg => Ok(Value::Int(val.wrapping_neg())),
+                    UnaryOp::Pos => Ok(Value::Int(val)),
+                }
+            }
+            Expr::Binary(e) => {
+                let lhs = self.eval_expr(&e.lhs)?.as_int()?;
+                let rhs = self.eval_expr(&e.rhs)?.as_int()?;
+                let res = match e.op {
+                    BinaryOp::Add => lhs.wrapping_add(rhs),
+                    BinaryOp::Sub => lhs.wrapping_sub(rhs),
+                    BinaryOp::Mul => lhs.wrapping_mul(rhs),
+                    BinaryOp::Div => {
+                        if rhs == 0 {
+                            return Err(Error::DivideByZero);
+                        }
+                        lhs.wrapping_div(rhs)
+                    }
+                    BinaryOp::Gt => (lhs > rhs) as i64,
+                    BinaryOp::Lt => (lhs < rhs) as i64,
+                    BinaryOp::Ge => (lhs >= rhs) as i64,
+                    BinaryOp::Le => (lhs <= rhs) as i64,
+                    BinaryOp::Eq => (lhs == rhs) as i64,
+                    BinaryOp::Neq => (lhs != rhs) as i64,
+                };
+                Ok(Value::Int(res))
+            }
+            Expr::Call(e) => {
+                // Variables holding functions shadow functions of the same name
+                let name = match self.find_var(&e.func.name) {
+                    Some(_) => match self.eval_ident(&e.func)? {
+                        Value::Func(name) => name,
+                        _ => return Err(Error::NotCallable(e.func.name.clone())),
+                    },
+                    None => e.func.name.clone(),
+                };
+                let params = e
+                    .params
+                    .iter()
+                    .map(|param| self.eval_expr(param))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.call(&name, params)
+            }
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! Name resolution for lints and tools that need to know which function or
+//! variable an identifier refers to.
+
+use azuki_syntax::{ast::*, span::Span};
+use smol_str::SmolStr;
+
+/// What kind of declaration introduced a [`Binding`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindingKind {
+    Function,
+    Global,
+    Param,
+    Local,
+}
+
+/// A function or variable declared inside the program.
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub name: SmolStr,
+    /// The span of the name in its declaration.
+    pub span: Span,
+    /// The span of the whole declaration.
+    pub full_span: Span,
+    pub kind: BindingKind,
+    /// The signature of this binding, like `let x: int`.
+    pub signature: String,
+    /// Spans of every identifier referring to this binding, not including
+    /// its declaration, in source order.
+    pub references: Vec<Span>,
+    /// Whether the value of this variable is ever read.
+    pub used: bool,
+    /// The binding this binding shadows, if any.
+    pub shadows: Option<usize>,
+}
+
+/// Resolve all functions and variables inside `program`. Functions come
+/// first, followed by variables in the order they are declared.
+pub fn resolve(program: &Program) -> Vec<Binding> {
+    let mut resolver = Resolver {
+        bindings: vec![],
+        scopes: vec![vec![]],
+    };
+    // Functions are visible everywhere in the program
+    for func in &program.funcs {
+        let params = func
+            .params
+            .iter()
+            .map(|p| format!("{}: {}", p.name.name, p.ty))
+            .collect::<Vec<_>>();
+        let signature = format!(
+            "fn {}({}) -> {}",
+            func.name.name,
+            params.join(", "),
+            func.ret_ty
+        );
+        resolver.declare(&func.name, func.span, BindingKind::Function, signature);
+    }
+    for decl in &program.decls {
+        resolver.decl(decl, BindingKind::Global);
+    }
+    for func in &program.funcs {
+        resolver.scopes.push(vec![]);
+        for param in &func.params {
+            let signature = format!("{}: {}", param.name.name, param.ty);
+            let span = param.name.span + param.ty.span;
+            resolver.declare(&param.name, span, BindingKind::Param, signature);
+        }
+        resolver.block(&func.body);
+        resolver.scopes.pop();
+    }
+    resolver.bindings
+}
+
+struct Resolver {
+    bindings: Vec<Binding>,
+    /// Indices of bindings declared in each scope, innermost last.
+    scopes: Vec<Vec<usize>>,
+}
+
+impl Resolver {
+    fn lookup(&self, name: &str) -> Option<usize> {
+        self.scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev())
+            .copied()
+            .find(|&idx| self.bindings[idx].name == name)
+    }
+
+    fn declare(&mut self, name: &Ident, full_span: Span, kind: BindingKind, signature: String) {
+        // Only variables shadow each other
+        let shadows = self
+            .lookup(&name.name)
+            .filter(|&idx| self.bindings[idx].kind != BindingKind::Function);
+        let idx = self.bindings.len();
+        self.bindings.push(Binding {
+            name: name.name.clone(),
+            span: name.span,
+            full_span,
+            kind,
+            signature,
+            references: vec![],
+            used: false,
+            shadows,
+        });
+        self.scopes.last_mut().unwrap().push(idx);
+    }
+
+    /// Record a reference to `name`, returning the binding it refers to.
+    fn reference(&mut self, name: &Ident) -> Option<usize> {
+        let idx = self.lookup(&name.name)?;
+        self.bindings[idx].references.push(name.span);
+        Some(idx)
+    }
+
+    fn decl(&mut self, decl: &DeclStmt, kind: BindingKind) {
+        if let Some(val) = &decl.val {
+            self.expr(val);
+        }
+        let keyword = if decl.is_const { "const" } else { "let" };
+        let signature = format!("{} {}: {}", keyword, decl.name.name, decl.ty);
+        self.declare(&decl.name, decl.span, kind, signature);
+    }
+
+    fn block(&mut self, block: &BlockStmt) {
+        self.scopes.push(vec![]);
+        for stmt in &block.stmts {
+            self.stmt(stmt);
+        }
+        self.scopes.pop();
+    }
+
+    fn if_stmt(&mut self, stmt: &IfStmt) {
+        self.expr(&stmt.cond);
+        self.block(&stmt.if_block);
+        match &stmt.else_block {
+            IfElseBlock::None => {}
+            IfElseBlock::If(i) => self.if_stmt(i),
+            IfElseBlock::Block(b) => self.block(b),
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Block(b) => self.block(b),
+            Stmt::While(w) => {
+                self.expr(&w.cond);
+                self.block(&w.body);
+            }
+            Stmt::If(i) => self.if_stmt(i),
+            Stmt::Expr(e) => self.expr(e),
+            Stmt::Decl(d) => self.decl(d, BindingKind::Local),
+            Stmt::Return(r) => {
+                if let Some(val) = &r.val {
+                    self.expr(val);
+                }
+            }
+            Stmt::Break(_) | Stmt::Continue(_) | Stmt::Empty(_) => {}
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Ident(i) => {
+                if let Some(idx) = self.reference(i) {
+                    self.bindings[idx].used = true;
+                }
+            }
+            Expr::Assign(e) => {
+                // Writing to a variable does not count as using it.
+                match &*e.lhs {
+                    Expr::Ident(i) => {
+                        self.reference(i);
+                    }
+                    lhs => self.expr(lhs),
+                }
+                self.expr(&e.rhs);
+            }
+            Expr::As(e) => self.expr(&e.val),
+            Expr::Literal(_) => {}
+            Expr::Unary(e) => self.expr(&e.expr),
+            Expr::Binary(e) => {
+                self.expr(&e.lhs);
+                self.expr(&e.rhs);
+            }
+            Expr::Call(e) => {
+                if let Some(idx) = self.reference(&e.func) {
+                    self.bindings[idx].used = true;
+                }
+                for param in &e.params {
+                    self.expr(param);
+                }
+            }
+        }
+    }
+}
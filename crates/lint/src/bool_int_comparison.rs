@@ -0,0 +1,56 @@
+use std::borrow::Cow;
+
+use azuki_syntax::{
+    ast::*,
+    visitor::{AstInspector, AstVisitor},
+};
+
+use crate::{util::is_comparison, Lint, LintContext};
+
+/// Reports comparisons between the result of another comparison and an
+/// integer, like `a < b == 1` or `0 < a < 10`.
+pub struct BoolIntComparison;
+
+impl Lint for BoolIntComparison {
+    fn name(&self) -> Cow<'_, str> {
+        "bool-int-comparison".into()
+    }
+
+    fn check_program(&mut self, cx: &mut LintContext, program: &Program) {
+        BoolIntComparisonVisitor { cx }.visit_program(program);
+    }
+}
+
+struct BoolIntComparisonVisitor<'a, 'b> {
+    cx: &'a mut LintContext<'b>,
+}
+
+impl<'a, 'b> AstInspector for BoolIntComparisonVisitor<'a, 'b> {
+    fn inspect_expr(&mut self, expr: &Expr) {
+        if let Expr::Binary(bin) = expr {
+            if !is_comparison(bin.op) {
+                return;
+            }
+            let (lhs, rhs) = (&*bin.lhs, &*bin.rhs);
+            if (is_bool(lhs) && is_int(rhs)) || (is_int(lhs) && is_bool(rhs)) {
+                self.cx
+                    .report(bin.span, "comparison between a boolean and an integer");
+            }
+        }
+    }
+}
+
+fn is_bool(expr: &Expr) -> bool {
+    matches!(expr, Expr::Binary(b) if is_comparison(b.op))
+}
+
+/// Returns whether `expr` is known to be an integer without looking at types
+/// of variables.
+fn is_int(expr: &Expr) -> bool {
+    match expr {
+        Expr::Literal(l) => matches!(l.kind, LiteralKind::Integer(_)),
+        Expr::Unary(_) => true,
+        Expr::Binary(b) => !is_comparison(b.op),
+        _ => false,
+    }
+}
@@ -0,0 +1,52 @@
+use std::borrow::Cow;
+
+use azuki_syntax::{
+    ast::*,
+    visitor::{AstInspector, AstVisitor},
+};
+
+use crate::{Lint, LintContext};
+
+/// Reports `if` and `while` statements whose condition does not depend on
+/// any variable or function call.
+pub struct ConstantCondition;
+
+impl Lint for ConstantCondition {
+    fn name(&self) -> Cow<'_, str> {
+        "constant-condition".into()
+    }
+
+    fn check_program(&mut self, cx: &mut LintContext, program: &Program) {
+        ConstantConditionVisitor { cx }.visit_program(program);
+    }
+}
+
+struct ConstantConditionVisitor<'a, 'b> {
+    cx: &'a mut LintContext<'b>,
+}
+
+impl<'a, 'b> AstInspector for ConstantConditionVisitor<'a, 'b> {
+    fn inspect_if_stmt(&mut self, stmt: &IfStmt) {
+        if is_constant(&stmt.cond) {
+            self.cx
+                .report(stmt.cond.span(), "condition of `if` is always the same");
+        }
+    }
+
+    fn inspect_while_stmt(&mut self, stmt: &WhileStmt) {
+        if is_constant(&stmt.cond) {
+            self.cx
+                .report(stmt.cond.span(), "condition of `while` is always the same");
+        }
+    }
+}
+
+fn is_constant(expr: &Expr) -> bool {
+    match expr {
+        Expr::Literal(_) => true,
+        Expr::As(e) => is_constant(&e.val),
+        Expr::Unary(e) => is_constant(&e.expr),
+        Expr::Binary(e) => is_constant(&e.lhs) && is_constant(&e.rhs),
+        Expr::Ident(_) | Expr::Assign(_) | Expr::Call(_) => false,
+    }
+}
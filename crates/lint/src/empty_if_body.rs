@@ -0,0 +1,38 @@
+use std::borrow::Cow;
+
+use azuki_syntax::{
+    ast::*,
+    visitor::{AstInspector, AstVisitor},
+};
+
+use crate::{Lint, LintContext};
+
+/// Reports `if` statements whose body contains no statement.
+pub struct EmptyIfBody;
+
+impl Lint for EmptyIfBody {
+    fn name(&self) -> Cow<'_, str> {
+        "empty-if-body".into()
+    }
+
+    fn check_program(&mut self, cx: &mut LintContext, program: &Program) {
+        EmptyIfBodyVisitor { cx }.visit_program(program);
+    }
+}
+
+struct EmptyIfBodyVisitor<'a, 'b> {
+    cx: &'a mut LintContext<'b>,
+}
+
+impl<'a, 'b> AstInspector for EmptyIfBodyVisitor<'a, 'b> {
+    fn inspect_if_stmt(&mut self, stmt: &IfStmt) {
+        let is_empty = stmt
+            .if_block
+            .stmts
+            .iter()
+            .all(|s| matches!(s, Stmt::Empty(_)));
+        if is_empty {
+            self.cx.report(stmt.if_block.span, "body of `if` is empty");
+        }
+    }
+}
@@ -0,0 +1,173 @@
+//! Lints for C0 source programs.
+//!
+//! A lint is a check over [`ast::Program`](azuki_syntax::ast::Program) that
+//! reports suspicious, but otherwise valid code. Every lint has a name and a
+//! [`LintLevel`] that decides whether its findings are ignored, reported as
+//! warnings or reported as errors.
+//!
+//! Lints are registered into a [`LintRegistry`], similar to how passes are
+//! registered into an optimization pipeline.
+
+use std::{borrow::Cow, str::FromStr};
+
+use azuki_syntax::{ast::Program, diag::Diagnostic, span::Span};
+
+pub mod bindings;
+pub mod bool_int_comparison;
+pub mod constant_condition;
+pub mod empty_if_body;
+pub mod self_assignment;
+pub mod shadowed_variable;
+pub mod unreachable_code;
+pub mod unused;
+
+mod util;
+
+/// The level of a lint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LintLevel {
+    /// Findings of this lint are ignored.
+    Allow,
+    /// Findings of this lint are reported as warnings.
+    Warn,
+    /// Findings of this lint are reported as errors.
+    Deny,
+}
+
+impl FromStr for LintLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "allow" => Self::Allow,
+            "warn" => Self::Warn,
+            "deny" => Self::Deny,
+            _ => return Err(format!("Expected allow, warn or deny, got {}", s)),
+        })
+    }
+}
+
+/// Represents a single lint.
+pub trait Lint {
+    /// Returns the name of this lint.
+    fn name(&self) -> Cow<'_, str>;
+
+    /// Returns the level of this lint if it's not set by the user.
+    fn default_level(&self) -> LintLevel {
+        LintLevel::Warn
+    }
+
+    /// Check the given program, reporting findings into `cx`.
+    fn check_program(&mut self, cx: &mut LintContext, program: &Program);
+}
+
+/// Collects findings of a single lint.
+pub struct LintContext<'a> {
+    name: &'a str,
+    level: LintLevel,
+    diagnostics: &'a mut Vec<Diagnostic>,
+}
+
+impl<'a> LintContext<'a> {
+    /// Report a finding of the current lint at `span`.
+    pub fn report(&mut self, span: Span, message: impl Into<String>) {
+        let diag = match self.level {
+            LintLevel::Allow => return,
+            LintLevel::Warn => Diagnostic::warning(message, Some(span)),
+            LintLevel::Deny => Diagnostic::error(message, Some(span)),
+        };
+        self.diagnostics.push(diag.with_code(self.name));
+    }
+}
+
+struct RegisteredLint {
+    name: String,
+    level: LintLevel,
+    lint: Box<dyn Lint>,
+}
+
+/// A list of lints to be run over programs, with their levels.
+pub struct LintRegistry {
+    lints: Vec<RegisteredLint>,
+}
+
+impl Default for LintRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LintRegistry {
+    pub fn new() -> LintRegistry {
+        LintRegistry { lints: Vec::new() }
+    }
+
+    /// Create a registry containing all lints inside this crate.
+    pub fn with_default_lints() -> LintRegistry {
+        let mut registry = Self::new();
+        registry.add_lint(unused::UnusedVariable);
+        registry.add_lint(unused::UnusedParameter);
+        registry.add_lint(shadowed_variable::ShadowedVariable);
+        registry.add_lint(constant_condition::ConstantCondition);
+        registry.add_lint(self_assignment::SelfAssignment);
+        registry.add_lint(empty_if_body::EmptyIfBody);
+        registry.add_lint(bool_int_comparison::BoolIntComparison);
+        registry.add_lint(unreachable_code::UnreachableCode);
+        registry
+    }
+
+    pub fn add_lint<L: Lint + 'static>(&mut self, lint: L) {
+        self.add_lint_boxed(Box::new(lint));
+    }
+
+    /// Add a lint, replacing any lint with the same name.
+    pub fn add_lint_boxed(&mut self, lint: Box<dyn Lint>) {
+        let name = lint.name().into_owned();
+        let level = lint.default_level();
+        self.lints.retain(|x| x.name != name);
+        self.lints.push(RegisteredLint { name, level, lint });
+    }
+
+    pub fn list_lints(&self) -> impl Iterator<Item = &str> {
+        self.lints.iter().map(|x| x.name.as_str())
+    }
+
+    pub fn level(&self, lint: impl AsRef<str>) -> Option<LintLevel> {
+        let lint = lint.as_ref();
+        self.lints.iter().find(|x| x.name == lint).map(|x| x.level)
+    }
+
+    /// Set the level of the given lint. Returns `false` if the lint is not
+    /// found.
+    pub fn set_level(&mut self, lint: impl AsRef<str>, level: LintLevel) -> bool {
+        let lint = lint.as_ref();
+        match self.lints.iter_mut().find(|x| x.name == lint) {
+            Some(x) => {
+                x.level = level;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Run all lints that are not allowed over `program`. Returns the
+    /// diagnostics reported, in the order of lints registered.
+    pub fn check_program(&mut self, program: &Program) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for lint in &mut self.lints {
+            if lint.level == LintLevel::Allow {
+                continue;
+            }
+            let mut cx = LintContext {
+                name: &lint.name,
+                level: lint.level,
+                diagnostics: &mut diagnostics,
+            };
+            lint.lint.check_program(&mut cx, program);
+        }
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod test;
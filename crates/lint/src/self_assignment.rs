@@ -0,0 +1,38 @@
+use std::borrow::Cow;
+
+use azuki_syntax::{
+    ast::*,
+    visitor::{AstInspector, AstVisitor},
+};
+
+use crate::{Lint, LintContext};
+
+/// Reports assignments of a variable to itself, like `a = a`.
+pub struct SelfAssignment;
+
+impl Lint for SelfAssignment {
+    fn name(&self) -> Cow<'_, str> {
+        "self-assignment".into()
+    }
+
+    fn check_program(&mut self, cx: &mut LintContext, program: &Program) {
+        SelfAssignmentVisitor { cx }.visit_program(program);
+    }
+}
+
+struct SelfAssignmentVisitor<'a, 'b> {
+    cx: &'a mut LintContext<'b>,
+}
+
+impl<'a, 'b> AstInspector for SelfAssignmentVisitor<'a, 'b> {
+    fn inspect_expr(&mut self, expr: &Expr) {
+        if let Expr::Assign(assign) = expr {
+            if let (Expr::Ident(lhs), Expr::Ident(rhs)) = (&*assign.lhs, &*assign.rhs) {
+                if lhs.name == rhs.name {
+                    self.cx
+                        .report(assign.span, format!("`{}` is assigned to itself", lhs.name));
+                }
+            }
+        }
+    }
+}
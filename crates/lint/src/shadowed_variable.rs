@@ -0,0 +1,26 @@
+use std::borrow::Cow;
+
+use azuki_syntax::ast::Program;
+
+use crate::{bindings::resolve, Lint, LintContext};
+
+/// Reports variables declared with the same name as a variable in an
+/// enclosing scope.
+pub struct ShadowedVariable;
+
+impl Lint for ShadowedVariable {
+    fn name(&self) -> Cow<'_, str> {
+        "shadowed-variable".into()
+    }
+
+    fn check_program(&mut self, cx: &mut LintContext, program: &Program) {
+        for binding in resolve(program) {
+            if binding.shadows.is_some() {
+                cx.report(
+                    binding.span,
+                    format!("`{}` shadows a variable in an outer scope", binding.name),
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use azuki_syntax::parse;
+
+use crate::{
+    bindings::{resolve, BindingKind},
+    LintLevel, LintRegistry,
+};
+
+fn lint_codes(src: &str, registry: &mut LintRegistry) -> Vec<String> {
+    let program = parse(src).unwrap();
+    registry
+        .check_program(&program)
+        .into_iter()
+        .map(|x| x.code.unwrap())
+        .collect()
+}
+
+#[test]
+fn test_default_lints() {
+    let src = r"
+    fn f(a: int, _b: int, c: int) -> int {
+        let x: int = 1;
+        let y: int = a;
+        if c > 0 == 1 {
+        }
+        while 1 {
+            let a: int = 2;
+            y = y;
+            return a;
+        }
+        return y;
+        y = 0;
+    }
+    ";
+    let mut registry = LintRegistry::with_default_lints();
+    let mut codes = lint_codes(src, &mut registry);
+    codes.sort();
+    assert_eq!(
+        codes,
+        vec![
+            "bool-int-comparison",
+            "constant-condition",
+            "empty-if-body",
+            "self-assignment",
+            "shadowed-variable",
+            "unreachable-code",
+            "unused-variable",
+        ]
+    );
+}
+
+#[test]
+fn test_lint_levels() {
+    let src = r"
+    fn f(a: int) -> int {
+        return 0;
+    }
+    ";
+    let mut registry = LintRegistry::with_default_lints();
+    let program = parse(src).unwrap();
+    let diags = registry.check_program(&program);
+    assert_eq!(diags.len(), 1);
+    assert!(!diags[0].is_error());
+
+    assert!(registry.set_level("unused-parameter", LintLevel::Deny));
+    let diags = registry.check_program(&program);
+    assert!(diags[0].is_error());
+
+    assert!(registry.set_level("unused-parameter", LintLevel::Allow));
+    assert!(lint_codes(src, &mut registry).is_empty());
+    assert!(!registry.set_level("no-such-lint", LintLevel::Allow));
+}
+
+#[test]
+fn test_resolve_references() {
+    let src = r"
+    fn f(a: int) -> int {
+        let x: int = a;
+        x = f(a);
+        return a;
+    }
+    ";
+    let program = parse(src).unwrap();
+    let bindings = resolve(&program);
+    let summary = bindings
+        .iter()
+        .map(|b| (b.kind, b.signature.as_str(), b.references.len(), b.used))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        summary,
+        vec![
+            (BindingKind::Function, "fn f(a: int) -> int", 1, true),
+            (BindingKind::Param, "a: int", 3, true),
+            // Writing to `x` refers to it, but does not use it
+            (BindingKind::Local, "let x: int", 1, false),
+        ]
+    );
+    let a = &bindings[1];
+    assert!(a
+        .references
+        .iter()
+        .all(|span| &src[span.start()..span.end()] == "a"));
+}
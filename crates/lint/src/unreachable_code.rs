@@ -0,0 +1,59 @@
+use std::borrow::Cow;
+
+use azuki_syntax::{
+    ast::*,
+    visitor::{AstInspector, AstVisitor},
+};
+
+use crate::{Lint, LintContext};
+
+/// Reports statements following a `return`, `break` or `continue` in the
+/// same block.
+pub struct UnreachableCode;
+
+impl Lint for UnreachableCode {
+    fn name(&self) -> Cow<'_, str> {
+        "unreachable-code".into()
+    }
+
+    fn check_program(&mut self, cx: &mut LintContext, program: &Program) {
+        UnreachableCodeVisitor { cx }.visit_program(program);
+    }
+}
+
+struct UnreachableCodeVisitor<'a, 'b> {
+    cx: &'a mut LintContext<'b>,
+}
+
+impl<'a, 'b> AstInspector for UnreachableCodeVisitor<'a, 'b> {
+    fn inspect_block_stmt(&mut self, stmt: &BlockStmt) {
+        let unreachable = stmt
+            .stmts
+            .iter()
+            .skip_while(|s| !diverges(s))
+            .skip(1)
+            .find(|s| !matches!(s, Stmt::Empty(_)));
+        if let Some(unreachable) = unreachable {
+            self.cx.report(unreachable.span(), "unreachable statement");
+        }
+    }
+}
+
+/// Returns whether control never flows past `stmt`.
+fn diverges(stmt: &Stmt) -> bool {
+    match stmt {
+        Stmt::Return(_) | Stmt::Break(_) | Stmt::Continue(_) => true,
+        Stmt::Block(b) => b.stmts.iter().any(diverges),
+        Stmt::If(i) => if_diverges(i),
+        _ => false,
+    }
+}
+
+fn if_diverges(stmt: &IfStmt) -> bool {
+    let else_diverges = match &stmt.else_block {
+        IfElseBlock::None => false,
+        IfElseBlock::If(i) => if_diverges(i),
+        IfElseBlock::Block(b) => b.stmts.iter().any(diverges),
+    };
+    else_diverges && stmt.if_block.stmts.iter().any(diverges)
+}
@@ -0,0 +1,49 @@
+//! Lints reporting variables and parameters whose values are never read.
+//!
+//! Names starting with an underscore are never reported.
+
+use std::borrow::Cow;
+
+use azuki_syntax::ast::Program;
+
+use crate::{
+    bindings::{resolve, BindingKind},
+    Lint, LintContext,
+};
+
+fn check_unused(cx: &mut LintContext, program: &Program, kind: BindingKind, what: &str) {
+    for binding in resolve(program) {
+        if binding.kind == kind && !binding.used && !binding.name.starts_with('_') {
+            cx.report(
+                binding.span,
+                format!("{} `{}` is never used", what, binding.name),
+            );
+        }
+    }
+}
+
+/// Reports local variables that are never read.
+pub struct UnusedVariable;
+
+impl Lint for UnusedVariable {
+    fn name(&self) -> Cow<'_, str> {
+        "unused-variable".into()
+    }
+
+    fn check_program(&mut self, cx: &mut LintContext, program: &Program) {
+        check_unused(cx, program, BindingKind::Local, "variable")
+    }
+}
+
+/// Reports function parameters that are never read.
+pub struct UnusedParameter;
+
+impl Lint for UnusedParameter {
+    fn name(&self) -> Cow<'_, str> {
+        "unused-parameter".into()
+    }
+
+    fn check_program(&mut self, cx: &mut LintContext, program: &Program) {
+        check_unused(cx, program, BindingKind::Param, "parameter")
+    }
+}
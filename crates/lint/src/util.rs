@@ -0,0 +1,10 @@
+use azuki_syntax::ast::*;
+
+/// Returns whether the given binary operator is a comparison, i.e. evaluates
+/// to a boolean value.
+pub fn is_comparison(op: BinaryOp) -> bool {
+    matches!(
+        op,
+        BinaryOp::Gt | BinaryOp::Lt | BinaryOp::Ge | BinaryOp::Le | BinaryOp::Eq | BinaryOp::Neq
+    )
+}
@@ -0,0 +1,81 @@
+//! Analysis of a single source file, computed every time the file changes.
+
+use azuki_lint::{
+    bindings::{resolve, Binding},
+    LintRegistry,
+};
+use azuki_syntax::{
+    ast::*,
+    diag::Diagnostic,
+    parse,
+    span::Span,
+    visitor::{AstInspector, AstVisitor},
+};
+
+/// Everything the language server knows about a file.
+#[derive(Debug, Default)]
+pub struct Analysis {
+    pub diagnostics: Vec<Diagnostic>,
+    /// The program, if the file parses.
+    pub program: Option<Program>,
+    /// Functions and variables declared inside the file.
+    pub symbols: Vec<Binding>,
+    /// Spans of type names.
+    pub types: Vec<Span>,
+}
+
+impl Analysis {
+    pub fn new(source: &str) -> Analysis {
+        let program = match parse(source) {
+            Ok(p) => p,
+            Err(e) => {
+                return Analysis {
+                    diagnostics: vec![e.into()],
+                    ..Default::default()
+                }
+            }
+        };
+
+        let mut diagnostics = LintRegistry::with_default_lints().check_program(&program);
+        // Imported functions are not known here, so they are reported as
+        // unknown variables; skip type checking for such files.
+        if program.imports.is_empty() {
+            if let Err(e) = azuki_tacgen::compile(&program) {
+                diagnostics.push(e.into());
+            }
+        }
+
+        let mut types = TypeCollector(vec![]);
+        types.visit_program(&program);
+        Analysis {
+            diagnostics,
+            symbols: resolve(&program),
+            program: Some(program),
+            types: types.0,
+        }
+    }
+
+    /// Returns the symbol referred to at byte offset `offset`. A cursor right
+    /// after an identifier is also counted as pointing to it.
+    pub fn symbol_at(&self, offset: usize) -> Option<usize> {
+        (0..self.symbols.len()).find(|&symbol| {
+            self.references_of(symbol)
+                .any(|span| span.start() <= offset && offset <= span.end())
+        })
+    }
+
+    /// Returns the spans of every reference to `symbol`, starting with its
+    /// declaration.
+    pub fn references_of(&self, symbol: usize) -> impl Iterator<Item = Span> + '_ {
+        let symbol = &self.symbols[symbol];
+        std::iter::once(symbol.span).chain(symbol.references.iter().copied())
+    }
+}
+
+struct TypeCollector(Vec<Span>);
+
+impl AstInspector for TypeCollector {
+    fn inspect_ty(&mut self, ty: &TyDef) {
+        self.0.push(ty.span);
+    }
+}
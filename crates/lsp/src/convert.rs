@@ -0,0 +1,40 @@
+//! Conversion between byte offsets used by spans, and LSP positions counted
+//! in lines and UTF-16 code units.
+
+use azuki_syntax::span::Span;
+use lsp_types::{Position, Range};
+
+/// Returns the position of byte offset `offset` inside `text`.
+pub fn position(text: &str, offset: usize) -> Position {
+    let offset = offset.min(text.len());
+    let before = &text[..offset];
+    let line = before.matches('\n').count();
+    let line_start = before.rfind('\n').map_or(0, |x| x + 1);
+    let character = text[line_start..offset].encode_utf16().count();
+    Position::new(line as u32, character as u32)
+}
+
+/// Returns the byte offset of `pos` inside `text`. Positions past the end of
+/// a line are clamped to the end of that line.
+pub fn offset(text: &str, pos: Position) -> usize {
+    let mut line_start = 0;
+    for _ in 0..pos.line {
+        match text[line_start..].find('\n') {
+            Some(idx) => line_start += idx + 1,
+            None => return text.len(),
+        }
+    }
+    let line = text[line_start..].split('\n').next().unwrap_or("");
+    let mut units = 0;
+    for (idx, c) in line.char_indices() {
+        if units >= pos.character as usize {
+            return line_start + idx;
+        }
+        units += c.len_utf16();
+    }
+    line_start + line.len()
+}
+
+pub fn range(text: &str, span: Span) -> Range {
+    Range::new(position(text, span.start()), position(text, span.end()))
+}
@@ -0,0 +1,331 @@
+//! A language server for C0, speaking the Language Server Protocol over any
+//! [`Connection`], usually stdin and stdout.
+//!
+//! Documents are fully re-analyzed on every change; C0 source files are small
+//! enough for that. The server supports:
+//!
+//! - Diagnostics from the parser, the lints and the type checker;
+//! - Hover, showing the signature of variables and functions;
+//! - Go to definition and find references;
+//! - Document symbols of functions;
+//! - Semantic tokens.
+
+pub mod analysis;
+pub mod convert;
+pub mod semantic;
+mod test;
+
+use std::{collections::HashMap, error::Error};
+
+use analysis::Analysis;
+use azuki_lint::bindings::BindingKind;
+use azuki_syntax::diag::{self, Level};
+use lsp_server::{Connection, Message, Notification, Request, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+        Notification as LspNotification, PublishDiagnostics,
+    },
+    request::{
+        DocumentSymbolRequest, GotoDefinition, HoverRequest, References, Request as LspRequest,
+        SemanticTokensFullRequest,
+    },
+    DiagnosticSeverity, DocumentSymbol, DocumentSymbolResponse, GotoDefinitionResponse, Hover,
+    HoverContents, HoverProviderCapability, Location, MarkupContent, MarkupKind, NumberOrString,
+    OneOf, PublishDiagnosticsParams, SemanticTokens, SemanticTokensFullOptions,
+    SemanticTokensOptions, SemanticTokensResult, SemanticTokensServerCapabilities,
+    ServerCapabilities, TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+};
+use serde::de::DeserializeOwned;
+
+pub type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+pub fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        references_provider: Some(OneOf::Left(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        semantic_tokens_provider: Some(SemanticTokensServerCapabilities::SemanticTokensOptions(
+            SemanticTokensOptions {
+                legend: semantic::legend(),
+                full: Some(SemanticTokensFullOptions::Bool(true)),
+                ..Default::default()
+            },
+        )),
+        ..Default::default()
+    }
+}
+
+/// Initialize the connection and serve requests until the client shuts the
+/// server down.
+pub fn run(connection: &Connection) -> Result<()> {
+    connection.initialize(serde_json::to_value(capabilities())?)?;
+    let mut server = Server {
+        connection,
+        documents: HashMap::new(),
+    };
+    for msg in &connection.receiver {
+        match msg {
+            Message::Request(req) => {
+                if connection.handle_shutdown(&req)? {
+                    return Ok(());
+                }
+                server.handle_request(req)?;
+            }
+            Message::Notification(not) => server.handle_notification(not)?,
+            Message::Response(_) => {}
+        }
+    }
+    Ok(())
+}
+
+struct Document {
+    text: String,
+    analysis: Analysis,
+}
+
+struct Server<'a> {
+    connection: &'a Connection,
+    documents: HashMap<Url, Document>,
+}
+
+impl<'a> Server<'a> {
+    fn handle_request(&mut self, req: Request) -> Result<()> {
+        let id = req.id.clone();
+        let res = self.response(req).unwrap_or_else(|e| {
+            Response::new_err(
+                id,
+                lsp_server::ErrorCode::InvalidParams as i32,
+                e.to_string(),
+            )
+        });
+        self.connection.sender.send(res.into())?;
+        Ok(())
+    }
+
+    /// Returns the response to `req`, or an error if its params are malformed.
+    fn response(&mut self, req: Request) -> serde_json::Result<Response> {
+        let id = req.id.clone();
+        let res = match req.method.as_str() {
+            HoverRequest::METHOD => {
+                let params = extract::<HoverRequest>(req)?;
+                let pos = params.text_document_position_params;
+                let res = self.hover(&pos.text_document.uri, pos.position);
+                Response::new_ok(id, res)
+            }
+            GotoDefinition::METHOD => {
+                let params = extract::<GotoDefinition>(req)?;
+                let pos = params.text_document_position_params;
+                let res = self
+                    .definition(&pos.text_document.uri, pos.position)
+                    .map(GotoDefinitionResponse::Scalar);
+                Response::new_ok(id, res)
+            }
+            References::METHOD => {
+                let params = extract::<References>(req)?;
+                let pos = params.text_document_position;
+                let res = self.references(
+                    &pos.text_document.uri,
+                    pos.position,
+                    params.context.include_declaration,
+                );
+                Response::new_ok(id, res)
+            }
+            DocumentSymbolRequest::METHOD => {
+                let params = extract::<DocumentSymbolRequest>(req)?;
+                let res = self
+                    .document_symbols(&params.text_document.uri)
+                    .map(DocumentSymbolResponse::Nested);
+                Response::new_ok(id, res)
+            }
+            SemanticTokensFullRequest::METHOD => {
+                let params = extract::<SemanticTokensFullRequest>(req)?;
+                let res = self.documents.get(&params.text_document.uri).map(|doc| {
+                    SemanticTokensResult::Tokens(SemanticTokens {
+                        result_id: None,
+                        data: semantic::tokens(&doc.text, &doc.analysis),
+                    })
+                });
+                Response::new_ok(id, res)
+            }
+            _ => Response::new_err(
+                id,
+                lsp_server::ErrorCode::MethodNotFound as i32,
+                format!("Unknown method {}", req.method),
+            ),
+        };
+        Ok(res)
+    }
+
+    fn handle_notification(&mut self, not: Notification) -> Result<()> {
+        // There is nobody to report to, so notifications with malformed params
+        // are ignored.
+        match not.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params = match extract_notification::<DidOpenTextDocument>(not) {
+                    Some(params) => params,
+                    None => return Ok(()),
+                };
+                let doc = params.text_document;
+                self.update(doc.uri, doc.text, Some(doc.version))
+            }
+            DidChangeTextDocument::METHOD => {
+                let params = match extract_notification::<DidChangeTextDocument>(not) {
+                    Some(params) => params,
+                    None => return Ok(()),
+                };
+                // Only full document sync is supported
+                match params.content_changes.into_iter().last() {
+                    Some(change) => self.update(
+                        params.text_document.uri,
+                        change.text,
+                        Some(params.text_document.version),
+                    ),
+                    None => Ok(()),
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let params = match extract_notification::<DidCloseTextDocument>(not) {
+                    Some(params) => params,
+                    None => return Ok(()),
+                };
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                self.publish_diagnostics(uri, vec![], None)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Re-analyze the document and publish its diagnostics.
+    fn update(&mut self, uri: Url, text: String, version: Option<i32>) -> Result<()> {
+        let analysis = Analysis::new(&text);
+        let diagnostics = analysis
+            .diagnostics
+            .iter()
+            .map(|d| to_lsp_diagnostic(&text, d))
+            .collect();
+        self.documents
+            .insert(uri.clone(), Document { text, analysis });
+        self.publish_diagnostics(uri, diagnostics, version)
+    }
+
+    fn publish_diagnostics(
+        &self,
+        uri: Url,
+        diagnostics: Vec<lsp_types::Diagnostic>,
+        version: Option<i32>,
+    ) -> Result<()> {
+        let params = PublishDiagnosticsParams {
+            uri,
+            diagnostics,
+            version,
+        };
+        let not = Notification::new(PublishDiagnostics::METHOD.into(), params);
+        self.connection.sender.send(not.into())?;
+        Ok(())
+    }
+
+    /// Returns the document and the symbol at `pos` inside it.
+    fn symbol_at(&self, uri: &Url, pos: lsp_types::Position) -> Option<(&Document, usize)> {
+        let doc = self.documents.get(uri)?;
+        let symbol = doc.analysis.symbol_at(convert::offset(&doc.text, pos))?;
+        Some((doc, symbol))
+    }
+
+    fn hover(&self, uri: &Url, pos: lsp_types::Position) -> Option<Hover> {
+        let (doc, symbol) = self.symbol_at(uri, pos)?;
+        let symbol = &doc.analysis.symbols[symbol];
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!("```c0\n{}\n```", symbol.signature),
+            }),
+            range: None,
+        })
+    }
+
+    fn definition(&self, uri: &Url, pos: lsp_types::Position) -> Option<Location> {
+        let (doc, symbol) = self.symbol_at(uri, pos)?;
+        let span = doc.analysis.symbols[symbol].span;
+        Some(Location::new(uri.clone(), convert::range(&doc.text, span)))
+    }
+
+    fn references(
+        &self,
+        uri: &Url,
+        pos: lsp_types::Position,
+        include_declaration: bool,
+    ) -> Option<Vec<Location>> {
+        let (doc, symbol) = self.symbol_at(uri, pos)?;
+        let decl = doc.analysis.symbols[symbol].span;
+        let locations = doc
+            .analysis
+            .references_of(symbol)
+            .filter(|&span| include_declaration || span != decl)
+            .map(|span| Location::new(uri.clone(), convert::range(&doc.text, span)))
+            .collect();
+        Some(locations)
+    }
+
+    fn document_symbols(&self, uri: &Url) -> Option<Vec<DocumentSymbol>> {
+        let doc = self.documents.get(uri)?;
+        let symbols = doc
+            .analysis
+            .symbols
+            .iter()
+            .filter(|symbol| symbol.kind == BindingKind::Function)
+            .map(|symbol| {
+                #[allow(deprecated)]
+                DocumentSymbol {
+                    name: symbol.name.to_string(),
+                    detail: Some(symbol.signature.clone()),
+                    kind: lsp_types::SymbolKind::FUNCTION,
+                    tags: None,
+                    deprecated: None,
+                    range: convert::range(&doc.text, symbol.full_span),
+                    selection_range: convert::range(&doc.text, symbol.span),
+                    children: None,
+                }
+            })
+            .collect();
+        Some(symbols)
+    }
+}
+
+fn extract<R>(req: Request) -> serde_json::Result<R::Params>
+where
+    R: LspRequest,
+    R::Params: DeserializeOwned,
+{
+    serde_json::from_value(req.params)
+}
+
+fn extract_notification<N>(not: Notification) -> Option<N::Params>
+where
+    N: LspNotification,
+    N::Params: DeserializeOwned,
+{
+    serde_json::from_value(not.params).ok()
+}
+
+fn to_lsp_diagnostic(text: &str, diag: &diag::Diagnostic) -> lsp_types::Diagnostic {
+    let range = diag
+        .span
+        .map(|span| convert::range(text, span))
+        .unwrap_or_default();
+    let severity = match diag.level {
+        Level::Error => DiagnosticSeverity::ERROR,
+        Level::Warning => DiagnosticSeverity::WARNING,
+    };
+    lsp_types::Diagnostic {
+        range,
+        severity: Some(severity),
+        code: diag.code.clone().map(NumberOrString::String),
+        source: Some("azuki".into()),
+        message: diag.message.clone(),
+        ..Default::default()
+    }
+}
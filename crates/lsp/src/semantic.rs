@@ -0,0 +1,113 @@
+//! Semantic tokens, computed from the tokens of the lexer and refined with
+//! name resolution.
+
+use azuki_lint::bindings::BindingKind;
+use azuki_syntax::{lexer::spanned_lexer, Token};
+use lsp_types::{SemanticToken, SemanticTokenType, SemanticTokensLegend};
+
+use crate::{analysis::Analysis, convert};
+
+/// Token types reported by the server. Indices of this array are used in
+/// encoded tokens.
+const TOKEN_TYPES: &[SemanticTokenType] = &[
+    SemanticTokenType::KEYWORD,
+    SemanticTokenType::FUNCTION,
+    SemanticTokenType::PARAMETER,
+    SemanticTokenType::VARIABLE,
+    SemanticTokenType::TYPE,
+    SemanticTokenType::NUMBER,
+    SemanticTokenType::STRING,
+    SemanticTokenType::OPERATOR,
+];
+
+pub fn legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: TOKEN_TYPES.to_vec(),
+        token_modifiers: vec![],
+    }
+}
+
+fn token_type(ty: SemanticTokenType) -> u32 {
+    TOKEN_TYPES.iter().position(|x| *x == ty).unwrap() as u32
+}
+
+/// Returns semantic tokens of `text`, encoded relative to each other as
+/// specified by LSP.
+pub fn tokens(text: &str, analysis: &Analysis) -> Vec<SemanticToken> {
+    let mut res = vec![];
+    let mut last_line = 0;
+    let mut last_start = 0;
+    for (token, span) in spanned_lexer(text) {
+        let ty = match token {
+            Token::FnKw
+            | Token::LetKw
+            | Token::ConstKw
+            | Token::AsKw
+            | Token::WhileKw
+            | Token::IfKw
+            | Token::ElseKw
+            | Token::ReturnKw
+            | Token::BreakKw
+            | Token::ContinueKw
+            | Token::ImportKw => SemanticTokenType::KEYWORD,
+            Token::UIntLiteral(_) | Token::FloatLiteral(_) => SemanticTokenType::NUMBER,
+            Token::CharLiteral(_) | Token::StringLiteral(_) => SemanticTokenType::STRING,
+            Token::Plus
+            | Token::Minus
+            | Token::Mul
+            | Token::Div
+            | Token::Assign
+            | Token::Eq
+            | Token::Neq
+            | Token::Lt
+            | Token::Gt
+            | Token::Le
+            | Token::Ge
+            | Token::Arrow => SemanticTokenType::OPERATOR,
+            Token::Ident(_) => {
+                let symbol = (0..analysis.symbols.len())
+                    .find(|&symbol| analysis.references_of(symbol).any(|s| s == span))
+                    .map(|symbol| analysis.symbols[symbol].kind);
+                match symbol {
+                    Some(BindingKind::Function) => SemanticTokenType::FUNCTION,
+                    Some(BindingKind::Param) => SemanticTokenType::PARAMETER,
+                    Some(BindingKind::Global) | Some(BindingKind::Local) => {
+                        SemanticTokenType::VARIABLE
+                    }
+                    None if analysis
+                        .types
+                        .iter()
+                        .any(|t| t.start() <= span.start() && span.end() <= t.end()) =>
+                    {
+                        SemanticTokenType::TYPE
+                    }
+                    None => continue,
+                }
+            }
+            _ => continue,
+        };
+
+        let start = convert::position(text, span.start());
+        let end = convert::position(text, span.end());
+        // Tokens spanning multiple lines are not supported by most clients
+        if start.line != end.line {
+            continue;
+        }
+        let delta_line = start.line - last_line;
+        let delta_start = if delta_line == 0 {
+            start.character - last_start
+        } else {
+            start.character
+        };
+        res.push(SemanticToken {
+            delta_line,
+            delta_start,
+            length: end.character - start.character,
+            token_type: token_type(ty),
+            token_modifiers_bitset: 0,
+        });
+        last_line = start.line;
+        last_start = start.character;
+    }
+    res
+}
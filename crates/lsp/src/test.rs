@@ -0,0 +1,210 @@
+#![cfg(test)]
+
+use std::thread::JoinHandle;
+
+use lsp_server::{Connection, Message, Notification, Request, RequestId, Response};
+use serde_json::{json, Value};
+
+/// A scripted client talking to a server running in another thread.
+struct Client {
+    connection: Connection,
+    server: Option<JoinHandle<()>>,
+    next_id: i32,
+}
+
+impl Client {
+    fn start() -> Client {
+        let (server, connection) = Connection::memory();
+        let server = std::thread::spawn(move || crate::run(&server).unwrap());
+        let mut client = Client {
+            connection,
+            server: Some(server),
+            next_id: 0,
+        };
+        client.request("initialize", json!({ "capabilities": {} }));
+        client.notify("initialized", json!({}));
+        client
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        let res = self.raw_request(method, params);
+        assert!(res.error.is_none(), "{:?}", res.error);
+        res.result.unwrap_or(Value::Null)
+    }
+
+    fn raw_request(&mut self, method: &str, params: Value) -> Response {
+        self.next_id += 1;
+        let id = RequestId::from(self.next_id);
+        let req = Request::new(id.clone(), method.into(), params);
+        self.connection.sender.send(req.into()).unwrap();
+        loop {
+            match self.connection.receiver.recv().unwrap() {
+                Message::Response(res) if res.id == id => return res,
+                _ => {}
+            }
+        }
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        let not = Notification::new(method.into(), params);
+        self.connection.sender.send(not.into()).unwrap();
+    }
+
+    /// Wait for the next `publishDiagnostics` notification.
+    fn diagnostics(&mut self) -> Value {
+        loop {
+            if let Message::Notification(not) = self.connection.receiver.recv().unwrap() {
+                if not.method == "textDocument/publishDiagnostics" {
+                    return not.params;
+                }
+            }
+        }
+    }
+
+    fn open(&mut self, text: &str) -> Value {
+        self.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": {
+                    "uri": URI,
+                    "languageId": "c0",
+                    "version": 1,
+                    "text": text,
+                }
+            }),
+        );
+        self.diagnostics()
+    }
+
+    fn at(&mut self, method: &str, line: u32, character: u32) -> Value {
+        self.request(
+            method,
+            json!({
+                "textDocument": { "uri": URI },
+                "position": { "line": line, "character": character },
+                "context": { "includeDeclaration": true },
+            }),
+        )
+    }
+
+    fn shutdown(mut self) {
+        self.request("shutdown", Value::Null);
+        self.notify("exit", Value::Null);
+        self.server.take().unwrap().join().unwrap();
+    }
+}
+
+const URI: &str = "file:///test.c0";
+
+const SOURCE: &str = r"fn add(a: int, b: int) -> int {
+    return a + b;
+}
+fn main() -> int {
+    let x: int = add(1, 2);
+    return add(x, x);
+}
+";
+
+#[test]
+fn test_diagnostics() {
+    let mut client = Client::start();
+    let diags = client.open("fn main() -> int {\n    let x: int = y;\n    return 0;\n}\n");
+    let diags = diags["diagnostics"].as_array().unwrap();
+    // `x` is unused, `y` is unknown
+    assert_eq!(diags.len(), 2);
+    assert_eq!(diags[1]["severity"], 1);
+    assert_eq!(
+        diags[1]["range"],
+        json!({ "start": { "line": 1, "character": 17 }, "end": { "line": 1, "character": 18 } })
+    );
+
+    client.notify(
+        "textDocument/didChange",
+        json!({
+            "textDocument": { "uri": URI, "version": 2 },
+            "contentChanges": [{ "text": SOURCE }],
+        }),
+    );
+    let diags = client.diagnostics();
+    assert_eq!(diags["version"], 2);
+    assert_eq!(diags["diagnostics"], json!([]));
+    client.shutdown();
+}
+
+#[test]
+fn test_navigation() {
+    let mut client = Client::start();
+    client.open(SOURCE);
+
+    // `add` in `let x: int = add(1, 2);`
+    let hover = client.at("textDocument/hover", 4, 18);
+    assert_eq!(
+        hover["contents"]["value"],
+        "```c0\nfn add(a: int, b: int) -> int\n```"
+    );
+    let hover = client.at("textDocument/hover", 5, 15);
+    assert_eq!(hover["contents"]["value"], "```c0\nlet x: int\n```");
+
+    let def = client.at("textDocument/definition", 1, 11);
+    assert_eq!(
+        def["range"],
+        json!({ "start": { "line": 0, "character": 7 }, "end": { "line": 0, "character": 8 } })
+    );
+
+    let refs = client.at("textDocument/references", 4, 8);
+    let lines = refs
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["range"]["start"]["line"].as_u64().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(lines, vec![4, 5, 5]);
+
+    let symbols = client.request(
+        "textDocument/documentSymbol",
+        json!({ "textDocument": { "uri": URI } }),
+    );
+    let names = symbols
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| s["name"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["add", "main"]);
+
+    let tokens = client.request(
+        "textDocument/semanticTokens/full",
+        json!({ "textDocument": { "uri": URI } }),
+    );
+    // `fn` `add` `(` `a`: keyword, function, then parameter `a` 4 columns later
+    let data = tokens["data"].as_array().unwrap();
+    assert_eq!(
+        &data[..15],
+        &json!([0, 0, 2, 0, 0, 0, 3, 3, 1, 0, 0, 4, 1, 2, 0])
+            .as_array()
+            .unwrap()[..]
+    );
+    client.shutdown();
+}
+
+#[test]
+fn test_malformed_params() {
+    let mut client = Client::start();
+    client.open(SOURCE);
+
+    let res = client.raw_request(
+        "textDocument/hover",
+        json!({ "textDocument": { "uri": URI }, "position": "here" }),
+    );
+    assert_eq!(
+        res.error.unwrap().code,
+        lsp_server::ErrorCode::InvalidParams as i32
+    );
+    // A malformed notification is ignored, too
+    client.notify("textDocument/didChange", json!({ "textDocument": 1 }));
+
+    // The server keeps serving requests
+    let hover = client.at("textDocument/hover", 5, 15);
+    assert_eq!(hover["contents"]["value"], "```c0\nlet x: int\n```");
+    client.shutdown();
+}
@@ -32,7 +32,10 @@ use std::{
 use crate::util::graphs::cfg;
 use azuki_tac::{optimizer::FunctionOptimizer, BBId, Branch, InstId, InstKind, TacFunc, Value};
 use multimap::MultiMap;
-use petgraph::EdgeDirection::{Incoming, Outgoing};
+use petgraph::{
+    graphmap::DiGraphMap,
+    EdgeDirection::{Incoming, Outgoing},
+};
 use tracing::trace;
 
 /// Performs branching simplify. See [module documents](crate::branching_simplify).
@@ -51,14 +54,16 @@ impl FunctionOptimizer for BranchingSimplify {
         &mut self,
         env: &mut azuki_tac::optimizer::OptimizeEnvironment,
         func: &mut azuki_tac::TacFunc,
-    ) {
+    ) -> bool {
         if func.first_block.is_none() {
-            return;
+            return false;
         }
 
         let mut phis = MultiMap::new();
         let mut cfg = cfg(func);
         let mut vis = HashSet::new();
+        let mut detached = HashSet::new();
+        let mut changed = false;
 
         // Collect all phi sources
         for (i, _, _) in func.all_inst_unordered() {
@@ -74,6 +79,9 @@ impl FunctionOptimizer for BranchingSimplify {
         pending.push_back(func.first_block.unwrap());
 
         while let Some(bb_id) = pending.pop_front() {
+            if detached.contains(&bb_id) {
+                continue;
+            }
             let bb = func.bb_get(bb_id);
             match &bb.branch {
                 // Same branch simplification
@@ -83,6 +91,7 @@ impl FunctionOptimizer for BranchingSimplify {
                     trace!("brif _ {} {} ==>> br {}", if_true, if_false, if_true);
                     func.bb_get_mut(bb_id).branch = Branch::Jump(*if_true);
                     pending.push_back(bb_id);
+                    changed = true;
                 }
 
                 // Condition simplification
@@ -91,55 +100,77 @@ impl FunctionOptimizer for BranchingSimplify {
                     if_true,
                     if_false,
                 } => {
-                    if x == 0 {
-                        trace!("brif x {} {} ==>> br {}", if_true, if_false, if_false);
-
-                        func.bb_get_mut(bb_id).branch = Branch::Jump(if_false);
-                        cfg.remove_edge(bb_id, if_true);
+                    let (taken, dropped) = if x == 0 {
+                        (if_false, if_true)
                     } else {
-                        trace!("brif x {} {} ==>> br {}", if_true, if_false, if_true);
+                        (if_true, if_false)
+                    };
+                    trace!("brif x {} {} ==>> br {}", if_true, if_false, taken);
 
-                        func.bb_get_mut(bb_id).branch = Branch::Jump(if_true);
-                        cfg.remove_edge(bb_id, if_false);
-                    }
+                    func.bb_get_mut(bb_id).branch = Branch::Jump(taken);
+                    cfg.remove_edge(bb_id, dropped);
+                    remove_phi_source(func, dropped, bb_id);
                     pending.push_back(bb_id);
+                    changed = true;
                 }
 
                 // Connect bbs
-                &Branch::Jump(next) if cfg.neighbors_directed(next, Incoming).count() == 1 => {
+                &Branch::Jump(next)
+                    if next != bb_id && cfg.neighbors_directed(next, Incoming).count() == 1 =>
+                {
                     trace!("connect: {} <<== {}", bb_id, next);
 
+                    // `next` is only entered from `bb_id`, so its phis are
+                    // plain assignments after connecting
+                    resolve_phis(func, next, bb_id);
+
+                    // Instructions are moved with their source locations
                     func.bb_connect(bb_id, next);
                     func.bb_detach(next);
                     pending.push_back(bb_id);
+                    changed = true;
 
-                    replace_phis(&phis, func, next, bb_id);
+                    replace_phis(&mut phis, func, next, bb_id);
 
                     cfg.remove_edge(bb_id, next);
                     let next_neighbors = cfg.neighbors_directed(next, Outgoing).collect::<Vec<_>>();
                     for n in next_neighbors {
-                        cfg.remove_edge(next, n);
                         cfg.add_edge(bb_id, n, ());
                     }
+                    cfg.remove_node(next);
+                    detached.insert(next);
                 }
 
                 // Collapse empty jump
-                &Branch::Jump(next) if bb.is_empty() => {
+                &Branch::Jump(next)
+                    if bb.is_empty()
+                        && next != bb_id
+                        && Some(bb_id) != func.first_block
+                        && !phis_conflict(&phis, &cfg, bb_id, next) =>
+                {
                     let pred = cfg.neighbors_directed(bb_id, Incoming).collect::<Vec<_>>();
-                    cfg.remove_edge(bb_id, next);
+                    cfg.remove_node(bb_id);
                     for p in pred.iter().cloned() {
                         trace!("replace jump: {}::{} ==>> {}", p, bb_id, next);
                         func.bb_get_mut(p).branch.replace_target(bb_id, next);
                         cfg.add_edge(p, next, ());
                         pending.push_back(p);
                     }
-                    for &id in phis.get_vec(&bb_id).into_iter().flatten() {
+                    for id in phis.remove(&bb_id).into_iter().flatten() {
                         if let InstKind::Phi(phi) = &mut func.inst_get_mut(id).kind {
-                            let source = phi.remove(&bb_id).unwrap();
-                            phi.extend(pred.iter().map(|&x| (x, source)));
+                            // The source may be removed along with its edge
+                            if let Some(source) = phi.remove(&bb_id) {
+                                phi.extend(pred.iter().map(|&x| (x, source)));
+                            }
+                        }
+                        for &p in &pred {
+                            phis.insert(p, id);
                         }
                     }
                     func.bb_detach(bb_id);
+                    detached.insert(bb_id);
+                    changed = true;
+                    continue;
                 }
 
                 br if bb.is_empty() => {
@@ -156,11 +187,57 @@ impl FunctionOptimizer for BranchingSimplify {
                 }
             }
         }
+        changed
     }
 }
 
-fn replace_phis(phis: &MultiMap<BBId, InstId>, func: &mut TacFunc, replace: BBId, with: BBId) {
-    for &id in phis.get_vec(&replace).into_iter().flatten() {
+fn replace_phis(phis: &mut MultiMap<BBId, InstId>, func: &mut TacFunc, replace: BBId, with: BBId) {
+    for id in phis.remove(&replace).into_iter().flatten() {
         func.inst_get_mut(id).kind.replace_phi_source(replace, with);
+        phis.insert(with, id);
+    }
+}
+
+/// Whether jumping from predecessors of `bb` directly into `next` would make
+/// phis inside `next` choose between two sources from the same predecessor.
+fn phis_conflict(
+    phis: &MultiMap<BBId, InstId>,
+    cfg: &DiGraphMap<BBId, ()>,
+    bb: BBId,
+    next: BBId,
+) -> bool {
+    phis.contains_key(&bb)
+        && cfg
+            .neighbors_directed(bb, Incoming)
+            .any(|p| cfg.contains_edge(p, next))
+}
+
+/// Remove `pred` from the sources of phis inside `bb`.
+fn remove_phi_source(func: &mut TacFunc, bb: BBId, pred: BBId) {
+    let phis = func
+        .inst_of_bb_iter(bb)
+        .filter(|(_, inst)| matches!(inst.kind, InstKind::Phi(_)))
+        .map(|(id, _)| id)
+        .collect::<Vec<_>>();
+    for id in phis {
+        if let InstKind::Phi(phi) = &mut func.inst_get_mut(id).kind {
+            phi.remove(&pred);
+        }
+    }
+}
+
+/// Replace phis inside `bb` with their sources from `pred`.
+fn resolve_phis(func: &mut TacFunc, bb: BBId, pred: BBId) {
+    let phis = func
+        .inst_of_bb_iter(bb)
+        .filter_map(|(id, inst)| match &inst.kind {
+            InstKind::Phi(phi) => Some((id, phi.get(&pred).copied())),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    for (id, source) in phis {
+        if let Some(source) = source {
+            func.inst_get_mut(id).kind = InstKind::Assign(Value::Dest(source));
+        }
     }
 }
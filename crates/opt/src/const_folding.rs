@@ -5,8 +5,8 @@
 //! This pass calculates constant instructions whenever possible.
 
 use azuki_tac::{
-    builder::FuncEditor, optimizer::FunctionOptimizer, BinaryInst, BinaryOp, Inst, InstId,
-    InstKind, TacFunc, Value,
+    analysis::PreservedAnalyses, builder::FuncEditor, optimizer::FunctionOptimizer, BinaryInst,
+    BinaryOp, Inst, InstId, InstKind, TacFunc, Value,
 };
 use smallvec::SmallVec;
 use tracing::{debug, debug_span, trace};
@@ -34,20 +34,26 @@ impl FunctionOptimizer for ConstFolding {
         true
     }
 
+    /// Only instructions are changed, and branches are kept as is.
+    fn preserved_analyses(&self) -> PreservedAnalyses {
+        PreservedAnalyses::cfg()
+    }
+
     fn optimize_func(
         &mut self,
         _env: &mut azuki_tac::optimizer::OptimizeEnvironment,
         func: &mut azuki_tac::TacFunc,
-    ) {
+    ) -> bool {
         let _span = debug_span!("const_folding", %func.name).entered();
 
         if func.first_block.is_none() {
             debug!("Empty function");
-            return;
+            return false;
         }
         debug!("Parsing function");
 
         // In most cases, applying constant folding for one time is enough.
+        let mut changed = false;
         let mut cursor = FuncEditor::new(func);
         cursor.set_current_bb(cursor.func.first_block.unwrap());
         // yeah i know, do-while pattern
@@ -66,6 +72,10 @@ impl FunctionOptimizer for ConstFolding {
                                 match v {
                                     Some((mut i, n)) => {
                                         if let Some(n) = n {
+                                            // The split instruction comes from
+                                            // the same source code
+                                            let loc = cursor.current_tac().unwrap().loc;
+                                            cursor.set_loc(loc);
                                             let idx = cursor
                                                 .insert_before_current_place(Inst { kind: n, ty });
                                             cursor.move_forward();
@@ -81,6 +91,7 @@ impl FunctionOptimizer for ConstFolding {
                     InstKind::Assign(t) => Some(InstKind::Assign(eval_val(*t, &cursor.func))),
                     _ => None,
                 };
+                let replaced = replaced.filter(|r| *r != cursor.current_inst().unwrap().kind);
                 if let Some(r) = replaced {
                     trace!(
                         "replaced %{} with {:?}",
@@ -88,6 +99,7 @@ impl FunctionOptimizer for ConstFolding {
                         r
                     );
                     cursor.current_inst_mut().unwrap().kind = r;
+                    changed = true;
                 }
             }
             let next = cursor.current_bb().next;
@@ -99,6 +111,7 @@ impl FunctionOptimizer for ConstFolding {
                 None => false,
             }
         } {}
+        changed
     }
 }
 
@@ -238,8 +251,8 @@ fn eval_binary_deep(binary: &BinaryInst, f: &TacFunc) -> Option<(InstKind, Optio
 
             let op = variables[0]
                 .0
-                .then(|| BinaryOp::Add)
-                .unwrap_or(BinaryOp::Sub);
+                .then(|| BinaryOp::Sub)
+                .unwrap_or(BinaryOp::Add);
             let first_inst = InstKind::Binary(BinaryInst {
                 op,
                 lhs: Value::Dest(pos_term),
@@ -253,6 +266,10 @@ fn eval_binary_deep(binary: &BinaryInst, f: &TacFunc) -> Option<(InstKind, Optio
     } else if variables.len() == 2 {
         // "(a + b) + constant" type
         if constant == 0 && variables.iter().any(|x| !x.0) {
+            // The positive term goes first
+            if variables[0].0 {
+                variables.swap(0, 1);
+            }
             let op = ((variables[0].0) ^ (variables[1].0))
                 .then(|| BinaryOp::Sub)
                 .unwrap_or(BinaryOp::Add);
@@ -291,7 +308,7 @@ fn eval_binary_deep(binary: &BinaryInst, f: &TacFunc) -> Option<(InstKind, Optio
     } else if variables.len() == 1 {
         // "a + constant" type
 
-        if constant == 0 && variables[0].0 {
+        if constant == 0 && !variables[0].0 {
             Some((InstKind::Assign(Value::Dest(variables[0].1)), None))
         } else {
             let op = variables[0]
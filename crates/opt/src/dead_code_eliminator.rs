@@ -1,10 +1,7 @@
-use std::collections::{HashSet, VecDeque};
+use std::collections::HashSet;
 
-use crate::util::graphs::{cfg, dfg};
-use azuki_tac::{builder::FuncEditor, optimizer::FunctionOptimizer, Branch, InstId, Value};
-use petgraph::{algo::dominators, graphmap::DiGraphMap, visit};
+use azuki_tac::{analysis::PreservedAnalyses, builder::FuncEditor, optimizer::FunctionOptimizer};
 use tracing::{debug, debug_span, trace};
-use visit::Walker;
 
 pub struct DeadCodeEliminator;
 
@@ -29,45 +26,33 @@ impl FunctionOptimizer for DeadCodeEliminator {
         &mut self,
         _env: &mut azuki_tac::optimizer::OptimizeEnvironment,
         func: &mut azuki_tac::TacFunc,
-    ) {
-        let mut graph = DiGraphMap::new();
-        let mut find_roots = VecDeque::new();
+    ) -> bool {
         let _span = debug_span!("dead-code-eliminator", %func.name).entered();
 
-        debug!("Constructing reference map");
-        // Construct instruction reference map
-        for (idx, _, inst) in func.all_inst_unordered() {
-            for source in inst.kind.param_op_iter() {
-                graph.add_edge(idx, source, ());
-            }
-        }
-        for (_, bb) in func.all_bb_unordered() {
-            if let Branch::Return(Some(Value::Dest(idx))) = &bb.branch {
-                find_roots.push_back(*idx);
-            } else if let Branch::CondJump {
-                cond: Value::Dest(x),
-                ..
-            } = &bb.branch
-            {
-                // TODO: Add condition to find root only if it contributes to return value
-                find_roots.push_back(*x);
-            }
-        }
+        // Values used by branches and instructions with side effects are the
+        // roots
+        // TODO: Add condition to find root only if it contributes to return value
+        let mut worklist = func
+            .all_bb_unordered()
+            .filter_map(|(_, bb)| bb.branch.param_op())
+            .collect::<Vec<_>>();
+        worklist.extend(
+            func.all_inst_unordered()
+                .filter(|(_, _, inst)| inst.kind.has_side_effects())
+                .map(|(idx, _, _)| idx),
+        );
 
         debug!("Finding reachable variables");
-        // calculate spanning tree
         let mut retained = HashSet::new();
-        let mut dfs = petgraph::visit::Dfs::empty(&graph);
-        while let Some(root) = find_roots.pop_front() {
-            dfs.move_to(root);
-            retained.insert(root);
-            for point in (&mut dfs).iter(&graph) {
-                retained.insert(point);
+        while let Some(inst) = worklist.pop() {
+            if func.inst_exists(inst) && retained.insert(inst) {
+                worklist.extend(func.inst_get(inst).kind.param_op_iter());
             }
         }
 
         // Remove unused instruction.
 
+        let mut changed = false;
         let mut editor = FuncEditor::new(func);
         let bbs = editor
             .func
@@ -81,11 +66,13 @@ impl FunctionOptimizer for DeadCodeEliminator {
                 if !retained.contains(&editor.current_idx().unwrap()) {
                     trace!("removed %{}", editor.current_idx().unwrap().slot(),);
                     has_next = editor.remove_current().0;
+                    changed = true;
                 } else {
                     has_next = editor.move_forward();
                 }
             }
         }
+        changed
     }
 
     fn reset(&mut self) {}
@@ -93,4 +80,9 @@ impl FunctionOptimizer for DeadCodeEliminator {
     fn edits_program(&self) -> bool {
         true
     }
+
+    /// Only instructions are changed, and branches are kept as is.
+    fn preserved_analyses(&self) -> PreservedAnalyses {
+        PreservedAnalyses::cfg()
+    }
 }
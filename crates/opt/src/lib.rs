@@ -4,3 +4,76 @@ pub mod dead_code_eliminator;
 pub mod loop_unroll;
 
 pub mod util;
+
+use std::str::FromStr;
+
+use azuki_tac::optimizer::{sanity_checker::SanityChecker, schedule::Schedule, Pipeline};
+
+/// Named sets of optimizations, selected by `-O` in the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptLevel {
+    /// No optimization.
+    O0,
+    /// Run every optimization once.
+    O1,
+    /// Run optimizations until the program no longer changes.
+    O2,
+    /// Like `O2`, but without optimizations making the program larger, e.g.
+    /// `loop-unroll`.
+    Os,
+}
+
+impl OptLevel {
+    /// The passes to run at this level, as a [`Schedule`] description.
+    pub fn passes(self) -> &'static str {
+        match self {
+            OptLevel::O0 => "",
+            OptLevel::O1 => "sanity-check,const-folding,branching-simplify,dead-code-eliminator",
+            OptLevel::O2 => {
+                "sanity-check,fixpoint(const-folding,branching-simplify,dead-code-eliminator)"
+            }
+            // Size-growing passes (`loop-unroll`) are left out here
+            OptLevel::Os => {
+                "sanity-check,fixpoint(const-folding,branching-simplify,dead-code-eliminator)"
+            }
+        }
+    }
+
+    pub fn schedule(self) -> Schedule {
+        self.passes()
+            .parse()
+            .expect("Preset schedules should be valid")
+    }
+}
+
+/// The optimizations to run when the user doesn't specify any.
+impl Default for OptLevel {
+    fn default() -> Self {
+        OptLevel::O2
+    }
+}
+
+impl FromStr for OptLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "0" => OptLevel::O0,
+            "1" => OptLevel::O1,
+            "2" => OptLevel::O2,
+            "s" => OptLevel::Os,
+            _ => return Err(format!("Expected 0, 1, 2, s, got {}", s)),
+        })
+    }
+}
+
+/// Create a pipeline with every optimization inside this crate registered.
+pub fn default_pipeline() -> Pipeline {
+    let mut pipeline = Pipeline::new();
+    pipeline.add_func_optimizer(SanityChecker::default());
+    pipeline.add_func_optimizer(dead_code_eliminator::DeadCodeEliminator);
+    pipeline.add_func_optimizer(branching_simplify::BranchingSimplify);
+    pipeline.add_func_optimizer(const_folding::ConstFolding::new());
+    pipeline.add_func_optimizer(loop_unroll::LoopUnroll {});
+    pipeline
+}
@@ -1,6 +1,6 @@
 //! Graphs
 
-use azuki_tac::{BBId, InstId, TacFunc};
+use azuki_tac::{BBId, TacFunc};
 use petgraph::graphmap::DiGraphMap;
 
 /// Generate control flow graph of the given function.
@@ -15,17 +15,3 @@ pub fn cfg(f: &TacFunc) -> DiGraphMap<BBId, ()> {
 
     bb_graph
 }
-
-/// Generate data flow graph of the given function.
-pub fn dfg(f: &TacFunc) -> DiGraphMap<InstId, ()> {
-    let mut graph = DiGraphMap::new();
-
-    // Construct instruction reference map
-    for (idx, _bb, inst) in f.all_inst_unordered() {
-        for source in inst.kind.param_op_iter() {
-            graph.add_edge(idx, source, ());
-        }
-    }
-
-    graph
-}
@@ -0,0 +1,327 @@
+//! Reduction passes of C0 programs.
+//!
+//! Passes work on the syntax tree directly, so every variant they produce can
+//! be printed back into source code.
+
+use azuki_syntax::{ast::*, span::DUMMY_SPAN, util::P};
+
+use crate::{Pass, Reduce};
+
+impl Reduce for Program {
+    /// The number of nodes in the syntax tree. Identifiers count as two nodes,
+    /// so replacing a variable with a literal is a reduction.
+    fn size(&self) -> usize {
+        self.imports.len()
+            + self.decls.iter().map(decl_size).sum::<usize>()
+            + self
+                .funcs
+                .iter()
+                .map(|func| 1 + func.params.len() + block_size(&func.body))
+                .sum::<usize>()
+    }
+
+    fn passes() -> Vec<(&'static str, Pass<Self>)> {
+        vec![
+            ("remove-items", remove_item),
+            ("remove-stmts-8", |p, i| remove_stmts(p, i, 8)),
+            ("remove-stmts-4", |p, i| remove_stmts(p, i, 4)),
+            ("remove-stmts-2", |p, i| remove_stmts(p, i, 2)),
+            ("remove-stmts-1", |p, i| remove_stmts(p, i, 1)),
+            ("unwrap-stmts", unwrap_stmt),
+            ("inline-consts", inline_const),
+            ("simplify-exprs", simplify_expr),
+        ]
+    }
+}
+
+fn decl_size(decl: &DeclStmt) -> usize {
+    1 + decl.val.as_deref().map_or(0, expr_size)
+}
+
+fn block_size(block: &BlockStmt) -> usize {
+    1 + block.stmts.iter().map(stmt_size).sum::<usize>()
+}
+
+fn stmt_size(stmt: &Stmt) -> usize {
+    match stmt {
+        Stmt::Block(b) => block_size(b),
+        Stmt::While(w) => 1 + expr_size(&w.cond) + block_size(&w.body),
+        Stmt::If(i) => if_size(i),
+        Stmt::Expr(e) => expr_size(e),
+        Stmt::Decl(d) => decl_size(d),
+        Stmt::Return(r) => 1 + r.val.as_deref().map_or(0, expr_size),
+        Stmt::Break(_) | Stmt::Continue(_) | Stmt::Empty(_) => 1,
+    }
+}
+
+fn if_size(stmt: &IfStmt) -> usize {
+    let else_size = match &stmt.else_block {
+        IfElseBlock::None => 0,
+        IfElseBlock::If(i) => if_size(i),
+        IfElseBlock::Block(b) => block_size(b),
+    };
+    1 + expr_size(&stmt.cond) + block_size(&stmt.if_block) + else_size
+}
+
+fn expr_size(expr: &Expr) -> usize {
+    match expr {
+        Expr::Ident(_) => 2,
+        Expr::Literal(_) => 1,
+        Expr::Assign(e) => 1 + expr_size(&e.lhs) + expr_size(&e.rhs),
+        Expr::As(e) => 1 + expr_size(&e.val),
+        Expr::Unary(e) => 1 + expr_size(&e.expr),
+        Expr::Binary(e) => 1 + expr_size(&e.lhs) + expr_size(&e.rhs),
+        Expr::Call(e) => 2 + e.params.iter().map(expr_size).sum::<usize>(),
+    }
+}
+
+/// Remove the `index`-th import, global declaration or function.
+fn remove_item(program: &Program, index: usize) -> Option<Program> {
+    let mut program = program.clone();
+    let mut index = index;
+    if index < program.imports.len() {
+        program.imports.remove(index);
+        return Some(program);
+    }
+    index -= program.imports.len();
+    if index < program.decls.len() {
+        program.decls.remove(index);
+        return Some(program);
+    }
+    index -= program.decls.len();
+    if index < program.funcs.len() {
+        program.funcs.remove(index);
+        return Some(program);
+    }
+    None
+}
+
+/// Remove the `index`-th chunk of `chunk` consecutive statements.
+fn remove_stmts(program: &Program, index: usize, chunk: usize) -> Option<Program> {
+    let mut program = program.clone();
+    let mut index = index;
+    let found = blocks_mut(&mut program, &mut |block| {
+        let len = block.stmts.len();
+        // Smaller blocks are handled with smaller chunks
+        if chunk > 1 && len <= chunk / 2 {
+            return false;
+        }
+        let chunks = len.div_ceil(chunk);
+        if index < chunks {
+            let start = index * chunk;
+            block.stmts.drain(start..len.min(start + chunk));
+            true
+        } else {
+            index -= chunks;
+            false
+        }
+    });
+    found.then_some(program)
+}
+
+/// Replace the `index`-th compound statement variant with a simpler one, e.g.
+/// an `if` statement with its body.
+fn unwrap_stmt(program: &Program, index: usize) -> Option<Program> {
+    let mut program = program.clone();
+    let mut index = index;
+    let found = blocks_mut(&mut program, &mut |block| {
+        for i in 0..block.stmts.len() {
+            let mut unwraps = stmt_unwraps(&block.stmts[i]);
+            if index < unwraps.len() {
+                let stmts = unwraps.swap_remove(index);
+                block.stmts.splice(i..=i, stmts);
+                return true;
+            }
+            index -= unwraps.len();
+        }
+        false
+    });
+    found.then_some(program)
+}
+
+fn stmt_unwraps(stmt: &Stmt) -> Vec<Vec<Stmt>> {
+    match stmt {
+        Stmt::Block(b) => vec![b.stmts.clone()],
+        Stmt::While(w) => vec![w.body.stmts.clone()],
+        Stmt::If(i) => {
+            let mut res = vec![i.if_block.stmts.clone()];
+            let else_stmts = match &i.else_block {
+                IfElseBlock::None => return res,
+                IfElseBlock::If(e) => vec![Stmt::If((**e).clone())],
+                IfElseBlock::Block(b) => b.stmts.clone(),
+            };
+            res.push(else_stmts);
+            res.push(vec![Stmt::If(IfStmt {
+                else_block: IfElseBlock::None,
+                ..i.clone()
+            })]);
+            res
+        }
+        _ => vec![],
+    }
+}
+
+/// Remove the `index`-th declaration initialized with a literal, replacing
+/// every use of it with the literal.
+fn inline_const(program: &Program, index: usize) -> Option<Program> {
+    let mut program = program.clone();
+    let mut index = index;
+    let found = blocks_mut(&mut program, &mut |block| {
+        for i in 0..block.stmts.len() {
+            let (name, lit) = match &block.stmts[i] {
+                Stmt::Decl(DeclStmt {
+                    name,
+                    val: Some(val),
+                    ..
+                }) if matches!(**val, Expr::Literal(_)) => (name.name.clone(), (**val).clone()),
+                _ => continue,
+            };
+            if index > 0 {
+                index -= 1;
+                continue;
+            }
+            block.stmts.remove(i);
+            for stmt in &mut block.stmts[i..] {
+                stmt_exprs_mut(stmt, &mut |expr| {
+                    if matches!(expr, Expr::Ident(ident) if ident.name == name) {
+                        *expr = lit.clone();
+                    }
+                    false
+                });
+            }
+            return true;
+        }
+        false
+    });
+    found.then_some(program)
+}
+
+/// Replace the `index`-th expression variant with a simpler one, e.g. a binary
+/// expression with one of its operands.
+fn simplify_expr(program: &Program, index: usize) -> Option<Program> {
+    let mut program = program.clone();
+    let mut index = index;
+    let found = exprs_mut(&mut program, &mut |expr| {
+        let mut simplified = expr_simplifications(expr);
+        if index < simplified.len() {
+            *expr = simplified.swap_remove(index);
+            true
+        } else {
+            index -= simplified.len();
+            false
+        }
+    });
+    found.then_some(program)
+}
+
+fn expr_simplifications(expr: &Expr) -> Vec<Expr> {
+    let mut res = vec![];
+    if !matches!(expr, Expr::Literal(_)) {
+        res.push(Expr::Literal(LiteralExpr {
+            span: DUMMY_SPAN,
+            kind: LiteralKind::Integer(0),
+        }));
+    }
+    match expr {
+        Expr::Assign(e) => res.push((*e.rhs).clone()),
+        Expr::As(e) => res.push((*e.val).clone()),
+        Expr::Unary(e) => res.push((*e.expr).clone()),
+        Expr::Binary(e) => {
+            res.push((*e.lhs).clone());
+            res.push((*e.rhs).clone());
+        }
+        Expr::Call(e) => res.extend(e.params.iter().cloned()),
+        Expr::Ident(_) | Expr::Literal(_) => {}
+    }
+    res
+}
+
+/// Call `f` on every block inside `program` in pre-order, until `f` returns
+/// `true`. Returns whether `f` returned `true`.
+fn blocks_mut(program: &mut Program, f: &mut dyn FnMut(&mut BlockStmt) -> bool) -> bool {
+    program
+        .funcs
+        .iter_mut()
+        .any(|func| block_blocks_mut(&mut func.body, f))
+}
+
+fn block_blocks_mut(block: &mut BlockStmt, f: &mut dyn FnMut(&mut BlockStmt) -> bool) -> bool {
+    f(block)
+        || block.stmts.iter_mut().any(|stmt| match stmt {
+            Stmt::Block(b) => block_blocks_mut(b, f),
+            Stmt::While(w) => block_blocks_mut(P::make_mut(&mut w.body), f),
+            Stmt::If(i) => if_blocks_mut(i, f),
+            _ => false,
+        })
+}
+
+fn if_blocks_mut(stmt: &mut IfStmt, f: &mut dyn FnMut(&mut BlockStmt) -> bool) -> bool {
+    block_blocks_mut(P::make_mut(&mut stmt.if_block), f)
+        || match &mut stmt.else_block {
+            IfElseBlock::None => false,
+            IfElseBlock::If(i) => if_blocks_mut(P::make_mut(i), f),
+            IfElseBlock::Block(b) => block_blocks_mut(P::make_mut(b), f),
+        }
+}
+
+/// Call `f` on every expression inside `program` in pre-order, until `f`
+/// returns `true`. Returns whether `f` returned `true`.
+///
+/// The left hand side of assignments are not visited, since they can only be
+/// identifiers.
+fn exprs_mut(program: &mut Program, f: &mut dyn FnMut(&mut Expr) -> bool) -> bool {
+    program
+        .decls
+        .iter_mut()
+        .filter_map(|decl| decl.val.as_mut())
+        .any(|val| expr_exprs_mut(P::make_mut(val), f))
+        || program
+            .funcs
+            .iter_mut()
+            .any(|func| block_exprs_mut(&mut func.body, f))
+}
+
+fn block_exprs_mut(block: &mut BlockStmt, f: &mut dyn FnMut(&mut Expr) -> bool) -> bool {
+    block.stmts.iter_mut().any(|stmt| stmt_exprs_mut(stmt, f))
+}
+
+fn stmt_exprs_mut(stmt: &mut Stmt, f: &mut dyn FnMut(&mut Expr) -> bool) -> bool {
+    match stmt {
+        Stmt::Block(b) => block_exprs_mut(b, f),
+        Stmt::While(w) => {
+            expr_exprs_mut(P::make_mut(&mut w.cond), f)
+                || block_exprs_mut(P::make_mut(&mut w.body), f)
+        }
+        Stmt::If(i) => if_exprs_mut(i, f),
+        Stmt::Expr(e) => expr_exprs_mut(e, f),
+        Stmt::Decl(DeclStmt { val: Some(val), .. })
+        | Stmt::Return(ReturnStmt { val: Some(val), .. }) => expr_exprs_mut(P::make_mut(val), f),
+        _ => false,
+    }
+}
+
+fn if_exprs_mut(stmt: &mut IfStmt, f: &mut dyn FnMut(&mut Expr) -> bool) -> bool {
+    expr_exprs_mut(P::make_mut(&mut stmt.cond), f)
+        || block_exprs_mut(P::make_mut(&mut stmt.if_block), f)
+        || match &mut stmt.else_block {
+            IfElseBlock::None => false,
+            IfElseBlock::If(i) => if_exprs_mut(P::make_mut(i), f),
+            IfElseBlock::Block(b) => block_exprs_mut(P::make_mut(b), f),
+        }
+}
+
+fn expr_exprs_mut(expr: &mut Expr, f: &mut dyn FnMut(&mut Expr) -> bool) -> bool {
+    if f(expr) {
+        return true;
+    }
+    match expr {
+        Expr::Assign(e) => expr_exprs_mut(P::make_mut(&mut e.rhs), f),
+        Expr::As(e) => expr_exprs_mut(P::make_mut(&mut e.val), f),
+        Expr::Unary(e) => expr_exprs_mut(P::make_mut(&mut e.expr), f),
+        Expr::Binary(e) => {
+            expr_exprs_mut(P::make_mut(&mut e.lhs), f) || expr_exprs_mut(P::make_mut(&mut e.rhs), f)
+        }
+        Expr::Call(e) => e.params.iter_mut().any(|param| expr_exprs_mut(param, f)),
+        Expr::Ident(_) | Expr::Literal(_) => false,
+    }
+}
@@ -0,0 +1,91 @@
+//! Automatic reduction of test cases, in the style of delta debugging.
+//!
+//! Given a program and an _interestingness_ predicate (e.g. "the compiler
+//! panics with this message", or "optimized and unoptimized code disagree"),
+//! the reducer repeatedly tries smaller variants of the program, keeping every
+//! variant that is still interesting, until no pass can make any progress.
+//!
+//! Programs are reduced structurally, using [`ast`] passes for C0 source code
+//! and [`tac`] passes for TAC programs.
+
+pub mod ast;
+pub mod tac;
+mod test;
+
+/// A reduction pass.
+///
+/// Given a program and an index, returns the `index`-th smaller variant of the
+/// program, or `None` if there are fewer variants than that.
+pub type Pass<T> = fn(&T, usize) -> Option<T>;
+
+/// A program that can be reduced.
+pub trait Reduce: Clone {
+    /// The size of this program. Every variant kept by the reducer must be
+    /// strictly smaller than the program it comes from, which guarantees the
+    /// reduction terminates.
+    fn size(&self) -> usize;
+
+    /// Passes of this program, tried in order.
+    fn passes() -> Vec<(&'static str, Pass<Self>)>;
+}
+
+/// Statistics of a reduction.
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    /// The number of variants tested for interestingness.
+    pub tests: usize,
+    /// The number of variants kept.
+    pub reductions: usize,
+}
+
+/// Reduce `program` until no pass can produce a smaller program that is still
+/// interesting. `program` itself should be interesting.
+pub fn reduce<T: Reduce>(program: T, is_interesting: impl FnMut(&T) -> bool) -> (T, Stats) {
+    let mut reducer = Reducer {
+        is_interesting,
+        stats: Stats::default(),
+    };
+    let program = reducer.reduce(program);
+    (program, reducer.stats)
+}
+
+struct Reducer<F> {
+    is_interesting: F,
+    stats: Stats,
+}
+
+impl<F> Reducer<F> {
+    fn reduce<T: Reduce>(&mut self, mut program: T) -> T
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let passes = T::passes();
+        loop {
+            let mut changed = false;
+            for (name, pass) in &passes {
+                let mut size = program.size();
+                let mut index = 0;
+                while let Some(variant) = pass(&program, index) {
+                    let variant_size = variant.size();
+                    if variant_size < size {
+                        self.stats.tests += 1;
+                        if (self.is_interesting)(&variant) {
+                            tracing::debug!("{}: {} -> {}", name, size, variant_size);
+                            self.stats.reductions += 1;
+                            program = variant;
+                            size = variant_size;
+                            changed = true;
+                            // Variants after the removed one have shifted into
+                            // this index, so try this index again
+                            continue;
+                        }
+                    }
+                    index += 1;
+                }
+            }
+            if !changed {
+                return program;
+            }
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Reduction passes of TAC programs.
+//!
+//! Functions are visited in the order of their names, so reductions are
+//! deterministic.
+
+use std::collections::{BTreeSet, HashSet};
+
+use azuki_tac::{BBId, Branch, InstId, InstKind, Program, TacFunc, Value};
+use smol_str::SmolStr;
+
+use crate::{Pass, Reduce};
+
+impl Reduce for Program {
+    /// The number of functions, basic blocks, instructions and operands in the
+    /// program. Assigning a constant is counted as the smallest instruction.
+    fn size(&self) -> usize {
+        self.functions
+            .values()
+            .map(|func| {
+                1 + func
+                    .bb_iter()
+                    .map(|(bb, block)| {
+                        let branch_size = match block.branch {
+                            Branch::CondJump { .. } => 2,
+                            _ => 1,
+                        };
+                        let inst_size = func
+                            .inst_of_bb_iter(bb)
+                            .map(|(_, inst)| match inst.kind {
+                                InstKind::Assign(Value::Imm(_)) => 1,
+                                _ => 2 + inst.kind.params_iter().count(),
+                            })
+                            .sum::<usize>();
+                        1 + branch_size + inst_size
+                    })
+                    .sum::<usize>()
+            })
+            .sum()
+    }
+
+    fn passes() -> Vec<(&'static str, Pass<Self>)> {
+        vec![
+            ("remove-functions", remove_function),
+            ("remove-unreachable-blocks", remove_unreachable_block),
+            ("simplify-branches", simplify_branch),
+            ("zero-insts", zero_inst),
+            ("remove-unused-insts", remove_unused_inst),
+        ]
+    }
+}
+
+fn func_names(program: &Program) -> Vec<SmolStr> {
+    let mut names = program.functions.keys().cloned().collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+/// Find the function containing the `index`-th item returned by `items`, and
+/// call `f` on the function and the item.
+fn edit_nth<T>(
+    program: &Program,
+    index: usize,
+    items: impl Fn(&TacFunc) -> Vec<T>,
+    f: impl FnOnce(&mut TacFunc, T),
+) -> Option<Program> {
+    let mut index = index;
+    for name in func_names(program) {
+        let mut func_items = items(&program.functions[&name]);
+        if index < func_items.len() {
+            let mut program = program.clone();
+            f(
+                program.functions.get_mut(&name).unwrap(),
+                func_items.swap_remove(index),
+            );
+            return Some(program);
+        }
+        index -= func_items.len();
+    }
+    None
+}
+
+fn all_insts(func: &TacFunc) -> Vec<InstId> {
+    func.bb_iter()
+        .flat_map(|(bb, _)| func.inst_of_bb_iter(bb).map(|(inst, _)| inst))
+        .collect()
+}
+
+/// Remove the `index`-th function.
+fn remove_function(program: &Program, index: usize) -> Option<Program> {
+    let name = func_names(program).into_iter().nth(index)?;
+    let mut program = program.clone();
+    program.functions.shift_remove(&name);
+    Some(program)
+}
+
+/// Remove the `index`-th basic block not reachable from the starting block.
+fn remove_unreachable_block(program: &Program, index: usize) -> Option<Program> {
+    edit_nth(program, index, unreachable_blocks, |func, bb| {
+        for inst in func.inst_of_bb_iter(bb).map(|(i, _)| i).collect::<Vec<_>>() {
+            func.inst_detach(inst);
+            func.inst_remove(inst);
+        }
+        func.bb_get_mut(bb).branch = Branch::Unreachable;
+        func.bb_detach(bb);
+
+        for inst in all_insts(func) {
+            if let InstKind::Phi(sources) = &mut func.inst_get_mut(inst).kind {
+                sources.remove(&bb);
+            }
+        }
+    })
+}
+
+fn unreachable_blocks(func: &TacFunc) -> Vec<BBId> {
+    let mut reachable = HashSet::new();
+    let mut stack = func.starting_block().into_iter().collect::<Vec<_>>();
+    while let Some(bb) = stack.pop() {
+        if reachable.insert(bb) {
+            stack.extend(func.bb_get(bb).branch.target_iter());
+        }
+    }
+    func.bb_iter()
+        .map(|(bb, _)| bb)
+        .filter(|bb| !reachable.contains(bb))
+        .collect()
+}
+
+/// Replace the `index`-th conditional jump variant with an unconditional jump
+/// to one of its targets.
+fn simplify_branch(program: &Program, index: usize) -> Option<Program> {
+    let branches = |func: &TacFunc| {
+        func.bb_iter()
+            .filter_map(|(bb, block)| match block.branch {
+                Branch::CondJump {
+                    if_true, if_false, ..
+                } => Some(vec![(bb, if_true), (bb, if_false)]),
+                _ => None,
+            })
+            .flatten()
+            .collect()
+    };
+    edit_nth(program, index, branches, |func, (bb, target)| {
+        func.bb_get_mut(bb).branch = Branch::Jump(target);
+    })
+}
+
+/// Replace the `index`-th instruction not assigning a constant with `0`.
+fn zero_inst(program: &Program, index: usize) -> Option<Program> {
+    let insts = |func: &TacFunc| {
+        all_insts(func)
+            .into_iter()
+            .filter(|&inst| !matches!(func.inst_get(inst).kind, InstKind::Assign(Value::Imm(_))))
+            .collect()
+    };
+    edit_nth(program, index, insts, |func, inst| {
+        func.inst_get_mut(inst).kind = InstKind::Assign(Value::Imm(0));
+    })
+}
+
+/// Remove the `index`-th instruction whose value is never used.
+fn remove_unused_inst(program: &Program, index: usize) -> Option<Program> {
+    edit_nth(program, index, unused_insts, |func, inst| {
+        func.inst_detach(inst);
+        func.inst_remove(inst);
+    })
+}
+
+fn unused_insts(func: &TacFunc) -> Vec<InstId> {
+    let insts = all_insts(func);
+    let mut used = BTreeSet::new();
+    for &inst in &insts {
+        used.extend(func.inst_get(inst).kind.param_op_iter());
+    }
+    for (_, block) in func.bb_iter() {
+        match block.branch {
+            Branch::Return(Some(v)) | Branch::CondJump { cond: v, .. } => used.extend(v.get_inst()),
+            _ => {}
+        }
+    }
+    insts
+        .into_iter()
+        .filter(|inst| !used.contains(inst))
+        .collect()
+}
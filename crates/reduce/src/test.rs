@@ -0,0 +1,79 @@
+#![cfg(test)]
+
+use azuki_interp::Interpreter;
+use azuki_syntax::parse;
+
+use crate::{reduce, Reduce};
+
+fn eval(program: &azuki_syntax::ast::Program) -> Option<i64> {
+    Interpreter::new(program).run_func("main", vec![]).ok()?
+}
+
+#[test]
+fn test_reduce_ast() {
+    let input = r"
+        fn unrelated(x: int) -> int {
+            return x * 2;
+        }
+        fn helper(x: int) -> int {
+            let y: int = x + 1;
+            if y > 3 {
+                y = y * 7;
+            } else {
+                y = unrelated(y);
+            }
+            return y;
+        }
+        fn main() -> int {
+            let a: int = 3;
+            let b: int = helper(a + 2);
+            while a > 0 {
+                a = a - 1;
+            }
+            return b / 6;
+        }
+    ";
+    let program = parse(input).unwrap();
+    assert_eq!(eval(&program), Some(7));
+
+    // Any program returning 7
+    let (reduced, stats) = reduce(program.clone(), |p| eval(p) == Some(7));
+    assert!(stats.reductions > 0);
+    assert!(reduced.size() < program.size());
+    assert_eq!(eval(&reduced), Some(7));
+    assert!(reduced.funcs.iter().all(|f| f.name.name != "unrelated"));
+}
+
+#[test]
+fn test_reduce_tac() {
+    let input = r"
+        fn helper(x: int) -> int {
+            return x * 3;
+        }
+        fn main() -> int {
+            let a: int = helper(4);
+            if a > 10 {
+                a = a - 1;
+            }
+            return a;
+        }
+    ";
+    let program = azuki_tacgen::compile(&parse(input).unwrap()).unwrap();
+    let run = |p: &azuki_tac::Program| {
+        std::panic::catch_unwind(|| azuki_tacvm::Vm::new(p).run_func("main", vec![]))
+            .ok()
+            .flatten()
+    };
+    assert_eq!(run(&program), Some(11));
+
+    // Any program returning a value
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(|_| {}));
+    let (reduced, _) = reduce(program.clone(), |p| run(p).is_some());
+    std::panic::set_hook(hook);
+    assert!(reduced.size() < program.size());
+    assert!(!reduced.functions.contains_key("helper"));
+    // The reduced program can be printed and parsed back
+    let reparsed = azuki_tac::parser::parse_program_from_string(&reduced.to_string()).unwrap();
+    assert_eq!(run(&reparsed), run(&reduced));
+}
@@ -12,6 +12,7 @@ use smol_str::SmolStr;
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
 pub struct Program {
+    pub imports: Vec<ImportStmt>,
     pub decls: Vec<DeclStmt>,
     pub funcs: Vec<FuncStmt>,
 }
@@ -20,10 +21,21 @@ pub trait AstNode {
     fn span(&self) -> Span;
 }
 
+/// `import "path";`, which makes functions inside another source file
+/// available to this file. The path is relative to the importing file.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+pub struct ImportStmt {
+    pub span: Span,
+    pub path: String,
+}
+
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
 pub struct FuncStmt {
     pub span: Span,
+    /// Attributes like `#[inline]` written before the function.
+    pub attrs: Vec<Ident>,
     pub name: Ident,
     pub params: Vec<FuncParam>,
     pub ret_ty: TyDef,
@@ -47,8 +59,8 @@ pub enum Stmt {
     Expr(Expr),
     Decl(DeclStmt),
     Return(ReturnStmt),
-    Break(Span),
-    Continue(Span),
+    Break(JumpStmt),
+    Continue(JumpStmt),
     Empty(Span),
 }
 
@@ -61,8 +73,8 @@ impl Stmt {
             Stmt::Expr(i) => i.span(),
             Stmt::Decl(i) => i.span,
             Stmt::Return(i) => i.span,
-            Stmt::Break(s) => *s,
-            Stmt::Continue(s) => *s,
+            Stmt::Break(s) => s.span,
+            Stmt::Continue(s) => s.span,
             Stmt::Empty(s) => *s,
         }
     }
@@ -91,6 +103,9 @@ pub struct TyDef {
     pub span: Span,
     pub name: SmolStr,
     pub params: Option<Vec<TyDef>>,
+    /// Return type of a function type `fn(T, ...) -> R`. Function types are
+    /// named `fn` and store their parameter types inside `params`.
+    pub ret: Option<P<TyDef>>,
 }
 
 #[derive(Debug, Clone)]
@@ -104,10 +119,20 @@ pub struct BlockStmt {
 #[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
 pub struct WhileStmt {
     pub span: Span,
+    /// The label of this loop, like `'outer` in `'outer: while ...`.
+    pub label: Option<Ident>,
     pub cond: P<Expr>,
     pub body: P<BlockStmt>,
 }
 
+/// `break` or `continue`, with an optional label of the loop to jump out of.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+pub struct JumpStmt {
+    pub span: Span,
+    pub label: Option<Ident>,
+}
+
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
 pub struct IfStmt {
@@ -0,0 +1,280 @@
+//! Printing AST back into source code.
+//!
+//! The printed code parses back into the same tree (apart from spans).
+//! Compound expressions inside other expressions are always parenthesized,
+//! so the output does not depend on operator precedence.
+
+use std::fmt::{Display, Formatter, Result};
+
+use super::*;
+
+#[cfg(test)]
+mod test;
+
+const INDENT: &str = "    ";
+
+struct Printer<'f, 'a> {
+    f: &'f mut Formatter<'a>,
+    indent: usize,
+}
+
+impl<'f, 'a> Printer<'f, 'a> {
+    fn new(f: &'f mut Formatter<'a>) -> Self {
+        Printer { f, indent: 0 }
+    }
+
+    fn newline(&mut self) -> Result {
+        writeln!(self.f)?;
+        for _ in 0..self.indent {
+            write!(self.f, "{}", INDENT)?;
+        }
+        Ok(())
+    }
+
+    fn program(&mut self, program: &Program) -> Result {
+        for import in &program.imports {
+            writeln!(self.f, "import {:?};", import.path)?;
+        }
+        for decl in &program.decls {
+            self.decl(decl)?;
+            writeln!(self.f)?;
+        }
+        for func in &program.funcs {
+            self.func(func)?;
+            writeln!(self.f)?;
+        }
+        Ok(())
+    }
+
+    fn func(&mut self, func: &FuncStmt) -> Result {
+        for attr in &func.attrs {
+            write!(self.f, "#[{}]", attr.name)?;
+            self.newline()?;
+        }
+        write!(self.f, "fn {}(", func.name.name)?;
+        for (idx, param) in func.params.iter().enumerate() {
+            if idx != 0 {
+                write!(self.f, ", ")?;
+            }
+            if param.is_const {
+                write!(self.f, "const ")?;
+            }
+            write!(self.f, "{}: {}", param.name.name, param.ty)?;
+        }
+        write!(self.f, ") -> {} ", func.ret_ty)?;
+        self.block(&func.body)
+    }
+
+    fn block(&mut self, block: &BlockStmt) -> Result {
+        write!(self.f, "{{")?;
+        self.indent += 1;
+        for stmt in &block.stmts {
+            self.newline()?;
+            self.stmt(stmt)?;
+        }
+        self.indent -= 1;
+        self.newline()?;
+        write!(self.f, "}}")
+    }
+
+    fn decl(&mut self, decl: &DeclStmt) -> Result {
+        let kw = if decl.is_const { "const" } else { "let" };
+        write!(self.f, "{} {}: {}", kw, decl.name.name, decl.ty)?;
+        if let Some(val) = &decl.val {
+            write!(self.f, " = ")?;
+            self.expr(val, true)?;
+        }
+        write!(self.f, ";")
+    }
+
+    fn if_stmt(&mut self, stmt: &IfStmt) -> Result {
+        write!(self.f, "if ")?;
+        self.expr(&stmt.cond, true)?;
+        write!(self.f, " ")?;
+        self.block(&stmt.if_block)?;
+        match &stmt.else_block {
+            IfElseBlock::None => Ok(()),
+            IfElseBlock::If(i) => {
+                write!(self.f, " else ")?;
+                self.if_stmt(i)
+            }
+            IfElseBlock::Block(b) => {
+                write!(self.f, " else ")?;
+                self.block(b)
+            }
+        }
+    }
+
+    fn jump(&mut self, kw: &str, stmt: &JumpStmt) -> Result {
+        write!(self.f, "{}", kw)?;
+        if let Some(label) = &stmt.label {
+            write!(self.f, " '{}", label.name)?;
+        }
+        write!(self.f, ";")
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> Result {
+        match stmt {
+            Stmt::Block(b) => self.block(b),
+            Stmt::While(w) => {
+                if let Some(label) = &w.label {
+                    write!(self.f, "'{}: ", label.name)?;
+                }
+                write!(self.f, "while ")?;
+                self.expr(&w.cond, true)?;
+                write!(self.f, " ")?;
+                self.block(&w.body)
+            }
+            Stmt::If(i) => self.if_stmt(i),
+            Stmt::Expr(e) => {
+                self.expr(e, true)?;
+                write!(self.f, ";")
+            }
+            Stmt::Decl(d) => self.decl(d),
+            Stmt::Return(r) => {
+                write!(self.f, "return")?;
+                if let Some(val) = &r.val {
+                    write!(self.f, " ")?;
+                    self.expr(val, true)?;
+                }
+                write!(self.f, ";")
+            }
+            Stmt::Break(j) => self.jump("break", j),
+            Stmt::Continue(j) => self.jump("continue", j),
+            Stmt::Empty(_) => write!(self.f, ";"),
+        }
+    }
+
+    /// Print `expr`. Compound expressions are parenthesized unless `bare`.
+    fn expr(&mut self, expr: &Expr, bare: bool) -> Result {
+        let (open, close) = if bare { ("", "") } else { ("(", ")") };
+        match expr {
+            Expr::Ident(i) => write!(self.f, "{}", i.name),
+            Expr::Assign(e) => {
+                write!(self.f, "{}", open)?;
+                self.expr(&e.lhs, false)?;
+                write!(self.f, " = ")?;
+                self.expr(&e.rhs, false)?;
+                write!(self.f, "{}", close)
+            }
+            Expr::As(e) => {
+                write!(self.f, "{}", open)?;
+                self.expr(&e.val, false)?;
+                write!(self.f, " as {}{}", e.ty, close)
+            }
+            Expr::Literal(l) => match &l.kind {
+                LiteralKind::Integer(i) => write!(self.f, "{}", i),
+                LiteralKind::Float(x) => write!(self.f, "{:?}", x),
+                LiteralKind::String(s) => self.quoted(s.chars(), '"'),
+                // Char literals only hold ASCII characters, but mean the same
+                // as their integer value
+                LiteralKind::Char(c) if !c.is_ascii() => write!(self.f, "{}", *c as u32),
+                LiteralKind::Char(c) => self.quoted(std::iter::once(*c), '\''),
+            },
+            Expr::Unary(e) => match e.op {
+                UnaryOp::Neg => {
+                    write!(self.f, "-")?;
+                    self.expr(&e.expr, false)
+                }
+                // There's no syntax for unary plus, and it does nothing anyway
+                UnaryOp::Pos => self.expr(&e.expr, bare),
+            },
+            Expr::Binary(e) => {
+                write!(self.f, "{}", open)?;
+                self.expr(&e.lhs, false)?;
+                write!(self.f, " {} ", e.op)?;
+                self.expr(&e.rhs, false)?;
+                write!(self.f, "{}", close)
+            }
+            Expr::Call(e) => {
+                write!(self.f, "{}(", e.func.name)?;
+                for (idx, param) in e.params.iter().enumerate() {
+                    if idx != 0 {
+                        write!(self.f, ", ")?;
+                    }
+                    self.expr(param, true)?;
+                }
+                write!(self.f, ")")
+            }
+        }
+    }
+
+    /// Prints a string or character literal. Only the escapes of C0 are used,
+    /// every other character is printed as is.
+    fn quoted(&mut self, chars: impl Iterator<Item = char>, quote: char) -> Result {
+        write!(self.f, "{}", quote)?;
+        for c in chars {
+            match c {
+                '\\' => write!(self.f, "\\\\")?,
+                '\n' => write!(self.f, "\\n")?,
+                '\r' => write!(self.f, "\\r")?,
+                '\t' => write!(self.f, "\\t")?,
+                c if c == quote => write!(self.f, "\\{}", c)?,
+                c => write!(self.f, "{}", c)?,
+            }
+        }
+        write!(self.f, "{}", quote)
+    }
+}
+
+impl Display for Program {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        Printer::new(f).program(self)
+    }
+}
+
+impl Display for FuncStmt {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        Printer::new(f).func(self)
+    }
+}
+
+impl Display for Stmt {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        Printer::new(f).stmt(self)
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        Printer::new(f).expr(self, true)
+    }
+}
+
+impl Display for TyDef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        if self.name != "fn" {
+            return write!(f, "{}", self.name);
+        }
+        write!(f, "fn(")?;
+        for (idx, param) in self.params.iter().flatten().enumerate() {
+            if idx != 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", param)?;
+        }
+        write!(f, ") -> ")?;
+        match &self.ret {
+            Some(ret) => write!(f, "{}", ret),
+            None => write!(f, "void"),
+        }
+    }
+}
+
+impl Display for BinaryOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let s = match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Gt => ">",
+            BinaryOp::Lt => "<",
+            BinaryOp::Ge => ">=",
+            BinaryOp::Le => "<=",
+            BinaryOp::Eq => "==",
+            BinaryOp::Neq => "!=",
+        };
+        write!(f, "{}", s)
+    }
+}
@@ -0,0 +1,56 @@
+use crate::{ast::*, parse, span::DUMMY_SPAN};
+
+/// Prints `kind` as the initial value of a variable, and parses it back.
+fn round_trip(kind: LiteralKind) -> LiteralKind {
+    let lit = Expr::Literal(LiteralExpr {
+        span: DUMMY_SPAN,
+        kind,
+    });
+    let input = format!("fn main() -> void {{ let x: int = {}; }}", lit);
+    let program = parse(&input).unwrap_or_else(|e| panic!("{:?}\n{}", e, input));
+    match &program.funcs[0].body.stmts[0] {
+        Stmt::Decl(DeclStmt { val: Some(val), .. }) => match &**val {
+            Expr::Literal(l) => l.kind.clone(),
+            val => panic!("{}", val),
+        },
+        stmt => panic!("{}", stmt),
+    }
+}
+
+#[test]
+fn test_print_escapes() {
+    let lit = |kind| {
+        Expr::Literal(LiteralExpr {
+            span: DUMMY_SPAN,
+            kind,
+        })
+        .to_string()
+    };
+    // Only escapes of C0 are printed, other characters are printed as is
+    assert_eq!(lit(LiteralKind::Char('\'')), r"'\''");
+    assert_eq!(lit(LiteralKind::Char('\u{7f}')), "'\u{7f}'");
+    assert_eq!(
+        lit(LiteralKind::String("\\\t\u{7f}\"'".into())),
+        "\"\\\\\\t\u{7f}\\\"'\""
+    );
+
+    assert_eq!(lit(LiteralKind::Char('é')), "233");
+
+    // Char literals are parsed as integers
+    for c in ['\'', '"', '\\', '\n', '\u{7f}', 'é'].iter().copied() {
+        let parsed = round_trip(LiteralKind::Char(c));
+        assert!(
+            matches!(parsed, LiteralKind::Integer(i) if i == c as u64),
+            "{:?}: {:?}",
+            c,
+            parsed
+        );
+    }
+    let s = "a\\\t\r\n\u{1}\u{7f}é\"'";
+    let parsed = round_trip(LiteralKind::String(s.into()));
+    assert!(
+        matches!(&parsed, LiteralKind::String(x) if x == s),
+        "{:?}",
+        parsed
+    );
+}
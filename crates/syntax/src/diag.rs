@@ -0,0 +1,160 @@
+//! Diagnostics reported to the user, and utilities to display them along with
+//! the source code they point at.
+
+use std::fmt::Display;
+
+use crate::{
+    parser::err::{ParseError, ParseErrorKind},
+    span::Span,
+};
+
+/// The severity of a diagnostic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Warning,
+    Error,
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Level::Warning => write!(f, "warning"),
+            Level::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A message about some place inside the source file.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub level: Level,
+    pub message: String,
+    pub span: Option<Span>,
+    /// The name of the check producing this diagnostic, e.g. a lint name.
+    pub code: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(level: Level, message: impl Into<String>, span: Option<Span>) -> Diagnostic {
+        Diagnostic {
+            level,
+            message: message.into(),
+            span,
+            code: None,
+        }
+    }
+
+    pub fn error(message: impl Into<String>, span: Option<Span>) -> Diagnostic {
+        Self::new(Level::Error, message, span)
+    }
+
+    pub fn warning(message: impl Into<String>, span: Option<Span>) -> Diagnostic {
+        Self::new(Level::Warning, message, span)
+    }
+
+    pub fn with_code(mut self, code: impl Into<String>) -> Diagnostic {
+        self.code = Some(code.into());
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.level == Level::Error
+    }
+
+    /// Returns a displayable form of this diagnostic, showing the source line
+    /// it points at.
+    pub fn display<'a>(&'a self, file_name: &'a str, source: &'a str) -> DiagnosticDisplay<'a> {
+        DiagnosticDisplay {
+            diag: self,
+            file_name,
+            source,
+        }
+    }
+}
+
+impl From<ParseError> for Diagnostic {
+    fn from(e: ParseError) -> Self {
+        Diagnostic::error(e.kind.to_string(), e.span)
+    }
+}
+
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseErrorKind::ExpectToken(t) => write!(f, "expected {}", t),
+            ParseErrorKind::ExpectedPattern(p) => write!(f, "expected {}", p),
+            ParseErrorKind::UnexpectedEof => write!(f, "unexpected end of file"),
+            ParseErrorKind::LexError(e) => e.fmt(f),
+            ParseErrorKind::FeatureNotAvailable {
+                feature,
+                level: Some(level),
+            } if feature.level() > *level => write!(
+                f,
+                "feature `{}` is not available at level {}, it requires level {}",
+                feature,
+                level,
+                feature.level()
+            ),
+            ParseErrorKind::FeatureNotAvailable { feature, .. } => {
+                write!(f, "feature `{}` is disabled", feature)
+            }
+            ParseErrorKind::Dummy => write!(f, "parse error"),
+        }
+    }
+}
+
+/// Returns the zero-based line and column (in chars) of byte index `idx`.
+///
+/// Indices past the end of `source` (e.g. [`Span::eof`]) are placed at the
+/// end of file.
+pub fn line_col(source: &str, idx: usize) -> (usize, usize) {
+    let idx = idx.min(source.len());
+    let before = &source[..idx];
+    let line = before.matches('\n').count();
+    let line_start = before.rfind('\n').map_or(0, |x| x + 1);
+    (line, source[line_start..idx].chars().count())
+}
+
+/// A diagnostic with its source code. Created by [`Diagnostic::display`].
+pub struct DiagnosticDisplay<'a> {
+    diag: &'a Diagnostic,
+    file_name: &'a str,
+    source: &'a str,
+}
+
+impl<'a> Display for DiagnosticDisplay<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let diag = self.diag;
+        write!(f, "{}", diag.level)?;
+        if let Some(code) = &diag.code {
+            write!(f, "[{}]", code)?;
+        }
+        writeln!(f, ": {}", diag.message)?;
+
+        let span = match diag.span {
+            Some(span) => span,
+            None => return write!(f, " --> {}", self.file_name),
+        };
+        let (line, col) = line_col(self.source, span.start());
+        writeln!(f, " --> {}:{}:{}", self.file_name, line + 1, col + 1)?;
+
+        let line_text = self.source.lines().nth(line).unwrap_or("");
+        let line_no = (line + 1).to_string();
+        let pad = " ".repeat(line_no.len());
+        let (end_line, end_col) = line_col(self.source, span.end());
+        let underline_len = if end_line == line && end_col > col {
+            end_col - col
+        } else {
+            1
+        };
+        writeln!(f, "{} |", pad)?;
+        writeln!(f, "{} | {}", line_no, line_text)?;
+        write!(
+            f,
+            "{} | {}{}",
+            pad,
+            " ".repeat(col),
+            "^".repeat(underline_len)
+        )
+    }
+}
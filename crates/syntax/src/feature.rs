@@ -0,0 +1,183 @@
+//! Language levels and feature gates.
+//!
+//! The same compiler is used as the reference implementation of every lab, but
+//! later labs extend the language. A language level enables the features
+//! available in the corresponding lab:
+//!
+//! | Level | Features                          |
+//! | ----- | --------------------------------- |
+//! | 1, 2  | (the base language)               |
+//! | 3     | `break-continue`, `label`         |
+//! | 4     | `as`, `char`, `string`, `float`   |
+//! | 5     | `fn-type`, `import`, `attrs`      |
+//!
+//! Features can also be enabled or disabled individually on top of a level.
+
+use std::{collections::BTreeSet, fmt::Display, str::FromStr};
+
+use crate::Token;
+
+/// The highest language level. Every feature is available at this level.
+pub const MAX_LEVEL: u32 = 5;
+
+/// A part of the language that can be disabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Feature {
+    /// `break` and `continue` statements.
+    BreakContinue,
+    /// Labelled loops, and `break` and `continue` out of them.
+    Label,
+    /// Type casts using `as`.
+    As,
+    /// Character literals.
+    Char,
+    /// String literals.
+    String,
+    /// Floating-point literals.
+    Float,
+    /// Function types, like `fn(int) -> int`.
+    FnType,
+    /// `import` declarations.
+    Import,
+    /// Function attributes, like `#[inline]`.
+    Attrs,
+}
+
+impl Feature {
+    pub const ALL: &'static [Feature] = &[
+        Feature::BreakContinue,
+        Feature::Label,
+        Feature::As,
+        Feature::Char,
+        Feature::String,
+        Feature::Float,
+        Feature::FnType,
+        Feature::Import,
+        Feature::Attrs,
+    ];
+
+    /// The name of this feature, as used on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Feature::BreakContinue => "break-continue",
+            Feature::Label => "label",
+            Feature::As => "as",
+            Feature::Char => "char",
+            Feature::String => "string",
+            Feature::Float => "float",
+            Feature::FnType => "fn-type",
+            Feature::Import => "import",
+            Feature::Attrs => "attrs",
+        }
+    }
+
+    /// The lowest language level this feature is available at.
+    pub fn level(self) -> u32 {
+        match self {
+            Feature::BreakContinue | Feature::Label => 3,
+            Feature::As | Feature::Char | Feature::String | Feature::Float => 4,
+            Feature::FnType | Feature::Import | Feature::Attrs => 5,
+        }
+    }
+
+    /// Returns the feature needed to use `token`, if any.
+    pub fn of_token(token: &Token) -> Option<Feature> {
+        match token {
+            Token::BreakKw | Token::ContinueKw => Some(Feature::BreakContinue),
+            Token::Label(_) => Some(Feature::Label),
+            Token::AsKw => Some(Feature::As),
+            Token::CharLiteral(_) => Some(Feature::Char),
+            Token::StringLiteral(_) => Some(Feature::String),
+            Token::FloatLiteral(_) => Some(Feature::Float),
+            Token::ImportKw => Some(Feature::Import),
+            Token::Hash => Some(Feature::Attrs),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Feature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Feature {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Feature::ALL
+            .iter()
+            .copied()
+            .find(|f| f.name() == s)
+            .ok_or_else(|| {
+                let names = Feature::ALL.iter().map(|f| f.name()).collect::<Vec<_>>();
+                format!("Expected {}, got {}", names.join(", "), s)
+            })
+    }
+}
+
+/// The set of features enabled when parsing a program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Features {
+    /// The language level, or `None` if no level is set.
+    level: Option<u32>,
+    enabled: BTreeSet<Feature>,
+}
+
+impl Features {
+    /// Every feature enabled, with no language level set.
+    pub fn all() -> Features {
+        Features {
+            level: None,
+            enabled: Feature::ALL.iter().copied().collect(),
+        }
+    }
+
+    /// The features available at language level `level`.
+    pub fn at_level(level: u32) -> Features {
+        Features {
+            level: Some(level),
+            enabled: Feature::ALL
+                .iter()
+                .copied()
+                .filter(|f| f.level() <= level)
+                .collect(),
+        }
+    }
+
+    pub fn level(&self) -> Option<u32> {
+        self.level
+    }
+
+    pub fn enable(&mut self, feature: Feature) {
+        self.enabled.insert(feature);
+    }
+
+    pub fn disable(&mut self, feature: Feature) {
+        self.enabled.remove(&feature);
+    }
+
+    pub fn is_enabled(&self, feature: Feature) -> bool {
+        self.enabled.contains(&feature)
+    }
+
+    /// Returns the first feature used by `tokens` that is not enabled, along
+    /// with the index of the token using it.
+    pub fn check_tokens<'a>(
+        &self,
+        tokens: impl IntoIterator<Item = &'a Token>,
+    ) -> Option<(usize, Feature)> {
+        tokens.into_iter().enumerate().find_map(|(idx, token)| {
+            Feature::of_token(token)
+                .filter(|&f| !self.is_enabled(f))
+                .map(|f| (idx, f))
+        })
+    }
+}
+
+impl Default for Features {
+    fn default() -> Self {
+        Features::all()
+    }
+}
@@ -1,15 +1,145 @@
+use std::fmt::Display;
+
 use logos::Logos;
 
-use crate::{prelude::Span, token::Token};
+use crate::{
+    prelude::Span,
+    token::{parse_int, Token},
+};
 
 pub type Lexer<'src> = logos::Lexer<'src, Token>;
 
-pub fn lexer(s: &str) -> Lexer {
+pub fn lexer(s: &str) -> Lexer<'_> {
     Token::lexer(s)
 }
 
+/// Returns an iterator of tokens and their spans. Invalid pieces of source
+/// code are reported as [`Token::LexError`] with the reason of failure.
 pub fn spanned_lexer(s: &str) -> impl Iterator<Item = (Token, Span)> + '_ {
-    Token::lexer(s)
-        .spanned()
-        .map(|(t, s)| (t, crate::prelude::Span::new_idx(s.start, s.end)))
+    Token::lexer(s).spanned().map(move |(t, range)| {
+        let span = crate::prelude::Span::new_idx(range.start, range.end);
+        let t = match t {
+            // Comments are only emitted when they are unterminated.
+            Token::Error | Token::Comment => Token::LexError(LexError::classify(&s[range])),
+            t => t,
+        };
+        (t, span)
+    })
+}
+
+/// The reason why a piece of source code is not a valid token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LexError {
+    UnknownCharacter,
+    UnterminatedBlockComment,
+    /// An integer literal has no digits after its radix prefix.
+    EmptyIntegerLiteral,
+    /// An integer literal contains a digit not allowed by its radix.
+    InvalidDigit {
+        radix: u32,
+    },
+    /// An integer literal does not fit in 64 bits. Literals that fit but are
+    /// larger than the largest `int` are rejected when they are compiled or
+    /// evaluated instead.
+    IntegerOutOfRange,
+    InvalidCharLiteral,
+    InvalidStringLiteral,
+}
+
+impl LexError {
+    /// Find out why `s` failed to lex.
+    pub fn classify(s: &str) -> LexError {
+        if s.starts_with("/*") {
+            LexError::UnterminatedBlockComment
+        } else if s.starts_with('"') {
+            LexError::InvalidStringLiteral
+        } else if s.starts_with('\'') {
+            LexError::InvalidCharLiteral
+        } else if s.starts_with(|c: char| c.is_ascii_digit()) {
+            Self::classify_int(s)
+        } else {
+            LexError::UnknownCharacter
+        }
+    }
+
+    fn classify_int(s: &str) -> LexError {
+        let (radix, digits) = match s.get(..2) {
+            Some("0x") | Some("0X") => (16, &s[2..]),
+            Some("0o") | Some("0O") => (8, &s[2..]),
+            Some("0b") | Some("0B") => (2, &s[2..]),
+            _ => (10, s),
+        };
+        if !digits.chars().any(|c| c != '_') {
+            LexError::EmptyIntegerLiteral
+        } else if !digits.chars().all(|c| c == '_' || c.is_digit(radix)) {
+            LexError::InvalidDigit { radix }
+        } else {
+            debug_assert!(parse_int(s).is_none());
+            LexError::IntegerOutOfRange
+        }
+    }
+}
+
+impl Display for LexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LexError::UnknownCharacter => write!(f, "unknown character"),
+            LexError::UnterminatedBlockComment => write!(f, "unterminated block comment"),
+            LexError::EmptyIntegerLiteral => write!(f, "missing digits in integer literal"),
+            LexError::InvalidDigit { radix } => {
+                write!(f, "invalid digit for a base {} literal", radix)
+            }
+            LexError::IntegerOutOfRange => {
+                write!(
+                    f,
+                    "integer literal is too large, the largest `int` is {}",
+                    i64::MAX
+                )
+            }
+            LexError::InvalidCharLiteral => write!(f, "invalid character literal"),
+            LexError::InvalidStringLiteral => write!(f, "invalid string literal"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tokens(s: &str) -> Vec<Token> {
+        spanned_lexer(s).map(|(t, _)| t).collect()
+    }
+
+    #[test]
+    fn test_int_literals() {
+        let res = tokens("0x1F 0o17 0b1010 1_000_000")
+            .into_iter()
+            .map(|t| t.get_uint().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(res, vec![31, 15, 10, 1_000_000]);
+    }
+
+    #[test]
+    fn test_lex_errors() {
+        let err = |s: &str| match spanned_lexer(s).last().unwrap() {
+            (Token::LexError(e), span) => (e, span),
+            (t, _) => panic!("expected error, got {}", t),
+        };
+        assert_eq!(err("1 /* never ends").0, LexError::UnterminatedBlockComment);
+        assert_eq!(err("1 /* never ends").1, Span::new(2, 13));
+        assert_eq!(err("18446744073709551616").0, LexError::IntegerOutOfRange);
+        assert!(LexError::IntegerOutOfRange
+            .to_string()
+            .ends_with("the largest `int` is 9223372036854775807"));
+        assert_eq!(err("0b102").0, LexError::InvalidDigit { radix: 2 });
+        assert_eq!(err("0x").0, LexError::EmptyIntegerLiteral);
+        assert_eq!(err("$").0, LexError::UnknownCharacter);
+    }
+
+    #[test]
+    fn test_comments() {
+        assert_eq!(tokens("a /* b \n c */ // d").len(), 1);
+        assert_eq!(tokens("a // comment at end of file").len(), 1);
+    }
 }
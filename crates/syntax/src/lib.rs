@@ -4,11 +4,17 @@ pub mod span;
 /// Utilities
 pub mod util;
 
+/// Diagnostics for r0 programs
+pub mod diag;
+
 /// Lexer for r0 tokens
 pub mod lexer;
 /// Models of r0 tokens
 pub mod token;
 
+/// Language levels and feature gates
+pub mod feature;
+
 /// Models of the abstract syntax tree.
 pub mod ast;
 /// Parser for r0 programs
@@ -26,6 +32,33 @@ pub mod prelude {
 }
 
 pub fn parse(program: &str) -> Result<ast::Program, parser::err::ParseError> {
-    let mut parser = parser::Parser::new(lexer::spanned_lexer(program));
+    parse_with_features(program, &feature::Features::all())
+}
+
+/// Parse `program`, rejecting every language feature not enabled in `features`.
+pub fn parse_with_features(
+    program: &str,
+    features: &feature::Features,
+) -> Result<ast::Program, parser::err::ParseError> {
+    let tokens = lexer::spanned_lexer(program).collect::<Vec<_>>();
+    // Report lexing errors before any parsing error they may cause
+    for (token, span) in &tokens {
+        if let Token::LexError(e) = token {
+            return Err(parser::err::ParseError::new_span(
+                parser::err::ParseErrorKind::LexError(*e),
+                *span,
+            ));
+        }
+    }
+    if let Some((idx, feature)) = features.check_tokens(tokens.iter().map(|(t, _)| t)) {
+        return Err(parser::err::ParseError::new_span(
+            parser::err::ParseErrorKind::FeatureNotAvailable {
+                feature,
+                level: features.level(),
+            },
+            tokens[idx].1,
+        ));
+    }
+    let mut parser = parser::Parser::with_features(tokens.into_iter(), features.clone());
     parser.parse()
 }
@@ -1,4 +1,4 @@
-use crate::{prelude::Span, Token};
+use crate::{feature::Feature, lexer::LexError, prelude::Span, Token};
 
 #[derive(Debug)]
 pub struct ParseError {
@@ -28,5 +28,12 @@ pub enum ParseErrorKind {
     ExpectToken(Token),
     ExpectedPattern(String),
     UnexpectedEof,
+    LexError(LexError),
+    /// A feature not enabled at the current language level, which is `None`
+    /// if the feature is disabled explicitly.
+    FeatureNotAvailable {
+        feature: Feature,
+        level: Option<u32>,
+    },
     Dummy,
 }
@@ -1,9 +1,12 @@
 #![allow(clippy::redundant_closure_call)]
 pub mod err;
+#[cfg(test)]
+mod test;
 
 use std::iter::Peekable;
 
 use crate::{
+    feature::{Feature, Features},
     prelude::{Span, P},
     Token,
 };
@@ -13,6 +16,7 @@ use crate::ast::*;
 
 pub struct Parser<L> {
     pub lexer: L,
+    pub features: Features,
 }
 
 macro_rules! expect {
@@ -37,16 +41,22 @@ macro_rules! is_next {
 macro_rules! separated {
     ( $parse:expr, $detect_sep:expr, $parse_sep:expr) => {{
         let first: Result<_, ParseError> = (|| $parse)();
-        if let Ok(val) = first {
-            let mut v = vec![val];
-            while $detect_sep {
-                let _ = $parse_sep;
-                let next = (|| $parse)()?;
-                v.push(next);
+        match first {
+            Ok(val) => {
+                let mut v = vec![val];
+                while $detect_sep {
+                    let _ = $parse_sep;
+                    let next = (|| $parse)()?;
+                    v.push(next);
+                }
+                v
             }
-            v
-        } else {
-            Vec::new()
+            // A disabled feature is an error even if the list may be empty
+            Err(e @ ParseError {
+                kind: ParseErrorKind::FeatureNotAvailable { .. },
+                ..
+            }) => return Err(e),
+            Err(_) => Vec::new(),
         }
     }};
 }
@@ -96,8 +106,16 @@ where
     L: Iterator<Item = (Token, Span)>,
 {
     pub fn new(lexer: L) -> Parser<Peekable<L>> {
+        Self::with_features(lexer, Features::all())
+    }
+
+    /// Create a parser accepting only grammar productions enabled in
+    /// `features`. Tokens of disabled features should be rejected before
+    /// parsing; see [`parse_with_features`](crate::parse_with_features).
+    pub fn with_features(lexer: L, features: Features) -> Parser<Peekable<L>> {
         Parser {
             lexer: lexer.peekable(),
+            features,
         }
     }
 
@@ -121,11 +139,30 @@ where
         }
     }
 
+    /// Report an error at `span` if `feature` is not enabled.
+    fn gate(&self, feature: Feature, span: Span) -> Result<(), ParseError> {
+        if self.features.is_enabled(feature) {
+            Ok(())
+        } else {
+            Err(ParseError::new_span(
+                ParseErrorKind::FeatureNotAvailable {
+                    feature,
+                    level: self.features.level(),
+                },
+                span,
+            ))
+        }
+    }
+
     fn parse_program(&mut self) -> Result<Program, ParseError> {
+        let mut imports = vec![];
         let mut funcs = vec![];
         let mut decls = vec![];
         loop {
-            if is_next!(self, Token::FnKw) {
+            if is_next!(self, Token::ImportKw) {
+                let res = self.parse_import()?;
+                imports.push(res);
+            } else if is_next!(self, Token::FnKw | Token::Hash) {
                 let res = self.parse_fn_decl()?;
                 funcs.push(res);
             } else if is_next!(self, Token::LetKw) {
@@ -138,7 +175,21 @@ where
                 break;
             }
         }
-        Ok(Program { decls, funcs })
+        Ok(Program {
+            imports,
+            decls,
+            funcs,
+        })
+    }
+
+    fn parse_import(&mut self) -> Result<ImportStmt, ParseError> {
+        let (_, start_span) = expect!(self, Token::ImportKw)?;
+        let (path, _) = expect!(self, Token::StringLiteral(_))?;
+        let (_, end_span) = expect!(self, Token::Semicolon)?;
+        Ok(ImportStmt {
+            span: start_span + end_span,
+            path: path.get_string_owned().unwrap(),
+        })
     }
 
     fn parse_ident(&mut self) -> Result<Ident, ParseError> {
@@ -149,12 +200,48 @@ where
         })
     }
 
+    fn parse_label(&mut self) -> Result<Ident, ParseError> {
+        let (name, name_span) = expect!(self, Token::Label(_))?;
+        Ok(Ident {
+            span: name_span,
+            name: name.get_label_owned().unwrap(),
+        })
+    }
+
     fn parse_ty(&mut self) -> Result<TyDef, ParseError> {
+        // Ty -> Ident | 'fn' '(' (Ty (',' Ty)*)? ')' '->' Ty
+        if is_next!(self, Token::FnKw) {
+            return self.parse_func_ty();
+        }
         let (name, name_span) = expect!(self, Token::Ident(_))?;
         Ok(TyDef {
             span: name_span,
             name: name.get_ident_owned().unwrap(),
             params: None,
+            ret: None,
+        })
+    }
+
+    fn parse_func_ty(&mut self) -> Result<TyDef, ParseError> {
+        let (_, start_span) = expect!(self, Token::FnKw)?;
+        self.gate(Feature::FnType, start_span)?;
+
+        expect!(self, Token::LParen)?;
+        let params = separated!(
+            self.parse_ty(),
+            is_next!(self, Token::Comma),
+            self.lexer.next()
+        );
+        expect!(self, Token::RParen)?;
+
+        expect!(self, Token::Arrow)?;
+        let ret = self.parse_ty()?;
+
+        Ok(TyDef {
+            span: start_span + ret.span,
+            name: "fn".into(),
+            params: Some(params),
+            ret: Some(P::new(ret)),
         })
     }
 
@@ -237,7 +324,7 @@ where
             } else {
                 Ok(Expr::Ident(ident))
             }
-        } else if is_next!(self, Token::UIntLiteral(_)|Token::CharLiteral(_)) {
+        } else if is_next!(self, Token::UIntLiteral(_) | Token::CharLiteral(_)) {
             let (num, span) = self.lexer.next().unwrap();
             Ok(Expr::Literal(LiteralExpr {
                 span,
@@ -390,11 +477,23 @@ where
     }
 
     fn parse_while_stmt(&mut self) -> Result<WhileStmt, ParseError> {
+        // WhileStmt -> (Label ':')? 'while' Expr Block
+        let label = if is_next!(self, Token::Label(_)) {
+            let label = self.parse_label()?;
+            expect!(self, Token::Colon)?;
+            Some(label)
+        } else {
+            None
+        };
         expect!(self, Token::WhileKw)?;
         let cond = self.parse_expr()?;
         let body = self.parse_block()?;
-        let span = cond.span() + body.span;
+        let mut span = cond.span() + body.span;
+        if let Some(label) = &label {
+            span = label.span + span;
+        }
         Ok(WhileStmt {
+            label,
             cond: P::new(cond),
             body: P::new(body),
             span,
@@ -417,16 +516,28 @@ where
         })
     }
 
-    fn parse_break_stmt(&mut self) -> Result<Span, ParseError> {
+    fn parse_break_stmt(&mut self) -> Result<JumpStmt, ParseError> {
         let (_, span) = expect!(self, Token::BreakKw)?;
-        expect!(self, Token::Semicolon)?;
-        Ok(span)
+        self.parse_jump_rest(span)
     }
 
-    fn parse_continue_stmt(&mut self) -> Result<Span, ParseError> {
+    fn parse_continue_stmt(&mut self) -> Result<JumpStmt, ParseError> {
         let (_, span) = expect!(self, Token::ContinueKw)?;
-        expect!(self, Token::Semicolon)?;
-        Ok(span)
+        self.parse_jump_rest(span)
+    }
+
+    /// Parses the optional label and the semicolon after `break` or `continue`.
+    fn parse_jump_rest(&mut self, span: Span) -> Result<JumpStmt, ParseError> {
+        let label = if is_next!(self, Token::Label(_)) {
+            Some(self.parse_label()?)
+        } else {
+            None
+        };
+        let (_, end_span) = expect!(self, Token::Semicolon)?;
+        Ok(JumpStmt {
+            span: span + end_span,
+            label,
+        })
     }
 
     fn parse_stmt(&mut self) -> Result<Stmt, ParseError> {
@@ -438,7 +549,7 @@ where
             Stmt::Block(self.parse_block()?)
         } else if is_next!(self, Token::IfKw) {
             Stmt::If(self.parse_if_stmt()?)
-        } else if is_next!(self, Token::WhileKw) {
+        } else if is_next!(self, Token::WhileKw | Token::Label(_)) {
             Stmt::While(self.parse_while_stmt()?)
         } else if is_next!(self, Token::BreakKw) {
             Stmt::Break(self.parse_break_stmt()?)
@@ -454,7 +565,24 @@ where
         Ok(val)
     }
 
+    /// Parses attributes before a function, like `#[inline] #[cold, pure]`.
+    fn parse_attrs(&mut self) -> Result<Vec<Ident>, ParseError> {
+        let mut attrs = vec![];
+        while is_next!(self, Token::Hash) {
+            expect!(self, Token::Hash)?;
+            expect!(self, Token::LBracket)?;
+            attrs.extend(separated!(
+                self.parse_ident(),
+                is_next!(self, Token::Comma),
+                expect!(self, Token::Comma)
+            ));
+            expect!(self, Token::RBracket)?;
+        }
+        Ok(attrs)
+    }
+
     fn parse_fn_decl(&mut self) -> Result<FuncStmt, ParseError> {
+        let attrs = self.parse_attrs()?;
         let (_, _start_span) = expect!(self, Token::FnKw)?;
         let fn_name = self.parse_ident()?;
 
@@ -487,6 +615,7 @@ where
         let span = _start_span + body.span;
 
         Ok(FuncStmt {
+            attrs,
             name: fn_name,
             params,
             ret_ty,
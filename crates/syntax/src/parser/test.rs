@@ -0,0 +1,61 @@
+use crate::{
+    feature::{Feature, Features},
+    parse_with_features,
+};
+
+fn error(input: &str, features: &Features) -> String {
+    parse_with_features(input, features)
+        .unwrap_err()
+        .kind
+        .to_string()
+}
+
+#[test]
+fn test_feature_levels() {
+    let looping = "fn main() -> int { while 1 { break; } return 0; }";
+    assert_eq!(
+        error(looping, &Features::at_level(2)),
+        "feature `break-continue` is not available at level 2, it requires level 3"
+    );
+    parse_with_features(looping, &Features::at_level(3)).unwrap();
+
+    let fn_type = "fn main() -> int { let f: fn(int) -> int; return 0; }";
+    assert_eq!(
+        error(fn_type, &Features::at_level(4)),
+        "feature `fn-type` is not available at level 4, it requires level 5"
+    );
+    parse_with_features(fn_type, &Features::at_level(5)).unwrap();
+
+    let attrs = "#[inline] fn main() -> int { return 0; }";
+    assert_eq!(
+        error(attrs, &Features::at_level(4)),
+        "feature `attrs` is not available at level 4, it requires level 5"
+    );
+    parse_with_features(attrs, &Features::at_level(5)).unwrap();
+}
+
+#[test]
+fn test_enable_disable_features() {
+    let looping = "fn main() -> int { while 1 { continue; } return 0; }";
+    let mut features = Features::at_level(2);
+    features.enable(Feature::BreakContinue);
+    parse_with_features(looping, &features).unwrap();
+
+    let fn_type = "fn main() -> int { let f: fn(int) -> int; return 0; }";
+    let mut features = Features::at_level(5);
+    features.disable(Feature::FnType);
+    assert_eq!(error(fn_type, &features), "feature `fn-type` is disabled");
+    features.enable(Feature::FnType);
+    parse_with_features(fn_type, &features).unwrap();
+}
+
+#[test]
+fn test_disabled_feature_in_list() {
+    // Parameter lists may be empty, but a disabled feature inside one must not
+    // be taken as the end of the list
+    let input = "fn apply(f: fn(int) -> int, x: int) -> int { return f(x); }";
+    assert_eq!(
+        error(input, &Features::at_level(4)),
+        "feature `fn-type` is not available at level 4, it requires level 5"
+    );
+}
@@ -1,8 +1,10 @@
 pub mod formatter;
 
-use logos::{Lexer, Logos};
+use logos::{Filter, Lexer, Logos};
 use smol_str::SmolStr;
 
+use crate::lexer::LexError;
+
 fn parse_string_literal(i: &mut Lexer<Token>) -> Option<String> {
     unescape::unescape(&i.slice()[1..i.slice().len() - 1])
     // Some(i.slice().into())
@@ -12,6 +14,52 @@ fn parse_char_literal(i: &mut Lexer<Token>) -> Option<char> {
     unescape::unescape(&i.slice()[1..i.slice().len() - 1]).and_then(|x| x.chars().next())
 }
 
+/// Parses an integer literal with an optional radix prefix (`0x`, `0o`, `0b`)
+/// and `_` separators. Returns `None` on invalid digits or overflow; the
+/// reason is recovered by [`LexError::classify`].
+pub(crate) fn parse_int(s: &str) -> Option<u64> {
+    let (radix, digits) = match s.get(..2) {
+        Some("0x") | Some("0X") => (16, &s[2..]),
+        Some("0o") | Some("0O") => (8, &s[2..]),
+        Some("0b") | Some("0B") => (2, &s[2..]),
+        _ => (10, s),
+    };
+    let mut has_digit = false;
+    let mut val = 0u64;
+    for c in digits.chars() {
+        if c == '_' {
+            continue;
+        }
+        let digit = c.to_digit(radix)?;
+        has_digit = true;
+        val = val.checked_mul(radix as u64)?.checked_add(digit as u64)?;
+    }
+    if has_digit {
+        Some(val)
+    } else {
+        None
+    }
+}
+
+fn parse_int_literal(i: &mut Lexer<Token>) -> Option<u64> {
+    parse_int(i.slice())
+}
+
+/// Skips a block comment. Unterminated comments consume the rest of the
+/// input and become an error token.
+fn skip_block_comment(i: &mut Lexer<Token>) -> Filter<()> {
+    match i.remainder().find("*/") {
+        Some(end) => {
+            i.bump(end + 2);
+            Filter::Skip
+        }
+        None => {
+            i.bump(i.remainder().len());
+            Filter::Emit(())
+        }
+    }
+}
+
 #[derive(Debug, Clone, Logos)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Token {
@@ -35,8 +83,11 @@ pub enum Token {
     BreakKw,
     #[token("continue")]
     ContinueKw,
+    #[token("import")]
+    ImportKw,
 
-    #[regex(r"\d+", |lex| lex.slice().parse())]
+    #[regex(r"\d[\d_]*", parse_int_literal)]
+    #[regex(r"0[xXoObB][0-9a-zA-Z_]*", parse_int_literal)]
     UIntLiteral(u64),
     #[regex(r"\d+\.\d+([eE][+-]?\d+)?", |lex| lex.slice().parse())]
     FloatLiteral(f64),
@@ -46,6 +97,9 @@ pub enum Token {
     StringLiteral(String),
     #[regex(r"[_a-zA-Z][_a-zA-Z0-9]*", |lex| SmolStr::new(lex.slice()))]
     Ident(SmolStr),
+    /// A loop label like `'outer`, storing the name without the quote.
+    #[regex(r"'[_a-zA-Z][_a-zA-Z0-9]*", |lex| SmolStr::new(&lex.slice()[1..]))]
+    Label(SmolStr),
 
     #[token(r"+")]
     Plus,
@@ -77,6 +131,12 @@ pub enum Token {
     LBrace,
     #[token("}")]
     RBrace,
+    #[token("[")]
+    LBracket,
+    #[token("]")]
+    RBracket,
+    #[token("#")]
+    Hash,
     #[token(r"->")]
     Arrow,
     #[token(r",")]
@@ -89,9 +149,14 @@ pub enum Token {
     // Empty stuff
     #[regex(r"\s+", logos::skip, priority = 1)]
     Whitespace,
-    #[regex(r"//.*\n", logos::skip)]
+    #[regex(r"//[^\n]*", logos::skip)]
+    #[token("/*", skip_block_comment)]
     Comment,
 
+    /// A piece of source code that is not a valid token. This token is never
+    /// produced by the lexer directly; see [`spanned_lexer`](crate::lexer::spanned_lexer).
+    LexError(LexError),
+
     // Error token
     #[error]
     Error,
@@ -112,6 +177,13 @@ impl Token {
         }
     }
 
+    pub fn get_label_owned(self) -> Option<SmolStr> {
+        match self {
+            Token::Label(l) => Some(l),
+            _ => None,
+        }
+    }
+
     pub fn get_uint(&self) -> Option<u64> {
         match self {
             Token::UIntLiteral(i) => Some(*i),
@@ -28,11 +28,13 @@ impl Display for Token {
             Token::ReturnKw => {"return"}
             Token::BreakKw => {"break"}
             Token::ContinueKw => {"continue"}
+            Token::ImportKw => {"import"}
             Token::UIntLiteral(i) => {"uint {}",i}
             Token::FloatLiteral(i) => {"float {}", i}
             Token::CharLiteral(c) => {"char {}", c}
             Token::StringLiteral(s) => {"string {}", s}
             Token::Ident(id) => {"ident {}", id}
+            Token::Label(l) => {"label '{}", l}
             Token::Plus => {"plus"}
             Token::Minus => {"minus"}
             Token::Mul => {"mul"}
@@ -48,12 +50,16 @@ impl Display for Token {
             Token::RParen => {"rparen"}
             Token::LBrace => {"lbrace"}
             Token::RBrace => {"rbrace"}
+            Token::LBracket => {"lbracket"}
+            Token::RBracket => {"rbracket"}
+            Token::Hash => {"hash"}
             Token::Arrow => {"arrow"}
             Token::Comma => {"comma"}
             Token::Colon => {"colon"}
             Token::Semicolon => {"semicolon"}
             Token::Whitespace => {"WS"}
             Token::Comment => {"comment"}
+            Token::LexError(e) => {"err {}", e}
             Token::Error => {"err"}
         }
     }
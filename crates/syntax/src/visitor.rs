@@ -21,20 +21,12 @@ pub trait AstVisitor {
     type FuncResult;
 
     fn visit_program(&mut self, program: &Program) -> Self::ProgramResult {
-        for decl in &program.decls {
-            self.visit_decl_stmt(decl);
-        }
-        for func in &program.funcs {
-            self.visit_func(func);
-        }
+        walk_program(self, program);
         todo!("Visit program")
     }
 
     fn visit_func(&mut self, func: &FuncStmt) -> Self::FuncResult {
-        for param in &func.params {
-            self.visit_func_param(param);
-        }
-        self.visit_block_stmt(&func.body);
+        walk_func(self, func);
         todo!("Visit function")
     }
 
@@ -59,8 +51,7 @@ pub trait AstVisitor {
     }
 
     fn visit_assign_expr(&mut self, expr: &AssignExpr) -> Self::ExprResult {
-        self.visit_lexpr(&expr.lhs);
-        self.visit_expr(&expr.rhs);
+        walk_assign_expr(self, expr);
         todo!("visit")
     }
 
@@ -69,26 +60,22 @@ pub trait AstVisitor {
     }
 
     fn visit_binary_expr(&mut self, expr: &BinaryExpr) -> Self::ExprResult {
-        self.visit_expr(&expr.lhs);
-        self.visit_expr(&expr.rhs);
+        walk_binary_expr(self, expr);
         todo!("visit")
     }
 
     fn visit_unary_expr(&mut self, expr: &UnaryExpr) -> Self::ExprResult {
-        self.visit_expr(&expr.expr);
+        walk_unary_expr(self, expr);
         todo!("visit")
     }
 
     fn visit_call_expr(&mut self, expr: &CallExpr) -> Self::ExprResult {
-        for subexpr in &expr.params {
-            self.visit_expr(&subexpr);
-        }
+        walk_call_expr(self, expr);
         todo!("visit")
     }
 
     fn visit_as_expr(&mut self, expr: &AsExpr) -> Self::ExprResult {
-        self.visit_ty(&expr.ty);
-        self.visit_expr(&expr.val);
+        walk_as_expr(self, expr);
         todo!("visit")
     }
 
@@ -97,30 +84,17 @@ pub trait AstVisitor {
     }
 
     fn visit_block_stmt(&mut self, stmt: &BlockStmt) -> Self::StmtResult {
-        for substmt in &stmt.stmts {
-            self.visit_stmt(substmt);
-        }
+        walk_block_stmt(self, stmt);
         todo!("visit")
     }
 
     fn visit_while_stmt(&mut self, stmt: &WhileStmt) -> Self::StmtResult {
-        self.visit_expr(&stmt.cond);
-        self.visit_block_stmt(&stmt.body);
+        walk_while_stmt(self, stmt);
         todo!("visit")
     }
 
     fn visit_if_stmt(&mut self, stmt: &IfStmt) -> Self::StmtResult {
-        self.visit_expr(&stmt.cond);
-        self.visit_block_stmt(&stmt.if_block);
-        match &stmt.else_block {
-            IfElseBlock::None => {}
-            IfElseBlock::If(stmt) => {
-                self.visit_if_stmt(&stmt);
-            }
-            IfElseBlock::Block(blk) => {
-                self.visit_block_stmt(&blk);
-            }
-        }
+        walk_if_stmt(self, stmt);
         todo!("visit")
     }
 
@@ -130,25 +104,20 @@ pub trait AstVisitor {
     }
 
     fn visit_decl_stmt(&mut self, stmt: &DeclStmt) -> Self::StmtResult {
-        self.visit_ty(&stmt.ty);
-        if let Some(expr) = &stmt.val {
-            self.visit_expr(expr);
-        }
+        walk_decl_stmt(self, stmt);
         todo!("visit")
     }
 
     fn visit_return_stmt(&mut self, stmt: &ReturnStmt) -> Self::StmtResult {
-        if let Some(res) = &stmt.val {
-            self.visit_expr(&res);
-        }
+        walk_return_stmt(self, stmt);
         todo!("visit")
     }
 
-    fn visit_break_stmt(&mut self, _span: Span) -> Self::StmtResult {
+    fn visit_break_stmt(&mut self, _stmt: &JumpStmt) -> Self::StmtResult {
         todo!("visit")
     }
 
-    fn visit_continue_stmt(&mut self, _span: Span) -> Self::StmtResult {
+    fn visit_continue_stmt(&mut self, _stmt: &JumpStmt) -> Self::StmtResult {
         todo!("visit")
     }
 
@@ -157,6 +126,109 @@ pub trait AstVisitor {
     }
 }
 
+/// A visitor that only looks at the tree and produces no results.
+///
+/// Every type implementing this trait is an [`AstVisitor`] that walks the whole
+/// tree, calling the `inspect_*` method of a node before visiting its children.
+pub trait AstInspector {
+    fn inspect_block_stmt(&mut self, _stmt: &BlockStmt) {}
+    fn inspect_if_stmt(&mut self, _stmt: &IfStmt) {}
+    fn inspect_while_stmt(&mut self, _stmt: &WhileStmt) {}
+    fn inspect_expr(&mut self, _expr: &Expr) {}
+    fn inspect_ty(&mut self, _ty: &TyDef) {}
+}
+
+impl<T: AstInspector + ?Sized> AstVisitor for T {
+    type LExprResult = ();
+    type ExprResult = ();
+    type TyResult = ();
+    type StmtResult = ();
+    type ProgramResult = ();
+    type FuncResult = ();
+
+    fn visit_program(&mut self, program: &Program) {
+        walk_program(self, program)
+    }
+
+    fn visit_func(&mut self, func: &FuncStmt) {
+        walk_func(self, func)
+    }
+
+    fn visit_func_param(&mut self, param: &FuncParam) {
+        self.visit_ty(&param.ty)
+    }
+
+    fn visit_ty(&mut self, ty: &TyDef) {
+        self.inspect_ty(ty)
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        self.inspect_expr(expr);
+        walk_expr(self, expr)
+    }
+
+    fn visit_literal_expr(&mut self, _expr: &LiteralExpr) {}
+
+    fn visit_ident_expr(&mut self, _expr: &Ident) {}
+
+    fn visit_assign_expr(&mut self, expr: &AssignExpr) {
+        walk_assign_expr(self, expr)
+    }
+
+    fn visit_lexpr(&mut self, expr: &Expr) {
+        self.visit_expr(expr)
+    }
+
+    fn visit_binary_expr(&mut self, expr: &BinaryExpr) {
+        walk_binary_expr(self, expr)
+    }
+
+    fn visit_unary_expr(&mut self, expr: &UnaryExpr) {
+        walk_unary_expr(self, expr)
+    }
+
+    fn visit_call_expr(&mut self, expr: &CallExpr) {
+        walk_call_expr(self, expr)
+    }
+
+    fn visit_as_expr(&mut self, expr: &AsExpr) {
+        walk_as_expr(self, expr)
+    }
+
+    fn visit_block_stmt(&mut self, stmt: &BlockStmt) {
+        self.inspect_block_stmt(stmt);
+        walk_block_stmt(self, stmt)
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &WhileStmt) {
+        self.inspect_while_stmt(stmt);
+        walk_while_stmt(self, stmt)
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &IfStmt) {
+        self.inspect_if_stmt(stmt);
+        walk_if_stmt(self, stmt)
+    }
+
+    fn visit_expr_stmt(&mut self, stmt: &Expr) {
+        self.visit_expr(stmt)
+    }
+
+    fn visit_decl_stmt(&mut self, stmt: &DeclStmt) {
+        walk_decl_stmt(self, stmt)
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &ReturnStmt) {
+        walk_return_stmt(self, stmt)
+    }
+
+    fn visit_break_stmt(&mut self, _stmt: &JumpStmt) {}
+
+    fn visit_continue_stmt(&mut self, _stmt: &JumpStmt) {}
+
+    fn visit_empty_stmt(&mut self, _span: Span) {}
+}
+
 walk! { walk_stmt(v, stmt: Stmt) -> V::StmtResult {
     match stmt {
         Stmt::Block(b) => v.visit_block_stmt(b),
@@ -165,8 +237,8 @@ walk! { walk_stmt(v, stmt: Stmt) -> V::StmtResult {
         Stmt::Expr(s) => {v.visit_expr_stmt(s)}
         Stmt::Decl(s) => {v.visit_decl_stmt(s)}
         Stmt::Return(s) => {v.visit_return_stmt(s)}
-        Stmt::Break(s) => {v.visit_break_stmt(*s)}
-        Stmt::Continue(s) => {v.visit_continue_stmt(*s)}
+        Stmt::Break(s) => {v.visit_break_stmt(s)}
+        Stmt::Continue(s) => {v.visit_continue_stmt(s)}
         Stmt::Empty(s) => {v.visit_empty_stmt(*s)}
     }
 }}
@@ -182,3 +254,85 @@ walk! { walk_expr(v, expr: Expr) -> V::ExprResult{
         Expr::Unary(x)=>{v.visit_unary_expr(x)}
     }
 }}
+
+// The functions below visit the children of a node, discarding their results.
+
+walk! { walk_program(v, program: Program) {
+    for decl in &program.decls {
+        v.visit_decl_stmt(decl);
+    }
+    for func in &program.funcs {
+        v.visit_func(func);
+    }
+}}
+
+walk! { walk_func(v, func: FuncStmt) {
+    for param in &func.params {
+        v.visit_func_param(param);
+    }
+    v.visit_ty(&func.ret_ty);
+    v.visit_block_stmt(&func.body);
+}}
+
+walk! { walk_assign_expr(v, expr: AssignExpr) {
+    v.visit_lexpr(&expr.lhs);
+    v.visit_expr(&expr.rhs);
+}}
+
+walk! { walk_binary_expr(v, expr: BinaryExpr) {
+    v.visit_expr(&expr.lhs);
+    v.visit_expr(&expr.rhs);
+}}
+
+walk! { walk_unary_expr(v, expr: UnaryExpr) {
+    v.visit_expr(&expr.expr);
+}}
+
+walk! { walk_call_expr(v, expr: CallExpr) {
+    for subexpr in &expr.params {
+        v.visit_expr(subexpr);
+    }
+}}
+
+walk! { walk_as_expr(v, expr: AsExpr) {
+    v.visit_ty(&expr.ty);
+    v.visit_expr(&expr.val);
+}}
+
+walk! { walk_block_stmt(v, stmt: BlockStmt) {
+    for substmt in &stmt.stmts {
+        v.visit_stmt(substmt);
+    }
+}}
+
+walk! { walk_while_stmt(v, stmt: WhileStmt) {
+    v.visit_expr(&stmt.cond);
+    v.visit_block_stmt(&stmt.body);
+}}
+
+walk! { walk_if_stmt(v, stmt: IfStmt) {
+    v.visit_expr(&stmt.cond);
+    v.visit_block_stmt(&stmt.if_block);
+    match &stmt.else_block {
+        IfElseBlock::None => {}
+        IfElseBlock::If(stmt) => {
+            v.visit_if_stmt(stmt);
+        }
+        IfElseBlock::Block(blk) => {
+            v.visit_block_stmt(blk);
+        }
+    }
+}}
+
+walk! { walk_decl_stmt(v, stmt: DeclStmt) {
+    v.visit_ty(&stmt.ty);
+    if let Some(expr) = &stmt.val {
+        v.visit_expr(expr);
+    }
+}}
+
+walk! { walk_return_stmt(v, stmt: ReturnStmt) {
+    if let Some(res) = &stmt.val {
+        v.visit_expr(res);
+    }
+}}
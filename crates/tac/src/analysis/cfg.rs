@@ -0,0 +1,124 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{BBId, TacFunc};
+
+use super::{Analysis, AnalysisManager};
+
+/// The control flow graph of a function.
+///
+/// Only basic blocks inside the function's list of basic blocks are included,
+/// and branches to other basic blocks are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct Cfg {
+    entry: Option<BBId>,
+    /// Basic blocks in order.
+    blocks: Vec<BBId>,
+    succs: HashMap<BBId, Vec<BBId>>,
+    preds: HashMap<BBId, Vec<BBId>>,
+}
+
+impl Cfg {
+    pub fn new(func: &TacFunc) -> Cfg {
+        let mut blocks = vec![];
+        let mut seen = HashSet::new();
+        for (bb, _) in func.bb_iter() {
+            // A broken list may loop back, and we would never stop
+            if !seen.insert(bb) {
+                break;
+            }
+            blocks.push(bb);
+        }
+
+        let mut succs: HashMap<_, Vec<_>> = HashMap::new();
+        let mut preds: HashMap<_, Vec<_>> = HashMap::new();
+        for &bb in &blocks {
+            succs.entry(bb).or_default();
+            preds.entry(bb).or_default();
+        }
+        for &bb in &blocks {
+            for target in func.bb_get(bb).branch.target_iter() {
+                if !seen.contains(&target) || succs[&bb].contains(&target) {
+                    continue;
+                }
+                succs.get_mut(&bb).unwrap().push(target);
+                preds.get_mut(&target).unwrap().push(bb);
+            }
+        }
+
+        Cfg {
+            entry: func.starting_block().filter(|bb| seen.contains(bb)),
+            blocks,
+            succs,
+            preds,
+        }
+    }
+
+    /// The starting block of the function.
+    pub fn entry(&self) -> Option<BBId> {
+        self.entry
+    }
+
+    /// All basic blocks, in order.
+    pub fn blocks(&self) -> &[BBId] {
+        &self.blocks
+    }
+
+    pub fn contains(&self, bb: BBId) -> bool {
+        self.succs.contains_key(&bb)
+    }
+
+    /// Successors of `bb`. Every successor appears once.
+    pub fn succs(&self, bb: BBId) -> &[BBId] {
+        &self.succs[&bb]
+    }
+
+    /// Predecessors of `bb`. Every predecessor appears once.
+    pub fn preds(&self, bb: BBId) -> &[BBId] {
+        &self.preds[&bb]
+    }
+
+    /// Basic blocks reachable from the entry, in reverse post order.
+    pub fn reverse_post_order(&self) -> Vec<BBId> {
+        match self.entry {
+            Some(entry) => reverse_post_order(entry, |bb| self.succs(bb).iter().copied()),
+            None => vec![],
+        }
+    }
+}
+
+impl Analysis for Cfg {
+    fn compute(func: &TacFunc, _am: &mut AnalysisManager) -> Self {
+        Cfg::new(func)
+    }
+}
+
+/// Returns nodes reachable from `root` in reverse post order.
+pub(super) fn reverse_post_order<T, F, I>(root: T, succs: F) -> Vec<T>
+where
+    T: Copy + Eq + std::hash::Hash,
+    F: Fn(T) -> I,
+    I: IntoIterator<Item = T>,
+{
+    let mut visited = HashSet::new();
+    let mut post_order = vec![];
+    // (node, whether its successors are pushed)
+    let mut stack = vec![(root, false)];
+    while let Some((node, expanded)) = stack.pop() {
+        if expanded {
+            post_order.push(node);
+            continue;
+        }
+        if !visited.insert(node) {
+            continue;
+        }
+        stack.push((node, true));
+        let next = succs(node).into_iter().collect::<Vec<_>>();
+        for &next in next.iter().rev() {
+            if !visited.contains(&next) {
+                stack.push((next, false));
+            }
+        }
+    }
+    post_order.reverse();
+    post_order
+}
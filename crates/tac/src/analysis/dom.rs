@@ -0,0 +1,324 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::{BBId, TacFunc};
+
+use super::{cfg::reverse_post_order, Analysis, AnalysisManager, Cfg};
+
+/// A tree of nodes with one or more roots, numbered for constant-time
+/// ancestor queries.
+#[derive(Debug, Clone, Default)]
+struct Tree {
+    parent: HashMap<BBId, BBId>,
+    children: HashMap<BBId, Vec<BBId>>,
+    roots: Vec<BBId>,
+    /// (preorder, postorder) numbers of every node in the tree
+    order: HashMap<BBId, (usize, usize)>,
+}
+
+impl Tree {
+    /// Build a tree from nodes in reverse post order, and the parent of every
+    /// node that is not a root.
+    fn new(nodes: &[BBId], parent: HashMap<BBId, BBId>) -> Tree {
+        let mut children: HashMap<BBId, Vec<BBId>> = HashMap::new();
+        let mut roots = vec![];
+        for &node in nodes {
+            children.entry(node).or_default();
+            match parent.get(&node) {
+                Some(&p) => children.entry(p).or_default().push(node),
+                None => roots.push(node),
+            }
+        }
+
+        let mut order: HashMap<BBId, (usize, usize)> = HashMap::new();
+        let mut counter = 0;
+        // (node, whether its children are visited)
+        let mut stack = roots.iter().rev().map(|&r| (r, false)).collect::<Vec<_>>();
+        while let Some((node, visited)) = stack.pop() {
+            if visited {
+                if let Some(o) = order.get_mut(&node) {
+                    o.1 = counter;
+                }
+                counter += 1;
+                continue;
+            }
+            order.insert(node, (counter, 0));
+            counter += 1;
+            stack.push((node, true));
+            stack.extend(children[&node].iter().rev().map(|&c| (c, false)));
+        }
+
+        Tree {
+            parent,
+            children,
+            roots,
+            order,
+        }
+    }
+
+    fn contains(&self, node: BBId) -> bool {
+        self.order.contains_key(&node)
+    }
+
+    fn is_ancestor(&self, a: BBId, b: BBId) -> bool {
+        match (self.order.get(&a), self.order.get(&b)) {
+            (Some(a), Some(b)) => a.0 <= b.0 && b.1 <= a.1,
+            _ => false,
+        }
+    }
+
+    fn children(&self, node: BBId) -> &[BBId] {
+        self.children.get(&node).map_or(&[], |c| c.as_slice())
+    }
+}
+
+/// Computes immediate dominators with the algorithm by Cooper, Harvey and
+/// Kennedy. `nodes` must be in reverse post order starting from the root, and
+/// `preds` returns the predecessors of a node. The root has no immediate
+/// dominator.
+fn compute_idoms<F, I>(nodes: &[Option<BBId>], preds: F) -> HashMap<Option<BBId>, Option<BBId>>
+where
+    F: Fn(Option<BBId>) -> I,
+    I: IntoIterator<Item = Option<BBId>>,
+{
+    let index = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, &n)| (n, i))
+        .collect::<HashMap<_, _>>();
+    let preds = nodes
+        .iter()
+        .map(|&n| {
+            preds(n)
+                .into_iter()
+                .filter_map(|p| index.get(&p).copied())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let mut idom = vec![None; nodes.len()];
+    if !nodes.is_empty() {
+        idom[0] = Some(0);
+    }
+    let intersect = |idom: &[Option<usize>], mut a: usize, mut b: usize| {
+        while a != b {
+            while a > b {
+                a = idom[a].unwrap();
+            }
+            while b > a {
+                b = idom[b].unwrap();
+            }
+        }
+        a
+    };
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for i in 1..nodes.len() {
+            let mut new_idom = None;
+            for &p in &preds[i] {
+                if idom[p].is_none() {
+                    continue;
+                }
+                new_idom = Some(match new_idom {
+                    None => p,
+                    Some(d) => intersect(&idom, p, d),
+                });
+            }
+            if new_idom != idom[i] {
+                idom[i] = new_idom;
+                changed = true;
+            }
+        }
+    }
+
+    nodes
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter_map(|(i, &n)| idom[i].map(|d| (n, nodes[d])))
+        .collect()
+}
+
+/// The dominator tree of a function. Only basic blocks reachable from the
+/// entry are included.
+#[derive(Debug, Clone, Default)]
+pub struct DomTree {
+    tree: Tree,
+    rpo: Vec<BBId>,
+}
+
+impl DomTree {
+    pub fn new(cfg: &Cfg) -> DomTree {
+        let rpo = cfg.reverse_post_order();
+        let nodes = rpo.iter().map(|&bb| Some(bb)).collect::<Vec<_>>();
+        let idoms = compute_idoms(&nodes, |bb| cfg.preds(bb.unwrap()).iter().map(|&p| Some(p)));
+        let parent = idoms
+            .into_iter()
+            .map(|(bb, idom)| (bb.unwrap(), idom.unwrap()))
+            .collect();
+        DomTree {
+            tree: Tree::new(&rpo, parent),
+            rpo,
+        }
+    }
+
+    /// The entry block, which is the root of the tree.
+    pub fn root(&self) -> Option<BBId> {
+        self.rpo.first().copied()
+    }
+
+    /// The immediate dominator of `bb`, if `bb` is reachable and not the entry.
+    pub fn idom(&self, bb: BBId) -> Option<BBId> {
+        self.tree.parent.get(&bb).copied()
+    }
+
+    /// Basic blocks immediately dominated by `bb`.
+    pub fn children(&self, bb: BBId) -> &[BBId] {
+        self.tree.children(bb)
+    }
+
+    pub fn is_reachable(&self, bb: BBId) -> bool {
+        self.tree.contains(bb)
+    }
+
+    /// Whether `a` dominates `b`. Every reachable block dominates itself.
+    pub fn dominates(&self, a: BBId, b: BBId) -> bool {
+        self.tree.is_ancestor(a, b)
+    }
+
+    pub fn strictly_dominates(&self, a: BBId, b: BBId) -> bool {
+        a != b && self.dominates(a, b)
+    }
+
+    /// Reachable basic blocks in reverse post order of the control flow graph.
+    pub fn reverse_post_order(&self) -> &[BBId] {
+        &self.rpo
+    }
+}
+
+impl Analysis for DomTree {
+    fn compute(func: &TacFunc, am: &mut AnalysisManager) -> Self {
+        DomTree::new(&am.get::<Cfg>(func))
+    }
+}
+
+/// The post-dominator tree of a function.
+///
+/// Blocks without successors are exits of the function, and are the roots of
+/// the tree. Blocks that can't reach any exit are not included.
+#[derive(Debug, Clone, Default)]
+pub struct PostDomTree {
+    tree: Tree,
+}
+
+impl PostDomTree {
+    pub fn new(cfg: &Cfg) -> PostDomTree {
+        let exits = cfg
+            .blocks()
+            .iter()
+            .copied()
+            .filter(|&bb| cfg.succs(bb).is_empty())
+            .collect::<Vec<_>>();
+
+        // Walk the reversed graph from a virtual exit, represented as `None`
+        let mut rev_succs: HashMap<Option<BBId>, Vec<Option<BBId>>> = HashMap::new();
+        let mut rev_preds: HashMap<Option<BBId>, Vec<Option<BBId>>> = HashMap::new();
+        rev_succs.insert(None, exits.iter().map(|&bb| Some(bb)).collect());
+        for &bb in cfg.blocks() {
+            rev_succs.insert(Some(bb), cfg.preds(bb).iter().map(|&p| Some(p)).collect());
+            let mut succs = cfg.succs(bb).iter().map(|&s| Some(s)).collect::<Vec<_>>();
+            if succs.is_empty() {
+                succs.push(None);
+            }
+            rev_preds.insert(Some(bb), succs);
+        }
+        let rpo = reverse_post_order(None, |bb| rev_succs[&bb].iter().copied());
+        let idoms = compute_idoms(&rpo, |bb| {
+            rev_preds
+                .get(&bb)
+                .into_iter()
+                .flat_map(|p| p.iter().copied())
+        });
+
+        let nodes = rpo.iter().filter_map(|&bb| bb).collect::<Vec<_>>();
+        let parent = idoms
+            .into_iter()
+            .filter_map(|(bb, idom)| Some((bb?, idom?)))
+            .collect();
+        PostDomTree {
+            tree: Tree::new(&nodes, parent),
+        }
+    }
+
+    /// Exits of the function.
+    pub fn roots(&self) -> &[BBId] {
+        &self.tree.roots
+    }
+
+    /// The immediate post-dominator of `bb`, if it has one.
+    pub fn ipdom(&self, bb: BBId) -> Option<BBId> {
+        self.tree.parent.get(&bb).copied()
+    }
+
+    /// Basic blocks immediately post-dominated by `bb`.
+    pub fn children(&self, bb: BBId) -> &[BBId] {
+        self.tree.children(bb)
+    }
+
+    /// Whether `a` post-dominates `b`. Every block in the tree post-dominates
+    /// itself.
+    pub fn post_dominates(&self, a: BBId, b: BBId) -> bool {
+        self.tree.is_ancestor(a, b)
+    }
+}
+
+impl Analysis for PostDomTree {
+    fn compute(func: &TacFunc, am: &mut AnalysisManager) -> Self {
+        PostDomTree::new(&am.get::<Cfg>(func))
+    }
+}
+
+/// The dominance frontier of every reachable basic block.
+#[derive(Debug, Clone, Default)]
+pub struct DomFrontier {
+    frontiers: HashMap<BBId, BTreeSet<BBId>>,
+}
+
+impl DomFrontier {
+    pub fn new(cfg: &Cfg, dom: &DomTree) -> DomFrontier {
+        let mut frontiers: HashMap<BBId, BTreeSet<BBId>> = HashMap::new();
+        for &bb in dom.reverse_post_order() {
+            let preds = cfg.preds(bb);
+            if preds.len() < 2 {
+                continue;
+            }
+            let idom = dom.idom(bb);
+            for &pred in preds.iter().filter(|&&p| dom.is_reachable(p)) {
+                let mut runner = Some(pred);
+                while runner.is_some() && runner != idom {
+                    let r = runner.unwrap();
+                    frontiers.entry(r).or_default().insert(bb);
+                    runner = dom.idom(r);
+                }
+            }
+        }
+        DomFrontier { frontiers }
+    }
+
+    /// The dominance frontier of `bb`.
+    pub fn frontier(&self, bb: BBId) -> impl Iterator<Item = BBId> + '_ {
+        self.frontiers
+            .get(&bb)
+            .into_iter()
+            .flat_map(|f| f.iter().copied())
+    }
+}
+
+impl Analysis for DomFrontier {
+    fn compute(func: &TacFunc, am: &mut AnalysisManager) -> Self {
+        let cfg = am.get::<Cfg>(func);
+        let dom = am.get::<DomTree>(func);
+        DomFrontier::new(&cfg, &dom)
+    }
+}
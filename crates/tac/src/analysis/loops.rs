@@ -0,0 +1,162 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::{BBId, TacFunc};
+
+use super::{Analysis, AnalysisManager, Cfg, DomTree};
+
+/// Index of a loop in a [`LoopForest`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LoopId(usize);
+
+/// A natural loop.
+#[derive(Debug, Clone)]
+pub struct Loop {
+    /// The only entry of the loop, which dominates every block in it.
+    pub header: BBId,
+    /// Blocks inside the loop jumping back to the header.
+    pub latches: BTreeSet<BBId>,
+    /// All blocks inside the loop, including the header and inner loops.
+    pub blocks: BTreeSet<BBId>,
+    /// Blocks outside the loop that are jumped to from inside the loop.
+    pub exits: BTreeSet<BBId>,
+    /// The innermost loop containing this one.
+    pub parent: Option<LoopId>,
+    /// Loops immediately nested inside this one.
+    pub children: Vec<LoopId>,
+    /// Nesting depth, starting from 1 for outermost loops.
+    pub depth: usize,
+}
+
+impl Loop {
+    pub fn contains(&self, bb: BBId) -> bool {
+        self.blocks.contains(&bb)
+    }
+}
+
+/// Natural loops of a function, organized by nesting.
+///
+/// Loops are found from back edges, i.e. edges to a block that dominates the
+/// source. Back edges to the same header make up one loop. Irreducible cycles
+/// have no such header and are not reported.
+#[derive(Debug, Clone, Default)]
+pub struct LoopForest {
+    /// Loops, ordered by their headers in reverse post order. Outer loops
+    /// always come before inner ones.
+    loops: Vec<Loop>,
+    /// The innermost loop containing each basic block.
+    innermost: HashMap<BBId, LoopId>,
+}
+
+impl LoopForest {
+    pub fn new(cfg: &Cfg, dom: &DomTree) -> LoopForest {
+        let mut loops = vec![];
+        for &header in dom.reverse_post_order() {
+            let latches = cfg
+                .preds(header)
+                .iter()
+                .copied()
+                .filter(|&pred| dom.dominates(header, pred))
+                .collect::<BTreeSet<_>>();
+            if latches.is_empty() {
+                continue;
+            }
+
+            // Every block reaching a latch without passing the header
+            let mut blocks = BTreeSet::new();
+            blocks.insert(header);
+            let mut stack = latches.iter().copied().collect::<Vec<_>>();
+            while let Some(bb) = stack.pop() {
+                if !blocks.insert(bb) {
+                    continue;
+                }
+                stack.extend(
+                    cfg.preds(bb)
+                        .iter()
+                        .copied()
+                        .filter(|&p| dom.is_reachable(p) && !blocks.contains(&p)),
+                );
+            }
+
+            let exits = blocks
+                .iter()
+                .flat_map(|&bb| cfg.succs(bb).iter().copied())
+                .filter(|succ| !blocks.contains(succ))
+                .collect();
+
+            loops.push(Loop {
+                header,
+                latches,
+                blocks,
+                exits,
+                parent: None,
+                children: vec![],
+                depth: 1,
+            });
+        }
+
+        // An outer loop's header dominates inner loops' headers, so it comes
+        // earlier in reverse post order. The parent of a loop is thus the
+        // last loop before it containing its header.
+        for i in 0..loops.len() {
+            let header = loops[i].header;
+            let parent = (0..i).rev().find(|&j| loops[j].contains(header));
+            if let Some(parent) = parent {
+                loops[i].parent = Some(LoopId(parent));
+                loops[i].depth = loops[parent].depth + 1;
+                loops[parent].children.push(LoopId(i));
+            }
+        }
+
+        // Inner loops come later, so they overwrite outer ones
+        let mut innermost = HashMap::new();
+        for (i, l) in loops.iter().enumerate() {
+            for &bb in &l.blocks {
+                innermost.insert(bb, LoopId(i));
+            }
+        }
+
+        LoopForest { loops, innermost }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.loops.is_empty()
+    }
+
+    pub fn get(&self, id: LoopId) -> &Loop {
+        &self.loops[id.0]
+    }
+
+    /// All loops, with outer loops before inner ones.
+    pub fn loops(&self) -> impl Iterator<Item = (LoopId, &Loop)> {
+        self.loops.iter().enumerate().map(|(i, l)| (LoopId(i), l))
+    }
+
+    /// Loops not nested inside other loops.
+    pub fn top_level(&self) -> impl Iterator<Item = (LoopId, &Loop)> {
+        self.loops().filter(|(_, l)| l.parent.is_none())
+    }
+
+    /// The innermost loop containing `bb`.
+    pub fn innermost_loop(&self, bb: BBId) -> Option<LoopId> {
+        self.innermost.get(&bb).copied()
+    }
+
+    /// The loop whose header is `bb`.
+    pub fn loop_of_header(&self, bb: BBId) -> Option<LoopId> {
+        self.innermost_loop(bb)
+            .filter(|&id| self.get(id).header == bb)
+    }
+
+    /// Number of loops containing `bb`.
+    pub fn loop_depth(&self, bb: BBId) -> usize {
+        self.innermost_loop(bb).map_or(0, |id| self.get(id).depth)
+    }
+}
+
+impl Analysis for LoopForest {
+    fn compute(func: &TacFunc, am: &mut AnalysisManager) -> Self {
+        let cfg = am.get::<Cfg>(func);
+        let dom = am.get::<DomTree>(func);
+        LoopForest::new(&cfg, &dom)
+    }
+}
@@ -0,0 +1,137 @@
+//! Analyses of functions, and a manager caching their results.
+//!
+//! Analyses are requested from an [`AnalysisManager`], which computes them on
+//! demand and keeps the results until a pass invalidates them. Every pass
+//! declares the analyses it preserves through [`PreservedAnalyses`], and the
+//! pipeline drops the other ones after running it.
+
+mod cfg;
+mod dom;
+mod loops;
+#[cfg(test)]
+mod test;
+
+pub use cfg::Cfg;
+pub use dom::{DomFrontier, DomTree, PostDomTree};
+pub use loops::{Loop, LoopForest, LoopId};
+
+use std::{
+    any::{Any, TypeId},
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+use smol_str::SmolStr;
+
+use crate::TacFunc;
+
+/// An analysis of a single function.
+pub trait Analysis: 'static {
+    /// Compute this analysis for `func`. Analyses this one depends on can be
+    /// requested from `am`.
+    fn compute(func: &TacFunc, am: &mut AnalysisManager) -> Self
+    where
+        Self: Sized;
+}
+
+/// Computes and caches analyses of functions.
+///
+/// Results are cached by function name, so a manager should only be used
+/// with one program at a time. Call [`invalidate_all`](Self::invalidate_all)
+/// before switching to another one.
+#[derive(Default)]
+pub struct AnalysisManager {
+    cache: HashMap<SmolStr, HashMap<TypeId, Rc<dyn Any>>>,
+}
+
+impl AnalysisManager {
+    pub fn new() -> AnalysisManager {
+        Self::default()
+    }
+
+    /// Returns analysis `A` of `func`, computing it if it's not cached.
+    pub fn get<A: Analysis>(&mut self, func: &TacFunc) -> Rc<A> {
+        let key = TypeId::of::<A>();
+        if let Some(res) = self.cache.get(&func.name).and_then(|c| c.get(&key)) {
+            return res
+                .clone()
+                .downcast::<A>()
+                .expect("Analyses are keyed by their type");
+        }
+
+        let res = Rc::new(A::compute(func, self));
+        self.cache
+            .entry(func.name.clone())
+            .or_default()
+            .insert(key, res.clone());
+        res
+    }
+
+    /// Whether analysis `A` of function `func` is cached.
+    pub fn is_cached<A: Analysis>(&self, func: &str) -> bool {
+        self.cache
+            .get(func)
+            .is_some_and(|c| c.contains_key(&TypeId::of::<A>()))
+    }
+
+    /// Drop analyses of function `func` that are not preserved.
+    pub fn invalidate(&mut self, func: &str, preserved: &PreservedAnalyses) {
+        if let Some(cache) = self.cache.get_mut(func) {
+            cache.retain(|&key, _| preserved.is_preserved_id(key));
+        }
+    }
+
+    /// Drop analyses of every function that are not preserved.
+    pub fn invalidate_all(&mut self, preserved: &PreservedAnalyses) {
+        for cache in self.cache.values_mut() {
+            cache.retain(|&key, _| preserved.is_preserved_id(key));
+        }
+    }
+}
+
+/// The set of analyses that are still valid after running a pass.
+#[derive(Debug, Clone, Default)]
+pub struct PreservedAnalyses {
+    all: bool,
+    preserved: HashSet<TypeId>,
+}
+
+impl PreservedAnalyses {
+    /// Every analysis is preserved, e.g. for passes not editing the program.
+    pub fn all() -> PreservedAnalyses {
+        PreservedAnalyses {
+            all: true,
+            preserved: HashSet::new(),
+        }
+    }
+
+    /// No analysis is preserved.
+    pub fn none() -> PreservedAnalyses {
+        PreservedAnalyses::default()
+    }
+
+    /// Analyses that only depend on the control flow graph are preserved, for
+    /// passes not changing basic blocks or branches.
+    pub fn cfg() -> PreservedAnalyses {
+        PreservedAnalyses::none()
+            .preserve::<Cfg>()
+            .preserve::<DomTree>()
+            .preserve::<DomFrontier>()
+            .preserve::<PostDomTree>()
+            .preserve::<LoopForest>()
+    }
+
+    /// Mark analysis `A` as preserved.
+    pub fn preserve<A: Analysis>(mut self) -> PreservedAnalyses {
+        self.preserved.insert(TypeId::of::<A>());
+        self
+    }
+
+    pub fn is_preserved<A: Analysis>(&self) -> bool {
+        self.is_preserved_id(TypeId::of::<A>())
+    }
+
+    fn is_preserved_id(&self, id: TypeId) -> bool {
+        self.all || self.preserved.contains(&id)
+    }
+}
@@ -0,0 +1,120 @@
+use crate::{parser::parse_program_from_string, BBId, TacFunc};
+
+use super::{
+    AnalysisManager, Cfg, DomFrontier, DomTree, LoopForest, PostDomTree, PreservedAnalyses,
+};
+
+/// Nested loops:
+///
+/// ```text
+/// bb0 -> bb1 -> bb2 -> bb3 -> bb2
+///         ^             |
+///         |             v
+///         +----------- bb4 -> bb5
+/// ```
+fn nested_loops() -> TacFunc {
+    let input = r"
+    (fn f (i32) i32
+        (bb0 (
+            (%0 i32 param 0))
+            (br bb1))
+        (bb1 ()
+            (br bb2))
+        (bb2 ()
+            (br bb3))
+        (bb3 ()
+            (brif %0 bb2 bb4))
+        (bb4 ()
+            (brif %0 bb1 bb5))
+        (bb5 ()
+            (return %0)))
+    ";
+    let mut program = parse_program_from_string(input).unwrap();
+    program.functions.shift_remove("f").unwrap()
+}
+
+fn blocks(func: &TacFunc) -> Vec<BBId> {
+    func.bb_iter().map(|(bb, _)| bb).collect()
+}
+
+#[test]
+fn test_dominators() {
+    let func = nested_loops();
+    let bb = blocks(&func);
+    let cfg = Cfg::new(&func);
+    let dom = DomTree::new(&cfg);
+
+    assert_eq!(dom.root(), Some(bb[0]));
+    assert_eq!(dom.idom(bb[0]), None);
+    let idoms = (1..6).map(|i| dom.idom(bb[i]).unwrap()).collect::<Vec<_>>();
+    assert_eq!(idoms, vec![bb[0], bb[1], bb[2], bb[3], bb[4]]);
+    assert!(dom.dominates(bb[1], bb[5]));
+    assert!(dom.dominates(bb[3], bb[3]));
+    assert!(!dom.strictly_dominates(bb[3], bb[3]));
+    assert!(!dom.dominates(bb[4], bb[2]));
+
+    let df = DomFrontier::new(&cfg, &dom);
+    let frontier = |b: BBId| df.frontier(b).collect::<Vec<_>>();
+    assert_eq!(frontier(bb[3]), {
+        let mut f = vec![bb[1], bb[2]];
+        f.sort();
+        f
+    });
+    assert_eq!(frontier(bb[4]), vec![bb[1]]);
+    assert_eq!(frontier(bb[0]), vec![]);
+
+    let pdom = PostDomTree::new(&cfg);
+    assert_eq!(pdom.roots(), &[bb[5]]);
+    assert_eq!(pdom.ipdom(bb[0]), Some(bb[1]));
+    assert_eq!(pdom.ipdom(bb[3]), Some(bb[4]));
+    assert!(pdom.post_dominates(bb[4], bb[2]));
+    assert!(!pdom.post_dominates(bb[2], bb[4]));
+}
+
+#[test]
+fn test_loop_forest() {
+    let func = nested_loops();
+    let bb = blocks(&func);
+    let cfg = Cfg::new(&func);
+    let loops = LoopForest::new(&cfg, &DomTree::new(&cfg));
+
+    let (outer_id, outer) = loops.top_level().next().unwrap();
+    assert_eq!(loops.top_level().count(), 1);
+    assert_eq!(outer.header, bb[1]);
+    assert_eq!(
+        outer.latches.iter().copied().collect::<Vec<_>>(),
+        vec![bb[4]]
+    );
+    assert_eq!(outer.blocks.len(), 4);
+    assert_eq!(outer.exits.iter().copied().collect::<Vec<_>>(), vec![bb[5]]);
+
+    let inner = loops.get(outer.children[0]);
+    assert_eq!(inner.header, bb[2]);
+    assert_eq!(inner.parent, Some(outer_id));
+    assert_eq!(inner.depth, 2);
+    assert_eq!(inner.exits.iter().copied().collect::<Vec<_>>(), vec![bb[4]]);
+
+    assert_eq!(loops.loop_depth(bb[0]), 0);
+    assert_eq!(loops.loop_depth(bb[4]), 1);
+    assert_eq!(loops.loop_depth(bb[3]), 2);
+    assert_eq!(loops.loop_of_header(bb[2]), Some(outer.children[0]));
+    assert_eq!(loops.loop_of_header(bb[3]), None);
+}
+
+#[test]
+fn test_analysis_manager() {
+    let func = nested_loops();
+    let mut am = AnalysisManager::new();
+
+    let loops = am.get::<LoopForest>(&func);
+    assert!(am.is_cached::<DomTree>("f"));
+    assert!(std::rc::Rc::ptr_eq(&loops, &am.get::<LoopForest>(&func)));
+
+    am.invalidate("f", &PreservedAnalyses::none().preserve::<DomTree>());
+    assert!(am.is_cached::<DomTree>("f"));
+    assert!(!am.is_cached::<LoopForest>("f"));
+    assert!(!am.is_cached::<Cfg>("f"));
+
+    am.invalidate_all(&PreservedAnalyses::none());
+    assert!(!am.is_cached::<DomTree>("f"));
+}
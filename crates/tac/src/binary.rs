@@ -0,0 +1,661 @@
+//! A compact binary encoding of TAC programs.
+//!
+//! The text representation is slow to parse for large programs. This encoding
+//! is much faster to read and write, and keeps everything inside a program,
+//! including imports and source locations.
+//!
+//! # Format
+//!
+//! Integers are encoded in LEB128, signed for immediates and unsigned for
+//! everything else. Strings are encoded as their length followed by UTF-8 bytes.
+//!
+//! ```plaintext
+//! program  := MAGIC version types imports functions
+//! types    := count type*         ; referred to by their index
+//! type     := 0                   ; unit
+//!           | 1 kind:u8 size:u8   ; numeric, kind 0 is bool and 1 is int
+//!           | 2 ty                ; pointer
+//!           | 3 ty count ty*      ; function with return and parameter types
+//! imports  := count (name ty)*
+//! function := name ty attrs block_count inst_count block*
+//! block    := count inst* branch
+//! inst     := ty line [col] kind  ; line 0 means no source location
+//! ```
+//!
+//! Basic blocks are numbered in order starting from the entry, and
+//! instructions are numbered in the order they appear across all basic blocks.
+//! Functions are kept in order, and imports are sorted by their names.
+
+#[cfg(test)]
+mod test;
+
+use std::collections::{BTreeMap, HashMap};
+
+use smol_str::SmolStr;
+
+use crate::{
+    builder::FuncEditor, err::BinaryError, ty::FuncTy, AllocaInst, BBId, BinaryInst, BinaryOp,
+    Branch, FuncAttr, FunctionCall, IndirectCall, Inst, InstId, InstKind, NumericTy, OffsetInst,
+    Program, SourceLoc, StoreInst, TacFunc, Ty, TyKind, Value,
+};
+
+/// Bytes every encoded program starts with.
+pub const MAGIC: &[u8] = b"\0AZTAC";
+
+/// The version of the encoding written by [`write_program`].
+pub const VERSION: u32 = 1;
+
+const BINARY_OPS: &[BinaryOp] = &[
+    BinaryOp::Add,
+    BinaryOp::Sub,
+    BinaryOp::Mul,
+    BinaryOp::Div,
+    BinaryOp::Lt,
+    BinaryOp::Gt,
+    BinaryOp::Le,
+    BinaryOp::Ge,
+    BinaryOp::Eq,
+    BinaryOp::Ne,
+];
+
+/// Whether `input` looks like an encoded program rather than text.
+pub fn is_binary(input: &[u8]) -> bool {
+    input.starts_with(MAGIC)
+}
+
+/// Encode `program`.
+///
+/// # Panics
+///
+/// Panics if a function refers to instructions or basic blocks outside its
+/// lists of basic blocks and instructions. The [verifier](crate::verifier)
+/// rejects such functions.
+pub fn write_program(program: &Program) -> Vec<u8> {
+    let mut writer = Writer::default();
+
+    let mut imports = program.imports.iter().collect::<Vec<_>>();
+    imports.sort_by(|a, b| a.0.cmp(b.0));
+    writer.uint(imports.len() as u64);
+    for (name, ty) in imports {
+        writer.str(name);
+        writer.ty(ty);
+    }
+
+    writer.uint(program.functions.len() as u64);
+    for func in program.functions.values() {
+        writer.func(func);
+    }
+
+    let mut out = MAGIC.to_vec();
+    write_uint(&mut out, VERSION as u64);
+    write_uint(&mut out, writer.type_ids.len() as u64);
+    out.extend(writer.types);
+    out.extend(writer.body);
+    out
+}
+
+/// Decode a program encoded by [`write_program`].
+pub fn read_program(input: &[u8]) -> Result<Program, BinaryError> {
+    if !is_binary(input) {
+        return Err(BinaryError::NotBinary);
+    }
+    let mut reader = Reader {
+        input,
+        pos: MAGIC.len(),
+        types: vec![],
+    };
+    let version = reader.uint()?;
+    if version != VERSION as u64 {
+        return Err(BinaryError::UnsupportedVersion(version));
+    }
+
+    let type_count = reader.uint()?;
+    for _ in 0..type_count {
+        let ty = reader.type_entry()?;
+        reader.types.push(ty);
+    }
+
+    let mut program = Program::default();
+    for _ in 0..reader.uint()? {
+        let name = reader.str()?;
+        let ty = reader.ty()?;
+        program.imports.insert(name, ty);
+    }
+    for _ in 0..reader.uint()? {
+        let func = reader.func()?;
+        program.functions.insert(func.name.clone(), func);
+    }
+
+    if reader.pos != input.len() {
+        return Err(BinaryError::Malformed("trailing bytes"));
+    }
+    Ok(program)
+}
+
+fn write_uint(out: &mut Vec<u8>, mut val: u64) {
+    loop {
+        let byte = (val & 0x7f) as u8;
+        val >>= 7;
+        if val == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn write_int(out: &mut Vec<u8>, mut val: i64) {
+    loop {
+        let byte = (val & 0x7f) as u8;
+        val >>= 7;
+        let sign_bit = byte & 0x40 != 0;
+        if (val == 0 && !sign_bit) || (val == -1 && sign_bit) {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+#[derive(Default)]
+struct Writer {
+    /// Encoded types table.
+    types: Vec<u8>,
+    type_ids: HashMap<Ty, u64>,
+    /// Everything after the types table.
+    body: Vec<u8>,
+}
+
+/// Numbering of basic blocks and instructions inside a function.
+#[derive(Default)]
+struct Numbering {
+    bbs: HashMap<BBId, u64>,
+    insts: HashMap<InstId, u64>,
+}
+
+impl Numbering {
+    fn bb(&self, bb: BBId) -> u64 {
+        *self
+            .bbs
+            .get(&bb)
+            .expect("Referring to a basic block outside the function")
+    }
+
+    fn inst(&self, inst: InstId) -> u64 {
+        *self
+            .insts
+            .get(&inst)
+            .expect("Referring to an instruction outside the function")
+    }
+}
+
+impl Writer {
+    fn uint(&mut self, val: u64) {
+        write_uint(&mut self.body, val)
+    }
+
+    fn str(&mut self, s: &str) {
+        self.uint(s.len() as u64);
+        self.body.extend(s.as_bytes());
+    }
+
+    fn ty(&mut self, ty: &Ty) {
+        let id = self.type_id(ty);
+        self.uint(id);
+    }
+
+    /// Returns the index of `ty` inside the types table, adding it and the
+    /// types inside it if needed.
+    fn type_id(&mut self, ty: &Ty) -> u64 {
+        if let Some(&id) = self.type_ids.get(ty) {
+            return id;
+        }
+        let mut entry = vec![];
+        match ty {
+            Ty::Unit => entry.push(0),
+            Ty::Numeric(n) => {
+                let kind = match n.kind {
+                    TyKind::Bool => 0,
+                    TyKind::Int => 1,
+                };
+                entry.extend([1, kind, n.size].iter());
+            }
+            Ty::Ptr(inner) => {
+                entry.push(2);
+                write_uint(&mut entry, self.type_id(inner));
+            }
+            Ty::Func(f) => {
+                entry.push(3);
+                write_uint(&mut entry, self.type_id(&f.return_type));
+                write_uint(&mut entry, f.params.len() as u64);
+                for param in &f.params {
+                    write_uint(&mut entry, self.type_id(param));
+                }
+            }
+        }
+        let id = self.type_ids.len() as u64;
+        self.types.extend(entry);
+        self.type_ids.insert(ty.clone(), id);
+        id
+    }
+
+    fn value(&mut self, value: Value, numbering: &Numbering) {
+        match value {
+            Value::Imm(i) => {
+                self.body.push(0);
+                write_int(&mut self.body, i);
+            }
+            Value::Dest(inst) => {
+                self.body.push(1);
+                self.uint(numbering.inst(inst));
+            }
+        }
+    }
+
+    fn values(&mut self, values: &[Value], numbering: &Numbering) {
+        self.uint(values.len() as u64);
+        for &value in values {
+            self.value(value, numbering);
+        }
+    }
+
+    fn func(&mut self, func: &TacFunc) {
+        self.str(&func.name);
+        self.ty(&func.ty);
+        let attrs = FuncAttr::ALL
+            .iter()
+            .enumerate()
+            .filter(|(_, attr)| func.has_attr(**attr))
+            .fold(0, |mask, (idx, _)| mask | 1 << idx);
+        self.uint(attrs);
+
+        let mut numbering = Numbering::default();
+        for (bb, _) in func.bb_iter() {
+            numbering.bbs.insert(bb, numbering.bbs.len() as u64);
+            for (inst, _) in func.inst_of_bb_iter(bb) {
+                numbering.insts.insert(inst, numbering.insts.len() as u64);
+            }
+        }
+        self.uint(numbering.bbs.len() as u64);
+        self.uint(numbering.insts.len() as u64);
+
+        for (bb, block) in func.bb_iter() {
+            self.uint(func.inst_of_bb_iter(bb).count() as u64);
+            for (idx, inst) in func.inst_of_bb_iter(bb) {
+                self.ty(&inst.ty);
+                match func.tac_get(idx).loc {
+                    Some(loc) => {
+                        self.uint(loc.line as u64);
+                        self.uint(loc.col as u64);
+                    }
+                    None => self.uint(0),
+                }
+                self.inst_kind(&inst.kind, &numbering);
+            }
+            self.branch(&block.branch, &numbering);
+        }
+    }
+
+    fn inst_kind(&mut self, kind: &InstKind, numbering: &Numbering) {
+        match kind {
+            InstKind::Binary(b) => {
+                self.body.push(0);
+                let op = BINARY_OPS.iter().position(|&op| op == b.op).unwrap();
+                self.body.push(op as u8);
+                self.value(b.lhs, numbering);
+                self.value(b.rhs, numbering);
+            }
+            InstKind::FunctionCall(call) => {
+                self.body.push(1);
+                self.str(&call.name);
+                self.values(&call.params, numbering);
+            }
+            InstKind::IndirectCall(call) => {
+                self.body.push(2);
+                self.value(call.callee, numbering);
+                self.values(&call.params, numbering);
+            }
+            InstKind::FuncRef(name) => {
+                self.body.push(3);
+                self.str(name);
+            }
+            InstKind::Assign(value) => {
+                self.body.push(4);
+                self.value(*value, numbering);
+            }
+            InstKind::Phi(sources) => {
+                self.body.push(5);
+                self.uint(sources.len() as u64);
+                for (&bb, &inst) in sources {
+                    self.uint(numbering.bb(bb));
+                    self.uint(numbering.inst(inst));
+                }
+            }
+            InstKind::Param(idx) => {
+                self.body.push(6);
+                self.uint(*idx as u64);
+            }
+            InstKind::Alloca(alloca) => {
+                self.body.push(7);
+                self.ty(&alloca.ty);
+                self.uint(alloca.count as u64);
+            }
+            InstKind::Load(ptr) => {
+                self.body.push(8);
+                self.value(*ptr, numbering);
+            }
+            InstKind::Store(store) => {
+                self.body.push(9);
+                self.value(store.ptr, numbering);
+                self.value(store.value, numbering);
+            }
+            InstKind::Offset(offset) => {
+                self.body.push(10);
+                self.value(offset.ptr, numbering);
+                self.value(offset.offset, numbering);
+            }
+        }
+    }
+
+    fn branch(&mut self, branch: &Branch, numbering: &Numbering) {
+        match branch {
+            Branch::Unreachable => self.body.push(0),
+            Branch::Return(None) => self.body.push(1),
+            Branch::Return(Some(value)) => {
+                self.body.push(2);
+                self.value(*value, numbering);
+            }
+            Branch::Jump(target) => {
+                self.body.push(3);
+                self.uint(numbering.bb(*target));
+            }
+            Branch::CondJump {
+                cond,
+                if_true,
+                if_false,
+            } => {
+                self.body.push(4);
+                self.value(*cond, numbering);
+                self.uint(numbering.bb(*if_true));
+                self.uint(numbering.bb(*if_false));
+            }
+        }
+    }
+}
+
+struct Reader<'a> {
+    input: &'a [u8],
+    pos: usize,
+    types: Vec<Ty>,
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Result<u8, BinaryError> {
+        let byte = *self.input.get(self.pos).ok_or(BinaryError::UnexpectedEnd)?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn uint(&mut self) -> Result<u64, BinaryError> {
+        let mut val = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = self.byte()?;
+            if shift >= 64 || (shift == 63 && byte & 0x7e != 0) {
+                return Err(BinaryError::Malformed("integer too large"));
+            }
+            val |= ((byte & 0x7f) as u64) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Ok(val);
+            }
+        }
+    }
+
+    fn int(&mut self) -> Result<i64, BinaryError> {
+        let mut val = 0i64;
+        let mut shift = 0;
+        loop {
+            let byte = self.byte()?;
+            if shift >= 64 {
+                return Err(BinaryError::Malformed("integer too large"));
+            }
+            val |= ((byte & 0x7f) as i64) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                if shift < 64 && byte & 0x40 != 0 {
+                    val |= -1 << shift;
+                }
+                return Ok(val);
+            }
+        }
+    }
+
+    /// Read a count or index that should fit in memory.
+    fn usize(&mut self) -> Result<usize, BinaryError> {
+        let val = self.uint()?;
+        if val > self.input.len() as u64 * 8 {
+            return Err(BinaryError::Malformed("count too large"));
+        }
+        Ok(val as usize)
+    }
+
+    fn str(&mut self) -> Result<SmolStr, BinaryError> {
+        let len = self.usize()?;
+        let bytes = self
+            .input
+            .get(self.pos..self.pos + len)
+            .ok_or(BinaryError::UnexpectedEnd)?;
+        self.pos += len;
+        std::str::from_utf8(bytes)
+            .map(SmolStr::from)
+            .map_err(|_| BinaryError::Malformed("string is not UTF-8"))
+    }
+
+    fn ty(&mut self) -> Result<Ty, BinaryError> {
+        let idx = self.usize()?;
+        self.types
+            .get(idx)
+            .cloned()
+            .ok_or(BinaryError::Malformed("type index out of range"))
+    }
+
+    fn type_entry(&mut self) -> Result<Ty, BinaryError> {
+        Ok(match self.byte()? {
+            0 => Ty::Unit,
+            1 => {
+                let kind = match self.byte()? {
+                    0 => TyKind::Bool,
+                    1 => TyKind::Int,
+                    _ => return Err(BinaryError::Malformed("unknown numeric type")),
+                };
+                let size = self.byte()?;
+                Ty::Numeric(NumericTy { kind, size })
+            }
+            2 => Ty::ptr_of(self.ty()?),
+            3 => {
+                let return_type = self.ty()?;
+                let params = (0..self.usize()?)
+                    .map(|_| self.ty())
+                    .collect::<Result<_, _>>()?;
+                Ty::Func(std::sync::Arc::new(FuncTy {
+                    return_type,
+                    params,
+                }))
+            }
+            _ => return Err(BinaryError::Malformed("unknown type")),
+        })
+    }
+
+    fn func(&mut self) -> Result<TacFunc, BinaryError> {
+        let name = self.str()?;
+        let ty = self.ty()?;
+        let mut func = TacFunc::new(name, ty);
+        let attrs = self.uint()?;
+        if attrs >> FuncAttr::ALL.len() != 0 {
+            return Err(BinaryError::Malformed("unknown function attribute"));
+        }
+        func.attrs.extend(
+            FuncAttr::ALL
+                .iter()
+                .enumerate()
+                .filter(|(idx, _)| attrs & 1 << idx != 0)
+                .map(|(_, &attr)| attr),
+        );
+
+        let bb_count = self.usize()?;
+        let inst_count = self.usize()?;
+        let mut editor = FuncEditor::new(&mut func);
+        let bbs = (0..bb_count).map(|_| editor.new_bb()).collect::<Vec<_>>();
+        // Instructions may refer to later ones, so all of them are created
+        // first as placeholders
+        let insts = (0..inst_count)
+            .map(|_| {
+                editor.func.inst_new(Inst {
+                    kind: InstKind::empty_phi(),
+                    ty: Ty::unit(),
+                })
+            })
+            .collect::<Vec<_>>();
+        let numbering = ReadNumbering {
+            bbs: &bbs,
+            insts: &insts,
+        };
+
+        let mut next_inst = insts.iter();
+        for (idx, &bb) in bbs.iter().enumerate() {
+            editor.set_current_bb(bb);
+            for _ in 0..self.usize()? {
+                let &inst = next_inst
+                    .next()
+                    .ok_or(BinaryError::Malformed("instruction count mismatch"))?;
+                let ty = self.ty()?;
+                let loc = match self.uint()? {
+                    0 => None,
+                    line => Some(SourceLoc {
+                        line: line as u32,
+                        col: self.uint()? as u32,
+                    }),
+                };
+                let kind = self.inst_kind(&numbering)?;
+                *editor.func.inst_get_mut(inst) = Inst { kind, ty };
+                editor.func.tac_get_mut(inst).loc = loc;
+                editor.put_inst_after_current_place(inst);
+            }
+            editor.current_bb_mut().branch = self.branch(&numbering)?;
+            if idx > 0 {
+                editor.func.bb_set_after(bbs[idx - 1], bb);
+            }
+        }
+        if next_inst.next().is_some() {
+            return Err(BinaryError::Malformed("instruction count mismatch"));
+        }
+        func.first_block = bbs.first().copied();
+        Ok(func)
+    }
+
+    fn value(&mut self, numbering: &ReadNumbering) -> Result<Value, BinaryError> {
+        match self.byte()? {
+            0 => Ok(Value::Imm(self.int()?)),
+            1 => Ok(Value::Dest(numbering.inst(self.usize()?)?)),
+            _ => Err(BinaryError::Malformed("unknown value")),
+        }
+    }
+
+    fn values(&mut self, numbering: &ReadNumbering) -> Result<Vec<Value>, BinaryError> {
+        (0..self.usize()?).map(|_| self.value(numbering)).collect()
+    }
+
+    fn inst_kind(&mut self, numbering: &ReadNumbering) -> Result<InstKind, BinaryError> {
+        Ok(match self.byte()? {
+            0 => {
+                let op = *BINARY_OPS
+                    .get(self.byte()? as usize)
+                    .ok_or(BinaryError::Malformed("unknown binary operator"))?;
+                let lhs = self.value(numbering)?;
+                let rhs = self.value(numbering)?;
+                InstKind::Binary(BinaryInst { op, lhs, rhs })
+            }
+            1 => {
+                let name = self.str()?;
+                let params = self.values(numbering)?;
+                InstKind::FunctionCall(FunctionCall { name, params })
+            }
+            2 => {
+                let callee = self.value(numbering)?;
+                let params = self.values(numbering)?;
+                InstKind::IndirectCall(IndirectCall { callee, params })
+            }
+            3 => InstKind::FuncRef(self.str()?),
+            4 => InstKind::Assign(self.value(numbering)?),
+            5 => {
+                let mut sources = BTreeMap::new();
+                for _ in 0..self.usize()? {
+                    let bb = numbering.bb(self.usize()?)?;
+                    let inst = numbering.inst(self.usize()?)?;
+                    sources.insert(bb, inst);
+                }
+                InstKind::Phi(sources)
+            }
+            6 => InstKind::Param(self.usize()?),
+            7 => {
+                let ty = self.ty()?;
+                let count = self.uint()? as usize;
+                InstKind::Alloca(AllocaInst { ty, count })
+            }
+            8 => InstKind::Load(self.value(numbering)?),
+            9 => {
+                let ptr = self.value(numbering)?;
+                let value = self.value(numbering)?;
+                InstKind::Store(StoreInst { ptr, value })
+            }
+            10 => {
+                let ptr = self.value(numbering)?;
+                let offset = self.value(numbering)?;
+                InstKind::Offset(OffsetInst { ptr, offset })
+            }
+            _ => return Err(BinaryError::Malformed("unknown instruction")),
+        })
+    }
+
+    fn branch(&mut self, numbering: &ReadNumbering) -> Result<Branch, BinaryError> {
+        Ok(match self.byte()? {
+            0 => Branch::Unreachable,
+            1 => Branch::Return(None),
+            2 => Branch::Return(Some(self.value(numbering)?)),
+            3 => Branch::Jump(numbering.bb(self.usize()?)?),
+            4 => {
+                let cond = self.value(numbering)?;
+                let if_true = numbering.bb(self.usize()?)?;
+                let if_false = numbering.bb(self.usize()?)?;
+                Branch::CondJump {
+                    cond,
+                    if_true,
+                    if_false,
+                }
+            }
+            _ => return Err(BinaryError::Malformed("unknown branch")),
+        })
+    }
+}
+
+struct ReadNumbering<'a> {
+    bbs: &'a [BBId],
+    insts: &'a [InstId],
+}
+
+impl ReadNumbering<'_> {
+    fn bb(&self, idx: usize) -> Result<BBId, BinaryError> {
+        self.bbs
+            .get(idx)
+            .copied()
+            .ok_or(BinaryError::Malformed("basic block index out of range"))
+    }
+
+    fn inst(&self, idx: usize) -> Result<InstId, BinaryError> {
+        self.insts
+            .get(idx)
+            .copied()
+            .ok_or(BinaryError::Malformed("instruction index out of range"))
+    }
+}
@@ -0,0 +1,113 @@
+use crate::{
+    err::BinaryError, parser::parse_program_from_string, FuncAttr, Program, SourceLoc, Ty,
+};
+
+use super::{is_binary, read_program, write_program, MAGIC, VERSION};
+
+fn sample_program() -> Program {
+    let input = r"
+    (fn fib (i32) i32 (attrs inline pure)
+        (bb0 (
+            (%0 i32 param 0)
+            (%1 b32 le %0 1))
+            (brif %1 bb1 bb2))
+        (bb1 (
+            (%7 i32 -1000000))
+            (br bb3))
+        (bb2 (
+            (%2 i32 sub %0 1)
+            (%3 i32 sub %0 2)
+            (%4 i32 call fib (%2))
+            (%5 i32 call fib (%3))
+            (%6 i32 add %4 %5))
+            (br bb3))
+        (bb3 (
+            (%8 i32 phi (bb1 %7) (bb2 %6)))
+            (return %8)))
+
+    (fn main () i32 (attrs cold)
+        (bb0 (
+            (%0 (fn (i32) i32) funcref fib)
+            (%1 i32 call_indirect %0 (15)))
+            (return %1))
+        (bb1 ()
+            (unreachable)))
+
+    (fn nothing () ()
+        (bb0 ()
+            (return)))
+
+    (fn memory (i32) i32
+        (bb0 (
+            (%0 i32 param 0)
+            (%1 i32* alloca i32 16)
+            (%2 i32** alloca i32*)
+            (%3 () store %2 %1)
+            (%4 i32* load %2)
+            (%5 i32* offset %4 %0)
+            (%6 () store %5 -3)
+            (%7 i32 load %5))
+            (return %7)))
+    ";
+    let mut program = parse_program_from_string(input).unwrap();
+    let fib = program.functions.get_mut("fib").unwrap();
+    let bb = fib.first_block.unwrap();
+    let first = fib.bb_get(bb).head.unwrap();
+    fib.tac_get_mut(first).loc = Some(SourceLoc { line: 3, col: 14 });
+    program.imports.insert(
+        "putint".into(),
+        Ty::func_of(Ty::unit(), vec![Ty::ptr_of(Ty::int())]),
+    );
+    program
+}
+
+#[test]
+fn test_round_trip() {
+    let program = sample_program();
+    let encoded = write_program(&program);
+    assert!(is_binary(&encoded));
+    let decoded = read_program(&encoded).unwrap();
+
+    assert_eq!(decoded.imports, program.imports);
+    let names = decoded.functions.keys().collect::<Vec<_>>();
+    assert_eq!(names, vec!["fib", "main", "nothing", "memory"]);
+    for (name, func) in &program.functions {
+        let other = &decoded.functions[name];
+        assert_eq!(other.ty, func.ty);
+        assert_eq!(other.to_string(), func.to_string());
+    }
+    let fib = &decoded.functions["fib"];
+    assert!(fib.has_attr(FuncAttr::Inline) && fib.has_attr(FuncAttr::Pure));
+    assert!(!fib.has_attr(FuncAttr::Cold));
+    let first = fib.bb_get(fib.first_block.unwrap()).head.unwrap();
+    assert_eq!(fib.tac_get(first).loc, Some(SourceLoc { line: 3, col: 14 }));
+
+    // Encoding the decoded program gives the same bytes
+    assert_eq!(write_program(&decoded), encoded);
+}
+
+#[test]
+fn test_bad_input() {
+    assert_eq!(
+        read_program(b"(fn main () ())").unwrap_err(),
+        BinaryError::NotBinary
+    );
+
+    let mut future = MAGIC.to_vec();
+    future.push(VERSION as u8 + 1);
+    assert_eq!(
+        read_program(&future).unwrap_err(),
+        BinaryError::UnsupportedVersion(VERSION as u64 + 1)
+    );
+
+    let encoded = write_program(&sample_program());
+    for len in MAGIC.len()..encoded.len() {
+        assert!(read_program(&encoded[..len]).is_err(), "length {}", len);
+    }
+    let mut trailing = encoded;
+    trailing.push(0);
+    assert!(matches!(
+        read_program(&trailing),
+        Err(BinaryError::Malformed(_))
+    ));
+}
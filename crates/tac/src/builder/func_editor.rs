@@ -2,7 +2,8 @@ use std::collections::BTreeMap;
 
 use crate::{
     err::{Error, TacResult},
-    BBId, BasicBlock, Inst, InstId, InstKind, Tac, TacFunc, Ty,
+    users::{BBMut, InstMut, TacMut},
+    BBId, BasicBlock, Inst, InstId, InstKind, SourceLoc, Tac, TacFunc, Ty,
 };
 
 /// An editor attached to the given function for linear editing purposes.
@@ -22,6 +23,9 @@ pub struct FuncEditor<'a> {
     /// sentinel position that has `bb.head` as next and `bb.tail` as prev,
     /// or the basic block is completely empty.
     current_idx: Option<InstId>,
+
+    /// The source location given to inserted instructions.
+    loc: Option<SourceLoc>,
 }
 
 impl<'a> FuncEditor<'a> {
@@ -36,6 +40,7 @@ impl<'a> FuncEditor<'a> {
             func,
             current_bb_id: current_bb.unwrap_or_default(),
             current_idx: starting_idx,
+            loc: None,
         }
     }
 
@@ -52,7 +57,7 @@ impl<'a> FuncEditor<'a> {
         self.func.bb_get(self.current_bb_id)
     }
 
-    pub fn current_bb_mut(&mut self) -> &mut BasicBlock {
+    pub fn current_bb_mut(&mut self) -> BBMut<'_> {
         self.func.bb_get_mut(self.current_bb_id)
     }
 
@@ -70,14 +75,31 @@ impl<'a> FuncEditor<'a> {
         Some(self.func.tac_get(self.current_idx?))
     }
 
-    pub fn current_inst_mut(&mut self) -> Option<&mut Inst> {
-        self.current_tac_mut().map(|x| &mut x.inst)
+    pub fn current_inst_mut(&mut self) -> Option<InstMut<'_>> {
+        Some(self.func.inst_get_mut(self.current_idx?))
     }
 
-    pub fn current_tac_mut(&mut self) -> Option<&mut Tac> {
+    pub fn current_tac_mut(&mut self) -> Option<TacMut<'_>> {
         Some(self.func.tac_get_mut(self.current_idx?))
     }
 
+    /// Returns the source location given to inserted instructions.
+    pub fn loc(&self) -> Option<SourceLoc> {
+        self.loc
+    }
+
+    /// Sets the source location given to instructions inserted afterwards.
+    pub fn set_loc(&mut self, loc: Option<SourceLoc>) {
+        self.loc = loc;
+    }
+
+    /// Creates a free-standing instruction at the current source location.
+    fn new_inst(&mut self, inst: Inst) -> InstId {
+        let idx = self.func.inst_new(inst);
+        self.func.tac_get_mut(idx).loc = self.loc;
+        idx
+    }
+
     /// Add an empty basic block into the function.
     pub fn new_bb(&mut self) -> BBId {
         self.func.bb_new()
@@ -134,7 +156,7 @@ impl<'a> FuncEditor<'a> {
     /// If the current basic block is empty, the instruction is inserted as the
     /// only instruction of the basic block.
     pub fn insert_after_current_place(&mut self, inst: Inst) -> InstId {
-        let idx = self.func.inst_new(inst);
+        let idx = self.new_inst(inst);
         // this line is infailable
         self.put_inst_after_current_place(idx);
         idx
@@ -146,21 +168,21 @@ impl<'a> FuncEditor<'a> {
     /// If the current basic block is empty, the instruction is inserted as the
     /// only instruction of the basic block.
     pub fn insert_before_current_place(&mut self, inst: Inst) -> InstId {
-        let idx = self.func.inst_new(inst);
+        let idx = self.new_inst(inst);
         self.put_inst_before_current_place(idx);
         idx
     }
 
     /// Insert the given instruction at the **end** of the given basic block.
     pub fn insert_at_end_of(&mut self, inst: Inst, bb_id: BBId) -> TacResult<InstId> {
-        let inst = self.func.inst_new(inst);
+        let inst = self.new_inst(inst);
         self.func.inst_append_in_bb(inst, bb_id);
         Ok(inst)
     }
 
     /// Insert the given instruction at the **start** of the given basic block.
     pub fn insert_at_start_of(&mut self, inst: Inst, bb_id: BBId) -> TacResult<InstId> {
-        let inst = self.func.inst_new(inst);
+        let inst = self.new_inst(inst);
         self.func.inst_prepend_in_bb(inst, bb_id);
         Ok(inst)
     }
@@ -203,14 +225,25 @@ impl<'a> FuncEditor<'a> {
         self.current_idx = Some(idx);
     }
 
+    /// Insert a phi instruction at the **start** of the given basic block.
+    ///
+    /// If we are at the sentinel position of the same basic block, the position
+    /// is moved to the inserted phi, so instructions inserted afterwards are
+    /// placed after it.
     pub fn insert_phi(&mut self, bb_id: BBId, ty: Ty) -> Result<InstId, Error> {
-        self.insert_at_start_of(
+        let phi = self.insert_at_start_of(
             Inst {
                 kind: InstKind::Phi(BTreeMap::new()),
                 ty,
             },
             bb_id,
-        )
+        )?;
+        // Phis merge values from several places, so they have no location
+        self.func.tac_get_mut(phi).loc = None;
+        if bb_id == self.current_bb_id && self.current_idx.is_none() {
+            self.current_idx = Some(phi);
+        }
+        Ok(phi)
     }
 
     /// Move one instruction forward. Returns whether [`current_idx`] is a valid
@@ -103,6 +103,36 @@ where
         );
     }
 
+    /// Move every phi instruction to the head of its basic block, keeping their
+    /// order. Call this after all basic blocks are filled and sealed.
+    ///
+    /// Trivial phis are turned into assignments in place, which may leave them
+    /// in front of phis inserted earlier.
+    pub fn move_phis_to_head(&mut self) {
+        let func = &mut *self.editor.func;
+        let bbs = func
+            .all_bb_unordered()
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        for bb in bbs {
+            let insts = func
+                .inst_of_bb_iter(bb)
+                .map(|(idx, inst)| (idx, inst.kind.as_phi().is_some()))
+                .collect::<Vec<_>>();
+            let last_phi = match insts.iter().rposition(|&(_, is_phi)| is_phi) {
+                Some(pos) => pos,
+                None => continue,
+            };
+            if insts[..last_phi].iter().all(|&(_, is_phi)| is_phi) {
+                continue;
+            }
+            for &(idx, _) in insts.iter().rev().filter(|&&(_, is_phi)| is_phi) {
+                func.inst_detach(idx);
+                func.inst_prepend_in_bb(idx, bb);
+            }
+        }
+    }
+
     /// Mark the given basic block as _sealed_. Also completes all incomplete Phi commands
     /// inside this basic block.
     ///
@@ -246,16 +276,36 @@ where
             same = Some(val);
         }
 
-        let replace_value = match same {
-            // an empty phi is a dead value
-            None => InstKind::empty_phi(),
-            Some(same) => InstKind::Assign(same.into()),
+        let same = match same {
+            Some(same) => same,
+            None => {
+                // an empty phi is a dead value
+                self.editor.func.inst_get_mut(phi_op).kind = InstKind::empty_phi();
+                return;
+            }
         };
-        // remove traces of this phi
 
-        // FIXME: workaround for not being able to track a phi's users
-        // replace usage of this phi
-        self.editor.func.tac_get_mut(phi_op).inst.kind = replace_value;
+        // reroute all uses of phi to same
+        let phi_users = self
+            .editor
+            .func
+            .users_of(phi_op)
+            .filter_map(|user| match user {
+                UsePlace::Inst(user) if user != phi_op => Some(user),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        self.editor.func.replace_all_uses_with(phi_op, same);
+        // Variables may still be defined as this phi, so it is kept as an
+        // assignment for later reads
+        self.editor.func.inst_get_mut(phi_op).kind = InstKind::Assign(same.into());
+
+        // try to recursively remove all phi users, which might have become trivial
+        for user in phi_users {
+            if self.editor.func.inst_get(user).kind.as_phi().is_some() {
+                self.try_remove_trivial_phi(user);
+            }
+        }
     }
 
     pub fn add_branch(&mut self, from: BBId, to: BBId) {
@@ -1,6 +1,10 @@
 //! Error and Result types.
 
-use crate::{BBId, InstId};
+use std::{fmt::Display, path::PathBuf};
+
+use smol_str::SmolStr;
+
+use crate::{BBId, InstId, Ty, UsePlace};
 
 #[derive(Debug)]
 pub enum Error {
@@ -13,3 +17,288 @@ pub enum Error {
 }
 
 pub type TacResult<T> = Result<T, Error>;
+
+/// Errors when linking programs together.
+#[derive(Debug)]
+pub enum LinkError {
+    /// A function is defined in more than one program.
+    DuplicateSymbol(SmolStr),
+    /// A function is imported or called but not defined in any program.
+    MissingSymbol(SmolStr),
+    /// A function is imported with a type different from its definition.
+    TypeMismatch {
+        name: SmolStr,
+        expected: Ty,
+        found: Ty,
+    },
+}
+
+impl Display for LinkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LinkError::DuplicateSymbol(name) => {
+                write!(f, "function `{}` is defined more than once", name)
+            }
+            LinkError::MissingSymbol(name) => write!(f, "function `{}` is not defined", name),
+            LinkError::TypeMismatch {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "function `{}` is defined as {} but imported as {}",
+                name, expected, found
+            ),
+        }
+    }
+}
+
+/// Errors when parsing a [`Schedule`](crate::optimizer::schedule::Schedule).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduleParseError {
+    /// Byte offset of the error inside the input.
+    pub pos: usize,
+    pub kind: ScheduleParseErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScheduleParseErrorKind {
+    UnexpectedChar(char),
+    UnexpectedEnd,
+    /// A group other than `fixpoint(...)` is used.
+    UnknownGroup(String),
+}
+
+impl Display for ScheduleParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            ScheduleParseErrorKind::UnexpectedChar(ch) => {
+                write!(f, "unexpected character `{}` at {}", ch, self.pos)
+            }
+            ScheduleParseErrorKind::UnexpectedEnd => write!(f, "unexpected end of input"),
+            ScheduleParseErrorKind::UnknownGroup(name) => {
+                write!(f, "unknown group `{}` at {}", name, self.pos)
+            }
+        }
+    }
+}
+
+/// Errors when running a schedule inside a [`Pipeline`](crate::optimizer::Pipeline).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PipelineError {
+    /// No pass has this name.
+    UnknownPass(String),
+    /// A pass depends on itself through these passes.
+    CyclicDependency(Vec<String>),
+    /// An IR dump can't be written into this file.
+    DumpFailed(PathBuf, String),
+}
+
+impl Display for PipelineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PipelineError::UnknownPass(name) => write!(f, "cannot find pass `{}`", name),
+            PipelineError::CyclicDependency(passes) => {
+                write!(f, "passes depend on each other: {}", passes.join(" -> "))
+            }
+            PipelineError::DumpFailed(path, e) => {
+                write!(f, "cannot write IR dump `{}`: {}", path.display(), e)
+            }
+        }
+    }
+}
+
+/// Errors when reading a program encoded by [`binary`](crate::binary).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BinaryError {
+    /// The input doesn't start with [`MAGIC`](crate::binary::MAGIC).
+    NotBinary,
+    /// The input is encoded in a version this crate can't read.
+    UnsupportedVersion(u64),
+    UnexpectedEnd,
+    Malformed(&'static str),
+}
+
+impl Display for BinaryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BinaryError::NotBinary => write!(f, "input is not binary Azuki TAC"),
+            BinaryError::UnsupportedVersion(v) => write!(f, "unsupported version {}", v),
+            BinaryError::UnexpectedEnd => write!(f, "unexpected end of input"),
+            BinaryError::Malformed(what) => write!(f, "malformed input: {}", what),
+        }
+    }
+}
+
+/// A problem found by [`verifier`](crate::verifier) inside a function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyError {
+    /// The function containing the problem.
+    pub func: SmolStr,
+    pub kind: VerifyErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyErrorKind {
+    /// The function has no starting block.
+    NoEntryBlock,
+    /// The type of the function is not a function type.
+    NotAFuncTy(Ty),
+    /// The instruction list of the basic block is not properly linked.
+    BrokenInstList(BBId),
+    /// The instruction is inside a basic block other than the one it points to.
+    WrongBlockPointer {
+        inst: InstId,
+        expected: BBId,
+        found: BBId,
+    },
+    /// The users recorded for the instruction are not its actual users.
+    WrongUsers(InstId),
+    /// A branch targets a basic block that is not in the function.
+    MissingBranchTarget {
+        bb: BBId,
+        target: BBId,
+    },
+    /// A phi instruction comes after a non-phi instruction.
+    PhiNotAtHead(InstId),
+    /// The sources of a phi instruction are not the predecessors of its basic
+    /// block.
+    PhiSourceMismatch {
+        inst: InstId,
+        preds: Vec<BBId>,
+        sources: Vec<BBId>,
+    },
+    /// A value is used but not defined in any basic block.
+    UndefinedValue {
+        place: UsePlace,
+        value: InstId,
+    },
+    /// A value is used at a place not dominated by its definition.
+    UseNotDominated {
+        place: UsePlace,
+        value: InstId,
+    },
+    TypeMismatch {
+        place: UsePlace,
+        expected: Ty,
+        found: Ty,
+    },
+    UnknownFunction {
+        inst: InstId,
+        name: SmolStr,
+    },
+    /// A value of non-function type is called.
+    NotCallable {
+        inst: InstId,
+        ty: Ty,
+    },
+    WrongParamCount {
+        inst: InstId,
+        expected: usize,
+        found: usize,
+    },
+    /// A parameter instruction refers to a parameter the function doesn't have.
+    ParamOutOfRange(InstId),
+    /// A load, store or offset goes through a value of non-pointer type.
+    NotAPointer {
+        inst: InstId,
+        ty: Ty,
+    },
+    /// An arithmetic instruction results in a pointer.
+    PointerArithmetic(InstId),
+}
+
+impl Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "in function `{}`: ", self.func)?;
+        match &self.kind {
+            VerifyErrorKind::NoEntryBlock => write!(f, "no starting block"),
+            VerifyErrorKind::NotAFuncTy(ty) => write!(f, "{} is not a function type", ty),
+            VerifyErrorKind::BrokenInstList(bb) => {
+                write!(f, "instructions of {} are not properly linked", bb)
+            }
+            VerifyErrorKind::WrongBlockPointer {
+                inst,
+                expected,
+                found,
+            } => write!(f, "{} is inside {} but points to {}", inst, expected, found),
+            VerifyErrorKind::WrongUsers(inst) => {
+                write!(f, "users of {} are not properly recorded", inst)
+            }
+            VerifyErrorKind::MissingBranchTarget { bb, target } => {
+                write!(f, "{} branches to {}, which does not exist", bb, target)
+            }
+            VerifyErrorKind::PhiNotAtHead(inst) => {
+                write!(f, "phi {} is not at the head of its basic block", inst)
+            }
+            VerifyErrorKind::PhiSourceMismatch {
+                inst,
+                preds,
+                sources,
+            } => {
+                write!(f, "phi {} has sources [", inst)?;
+                write_bb_list(f, sources)?;
+                write!(f, "] but its basic block has predecessors [")?;
+                write_bb_list(f, preds)?;
+                write!(f, "]")
+            }
+            VerifyErrorKind::UndefinedValue { place, value } => {
+                write!(f, "{} uses {}, which is not defined", place, value)
+            }
+            VerifyErrorKind::UseNotDominated { place, value } => {
+                write!(f, "{} uses {}, which does not dominate it", place, value)
+            }
+            VerifyErrorKind::TypeMismatch {
+                place,
+                expected,
+                found,
+            } => write!(
+                f,
+                "mismatched types at {}: expected {}, found {}",
+                place, expected, found
+            ),
+            VerifyErrorKind::UnknownFunction { inst, name } => {
+                write!(
+                    f,
+                    "{} refers to function `{}`, which does not exist",
+                    inst, name
+                )
+            }
+            VerifyErrorKind::NotCallable { inst, ty } => {
+                write!(f, "{} calls a value of type {}", inst, ty)
+            }
+            VerifyErrorKind::WrongParamCount {
+                inst,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} passes {} parameter(s) where {} are expected",
+                inst, found, expected
+            ),
+            VerifyErrorKind::ParamOutOfRange(inst) => {
+                write!(f, "{} refers to a parameter that does not exist", inst)
+            }
+            VerifyErrorKind::NotAPointer { inst, ty } => {
+                write!(f, "{} accesses memory through a value of type {}", inst, ty)
+            }
+            VerifyErrorKind::PointerArithmetic(inst) => {
+                write!(
+                    f,
+                    "{} does arithmetic on pointers, use offset instead",
+                    inst
+                )
+            }
+        }
+    }
+}
+
+fn write_bb_list(f: &mut std::fmt::Formatter<'_>, bbs: &[BBId]) -> std::fmt::Result {
+    for (idx, bb) in bbs.iter().enumerate() {
+        if idx != 0 {
+            write!(f, " ")?;
+        }
+        write!(f, "{}", bb)?;
+    }
+    Ok(())
+}
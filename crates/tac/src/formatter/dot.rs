@@ -0,0 +1,204 @@
+//! Exporting functions as [Graphviz] DOT graphs, for visualizing them.
+//!
+//! Every basic block becomes a node listing its instructions and branch in
+//! TAC text, numbered like the [`Display`](std::fmt::Display) of
+//! [`TacFunc`]. Control flow edges go from branches to their targets, and are
+//! labelled `true` and `false` for conditional jumps. Data flow edges and the
+//! dominator tree can be drawn over them, see [`DotOptions`].
+//!
+//! [Graphviz]: https://graphviz.org
+
+use std::fmt::{Display, Write};
+
+use super::{FormatContext, Names};
+use crate::{
+    analysis::{Cfg, DomTree},
+    BBId, Branch, InstId, InstKind, Program, TacFunc,
+};
+
+/// What to draw besides basic blocks and control flow.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DotOptions {
+    /// Draw dashed blue edges from every instruction to the instructions and
+    /// branches using its value. Edges into phis are labelled with the basic
+    /// block the value comes from.
+    pub dfg: bool,
+    /// Draw dotted green edges from the immediate dominator of every basic
+    /// block to it.
+    pub dom_tree: bool,
+}
+
+impl TacFunc {
+    /// Export this function as a DOT graph. See [`dot`](self) for details.
+    pub fn to_dot(&self, options: &DotOptions) -> String {
+        func_to_dot(self, options)
+    }
+}
+
+/// Export `func` as a DOT graph.
+pub fn func_to_dot(func: &TacFunc, options: &DotOptions) -> String {
+    let mut out = String::new();
+    writeln!(out, "digraph {} {{", quote(&func.name)).unwrap();
+    writeln!(out, "\tnode [shape=plain, fontname=monospace];").unwrap();
+    write_func(&mut out, func, options, "\t");
+    out += "}\n";
+    out
+}
+
+/// Export every function of `program` as a cluster inside a single DOT graph,
+/// in the order they are defined.
+pub fn program_to_dot(program: &Program, options: &DotOptions) -> String {
+    let mut out = String::new();
+    writeln!(out, "digraph program {{").unwrap();
+    writeln!(out, "\tnode [shape=plain, fontname=monospace];").unwrap();
+    for func in program.functions.values() {
+        writeln!(
+            out,
+            "\tsubgraph {} {{",
+            quote(&format!("cluster_{}", func.name))
+        )
+        .unwrap();
+        writeln!(out, "\t\tlabel = {};", quote(&format!("fn {}", func.name))).unwrap();
+        write_func(&mut out, func, options, "\t\t");
+        writeln!(out, "\t}}").unwrap();
+    }
+    out += "}\n";
+    out
+}
+
+/// Writes nodes and edges of `func`, every line starting with `indent`.
+fn write_func(out: &mut String, func: &TacFunc, options: &DotOptions, indent: &str) {
+    let names = Names::canonical(func);
+    let node = |bb: BBId| quote(&format!("{}.{}", func.name, names.bb(bb)));
+    // The node and port of an instruction, if it's inside the layout
+    let port = |inst: InstId| {
+        let id = names.inst(inst);
+        (id.0 != u32::MAX).then(|| format!("{}:i{}", node(func.tac_get(inst).bb), id.0))
+    };
+
+    for (bb, block) in func.bb_iter() {
+        let mut label = String::from(r#"<table border="0" cellborder="1" cellspacing="0">"#);
+        write!(
+            label,
+            r#"<tr><td bgcolor="lightgrey"><b>{}</b></td></tr>"#,
+            names.bb(bb)
+        )
+        .unwrap();
+        for (idx, _) in func.inst_of_bb_iter(bb) {
+            let text = display(|f| func.tac_get(idx).fmt_ctx(f, (names.inst(idx), &names)));
+            write!(
+                label,
+                r#"<tr><td port="i{}" align="left">{}</td></tr>"#,
+                names.inst(idx).0,
+                escape_html(&text)
+            )
+            .unwrap();
+        }
+        let text = display(|f| block.branch.fmt_ctx(f, &names));
+        write!(
+            label,
+            r#"<tr><td port="br" align="left">{}</td></tr></table>"#,
+            escape_html(&text)
+        )
+        .unwrap();
+        writeln!(out, "{}{} [label=<{}>];", indent, node(bb), label).unwrap();
+
+        match &block.branch {
+            Branch::Jump(target) => {
+                writeln!(out, "{}{}:br -> {};", indent, node(bb), node(*target)).unwrap();
+            }
+            Branch::CondJump {
+                if_true, if_false, ..
+            } => {
+                for (target, label) in [(if_true, "true"), (if_false, "false")].iter() {
+                    writeln!(
+                        out,
+                        "{}{}:br -> {} [label={}];",
+                        indent,
+                        node(bb),
+                        node(**target),
+                        label
+                    )
+                    .unwrap();
+                }
+            }
+            Branch::Return(_) | Branch::Unreachable => {}
+        }
+    }
+
+    if options.dfg {
+        let mut edge = |from: InstId, to: String, label: Option<String>| {
+            if let Some(from) = port(from) {
+                let label = label.map_or(String::new(), |l| format!(", label={}", quote(&l)));
+                writeln!(
+                    out,
+                    "{}{} -> {} [style=dashed, color=blue, constraint=false{}];",
+                    indent, from, to, label
+                )
+                .unwrap();
+            }
+        };
+        for (bb, block) in func.bb_iter() {
+            for (idx, inst) in func.inst_of_bb_iter(bb) {
+                let to = port(idx).unwrap();
+                match &inst.kind {
+                    InstKind::Phi(sources) => {
+                        for (&source, &value) in sources {
+                            edge(value, to.clone(), Some(names.bb(source).to_string()));
+                        }
+                    }
+                    kind => {
+                        for value in kind.param_op_iter() {
+                            edge(value, to.clone(), None);
+                        }
+                    }
+                }
+            }
+            if let Some(value) = block.branch.param_op() {
+                edge(value, format!("{}:br", node(bb)), None);
+            }
+        }
+    }
+
+    if options.dom_tree {
+        let dom = DomTree::new(&Cfg::new(func));
+        for (bb, _) in func.bb_iter() {
+            if let Some(idom) = dom.idom(bb) {
+                writeln!(
+                    out,
+                    "{}{} -> {} [style=dotted, color=darkgreen, constraint=false];",
+                    indent,
+                    node(idom),
+                    node(bb)
+                )
+                .unwrap();
+            }
+        }
+    }
+}
+
+/// Text written by `fmt`.
+fn display(fmt: impl Fn(&mut std::fmt::Formatter<'_>) -> std::fmt::Result) -> String {
+    struct Adapter<F>(F);
+    impl<F> Display for Adapter<F>
+    where
+        F: Fn(&mut std::fmt::Formatter<'_>) -> std::fmt::Result,
+    {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            (self.0)(f)
+        }
+    }
+    Adapter(fmt).to_string()
+}
+
+/// Quote `s` as a DOT string.
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
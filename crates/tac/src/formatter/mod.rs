@@ -1,6 +1,10 @@
 //! Serialization and de-serialization for TAC code.
 
-use std::{fmt::Display, writeln};
+pub mod dot;
+#[cfg(test)]
+mod test;
+
+use std::{collections::HashMap, fmt::Display, writeln};
 use ty::FuncTy;
 use util::ListFormatter;
 
@@ -82,7 +86,7 @@ impl Display for BinaryOp {
 struct VarId(u32);
 impl Display for VarId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if self.0 != u32::max_value() {
+        if self.0 != u32::MAX {
             write!(f, "%{}", self.0)
         } else {
             write!(f, "_")
@@ -90,11 +94,70 @@ impl Display for VarId {
     }
 }
 
+struct BlockId(u32);
+impl Display for BlockId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0 != u32::MAX {
+            write!(f, "bb{}", self.0)
+        } else {
+            write!(f, "bb_")
+        }
+    }
+}
+
+/// How basic blocks and instructions are named when printing a function.
+enum Names {
+    /// Use their slots inside arenas, like the `Display` of [`BBId`] and
+    /// [`InstId`].
+    Slots,
+    /// Number them densely in layout order, so printing doesn't depend on how
+    /// the function was edited. Blocks and instructions outside the layout are
+    /// printed as `bb_` and `_`.
+    Canonical {
+        bbs: HashMap<BBId, u32>,
+        insts: HashMap<InstId, u32>,
+    },
+}
+
+impl Names {
+    fn canonical(func: &TacFunc) -> Names {
+        let mut bbs = HashMap::new();
+        let mut insts = HashMap::new();
+        for (bb, _) in func.bb_iter() {
+            bbs.insert(bb, bbs.len() as u32);
+            for (inst, _) in func.inst_of_bb_iter(bb) {
+                insts.insert(inst, insts.len() as u32);
+            }
+        }
+        Names::Canonical { bbs, insts }
+    }
+
+    fn bb(&self, bb: BBId) -> BlockId {
+        match self {
+            Names::Slots => BlockId(bb.slot()),
+            Names::Canonical { bbs, .. } => BlockId(*bbs.get(&bb).unwrap_or(&u32::MAX)),
+        }
+    }
+
+    fn inst(&self, inst: InstId) -> VarId {
+        match self {
+            Names::Slots => VarId(inst.slot()),
+            Names::Canonical { insts, .. } => VarId(*insts.get(&inst).unwrap_or(&u32::MAX)),
+        }
+    }
+}
+
 impl Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_ctx(f, &Names::Slots)
+    }
+}
+
+impl FormatContext<&Names> for Value {
+    fn fmt_ctx(&self, f: &mut std::fmt::Formatter<'_>, names: &Names) -> std::fmt::Result {
         match self {
             Value::Dest(i) => {
-                write!(f, "{}", i)
+                write!(f, "{}", names.inst(*i))
             }
             Value::Imm(imm) => {
                 write!(f, "{}", imm)
@@ -103,26 +166,54 @@ impl Display for Value {
     }
 }
 
-impl FormatContext<VarId> for Tac {
-    fn fmt_ctx(&self, f: &mut std::fmt::Formatter<'_>, ctx: VarId) -> std::fmt::Result {
-        write!(f, "({} {} ", ctx.0, self.inst.ty)?;
+fn fmt_values(
+    f: &mut std::fmt::Formatter<'_>,
+    values: &[Value],
+    names: &Names,
+) -> std::fmt::Result {
+    for (idx, param) in values.iter().enumerate() {
+        if idx != 0 {
+            write!(f, " ")?;
+        }
+        param.fmt_ctx(f, names)?;
+    }
+    Ok(())
+}
+
+impl FormatContext<(VarId, &Names)> for Tac {
+    fn fmt_ctx(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        (ctx, names): (VarId, &Names),
+    ) -> std::fmt::Result {
+        write!(f, "({} {} ", ctx, self.inst.ty)?;
         match &self.inst.kind {
             InstKind::Binary(i) => {
-                write!(f, "{} {} {}", i.op, i.lhs, i.rhs)?;
+                write!(f, "{} ", i.op)?;
+                i.lhs.fmt_ctx(f, names)?;
+                write!(f, " ")?;
+                i.rhs.fmt_ctx(f, names)?;
             }
             InstKind::FunctionCall(call) => {
                 write!(f, "call {} (", &call.name)?;
-                for (idx, param) in call.params.iter().enumerate() {
-                    if idx != 0 {
-                        write!(f, " ")?;
-                    }
-                    param.fmt(f)?;
-                }
+                fmt_values(f, &call.params, names)?;
                 write!(f, ")")?;
             }
 
+            InstKind::IndirectCall(call) => {
+                write!(f, "call_indirect ")?;
+                call.callee.fmt_ctx(f, names)?;
+                write!(f, " (")?;
+                fmt_values(f, &call.params, names)?;
+                write!(f, ")")?;
+            }
+
+            InstKind::FuncRef(name) => {
+                write!(f, "funcref {}", name)?;
+            }
+
             InstKind::Assign(i) => {
-                i.fmt(f)?;
+                i.fmt_ctx(f, names)?;
             }
 
             InstKind::Param(id) => {
@@ -138,9 +229,35 @@ impl FormatContext<VarId> for Tac {
                     } else {
                         first = false;
                     }
-                    write!(f, "({} {})", val, bb)?;
+                    write!(f, "({} {})", names.bb(bb), names.inst(val))?;
                 }
             }
+
+            InstKind::Alloca(alloca) => {
+                write!(f, "alloca {}", alloca.ty)?;
+                if alloca.count != 1 {
+                    write!(f, " {}", alloca.count)?;
+                }
+            }
+
+            InstKind::Load(ptr) => {
+                write!(f, "load ")?;
+                ptr.fmt_ctx(f, names)?;
+            }
+
+            InstKind::Store(store) => {
+                write!(f, "store ")?;
+                store.ptr.fmt_ctx(f, names)?;
+                write!(f, " ")?;
+                store.value.fmt_ctx(f, names)?;
+            }
+
+            InstKind::Offset(offset) => {
+                write!(f, "offset ")?;
+                offset.ptr.fmt_ctx(f, names)?;
+                write!(f, " ")?;
+                offset.offset.fmt_ctx(f, names)?;
+            }
         }
         write!(f, ")")?;
         Ok(())
@@ -149,29 +266,32 @@ impl FormatContext<VarId> for Tac {
 
 impl Display for Branch {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_ctx(f, &Names::Slots)
+    }
+}
+
+impl FormatContext<&Names> for Branch {
+    fn fmt_ctx(&self, f: &mut std::fmt::Formatter<'_>, names: &Names) -> std::fmt::Result {
         write!(f, "(")?;
         match self {
             Branch::Return(v) => {
                 write!(f, "return")?;
                 if let Some(val) = v {
-                    write!(f, " {}", val)?;
+                    write!(f, " ")?;
+                    val.fmt_ctx(f, names)?;
                 }
             }
             Branch::Jump(target) => {
-                write!(f, "br {}", target)?;
+                write!(f, "br {}", names.bb(*target))?;
             }
             Branch::CondJump {
                 cond,
                 if_true,
                 if_false,
             } => {
-                write!(
-                    f,
-                    "brif {} bb{} bb{}",
-                    cond,
-                    if_true.slot(),
-                    if_false.slot()
-                )?;
+                write!(f, "brif ")?;
+                cond.fmt_ctx(f, names)?;
+                write!(f, " {} {}", names.bb(*if_true), names.bb(*if_false))?;
             }
             Branch::Unreachable => {
                 write!(f, "unreachable")?;
@@ -182,24 +302,62 @@ impl Display for Branch {
     }
 }
 
+impl Display for SourceLoc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
+/// Writes the source location of an instruction as a comment. Comments are
+/// skipped by the parser, so locations are lost when parsing TAC.
+fn write_loc(f: &mut std::fmt::Formatter<'_>, loc: Option<SourceLoc>) -> std::fmt::Result {
+    match loc {
+        Some(loc) => write!(f, " ; {}", loc),
+        None => Ok(()),
+    }
+}
+
+impl std::fmt::Display for FuncAttr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Prints the function as TAC text. Basic blocks and instructions are numbered
+/// densely in layout order, so the text only depends on the code.
+///
+/// The alternate form (`{:#}`) names them by their slots instead, matching
+/// [`BBId`] and [`InstId`] inside debug output and error messages.
 impl std::fmt::Display for TacFunc {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names = if f.alternate() {
+            Names::Slots
+        } else {
+            Names::canonical(self)
+        };
         let ty = self.ty.as_func().unwrap();
-        let param_fmt = ListFormatter::new(ty.params.iter());
+        let param_fmt = ListFormatter::with_separator(ty.params.iter(), " ");
         write!(f, "(fn {} ({}) {}", &self.name, param_fmt, &ty.return_type)?;
+        if !self.attrs.is_empty() {
+            let attr_fmt = ListFormatter::with_separator(self.attrs.iter(), " ");
+            write!(f, " (attrs {})", attr_fmt)?;
+        }
 
         for (k, v) in self.bb_iter() {
             writeln!(f)?;
-            write!(f, "\t(bb{} (", k.slot())?;
+            write!(f, "\t({} (", names.bb(k))?;
+            // The location of the last instruction is written after the
+            // parentheses closing it, so the comment does not hide them
+            let mut loc = None;
             if let Some(x) = v.head {
                 let mut cur_idx = x;
                 loop {
                     let i = self.instructions_arena.get(cur_idx).unwrap();
-                    let cur_id = cur_idx.slot();
-
+                    write_loc(f, loc)?;
                     writeln!(f)?;
                     write!(f, "\t\t")?;
-                    i.fmt_ctx(f, VarId(cur_id))?;
+                    i.fmt_ctx(f, (names.inst(cur_idx), &names))?;
+                    loc = i.loc;
 
                     match i.next {
                         Some(x) => cur_idx = x,
@@ -209,12 +367,24 @@ impl std::fmt::Display for TacFunc {
                     }
                 }
             }
-            writeln!(f, ")")?;
+            write!(f, ")")?;
+            write_loc(f, loc)?;
+            writeln!(f)?;
             write!(f, "\t\t")?;
-            v.branch.fmt(f)?;
+            v.branch.fmt_ctx(f, &names)?;
             write!(f, ")")?;
         }
         writeln!(f, ")")?;
         Ok(())
     }
 }
+
+/// Prints every function of the program, in the order they are defined.
+impl std::fmt::Display for Program {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for function in self.functions.values() {
+            writeln!(f, "{}", function)?;
+        }
+        Ok(())
+    }
+}
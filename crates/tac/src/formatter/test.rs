@@ -0,0 +1,139 @@
+use crate::parser::parse_program_from_string;
+
+use super::dot::{program_to_dot, DotOptions};
+
+#[test]
+fn test_canonical_names() {
+    let input = r"
+    (fn g () i32
+        (bb0 ()
+            (return 0)))
+    (fn f (i32) i32
+        (bb7 (
+            (%12 i32 param 0)
+            (%3 i32 add %12 1)
+            (%40 b32 lt %3 10))
+            (brif %40 bb2 bb9))
+        (bb9 (
+            (%5 i32 phi (bb7 %3) (bb2 %5)))
+            (return %5))
+        (bb2 ()
+            (br bb9)))
+    ";
+    let mut program = parse_program_from_string(input).unwrap();
+    // Functions keep the order they are defined in
+    assert_eq!(program.functions.keys().collect::<Vec<_>>(), ["g", "f"]);
+
+    let expected = "\
+(fn f (i32) i32
+\t(bb0 (
+\t\t(%0 i32 param 0)
+\t\t(%1 i32 add %0 1)
+\t\t(%2 b32 lt %1 10))
+\t\t(brif %2 bb2 bb1))
+\t(bb1 (
+\t\t(%3 i32 phi (bb0 %1) (bb2 %3)))
+\t\t(return %3))
+\t(bb2 ()
+\t\t(br bb1)))
+";
+    let f = program.functions.get_mut("f").unwrap();
+    assert_eq!(f.to_string(), expected);
+    let reparsed = parse_program_from_string(expected).unwrap();
+    assert_eq!(reparsed.functions["f"].to_string(), expected);
+
+    // Detaching an instruction renumbers the rest
+    let first = f.bb_get(f.first_block.unwrap()).head.unwrap();
+    let add = f.tac_get(first).next.unwrap();
+    f.inst_detach(add);
+    let printed = f.to_string();
+    assert!(printed.contains("(%1 b32 lt _ 10)"), "{}", printed);
+    assert!(
+        printed.contains("(%2 i32 phi (bb0 _) (bb2 %2))"),
+        "{}",
+        printed
+    );
+
+    // The alternate form uses slots
+    let slots = format!("{:#}", f);
+    assert!(
+        slots.contains(&format!("({} i32 param 0)", first)),
+        "{}",
+        slots
+    );
+}
+
+#[test]
+fn test_dot() {
+    let input = r"
+    (fn f (i32) i32
+        (bb0 (
+            (%0 i32 param 0)
+            (%1 b32 lt %0 10))
+            (brif %1 bb1 bb2))
+        (bb1 ()
+            (br bb2))
+        (bb2 (
+            (%2 i32 phi (bb0 %0) (bb1 %1)))
+            (return %2)))
+    ";
+    let program = parse_program_from_string(input).unwrap();
+    let f = &program.functions["f"];
+
+    let dot = f.to_dot(&DotOptions::default());
+    assert!(dot.starts_with("digraph \"f\" {\n"), "{}", dot);
+    assert!(
+        dot.contains(r#"<td port="i1" align="left">(%1 b32 lt %0 10)</td>"#),
+        "{}",
+        dot
+    );
+    assert!(dot.contains("\"f.bb0\":br -> \"f.bb1\" [label=true];"));
+    assert!(dot.contains("\"f.bb0\":br -> \"f.bb2\" [label=false];"));
+    assert!(dot.contains("\"f.bb1\":br -> \"f.bb2\";"));
+    assert!(!dot.contains("dashed") && !dot.contains("dotted"));
+
+    let dot = f.to_dot(&DotOptions {
+        dfg: true,
+        dom_tree: true,
+    });
+    assert!(dot.contains("\"f.bb0\":i0 -> \"f.bb0\":i1 [style=dashed"));
+    assert!(dot.contains("\"f.bb0\":i1 -> \"f.bb0\":br [style=dashed"));
+    assert!(dot.contains(
+        "\"f.bb0\":i1 -> \"f.bb2\":i2 [style=dashed, color=blue, constraint=false, label=\"bb1\"];"
+    ));
+    assert!(dot.contains("\"f.bb0\" -> \"f.bb2\" [style=dotted"));
+    assert!(!dot.contains("\"f.bb1\" -> \"f.bb2\" [style=dotted"));
+
+    let dot = program_to_dot(&program, &DotOptions::default());
+    assert!(dot.contains("subgraph \"cluster_f\" {"));
+}
+
+#[test]
+fn test_inst_format() {
+    let input = r"
+    (fn f (b32) i32
+        (bb0 (
+            (%0 b32 param 0))
+            (brif %0 bb1 bb2))
+        (bb1 (
+            (%1 i32 1))
+            (br bb3))
+        (bb2 (
+            (%2 i32 2))
+            (br bb3))
+        (bb3 (
+            (%3 i32 phi (bb1 %1) (bb2 %2)))
+            (return %3)))
+    ";
+    let program = parse_program_from_string(input).unwrap();
+    let printed = program.functions["f"].to_string();
+    // Destinations are printed as values, and phi sources as `(bb value)`
+    assert!(printed.contains("(%0 b32 param 0)"), "{}", printed);
+    assert!(
+        printed.contains("(%3 i32 phi (bb1 %1) (bb2 %2))"),
+        "{}",
+        printed
+    );
+    let reparsed = parse_program_from_string(&printed).unwrap();
+    assert_eq!(reparsed.functions["f"].to_string(), printed);
+}
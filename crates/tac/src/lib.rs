@@ -12,35 +12,102 @@
 
 #![allow(clippy::upper_case_acronyms)]
 
+pub mod analysis;
+pub mod binary;
 pub mod builder;
 pub mod containers;
 pub mod err;
 pub mod formatter;
 mod linkedlist;
+pub mod linker;
 pub mod optimizer;
 pub mod parser;
+#[cfg(feature = "serde_impl")]
+pub mod serde_impl;
 pub mod ty;
+pub mod users;
 pub mod util;
+pub mod verifier;
 
-use std::collections::{BTreeMap, HashMap};
+#[cfg(test)]
+mod test;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 use enum_as_inner::EnumAsInner;
 use err::{Error, TacResult};
+use indexmap::IndexMap;
 
 pub use linkedlist::*;
 
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
 use slotmap::SlotMap;
 use smol_str::SmolStr;
 
 pub use ty::{NumericTy, Ty, TyKind};
+pub use users::UsePlace;
+use users::{BBMut, InstMut, TacMut, UseMap};
 use util::VarIter;
 
 pub use containers::BBId;
 pub use containers::InstId;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Program {
-    pub functions: HashMap<SmolStr, TacFunc>,
+    /// Functions defined in this program, in the order they are defined.
+    pub functions: IndexMap<SmolStr, TacFunc>,
+    /// Functions used by this program but defined in other programs, with
+    /// their types. They are resolved by [`linker::link`].
+    pub imports: HashMap<SmolStr, Ty>,
+}
+
+/// An attribute of a function, giving hints to optimization passes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde_impl", serde(rename_all = "lowercase"))]
+pub enum FuncAttr {
+    /// The function should be inlined into its callers.
+    Inline,
+    /// The function should never be inlined.
+    NoInline,
+    /// The function has no side effects, and its result only depends on its
+    /// parameters.
+    Pure,
+    /// The function is rarely called, and should not be optimized for speed.
+    Cold,
+}
+
+impl FuncAttr {
+    pub const ALL: &'static [FuncAttr] = &[
+        FuncAttr::Inline,
+        FuncAttr::NoInline,
+        FuncAttr::Pure,
+        FuncAttr::Cold,
+    ];
+
+    /// The name of this attribute, as written in source code and TAC.
+    pub fn name(self) -> &'static str {
+        match self {
+            FuncAttr::Inline => "inline",
+            FuncAttr::NoInline => "noinline",
+            FuncAttr::Pure => "pure",
+            FuncAttr::Cold => "cold",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<FuncAttr> {
+        FuncAttr::ALL.iter().copied().find(|a| a.name() == name)
+    }
+
+    /// The attribute that cannot be used together with this one, if any.
+    pub fn conflicting(self) -> Option<FuncAttr> {
+        match self {
+            FuncAttr::Inline => Some(FuncAttr::NoInline),
+            FuncAttr::NoInline => Some(FuncAttr::Inline),
+            FuncAttr::Pure | FuncAttr::Cold => None,
+        }
+    }
 }
 
 /// A function made of TAC instructions.
@@ -57,12 +124,16 @@ pub struct TacFunc {
     pub name: SmolStr,
     /// Function type
     pub ty: Ty,
+    /// Attributes of this function.
+    pub attrs: BTreeSet<FuncAttr>,
 
     // The followings are allocating spaces for data types
     /// An arena to allocate instructions
     instructions_arena: SlotMap<InstId, Tac>,
     /// An arena to allocate basic block info
     basic_block_arena: SlotMap<BBId, BasicBlock>,
+    /// Users of every instruction
+    users: UseMap,
 
     pub first_block: Option<BBId>,
 }
@@ -79,8 +150,10 @@ impl TacFunc {
         TacFunc {
             name,
             ty,
+            attrs: BTreeSet::new(),
             instructions_arena: SlotMap::with_key(),
             basic_block_arena: SlotMap::with_key(),
+            users: UseMap::default(),
             first_block: None,
         }
     }
@@ -92,6 +165,10 @@ impl TacFunc {
     pub fn starting_block(&self) -> Option<BBId> {
         self.first_block
     }
+
+    pub fn has_attr(&self, attr: FuncAttr) -> bool {
+        self.attrs.contains(&attr)
+    }
 }
 
 /// Methods for manipulating instructions inside a function.
@@ -102,8 +179,12 @@ impl TacFunc {
     /// Insert a new TAC into arena with no next instruction, without a proper
     /// basic block ID.
     pub fn inst_new(&mut self, inst: Inst) -> InstId {
-        self.instructions_arena
-            .insert(Tac::independent(inst, BBId::default()))
+        let operands = inst.kind.param_op_iter().collect::<Vec<_>>();
+        let idx = self
+            .instructions_arena
+            .insert(Tac::independent(inst, BBId::default()));
+        self.users.update(UsePlace::Inst(idx), None, operands);
+        idx
     }
 
     pub fn inst_exists(&self, inst: InstId) -> bool {
@@ -125,9 +206,16 @@ impl TacFunc {
     }
 
     /// Gets a mutable reference of the instruction with extra information around it
-    /// as a `Tac`
+    /// as a `Tac`. Users of values are updated when the reference is dropped.
+    #[inline]
+    pub fn tac_get_mut(&mut self, idx: InstId) -> TacMut<'_> {
+        TacMut::new(self, idx)
+    }
+
+    /// Gets a mutable reference of the instruction without updating users.
+    /// Only used for moving instructions around.
     #[inline]
-    pub fn tac_get_mut(&mut self, idx: InstId) -> &mut Tac {
+    fn tac_raw_mut(&mut self, idx: InstId) -> &mut Tac {
         self.instructions_arena.get_item_mut(idx)
     }
 
@@ -137,19 +225,26 @@ impl TacFunc {
         &self.tac_get(idx).inst
     }
 
-    /// Get a mutable reference of the instruction body
+    /// Get a mutable reference of the instruction body. Users of values are
+    /// updated when the reference is dropped.
+    #[inline]
+    pub fn inst_get_mut(&mut self, idx: InstId) -> InstMut<'_> {
+        InstMut(self.tac_get_mut(idx))
+    }
+
+    /// Get the source location of the instruction
     #[inline]
-    pub fn inst_get_mut(&mut self, idx: InstId) -> &mut Inst {
-        &mut self.tac_get_mut(idx).inst
+    pub fn inst_loc(&self, idx: InstId) -> Option<SourceLoc> {
+        self.tac_get(idx).loc
     }
 
     /// Position this instruction after the given instruction.
     pub fn inst_set_after(&mut self, after: InstId, inst: InstId) {
         self.instructions_arena.attach_after(after, inst);
         let bb = self.tac_get(after).bb;
-        self.tac_get_mut(inst).bb = bb;
+        self.tac_raw_mut(inst).bb = bb;
 
-        let bb = self.bb_get_mut(bb);
+        let bb = self.bb_raw_mut(bb);
         if bb.tail == Some(after) {
             bb.tail = Some(inst);
         }
@@ -159,9 +254,9 @@ impl TacFunc {
     pub fn inst_set_before(&mut self, before: InstId, inst: InstId) {
         self.instructions_arena.attach_before(before, inst);
         let bb = self.tac_get(before).bb;
-        self.tac_get_mut(inst).bb = bb;
+        self.tac_raw_mut(inst).bb = bb;
 
-        let bb = self.bb_get_mut(bb);
+        let bb = self.bb_raw_mut(bb);
         if bb.head == Some(before) {
             bb.head = Some(inst);
         }
@@ -171,8 +266,8 @@ impl TacFunc {
     pub fn inst_append_in_bb(&mut self, inst: InstId, bb: BBId) {
         debug_assert!(self.tac_get(inst).is_freestanding());
 
-        self.tac_get_mut(inst).bb = bb;
-        let bb = self.bb_get_mut(bb);
+        self.tac_raw_mut(inst).bb = bb;
+        let bb = self.bb_raw_mut(bb);
         let old_tail = bb.tail.replace(inst);
         if bb.head.is_none() {
             bb.head = Some(inst);
@@ -186,8 +281,8 @@ impl TacFunc {
     pub fn inst_prepend_in_bb(&mut self, inst: InstId, bb: BBId) {
         debug_assert!(self.tac_get(inst).is_freestanding());
 
-        self.tac_get_mut(inst).bb = bb;
-        let bb = self.bb_get_mut(bb);
+        self.tac_raw_mut(inst).bb = bb;
+        let bb = self.bb_raw_mut(bb);
         let old_head = bb.head.replace(inst);
         if bb.tail.is_none() {
             bb.tail = Some(inst);
@@ -199,17 +294,17 @@ impl TacFunc {
 
     /// Detaches this instruction from the instruction chain.
     pub fn inst_detach(&mut self, idx: InstId) {
-        let inst = self.tac_get_mut(idx);
+        let inst = self.tac_raw_mut(idx);
         let next = inst.next;
         let prev = inst.prev;
         let bb = inst.bb;
 
         self.instructions_arena.detach(idx);
 
-        let inst = self.tac_get_mut(idx);
+        let inst = self.tac_raw_mut(idx);
         inst.bb = BBId::default();
 
-        let bb = self.bb_get_mut(bb);
+        let bb = self.bb_raw_mut(bb);
         if bb.head == Some(idx) {
             bb.head = next;
         }
@@ -231,7 +326,10 @@ impl TacFunc {
             "The instruction should be detached from the chain"
         );
 
-        self.instructions_arena.remove(idx).unwrap().inst
+        let inst = self.instructions_arena.remove(idx).unwrap().inst;
+        let operands = inst.kind.param_op_iter();
+        self.users.update(UsePlace::Inst(idx), operands, None);
+        inst
     }
 
     /// Connect TAC instruction `head` to the place after `tail`.
@@ -253,7 +351,7 @@ impl TacFunc {
     ///
     /// Errors if `pos` does not exist or there is no code after `tail`.
     fn inst_split_off_after(&mut self, pos: InstId) -> Option<InstId> {
-        let tail = self.tac_get_mut(pos);
+        let tail = self.tac_raw_mut(pos);
         tail.next.take()
     }
 
@@ -266,9 +364,12 @@ impl TacFunc {
 
     pub fn inst_of_bb_iter(&self, bb: BBId) -> impl Iterator<Item = (InstId, &Inst)> {
         let bb = self.bb_get(bb);
-        self.inst_iter(bb.head, bb.tail)
+        // `inst_iter` stops before `end`, so end after the tail instead
+        let end = bb.tail.and_then(|tail| self.inst_next(tail));
+        self.inst_iter(bb.head, end)
     }
 
+    /// Iterates instructions from `start` until (but not including) `end`.
     pub fn inst_iter(
         &self,
         start: Option<InstId>,
@@ -280,6 +381,34 @@ impl TacFunc {
     }
 }
 
+/// Methods for querying and rewriting users of instructions. See
+/// [`users`] for how they are maintained.
+impl TacFunc {
+    /// Returns the places using the value of `value`.
+    pub fn users_of(&self, value: InstId) -> impl Iterator<Item = UsePlace> + '_ {
+        self.users.users_of(value)
+    }
+
+    /// Whether the value of `value` is not used anywhere.
+    pub fn is_unused(&self, value: InstId) -> bool {
+        self.users.is_unused(value)
+    }
+
+    /// Replace every use of `value` with `with`.
+    pub fn replace_all_uses_with(&mut self, value: InstId, with: InstId) {
+        if value == with {
+            return;
+        }
+        let users = self.users_of(value).collect::<Vec<_>>();
+        for user in users {
+            match user {
+                UsePlace::Inst(inst) => self.inst_get_mut(inst).kind.replace_dest(value, with),
+                UsePlace::Branch(bb) => self.bb_get_mut(bb).branch.replace_dest(value, with),
+            }
+        }
+    }
+}
+
 /// Methods for playing with basic blocks
 impl TacFunc {
     /// Insert a new basic block into this function
@@ -296,13 +425,24 @@ impl TacFunc {
         &self.basic_block_arena[idx]
     }
 
+    /// Get a mutable reference of the basic block. Users of values are updated
+    /// when the reference is dropped.
+    #[inline]
+    pub fn bb_get_mut(&mut self, idx: BBId) -> BBMut<'_> {
+        BBMut::new(self, idx)
+    }
+
+    /// Gets a mutable reference of the basic block without updating users.
+    /// Only used for moving instructions around.
     #[inline]
-    pub fn bb_get_mut(&mut self, idx: BBId) -> &mut BasicBlock {
+    fn bb_raw_mut(&mut self, idx: BBId) -> &mut BasicBlock {
         &mut self.basic_block_arena[idx]
     }
 
+    /// Get mutable references of two basic blocks. Users of values are _not_
+    /// updated.
     #[inline]
-    pub fn bb_get2_mut(&mut self, i1: BBId, i2: BBId) -> (&mut BasicBlock, &mut BasicBlock) {
+    fn bb_get2_mut(&mut self, i1: BBId, i2: BBId) -> (&mut BasicBlock, &mut BasicBlock) {
         let [v1, v2] = self
             .basic_block_arena
             .get_disjoint_mut([i1, i2])
@@ -343,7 +483,7 @@ impl TacFunc {
     pub fn bb_split_after(&mut self, inst: InstId, transfer_branches: bool) -> BBId {
         let after_head = self.inst_split_off_after(inst);
         let first_bb_id = self.tac_get(inst).bb;
-        let first_bb = self.bb_get_mut(first_bb_id);
+        let first_bb = self.bb_raw_mut(first_bb_id);
         let orig_tail = first_bb.tail.take();
 
         let jumps = transfer_branches
@@ -351,7 +491,13 @@ impl TacFunc {
             .unwrap_or(Branch::Unreachable);
         let new_bb_id = self.bb_new();
 
-        let new_bb = self.bb_get_mut(new_bb_id);
+        let moved_use = jumps.param_op();
+        self.users
+            .update(UsePlace::Branch(first_bb_id), moved_use, None);
+        self.users
+            .update(UsePlace::Branch(new_bb_id), None, moved_use);
+
+        let new_bb = self.bb_raw_mut(new_bb_id);
         new_bb.tail = orig_tail;
         new_bb.head = after_head;
         new_bb.branch = jumps;
@@ -360,7 +506,7 @@ impl TacFunc {
             // fix bb pointers
             let mut it = new_bb.head;
             while let Some(inst) = it {
-                let tac = self.tac_get_mut(inst);
+                let tac = self.tac_raw_mut(inst);
                 tac.bb = new_bb_id;
                 it = tac.next();
             }
@@ -384,6 +530,13 @@ impl TacFunc {
         let back_jump = std::mem::take(&mut back_bb.branch);
         let branches = std::mem::replace(&mut front_bb.branch, back_jump);
 
+        // The branch of `front` is removed, and the one of `back` moves into it
+        let back_use = front_bb.branch.param_op();
+        self.users
+            .update(UsePlace::Branch(front), branches.param_op(), back_use);
+        self.users.update(UsePlace::Branch(back), back_use, None);
+        let (front_bb, back_bb) = self.bb_get2_mut(front, back);
+
         let front_tail = front_bb.tail;
         let back_head = back_bb.head;
 
@@ -401,7 +554,7 @@ impl TacFunc {
                 // fix bb pointers
                 let mut it = back_head;
                 while let Some(inst) = it {
-                    let tac = self.tac_get_mut(inst);
+                    let tac = self.tac_raw_mut(inst);
                     tac.bb = front;
                     it = tac.next();
                 }
@@ -440,12 +593,24 @@ impl BasicBlock {
     }
 }
 
+/// A location in the source code producing an instruction, for debugging.
+/// Both line and column start from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+pub struct SourceLoc {
+    pub line: u32,
+    pub col: u32,
+}
+
 /// Represents a single TAC instruction inside an indirect doubly linked list of instructions.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Tac {
     /// The actual instruction.
     pub inst: Inst,
 
+    /// Where this instruction comes from in the source code, if known.
+    pub loc: Option<SourceLoc>,
+
     /// The basic block this instruction is in.
     pub bb: BBId,
 
@@ -459,6 +624,7 @@ impl Tac {
     pub fn new(inst: Inst, prev: Option<InstId>, next: Option<InstId>, bb: BBId) -> Self {
         Self {
             inst,
+            loc: None,
             prev,
             next,
             bb,
@@ -468,6 +634,7 @@ impl Tac {
     pub fn independent(inst: Inst, bb: BBId) -> Tac {
         Tac {
             inst,
+            loc: None,
             bb,
             prev: None,
             next: None,
@@ -488,6 +655,36 @@ pub struct FunctionCall {
     pub params: Vec<Value>,
 }
 
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct IndirectCall {
+    /// The function value being called. Must be of a function type.
+    pub callee: Value,
+    pub params: Vec<Value>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AllocaInst {
+    /// The type of values inside the stack slot.
+    pub ty: Ty,
+    /// The number of values inside the stack slot, more than 1 for arrays.
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct StoreInst {
+    /// The place to store into. Must be of a pointer type.
+    pub ptr: Value,
+    pub value: Value,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct OffsetInst {
+    /// The pointer being moved. Must be of a pointer type.
+    pub ptr: Value,
+    /// The number of values of the pointee type to move by. May be negative.
+    pub offset: Value,
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Inst {
     pub kind: InstKind,
@@ -503,6 +700,12 @@ pub enum InstKind {
     /// A call to another function.
     FunctionCall(FunctionCall),
 
+    /// A call to the function referred by a value.
+    IndirectCall(IndirectCall),
+
+    /// A reference to the function with the given name, as a value.
+    FuncRef(SmolStr),
+
     /// An assignment from another instruction or constant
     Assign(Value),
 
@@ -515,6 +718,19 @@ pub enum InstKind {
 
     /// A function parameter
     Param(usize),
+
+    /// A stack slot inside the frame of the current function, as a pointer to
+    /// its first value. The slot is freed when the function returns.
+    Alloca(AllocaInst),
+
+    /// A load of the value pointed to by a pointer.
+    Load(Value),
+
+    /// A store of a value into the place pointed to by a pointer.
+    Store(StoreInst),
+
+    /// A pointer moved by a number of values of its pointee type.
+    Offset(OffsetInst),
 }
 
 impl InstKind {
@@ -524,6 +740,10 @@ impl InstKind {
             InstKind::FunctionCall(f) => {
                 VarIter::Iter(Box::new(f.params.iter().cloned()) as Box<dyn Iterator<Item = _>>)
             }
+            InstKind::IndirectCall(f) => {
+                let iter = std::iter::once(f.callee).chain(f.params.iter().cloned());
+                VarIter::Iter(Box::new(iter) as Box<dyn Iterator<Item = _>>)
+            }
             InstKind::Assign(v) => VarIter::One(*v),
             InstKind::Phi(source) => {
                 if source.is_empty() {
@@ -533,7 +753,12 @@ impl InstKind {
                         as Box<dyn Iterator<Item = _>>)
                 }
             }
+            InstKind::FuncRef(_) => VarIter::None,
             InstKind::Param(_) => VarIter::None,
+            InstKind::Alloca(_) => VarIter::None,
+            InstKind::Load(v) => VarIter::One(*v),
+            InstKind::Store(s) => VarIter::Two(s.ptr, s.value),
+            InstKind::Offset(o) => VarIter::Two(o.ptr, o.offset),
         }
     }
 
@@ -547,16 +772,40 @@ impl InstKind {
                 .params
                 .iter_mut()
                 .for_each(|x| x.replace_dest(replace, with)),
+            InstKind::IndirectCall(f) => {
+                f.callee.replace_dest(replace, with);
+                f.params
+                    .iter_mut()
+                    .for_each(|x| x.replace_dest(replace, with));
+            }
             InstKind::Assign(v) => v.replace_dest(replace, with),
             InstKind::Phi(source) => source.iter_mut().for_each(|(_, v)| {
                 if *v == replace {
                     *v = with
                 }
             }),
-            InstKind::Param(_) => {}
+            InstKind::Load(v) => v.replace_dest(replace, with),
+            InstKind::Store(s) => {
+                s.ptr.replace_dest(replace, with);
+                s.value.replace_dest(replace, with);
+            }
+            InstKind::Offset(o) => {
+                o.ptr.replace_dest(replace, with);
+                o.offset.replace_dest(replace, with);
+            }
+            InstKind::FuncRef(_) | InstKind::Param(_) | InstKind::Alloca(_) => {}
         }
     }
 
+    /// Whether this instruction does anything besides producing its value, so
+    /// it must be kept even if the value is unused.
+    pub fn has_side_effects(&self) -> bool {
+        matches!(
+            self,
+            InstKind::Store(_) | InstKind::FunctionCall(_) | InstKind::IndirectCall(_)
+        )
+    }
+
     pub fn replace_phi_source(&mut self, replace: BBId, with: BBId) {
         if let InstKind::Phi(s) = self {
             if let Some(t) = s.remove(&replace) {
@@ -603,6 +852,24 @@ impl Default for Branch {
 }
 
 impl Branch {
+    /// Returns the instruction whose value is used by this branch, i.e. the
+    /// condition or the return value.
+    pub fn param_op(&self) -> Option<InstId> {
+        match self {
+            Branch::Return(Some(v)) | Branch::CondJump { cond: v, .. } => v.get_inst(),
+            _ => None,
+        }
+    }
+
+    pub fn replace_dest(&mut self, replace: InstId, with: InstId) {
+        match self {
+            Branch::Return(Some(v)) | Branch::CondJump { cond: v, .. } => {
+                v.replace_dest(replace, with)
+            }
+            _ => {}
+        }
+    }
+
     pub fn target_iter(&self) -> impl Iterator<Item = BBId> + '_ {
         match self {
             Branch::Return(_) => util::OptionIter::<BBId>::None,
@@ -640,6 +907,8 @@ impl Branch {
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Copy)]
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde_impl", serde(rename_all = "lowercase"))]
 pub enum BinaryOp {
     Add,
     Sub,
@@ -0,0 +1,54 @@
+//! Linking multiple programs into one.
+
+#[cfg(test)]
+mod test;
+
+use crate::{err::LinkError, InstKind, Program};
+
+/// Merge `programs` into a single program.
+///
+/// Every function must be defined exactly once. Imports of every program must
+/// match the type of the definition, and every called or referenced function
+/// must be defined in some program.
+pub fn link(programs: impl IntoIterator<Item = Program>) -> Result<Program, LinkError> {
+    let mut res = Program::default();
+    let mut imports = vec![];
+    for program in programs {
+        for (name, func) in program.functions {
+            if res.functions.contains_key(&name) {
+                return Err(LinkError::DuplicateSymbol(name));
+            }
+            res.functions.insert(name, func);
+        }
+        imports.extend(program.imports);
+    }
+
+    for (name, ty) in imports {
+        let func = res
+            .functions
+            .get(&name)
+            .ok_or_else(|| LinkError::MissingSymbol(name.clone()))?;
+        if func.ty != ty {
+            return Err(LinkError::TypeMismatch {
+                expected: func.ty.clone(),
+                found: ty,
+                name,
+            });
+        }
+    }
+
+    for func in res.functions.values() {
+        for (_, _, inst) in func.all_inst_unordered() {
+            let callee = match &inst.kind {
+                InstKind::FunctionCall(call) => &call.name,
+                InstKind::FuncRef(name) => name,
+                _ => continue,
+            };
+            if !res.functions.contains_key(callee) {
+                return Err(LinkError::MissingSymbol(callee.clone()));
+            }
+        }
+    }
+
+    Ok(res)
+}
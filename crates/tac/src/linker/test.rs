@@ -0,0 +1,71 @@
+use crate::{err::LinkError, parser::parse_program_from_string, Program, Ty};
+
+use super::link;
+
+fn parse(input: &str) -> Program {
+    parse_program_from_string(input).unwrap()
+}
+
+fn lib() -> Program {
+    parse(
+        r"
+    (fn add (i32 i32) i32
+        (bb0 (
+            (%0 i32 param 0)
+            (%1 i32 param 1)
+            (%2 i32 add %0 %1))
+            (return %2)))
+    ",
+    )
+}
+
+fn user(import: Ty) -> Program {
+    let mut program = parse(
+        r"
+    (fn main () i32
+        (bb0 (
+            (%0 i32 call add (1 2)))
+            (return %0)))
+    ",
+    );
+    program.imports.insert("add".into(), import);
+    program
+}
+
+fn add_ty() -> Ty {
+    Ty::func_of(Ty::int(), vec![Ty::int(), Ty::int()])
+}
+
+#[test]
+fn test_link() {
+    let program = link(vec![user(add_ty()), lib()]).unwrap();
+    let names = program.functions.keys().collect::<Vec<_>>();
+    assert_eq!(names, vec!["main", "add"]);
+    assert!(program.imports.is_empty());
+}
+
+#[test]
+fn test_duplicate_symbol() {
+    assert!(matches!(
+        link(vec![lib(), user(add_ty()), lib()]),
+        Err(LinkError::DuplicateSymbol(name)) if name == "add"
+    ));
+}
+
+#[test]
+fn test_type_mismatch() {
+    let import = Ty::func_of(Ty::int(), vec![Ty::int()]);
+    assert!(matches!(
+        link(vec![user(import.clone()), lib()]),
+        Err(LinkError::TypeMismatch { name, expected, found })
+            if name == "add" && expected == add_ty() && found == import
+    ));
+}
+
+#[test]
+fn test_missing_symbol() {
+    assert!(matches!(
+        link(vec![user(add_ty())]),
+        Err(LinkError::MissingSymbol(name)) if name == "add"
+    ));
+}
@@ -0,0 +1,287 @@
+//! Printing functions before and after passes, for debugging passes.
+//!
+//! Functions are printed with their [`Display`](std::fmt::Display)
+//! implementation, either into stderr or as files inside a dump directory.
+
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    path::PathBuf,
+};
+
+use smol_str::SmolStr;
+
+use crate::Program;
+
+/// Lines of context around changes inside diffs.
+const DIFF_CONTEXT: usize = 3;
+
+/// When and how to print functions around passes.
+#[derive(Debug, Clone, Default)]
+pub struct DumpOptions {
+    /// Print every function before these passes.
+    pub print_before: HashSet<String>,
+    /// Print every function after these passes.
+    pub print_after: HashSet<String>,
+    /// Print every function after all passes.
+    pub print_after_all: bool,
+    /// Print functions whose text is changed by a pass, after any pass. Other
+    /// functions are not printed after passes.
+    pub print_changed: bool,
+    /// Print changes to functions as unified diffs instead of the whole text.
+    /// Implies `print_changed`.
+    pub diff: bool,
+    /// Write every dump into a file inside this directory instead of stderr.
+    pub dump_dir: Option<PathBuf>,
+}
+
+impl DumpOptions {
+    fn prints_after(&self, pass: &str) -> bool {
+        self.print_after_all || self.print_changed || self.diff || self.print_after.contains(pass)
+    }
+
+    fn only_changed(&self) -> bool {
+        self.print_changed || self.diff
+    }
+}
+
+/// Text of functions before a pass.
+pub(super) type Snapshot = HashMap<SmolStr, String>;
+
+pub(super) struct Dumper {
+    pub(super) options: DumpOptions,
+    /// Number of dumps written, used to order files in the dump directory.
+    counter: usize,
+    /// The first file that failed to be written. Nothing is dumped after it.
+    error: Option<(PathBuf, io::Error)>,
+}
+
+impl Dumper {
+    pub(super) fn new(options: DumpOptions) -> Dumper {
+        Dumper {
+            options,
+            counter: 0,
+            error: None,
+        }
+    }
+
+    /// Take the error of the first dump that failed to be written, if any.
+    pub(super) fn take_error(&mut self) -> Option<(PathBuf, io::Error)> {
+        self.error.take()
+    }
+
+    /// Print functions before running `pass` if needed. Returns their text if
+    /// it's needed after the pass.
+    pub(super) fn before_pass(&mut self, pass: &str, program: &Program) -> Option<Snapshot> {
+        let print = self.options.print_before.contains(pass);
+        let snapshot = self.options.prints_after(pass) && self.options.only_changed();
+        if !print && !snapshot {
+            return None;
+        }
+
+        let texts = function_texts(program);
+        if print {
+            for (name, text) in &texts {
+                self.emit(pass, "before", name, "tac", text);
+            }
+        }
+        if snapshot {
+            Some(texts.into_iter().collect())
+        } else {
+            None
+        }
+    }
+
+    /// Print functions after running `pass` if needed. `before` is returned by
+    /// [`before_pass`](Self::before_pass).
+    pub(super) fn after_pass(
+        &mut self,
+        pass: &str,
+        program: &Program,
+        changed: bool,
+        before: Option<Snapshot>,
+    ) {
+        if !self.options.prints_after(pass) || (self.options.only_changed() && !changed) {
+            return;
+        }
+
+        for (name, text) in function_texts(program) {
+            let old = before.as_ref().and_then(|b| b.get(&name));
+            if self.options.only_changed() && old == Some(&text) {
+                continue;
+            }
+            if self.options.diff {
+                let old = old.map_or("", |s| s.as_str());
+                let diff = unified_diff(
+                    old,
+                    &text,
+                    &format!("{} before {}", name, pass),
+                    &format!("{} after {}", name, pass),
+                );
+                self.emit(pass, "diff", &name, "diff", &diff);
+            } else {
+                self.emit(pass, "after", &name, "tac", &text);
+            }
+        }
+    }
+
+    fn emit(&mut self, pass: &str, stage: &str, func: &str, ext: &str, text: &str) {
+        if self.error.is_some() {
+            return;
+        }
+        self.counter += 1;
+        match &self.options.dump_dir {
+            Some(dir) => {
+                let file = dir.join(format!(
+                    "{:04}-{}-{}-{}.{}",
+                    self.counter, pass, stage, func, ext
+                ));
+                if let Err(e) = std::fs::write(&file, text) {
+                    self.error = Some((file, e));
+                }
+            }
+            None => {
+                eprintln!(
+                    "; *** IR dump {} `{}` (function `{}`) ***",
+                    stage, pass, func
+                );
+                eprintln!("{}", text);
+            }
+        }
+    }
+}
+
+/// Text of every function, in the order they are defined.
+fn function_texts(program: &Program) -> Vec<(SmolStr, String)> {
+    program
+        .functions
+        .iter()
+        .map(|(name, func)| (name.clone(), func.to_string()))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DiffLine {
+    Same(usize, usize),
+    Removed(usize),
+    Added(usize),
+}
+
+/// Diff two texts by lines, in unified diff format. Returns an empty string if
+/// they are the same.
+pub(crate) fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    let a = old.lines().collect::<Vec<_>>();
+    let b = new.lines().collect::<Vec<_>>();
+    let lines = diff_lines(&a, &b);
+
+    // Group changes with their context into hunks
+    let mut hunks: Vec<(usize, usize)> = vec![];
+    for (idx, line) in lines.iter().enumerate() {
+        if matches!(line, DiffLine::Same(..)) {
+            continue;
+        }
+        let start = idx.saturating_sub(DIFF_CONTEXT);
+        let end = (idx + DIFF_CONTEXT + 1).min(lines.len());
+        match hunks.last_mut() {
+            Some(last) if last.1 >= start => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+    if hunks.is_empty() {
+        return String::new();
+    }
+
+    let mut out = format!("--- {}\n+++ {}\n", old_name, new_name);
+    for (start, end) in hunks {
+        let hunk = &lines[start..end];
+        // Line numbers of the hunk start, counting lines before it
+        let (mut old_start, mut new_start) = (0, 0);
+        for line in &lines[..start] {
+            match line {
+                DiffLine::Same(..) => {
+                    old_start += 1;
+                    new_start += 1;
+                }
+                DiffLine::Removed(_) => old_start += 1,
+                DiffLine::Added(_) => new_start += 1,
+            }
+        }
+        let old_len = hunk
+            .iter()
+            .filter(|l| !matches!(l, DiffLine::Added(_)))
+            .count();
+        let new_len = hunk
+            .iter()
+            .filter(|l| !matches!(l, DiffLine::Removed(_)))
+            .count();
+        // Empty ranges start at the line before them
+        let old_start = if old_len == 0 {
+            old_start
+        } else {
+            old_start + 1
+        };
+        let new_start = if new_len == 0 {
+            new_start
+        } else {
+            new_start + 1
+        };
+        out += &format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_start, old_len, new_start, new_len
+        );
+        for line in hunk {
+            match *line {
+                DiffLine::Same(i, _) => out += &format!(" {}\n", a[i]),
+                DiffLine::Removed(i) => out += &format!("-{}\n", a[i]),
+                DiffLine::Added(j) => out += &format!("+{}\n", b[j]),
+            }
+        }
+    }
+    out
+}
+
+/// Compute a line diff from the longest common subsequence of `a` and `b`.
+fn diff_lines(a: &[&str], b: &[&str]) -> Vec<DiffLine> {
+    // Common prefix and suffix are kept out of the quadratic part
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+    let (n, m) = (a_mid.len(), b_mid.len());
+
+    // lcs[i][j] is the LCS length of a_mid[i..] and b_mid[j..]
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if a_mid[i] == b_mid[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = (0..prefix)
+        .map(|i| DiffLine::Same(i, i))
+        .collect::<Vec<_>>();
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && a_mid[i] == b_mid[j] {
+            lines.push(DiffLine::Same(prefix + i, prefix + j));
+            i += 1;
+            j += 1;
+        } else if j == m || (i < n && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(DiffLine::Removed(prefix + i));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(prefix + j));
+            j += 1;
+        }
+    }
+    lines.extend((0..suffix).map(|k| DiffLine::Same(prefix + n + k, prefix + m + k)));
+    lines
+}
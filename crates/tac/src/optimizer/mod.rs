@@ -1,9 +1,25 @@
-use std::{borrow::Cow, collections::HashMap};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+};
 
-use crate::{Program, TacFunc};
+use crate::{
+    analysis::{AnalysisManager, PreservedAnalyses},
+    err::PipelineError,
+    verifier, Program, TacFunc,
+};
 use anymap::AnyMap;
 
+use self::{
+    dump::{DumpOptions, Dumper},
+    schedule::{Schedule, MAX_FIXPOINT_ITERATIONS},
+};
+
+pub mod dump;
 pub mod sanity_checker;
+pub mod schedule;
+#[cfg(test)]
+mod test;
 
 /// Represents a single pass inside the compilation pipeline.
 ///
@@ -19,8 +35,24 @@ pub trait Pass {
     /// are given anyway.
     fn edits_program(&self) -> bool;
 
-    /// Optimize at program level.
-    fn optimize_program(&mut self, env: &mut OptimizeEnvironment, program: &mut Program);
+    /// Analyses that are still valid after running this pass. By default,
+    /// passes editing the program preserve nothing.
+    fn preserved_analyses(&self) -> PreservedAnalyses {
+        if self.edits_program() {
+            PreservedAnalyses::none()
+        } else {
+            PreservedAnalyses::all()
+        }
+    }
+
+    /// Names of passes that must run before this pass. Dependencies not run
+    /// earlier inside a schedule are inserted right before this pass.
+    fn dependencies(&self) -> Vec<Cow<'_, str>> {
+        vec![]
+    }
+
+    /// Optimize at program level. Returns whether the program is changed.
+    fn optimize_program(&mut self, env: &mut OptimizeEnvironment, program: &mut Program) -> bool;
 }
 
 pub trait FunctionOptimizer {
@@ -32,11 +64,33 @@ pub trait FunctionOptimizer {
     /// are given anyway.
     fn edits_program(&self) -> bool;
 
+    /// Analyses that are still valid after running this pass. By default,
+    /// passes editing the program preserve nothing.
+    fn preserved_analyses(&self) -> PreservedAnalyses {
+        if self.edits_program() {
+            PreservedAnalyses::none()
+        } else {
+            PreservedAnalyses::all()
+        }
+    }
+
+    /// Names of passes that must run before this pass. Dependencies not run
+    /// earlier inside a schedule are inserted right before this pass.
+    fn dependencies(&self) -> Vec<Cow<'_, str>> {
+        vec![]
+    }
+
     /// Reset this instance for optimizing another function.
     fn reset(&mut self) {}
 
-    /// Optimize a single function.
-    fn optimize_func(&mut self, env: &mut OptimizeEnvironment, func: &mut TacFunc);
+    /// Whether this pass should optimize `func`. Passes can check the
+    /// attributes of the function here, e.g. to skip `cold` functions.
+    fn should_optimize(&self, _func: &TacFunc) -> bool {
+        true
+    }
+
+    /// Optimize a single function. Returns whether the function is changed.
+    fn optimize_func(&mut self, env: &mut OptimizeEnvironment, func: &mut TacFunc) -> bool;
 
     /// Perform initialization before any functions are processed.
     fn do_initialization(&mut self, _env: &mut OptimizeEnvironment, _prog: &Program) {}
@@ -68,13 +122,30 @@ where
         self.0.edits_program()
     }
 
-    fn optimize_program(&mut self, env: &mut OptimizeEnvironment, program: &mut Program) {
+    fn preserved_analyses(&self) -> PreservedAnalyses {
+        self.0.preserved_analyses()
+    }
+
+    fn dependencies(&self) -> Vec<Cow<'_, str>> {
+        self.0.dependencies()
+    }
+
+    fn optimize_program(&mut self, env: &mut OptimizeEnvironment, program: &mut Program) -> bool {
+        let mut changed = false;
         self.0.do_initialization(env, program);
         for func in program.functions.values_mut() {
+            if !self.0.should_optimize(func) {
+                continue;
+            }
             self.0.reset();
-            self.0.optimize_func(env, func);
+            if self.0.optimize_func(env, func) {
+                env.analyses
+                    .invalidate(&func.name, &self.0.preserved_analyses());
+                changed = true;
+            }
         }
         self.0.do_finalization(env, program);
+        changed
     }
 }
 
@@ -84,11 +155,16 @@ where
 pub struct OptimizeEnvironment {
     /// External data that passes could save, read or modify.
     pub data: AnyMap,
+    /// Cached analyses of functions. Analyses not preserved by a pass are
+    /// dropped after it runs.
+    pub analyses: AnalysisManager,
 }
 
 pub struct Pipeline {
     env: OptimizeEnvironment,
     passes: HashMap<String, Box<dyn Pass>>,
+    verify_each: bool,
+    dumper: Dumper,
 }
 
 impl Default for Pipeline {
@@ -102,11 +178,30 @@ impl Pipeline {
         Pipeline {
             env: OptimizeEnvironment {
                 data: AnyMap::new(),
+                analyses: AnalysisManager::new(),
             },
             passes: HashMap::new(),
+            verify_each: false,
+            dumper: Dumper::new(DumpOptions::default()),
         }
     }
 
+    /// Run the [verifier](crate::verifier) after every pass that edits the
+    /// program. This is slow, and is meant for debugging passes.
+    pub fn set_verify_each(&mut self, verify_each: bool) {
+        self.verify_each = verify_each;
+    }
+
+    /// Print functions around passes, see [`DumpOptions`]. The dump directory
+    /// is created if it doesn't exist.
+    pub fn set_dump_options(&mut self, options: DumpOptions) -> std::io::Result<()> {
+        if let Some(dir) = &options.dump_dir {
+            std::fs::create_dir_all(dir)?;
+        }
+        self.dumper = Dumper::new(options);
+        Ok(())
+    }
+
     pub fn add_pass<P: Pass + 'static>(&mut self, pass: P) {
         self.passes.insert(pass.name().into_owned(), Box::new(pass));
     }
@@ -126,13 +221,151 @@ impl Pipeline {
         self.passes.keys().map(|k| k.as_str())
     }
 
-    pub fn run_pass(&mut self, program: &mut Program, pass: impl AsRef<str>) -> bool {
-        let pass = self.passes.get_mut(pass.as_ref());
-        if let Some(pass) = pass {
-            pass.optimize_program(&mut self.env, program);
-            true
-        } else {
-            false
+    /// Run the pass named `pass`. Returns whether the program is changed, or
+    /// `None` if there's no such pass.
+    ///
+    /// # Panics
+    ///
+    /// Panics if verification is turned on by [`set_verify_each`](Self::set_verify_each)
+    /// and the pass leaves the program invalid.
+    pub fn run_pass(&mut self, program: &mut Program, pass: impl AsRef<str>) -> Option<bool> {
+        let name = pass.as_ref();
+        let pass = self.passes.get_mut(name)?;
+        let before = self.dumper.before_pass(name, program);
+        let changed = pass.optimize_program(&mut self.env, program);
+        self.dumper.after_pass(name, program, changed, before);
+        if !changed {
+            return Some(false);
         }
+        self.env.analyses.invalidate_all(&pass.preserved_analyses());
+        if self.verify_each {
+            if let Err(errors) = verifier::verify_program(program) {
+                let errors = errors
+                    .iter()
+                    .map(|e| e.to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                panic!("Invalid program after pass `{}`:\n{}", pass.name(), errors);
+            }
+        }
+        Some(true)
+    }
+
+    /// Run every pass inside `schedule`, after [resolving](Self::resolve) it.
+    /// Returns whether the program is changed. Failing to write an IR dump
+    /// stops further dumps, and is reported after every pass is run.
+    ///
+    /// # Panics
+    ///
+    /// See [`run_pass`](Self::run_pass).
+    pub fn run(
+        &mut self,
+        program: &mut Program,
+        schedule: &Schedule,
+    ) -> Result<bool, PipelineError> {
+        let schedule = self.resolve(schedule)?;
+        let changed = self.run_resolved(program, &schedule);
+        match self.dumper.take_error() {
+            Some((path, e)) => Err(PipelineError::DumpFailed(path, e.to_string())),
+            None => Ok(changed),
+        }
+    }
+
+    fn run_resolved(&mut self, program: &mut Program, schedule: &Schedule) -> bool {
+        match schedule {
+            Schedule::Pass(name) => self
+                .run_pass(program, name)
+                .expect("Passes are checked when resolving"),
+            Schedule::Sequence(items) => {
+                let mut changed = false;
+                for item in items {
+                    changed |= self.run_resolved(program, item);
+                }
+                changed
+            }
+            Schedule::Fixpoint(items) => {
+                let mut changed = false;
+                for _ in 0..MAX_FIXPOINT_ITERATIONS {
+                    let mut iteration_changed = false;
+                    for item in items {
+                        iteration_changed |= self.run_resolved(program, item);
+                    }
+                    if !iteration_changed {
+                        break;
+                    }
+                    changed = true;
+                }
+                changed
+            }
+        }
+    }
+
+    /// Check that every pass inside `schedule` exists, and insert the
+    /// [dependencies](Pass::dependencies) of passes that are not run before
+    /// them.
+    pub fn resolve(&self, schedule: &Schedule) -> Result<Schedule, PipelineError> {
+        self.resolve_item(schedule, &mut HashSet::new())
+    }
+
+    fn resolve_item(
+        &self,
+        item: &Schedule,
+        seen: &mut HashSet<String>,
+    ) -> Result<Schedule, PipelineError> {
+        match item {
+            Schedule::Pass(name) => {
+                let mut items = vec![];
+                self.resolve_pass(name, seen, &mut vec![], &mut items)?;
+                if items.len() == 1 {
+                    Ok(items.pop().unwrap())
+                } else {
+                    Ok(Schedule::Sequence(items))
+                }
+            }
+            Schedule::Sequence(items) => Ok(Schedule::Sequence(
+                items
+                    .iter()
+                    .map(|item| self.resolve_item(item, seen))
+                    .collect::<Result<_, _>>()?,
+            )),
+            Schedule::Fixpoint(items) => Ok(Schedule::Fixpoint(
+                items
+                    .iter()
+                    .map(|item| self.resolve_item(item, seen))
+                    .collect::<Result<_, _>>()?,
+            )),
+        }
+    }
+
+    /// Push pass `name` into `out`, after dependencies not inside `seen`.
+    /// `stack` contains passes whose dependencies are being resolved.
+    fn resolve_pass(
+        &self,
+        name: &str,
+        seen: &mut HashSet<String>,
+        stack: &mut Vec<String>,
+        out: &mut Vec<Schedule>,
+    ) -> Result<(), PipelineError> {
+        let pass = self
+            .passes
+            .get(name)
+            .ok_or_else(|| PipelineError::UnknownPass(name.into()))?;
+        if let Some(pos) = stack.iter().position(|n| n == name) {
+            let mut cycle = stack[pos..].to_vec();
+            cycle.push(name.into());
+            return Err(PipelineError::CyclicDependency(cycle));
+        }
+
+        stack.push(name.into());
+        for dep in pass.dependencies() {
+            if !seen.contains(dep.as_ref()) {
+                self.resolve_pass(&dep, seen, stack, out)?;
+            }
+        }
+        stack.pop();
+
+        seen.insert(name.into());
+        out.push(Schedule::Pass(name.into()));
+        Ok(())
     }
 }
@@ -2,16 +2,16 @@
 
 use std::collections::HashMap;
 
-use crate::Program;
+use crate::{err::VerifyError, verifier, Program, Ty};
 
 use super::FunctionOptimizer;
-use bit_set::BitSet;
 use smol_str::SmolStr;
 
+/// Runs the [verifier](crate::verifier) over every function, saving the
+/// problems found into [`SanityResult`].
 #[derive(Debug, Default)]
 pub struct SanityChecker {
-    decl_vars: BitSet,
-    use_vars: BitSet,
+    signatures: HashMap<SmolStr, Ty>,
 }
 
 impl FunctionOptimizer for SanityChecker {
@@ -20,51 +20,45 @@ impl FunctionOptimizer for SanityChecker {
     }
 
     fn edits_program(&self) -> bool {
-        true
+        false
     }
 
-    fn reset(&mut self) {
-        self.decl_vars.clear();
-        self.use_vars.clear();
-    }
-
-    fn do_initialization(&mut self, env: &mut super::OptimizeEnvironment, _prog: &Program) {
+    fn do_initialization(&mut self, env: &mut super::OptimizeEnvironment, prog: &Program) {
         env.data.remove::<SanityResult>();
+        self.signatures = verifier::signatures(prog);
     }
 
-    fn optimize_func(&mut self, env: &mut super::OptimizeEnvironment, func: &mut crate::TacFunc) {
-        for (idx, inst) in func.instructions_arena.iter() {
-            self.decl_vars.insert(idx.slot() as usize);
-            for usage in inst.inst.kind.param_op_iter() {
-                self.use_vars.insert(usage.slot() as usize);
-            }
-        }
-        let is_all_vars_declared = self.use_vars.is_subset(&self.decl_vars);
-
-        let is_all_jumps_declared = true;
-        for (_bb_id, bb) in func.basic_block_arena.iter() {
-            for _target in bb.branch.target_iter() {
-                // is_all_jumps_declared |= func.bb_get(target).is_some();
-            }
-        }
+    fn optimize_func(
+        &mut self,
+        env: &mut super::OptimizeEnvironment,
+        func: &mut crate::TacFunc,
+    ) -> bool {
+        let errors = verifier::verify_func(func, &self.signatures)
+            .err()
+            .unwrap_or_default();
 
         let entry = env.data.entry().or_insert_with(|| SanityResult {
-            is_valid_code: HashMap::new(),
+            errors: HashMap::new(),
         });
-
-        entry.is_valid_code.insert(
-            func.name.clone(),
-            is_all_vars_declared && is_all_jumps_declared,
-        );
+        entry.errors.insert(func.name.clone(), errors);
+        false
     }
 }
 
 pub struct SanityResult {
-    is_valid_code: HashMap<SmolStr, bool>,
+    errors: HashMap<SmolStr, Vec<VerifyError>>,
 }
 
 impl SanityResult {
-    pub fn is_valid_code(&self) -> &HashMap<SmolStr, bool> {
-        &self.is_valid_code
+    pub fn is_valid_code(&self) -> HashMap<SmolStr, bool> {
+        self.errors
+            .iter()
+            .map(|(name, errors)| (name.clone(), errors.is_empty()))
+            .collect()
+    }
+
+    /// Problems found inside every function.
+    pub fn errors(&self) -> &HashMap<SmolStr, Vec<VerifyError>> {
+        &self.errors
     }
 }
@@ -0,0 +1,157 @@
+//! Descriptions of the order to run passes in.
+//!
+//! A schedule is written as a comma-separated list of pass names. Passes inside
+//! `fixpoint(...)` are run repeatedly until none of them changes the program,
+//! and groups may be nested:
+//!
+//! ```plaintext
+//! sanity-check,fixpoint(const-folding,branching-simplify,dead-code-eliminator)
+//! ```
+
+use std::{fmt::Display, iter::Peekable, str::CharIndices, str::FromStr};
+
+use crate::err::{ScheduleParseError, ScheduleParseErrorKind};
+
+/// The maximum number of times a fixpoint group is run. Groups still changing
+/// the program after that many iterations are stopped anyway.
+pub const MAX_FIXPOINT_ITERATIONS: usize = 32;
+
+/// An ordered, possibly nested list of passes to run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schedule {
+    /// Run the pass with this name.
+    Pass(String),
+    /// Run every item in order.
+    Sequence(Vec<Schedule>),
+    /// Run every item in order, until none of them changes the program or
+    /// [`MAX_FIXPOINT_ITERATIONS`] is reached.
+    Fixpoint(Vec<Schedule>),
+}
+
+impl Schedule {
+    /// Iterate over the names of all passes inside this schedule, in order.
+    pub fn pass_names(&self) -> Box<dyn Iterator<Item = &str> + '_> {
+        match self {
+            Schedule::Pass(name) => Box::new(std::iter::once(name.as_str())),
+            Schedule::Sequence(items) | Schedule::Fixpoint(items) => {
+                Box::new(items.iter().flat_map(|item| item.pass_names()))
+            }
+        }
+    }
+}
+
+impl Display for Schedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let write_items = |f: &mut std::fmt::Formatter<'_>, items: &[Schedule]| {
+            for (idx, item) in items.iter().enumerate() {
+                if idx != 0 {
+                    write!(f, ",")?;
+                }
+                write!(f, "{}", item)?;
+            }
+            Ok(())
+        };
+        match self {
+            Schedule::Pass(name) => write!(f, "{}", name),
+            Schedule::Sequence(items) => write_items(f, items),
+            Schedule::Fixpoint(items) => {
+                write!(f, "fixpoint(")?;
+                write_items(f, items)?;
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = ScheduleParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = ScheduleParser {
+            input: s,
+            chars: s.char_indices().peekable(),
+        };
+        let items = parser.parse_list()?;
+        match parser.next_non_space() {
+            None => Ok(Schedule::Sequence(items)),
+            Some((pos, ch)) => Err(parser.error(pos, ScheduleParseErrorKind::UnexpectedChar(ch))),
+        }
+    }
+}
+
+struct ScheduleParser<'a> {
+    input: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> ScheduleParser<'a> {
+    fn error(&self, pos: usize, kind: ScheduleParseErrorKind) -> ScheduleParseError {
+        ScheduleParseError { pos, kind }
+    }
+
+    fn peek_non_space(&mut self) -> Option<(usize, char)> {
+        while let Some(&(_, ch)) = self.chars.peek() {
+            if !ch.is_whitespace() {
+                break;
+            }
+            self.chars.next();
+        }
+        self.chars.peek().copied()
+    }
+
+    fn next_non_space(&mut self) -> Option<(usize, char)> {
+        self.peek_non_space();
+        self.chars.next()
+    }
+
+    /// Parse a comma-separated list, stopping before `)` or the end of input.
+    fn parse_list(&mut self) -> Result<Vec<Schedule>, ScheduleParseError> {
+        let mut items = vec![];
+        if matches!(self.peek_non_space(), None | Some((_, ')'))) {
+            return Ok(items);
+        }
+        loop {
+            items.push(self.parse_item()?);
+            match self.peek_non_space() {
+                Some((_, ',')) => {
+                    self.chars.next();
+                }
+                _ => return Ok(items),
+            }
+        }
+    }
+
+    fn parse_item(&mut self) -> Result<Schedule, ScheduleParseError> {
+        let start = match self.peek_non_space() {
+            Some((pos, _)) => pos,
+            None => return Err(self.error(self.input.len(), ScheduleParseErrorKind::UnexpectedEnd)),
+        };
+        let mut end = start;
+        while let Some(&(pos, ch)) = self.chars.peek() {
+            if !(ch.is_alphanumeric() || ch == '-' || ch == '_') {
+                break;
+            }
+            end = pos + ch.len_utf8();
+            self.chars.next();
+        }
+        let name = &self.input[start..end];
+        if name.is_empty() {
+            let (pos, ch) = self.chars.next().unwrap();
+            return Err(self.error(pos, ScheduleParseErrorKind::UnexpectedChar(ch)));
+        }
+
+        if !matches!(self.peek_non_space(), Some((_, '('))) {
+            return Ok(Schedule::Pass(name.into()));
+        }
+        if name != "fixpoint" {
+            return Err(self.error(start, ScheduleParseErrorKind::UnknownGroup(name.into())));
+        }
+        self.chars.next();
+        let items = self.parse_list()?;
+        match self.next_non_space() {
+            Some((_, ')')) => Ok(Schedule::Fixpoint(items)),
+            Some((pos, ch)) => Err(self.error(pos, ScheduleParseErrorKind::UnexpectedChar(ch))),
+            None => Err(self.error(self.input.len(), ScheduleParseErrorKind::UnexpectedEnd)),
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use std::{borrow::Cow, cell::RefCell, rc::Rc};
+
+use crate::{
+    err::{PipelineError, ScheduleParseErrorKind},
+    parser::parse_program_from_string,
+    Program,
+};
+
+use super::{
+    dump::{unified_diff, DumpOptions, Dumper},
+    schedule::Schedule,
+    OptimizeEnvironment, Pass, Pipeline,
+};
+
+/// A pass logging its runs, which changes the program for the first
+/// `changes` runs.
+struct LogPass {
+    name: &'static str,
+    deps: Vec<&'static str>,
+    changes: usize,
+    log: Rc<RefCell<Vec<&'static str>>>,
+}
+
+impl Pass for LogPass {
+    fn name(&self) -> Cow<'_, str> {
+        self.name.into()
+    }
+
+    fn edits_program(&self) -> bool {
+        true
+    }
+
+    fn dependencies(&self) -> Vec<Cow<'_, str>> {
+        self.deps.iter().map(|&d| d.into()).collect()
+    }
+
+    fn optimize_program(&mut self, _env: &mut OptimizeEnvironment, _program: &mut Program) -> bool {
+        self.log.borrow_mut().push(self.name);
+        if self.changes > 0 {
+            self.changes -= 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+fn log_pipeline(
+    passes: &[(&'static str, usize, &[&'static str])],
+) -> (Pipeline, Rc<RefCell<Vec<&'static str>>>) {
+    let log = Rc::new(RefCell::new(vec![]));
+    let mut pipeline = Pipeline::new();
+    for &(name, changes, deps) in passes {
+        pipeline.add_pass(LogPass {
+            name,
+            deps: deps.to_vec(),
+            changes,
+            log: log.clone(),
+        });
+    }
+    (pipeline, log)
+}
+
+#[test]
+fn test_parse_schedule() {
+    let schedule: Schedule = " a, fixpoint(b ,fixpoint(c), d),e".parse().unwrap();
+    assert_eq!(
+        schedule,
+        Schedule::Sequence(vec![
+            Schedule::Pass("a".into()),
+            Schedule::Fixpoint(vec![
+                Schedule::Pass("b".into()),
+                Schedule::Fixpoint(vec![Schedule::Pass("c".into())]),
+                Schedule::Pass("d".into()),
+            ]),
+            Schedule::Pass("e".into()),
+        ])
+    );
+    assert_eq!(schedule.to_string(), "a,fixpoint(b,fixpoint(c),d),e");
+    assert_eq!("".parse::<Schedule>().unwrap(), Schedule::Sequence(vec![]));
+
+    let kind = |s: &str| s.parse::<Schedule>().unwrap_err().kind;
+    assert_eq!(kind("a,,b"), ScheduleParseErrorKind::UnexpectedChar(','));
+    assert_eq!(kind("fixpoint(a"), ScheduleParseErrorKind::UnexpectedEnd);
+    assert_eq!(kind("a b"), ScheduleParseErrorKind::UnexpectedChar('b'));
+    assert_eq!(
+        kind("repeat(a)"),
+        ScheduleParseErrorKind::UnknownGroup("repeat".into())
+    );
+}
+
+#[test]
+fn test_fixpoint() {
+    let (mut pipeline, log) = log_pipeline(&[("a", 2, &[]), ("b", 0, &[])]);
+    let schedule = "fixpoint(a,b),b".parse().unwrap();
+    let changed = pipeline.run(&mut Program::default(), &schedule).unwrap();
+    assert!(changed);
+    // `a` changes the program twice, and the third iteration changes nothing
+    assert_eq!(*log.borrow(), vec!["a", "b", "a", "b", "a", "b", "b"]);
+
+    let changed = pipeline.run(&mut Program::default(), &schedule).unwrap();
+    assert!(!changed);
+}
+
+#[test]
+fn test_dependencies() {
+    let (mut pipeline, log) = log_pipeline(&[("a", 0, &[]), ("b", 0, &["a"]), ("c", 0, &["b"])]);
+    pipeline
+        .run(&mut Program::default(), &"c,a,b".parse().unwrap())
+        .unwrap();
+    assert_eq!(*log.borrow(), vec!["a", "b", "c", "a", "b"]);
+
+    assert_eq!(
+        pipeline.resolve(&"d".parse().unwrap()),
+        Err(PipelineError::UnknownPass("d".into()))
+    );
+
+    let (cyclic, _) = log_pipeline(&[("a", 0, &["b"]), ("b", 0, &["a"])]);
+    assert!(matches!(
+        cyclic.resolve(&"a".parse().unwrap()),
+        Err(PipelineError::CyclicDependency(cycle)) if cycle == ["a", "b", "a"]
+    ));
+}
+
+#[test]
+fn test_unified_diff() {
+    let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+    let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\n";
+    let expected = "\
+--- old
++++ new
+@@ -1,5 +1,5 @@
+ a
+-b
++B
+ c
+ d
+ e
+@@ -8,3 +8,4 @@
+ h
+ i
+ j
++k
+";
+    assert_eq!(unified_diff(old, new, "old", "new"), expected);
+    assert_eq!(unified_diff(old, old, "old", "new"), "");
+}
+
+#[test]
+fn test_dump_changed() {
+    let input = r"
+    (fn f () i32
+        (bb0 ()
+            (return 1)))
+    (fn g () i32
+        (bb0 ()
+            (return 2)))
+    ";
+    let dir = std::env::temp_dir().join(format!("azuki-dump-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut dumper = Dumper::new(DumpOptions {
+        print_before: std::iter::once("p".to_string()).collect(),
+        diff: true,
+        dump_dir: Some(dir.clone()),
+        ..Default::default()
+    });
+    let mut program = parse_program_from_string(input).unwrap();
+    let before = dumper.before_pass("p", &program);
+    let g = program.functions.get_mut("g").unwrap();
+    let bb = g.first_block.unwrap();
+    g.bb_get_mut(bb).branch = crate::Branch::Return(Some(crate::Value::Imm(3)));
+    dumper.after_pass("p", &program, true, before);
+
+    let mut files = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|f| f.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    files.sort();
+    assert_eq!(
+        files,
+        vec![
+            "0001-p-before-f.tac",
+            "0002-p-before-g.tac",
+            "0003-p-diff-g.diff"
+        ]
+    );
+    let diff = std::fs::read_to_string(dir.join("0003-p-diff-g.diff")).unwrap();
+    assert!(diff.contains("-\t\t(return 2)"), "{}", diff);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_dump_dir_is_created() {
+    let input = r"
+    (fn f () i32
+        (bb0 ()
+            (return 1)))
+    ";
+    let root = std::env::temp_dir().join(format!("azuki-dump-dir-{}", std::process::id()));
+    let dir = root.join("nested");
+    let _ = std::fs::remove_dir_all(&root);
+
+    let (mut pipeline, _) = log_pipeline(&[("a", 1, &[])]);
+    pipeline
+        .set_dump_options(DumpOptions {
+            print_after_all: true,
+            dump_dir: Some(dir.clone()),
+            ..Default::default()
+        })
+        .unwrap();
+    let mut program = parse_program_from_string(input).unwrap();
+    pipeline.run(&mut program, &"a".parse().unwrap()).unwrap();
+    assert!(dir.join("0001-a-after-f.tac").is_file());
+
+    // Failing to write is an error instead of a panic
+    std::fs::remove_dir_all(&root).unwrap();
+    assert!(matches!(
+        pipeline.run(&mut program, &"a".parse().unwrap()),
+        Err(PipelineError::DumpFailed(path, _)) if path == dir.join("0002-a-after-f.tac")
+    ));
+}
@@ -7,8 +7,9 @@
 use std::{borrow::Cow, collections::BTreeMap, str::FromStr};
 
 use crate::{
-    builder::FuncEditor, BBId, BinaryInst, BinaryOp, Branch, FunctionCall, Inst, InstId, InstKind,
-    NumericTy, TacFunc, Ty, TyKind, Value,
+    builder::FuncEditor, AllocaInst, BBId, BinaryInst, BinaryOp, Branch, FuncAttr, FunctionCall,
+    IndirectCall, Inst, InstId, InstKind, NumericTy, OffsetInst, StoreInst, TacFunc, Ty, TyKind,
+    Value,
 };
 
 use lexpr::{datum::ListIter, datum::Ref as LRef};
@@ -47,7 +48,7 @@ impl<'f> VariableNamingCtx<'f> {
 
     // Until one day we use that...
     pub fn _set_var(&mut self, idx: InstId, inst: Inst) {
-        let inst_ref = self.func.func.tac_get_mut(idx);
+        let mut inst_ref = self.func.func.tac_get_mut(idx);
         inst_ref.inst = inst;
     }
 
@@ -177,10 +178,17 @@ fn expect_opt_name(
     }
 }
 
+// i32, b1, i32* ...
 fn parsed_named_type(val: LRef<'_>) -> Result<Ty, ParseError> {
     let name = val
         .as_name()
         .ok_or_else(|| ParseError::spanned(Expect("Type name".into()), val.span()))?;
+    let pointee = name.trim_end_matches('*');
+    let ptr_depth = name.len() - pointee.len();
+    let name = pointee;
+    if name.is_empty() {
+        return Err(ParseError::expect_span("Type name", val.span()));
+    }
     let ty_kind = match name.chars().next().unwrap() {
         'i' => TyKind::Int,
         'b' => TyKind::Bool,
@@ -195,15 +203,43 @@ fn parsed_named_type(val: LRef<'_>) -> Result<Ty, ParseError> {
     let size = u8::from_str(len).map_err(|e| {
         ParseError::expect_span(format!("a valid number width, got {}", e), val.span())
     })?;
-    Ok(Ty::Numeric(NumericTy {
+    let mut ty = Ty::Numeric(NumericTy {
         kind: ty_kind,
         size,
-    }))
+    });
+    for _ in 0..ptr_depth {
+        ty = Ty::ptr_of(ty);
+    }
+    Ok(ty)
+}
+
+// (fn (<param>...) <return>)
+fn parse_func_type(val: LRef<'_>) -> Result<Ty, ParseError> {
+    let mut list = val
+        .list_iter()
+        .ok_or_else(|| ParseError::expect_span("Function type", val.span()))?;
+
+    expect_opt_name(list.next(), "fn".into(), val.span().end())?;
+
+    let params = list
+        .next()
+        .and_then(|x| x.list_iter())
+        .ok_or_else(|| ParseError::expect_pos("'()' or list of param types", val.span().end()))?;
+    let params = params.map(parse_type).collect::<Result<Vec<_>, _>>()?;
+
+    let ret = list
+        .next()
+        .ok_or_else(|| ParseError::expect_pos("'()' or return type", val.span().end()))?;
+    let ret = parse_type(ret)?;
+
+    Ok(Ty::func_of(ret, params))
 }
 
 fn parse_type(val: LRef<'_>) -> Result<Ty, ParseError> {
     if val.is_null() {
         Ok(Ty::unit())
+    } else if val.is_list() {
+        parse_func_type(val)
     } else {
         parsed_named_type(val)
     }
@@ -297,6 +333,40 @@ fn parse_call_rest(
     }))
 }
 
+fn parse_indirect_call_rest(
+    mut val_iter: ListIter,
+    ctx: &mut VariableNamingCtx,
+) -> Result<InstKind, ParseError> {
+    let callee = val_iter
+        .next()
+        .ok_or_else(|| ParseError::expect("Callee value"))?;
+    let callee = parse_value(callee, ctx)?;
+
+    let param_list = val_iter
+        .next()
+        .ok_or_else(|| ParseError::expect("Function param list"))?;
+    let param_list = param_list.list_iter().ok_or_else(|| {
+        ParseError::expect_span("Function param list should be a list", param_list.span())
+    })?;
+
+    let params = param_list
+        .map(|x| parse_value(x, ctx))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(InstKind::IndirectCall(IndirectCall { callee, params }))
+}
+
+fn parse_func_ref_rest(mut val_iter: ListIter) -> Result<InstKind, ParseError> {
+    let name = val_iter
+        .next()
+        .ok_or_else(|| ParseError::expect("Function name"))?;
+    let name = name.as_name().ok_or_else(|| {
+        ParseError::expect("Function name should be a symbol or string")
+            .with_position(Position::Span(name.span()))
+    })?;
+    Ok(InstKind::FuncRef(name.into()))
+}
+
 fn parse_phi_rest(val_iter: ListIter, ctx: &mut VariableNamingCtx) -> Result<InstKind, ParseError> {
     let phi = val_iter
         .map(|x| {
@@ -321,6 +391,69 @@ fn parse_phi_rest(val_iter: ListIter, ctx: &mut VariableNamingCtx) -> Result<Ins
     Ok(InstKind::Phi(phi))
 }
 
+fn parse_alloca_rest(mut val_iter: ListIter) -> Result<InstKind, ParseError> {
+    let ty = val_iter
+        .next()
+        .ok_or_else(|| ParseError::expect("Type of stack slot"))?;
+    let ty = parse_type(ty)?;
+
+    let count = match val_iter.next() {
+        Some(count) => count
+            .as_u64()
+            .ok_or_else(|| ParseError::expect_span("an integer", count.span()))?
+            as usize,
+        None => 1,
+    };
+
+    Ok(InstKind::Alloca(AllocaInst { ty, count }))
+}
+
+fn parse_load_rest(
+    mut val_iter: ListIter,
+    ctx: &mut VariableNamingCtx,
+) -> Result<InstKind, ParseError> {
+    let ptr = val_iter
+        .next()
+        .ok_or_else(|| ParseError::expect("pointer to load from"))?;
+    let ptr = parse_value(ptr, ctx)?;
+
+    Ok(InstKind::Load(ptr))
+}
+
+fn parse_store_rest(
+    mut val_iter: ListIter,
+    ctx: &mut VariableNamingCtx,
+) -> Result<InstKind, ParseError> {
+    let ptr = val_iter
+        .next()
+        .ok_or_else(|| ParseError::expect("pointer to store into"))?;
+    let ptr = parse_value(ptr, ctx)?;
+
+    let value = val_iter
+        .next()
+        .ok_or_else(|| ParseError::expect("value to store"))?;
+    let value = parse_value(value, ctx)?;
+
+    Ok(InstKind::Store(StoreInst { ptr, value }))
+}
+
+fn parse_offset_rest(
+    mut val_iter: ListIter,
+    ctx: &mut VariableNamingCtx,
+) -> Result<InstKind, ParseError> {
+    let ptr = val_iter
+        .next()
+        .ok_or_else(|| ParseError::expect("pointer to offset"))?;
+    let ptr = parse_value(ptr, ctx)?;
+
+    let offset = val_iter
+        .next()
+        .ok_or_else(|| ParseError::expect("offset value"))?;
+    let offset = parse_value(offset, ctx)?;
+
+    Ok(InstKind::Offset(OffsetInst { ptr, offset }))
+}
+
 fn parse_inst_kind(
     mut val_iter: ListIter,
     ctx: &mut VariableNamingCtx,
@@ -354,11 +487,18 @@ fn parse_inst_kind(
         "param" => parse_param_rest(val_iter),
 
         "call" => parse_call_rest(val_iter, ctx),
+        "call_indirect" => parse_indirect_call_rest(val_iter, ctx),
+        "funcref" => parse_func_ref_rest(val_iter),
 
         "phi" => parse_phi_rest(val_iter, ctx),
 
+        "alloca" => parse_alloca_rest(val_iter),
+        "load" => parse_load_rest(val_iter, ctx),
+        "store" => parse_store_rest(val_iter, ctx),
+        "offset" => parse_offset_rest(val_iter, ctx),
+
         n if n.starts_with('%') => {
-            let id = usize::from_str(n)
+            let id = usize::from_str(&n[1..])
                 .map_err(|e| ParseError::expect_span(format!("var id, got {}", e), name_span))?;
             let id = ctx.declared_var(id);
             Ok(InstKind::Assign(Value::Dest(id)))
@@ -497,7 +637,26 @@ fn parse_bb(val: LRef<'_>, ctx: &mut VariableNamingCtx) -> Result<(), ParseError
     Ok(())
 }
 
-// (fn <name> <param> <return> ...<basic-blocks>)
+// (attrs ...<attribute>)
+fn parse_attrs(val: LRef<'_>) -> Result<Vec<FuncAttr>, ParseError> {
+    let mut list = val.list_iter().unwrap();
+    expect_opt_name(list.next(), "attrs".into(), val.span().end())?;
+    list.map(|attr| {
+        attr.as_name()
+            .and_then(FuncAttr::from_name)
+            .ok_or_else(|| ParseError::expect_span("function attribute", attr.span()))
+    })
+    .collect()
+}
+
+fn is_attrs(val: LRef<'_>) -> bool {
+    match val.list_iter().and_then(|mut list| list.next()) {
+        Some(head) => head.as_name() == Some("attrs"),
+        None => false,
+    }
+}
+
+// (fn <name> <param> <return> (attrs ...<attribute>)? ...<basic-blocks>)
 pub fn parse_function(val: LRef<'_>) -> Result<TacFunc, ParseError> {
     let mut list = val
         .list_iter()
@@ -525,6 +684,10 @@ pub fn parse_function(val: LRef<'_>) -> Result<TacFunc, ParseError> {
 
     let func_ty = Ty::func_of(ret, params);
     let mut func = TacFunc::new(name.into(), func_ty);
+    let mut list = list.peekable();
+    if let Some(attrs) = list.next_if(|x| is_attrs(*x)) {
+        func.attrs.extend(parse_attrs(attrs)?);
+    }
     let mut ctx = VariableNamingCtx::new(&mut func);
 
     list.try_for_each(|x| parse_bb(x, &mut ctx))?;
@@ -1,6 +1,10 @@
 mod implementation;
+#[cfg(test)]
+mod test;
 use std::{collections::HashMap};
 
+use indexmap::IndexMap;
+
 pub use implementation::*;
 
 use crate::Program;
@@ -13,7 +17,8 @@ pub fn parse_program_from_string(input: &str) -> Result<Program, ParseError> {
     let val = parser.datum_iter();
     let res = val.map(|x| x.map(|x| parse_function(x.as_ref())));
     let mut program = Program {
-        functions: HashMap::new(),
+        functions: IndexMap::new(),
+        imports: HashMap::new(),
     };
     for x in res {
         let x = x??;
@@ -0,0 +1,22 @@
+use crate::{InstKind, Value};
+
+use super::parse_program_from_string;
+
+#[test]
+fn test_parse_assign() {
+    let input = r"
+    (fn f (i32) i32
+        (bb0 (
+            (%0 i32 param 0)
+            (%1 i32 %0)
+            (%2 i32 -5))
+            (return %1)))
+    ";
+    let program = parse_program_from_string(input).unwrap();
+    let f = &program.functions["f"];
+    let insts = f
+        .inst_of_bb_iter(f.first_block.unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(insts[1].1.kind, InstKind::Assign(Value::Dest(insts[0].0)));
+    assert_eq!(insts[2].1.kind, InstKind::Assign(Value::Imm(-5)));
+}
@@ -0,0 +1,485 @@
+//! Serialization of programs with [serde], enabled by the `serde_impl` feature.
+//!
+//! Types, attributes, operators and source locations are serialized as-is.
+//! Functions can't be, because instructions and basic blocks refer to each
+//! other through arena keys, which mean nothing outside the function. Instead
+//! every basic block and instruction gets a number unique inside its function,
+//! and references use these numbers. Deserializing a function allocates fresh
+//! keys for all of them.
+//!
+//! # Schema
+//!
+//! Shown in JSON, with `<...>` marking values described elsewhere:
+//!
+//! ```json
+//! {
+//!     "functions": [<function>, ...],
+//!     "imports": { "putint": <type>, ... }
+//! }
+//! ```
+//!
+//! Functions are listed in the order they are defined. A function is:
+//!
+//! ```json
+//! {
+//!     "name": "fib",
+//!     "ty": <type>,
+//!     "attrs": ["inline", "noinline", "pure", "cold"],
+//!     "blocks": [
+//!         {
+//!             "id": 0,
+//!             "insts": [
+//!                 { "id": 0, "ty": <type>, "loc": { "line": 1, "col": 5 }, "kind": <kind> },
+//!                 ...
+//!             ],
+//!             "branch": <branch>
+//!         },
+//!         ...
+//!     ]
+//! }
+//! ```
+//!
+//! Basic blocks are listed in order, the first one being the entry. `loc` may
+//! be `null`. Serialized functions number basic blocks and instructions in
+//! order from 0, but any unique numbers are accepted when deserializing.
+//!
+//! - A type is `"unit"`, `{ "numeric": { "kind": "int" | "bool", "size": 32 } }`,
+//!   `{ "ptr": <type> }` or `{ "func": { "return_type": <type>, "params": [<type>, ...] } }`.
+//! - A value is `{ "imm": 42 }`, or `{ "inst": 3 }` referring to an instruction.
+//! - An instruction kind is one of:
+//!   - `{ "binary": { "op": "add", "lhs": <value>, "rhs": <value> } }`, where
+//!     `op` is one of `add`, `sub`, `mul`, `div`, `lt`, `gt`, `le`, `ge`,
+//!     `eq` and `ne`
+//!   - `{ "call": { "name": "fib", "params": [<value>, ...] } }`
+//!   - `{ "call_indirect": { "callee": <value>, "params": [<value>, ...] } }`
+//!   - `{ "funcref": "fib" }`
+//!   - `{ "assign": <value> }`
+//!   - `{ "phi": [{ "bb": 1, "inst": 3 }, ...] }`
+//!   - `{ "param": 0 }`
+//!   - `{ "alloca": { "ty": <type>, "count": 1 } }`
+//!   - `{ "load": <value> }`
+//!   - `{ "store": { "ptr": <value>, "value": <value> } }`
+//!   - `{ "offset": { "ptr": <value>, "offset": <value> } }`
+//! - A branch is `"unreachable"`, `{ "return": <value> | null }`,
+//!   `{ "br": 2 }` or `{ "brif": { "cond": <value>, "if_true": 2, "if_false": 3 } }`.
+
+#[cfg(test)]
+mod test;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+use smol_str::SmolStr;
+
+use crate::{
+    builder::FuncEditor, AllocaInst, BBId, BinaryInst, BinaryOp, Branch, FuncAttr, FunctionCall,
+    IndirectCall, Inst, InstId, InstKind, OffsetInst, Program, SourceLoc, StoreInst, TacFunc, Ty,
+    Value,
+};
+
+#[derive(Deserialize)]
+struct ProgramRepr {
+    functions: Vec<TacFunc>,
+    #[serde(default)]
+    imports: BTreeMap<SmolStr, Ty>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct FuncRepr {
+    name: SmolStr,
+    ty: Ty,
+    #[serde(default)]
+    attrs: BTreeSet<FuncAttr>,
+    blocks: Vec<BlockRepr>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BlockRepr {
+    id: u32,
+    insts: Vec<InstRepr>,
+    branch: BranchRepr,
+}
+
+#[derive(Serialize, Deserialize)]
+struct InstRepr {
+    id: u32,
+    ty: Ty,
+    #[serde(default)]
+    loc: Option<SourceLoc>,
+    kind: KindRepr,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum KindRepr {
+    Binary {
+        op: BinaryOp,
+        lhs: ValueRepr,
+        rhs: ValueRepr,
+    },
+    Call {
+        name: SmolStr,
+        params: Vec<ValueRepr>,
+    },
+    CallIndirect {
+        callee: ValueRepr,
+        params: Vec<ValueRepr>,
+    },
+    Funcref(SmolStr),
+    Assign(ValueRepr),
+    Phi(Vec<PhiSourceRepr>),
+    Param(usize),
+    Alloca {
+        ty: Ty,
+        count: usize,
+    },
+    Load(ValueRepr),
+    Store {
+        ptr: ValueRepr,
+        value: ValueRepr,
+    },
+    Offset {
+        ptr: ValueRepr,
+        offset: ValueRepr,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+struct PhiSourceRepr {
+    bb: u32,
+    inst: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum ValueRepr {
+    Imm(i64),
+    Inst(u32),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum BranchRepr {
+    Unreachable,
+    Return(Option<ValueRepr>),
+    Br(u32),
+    Brif {
+        cond: ValueRepr,
+        if_true: u32,
+        if_false: u32,
+    },
+}
+
+/// Numbers of basic blocks and instructions inside a function being
+/// serialized.
+#[derive(Default)]
+struct Numbering {
+    bbs: HashMap<BBId, u32>,
+    insts: HashMap<InstId, u32>,
+}
+
+impl Numbering {
+    fn bb(&self, bb: BBId) -> u32 {
+        *self
+            .bbs
+            .get(&bb)
+            .expect("Referring to a basic block outside the function")
+    }
+
+    fn inst(&self, inst: InstId) -> u32 {
+        *self
+            .insts
+            .get(&inst)
+            .expect("Referring to an instruction outside the function")
+    }
+
+    fn value(&self, value: Value) -> ValueRepr {
+        match value {
+            Value::Imm(i) => ValueRepr::Imm(i),
+            Value::Dest(inst) => ValueRepr::Inst(self.inst(inst)),
+        }
+    }
+
+    fn values(&self, values: &[Value]) -> Vec<ValueRepr> {
+        values.iter().map(|&v| self.value(v)).collect()
+    }
+}
+
+impl FuncRepr {
+    fn new(func: &TacFunc) -> FuncRepr {
+        let mut numbering = Numbering::default();
+        for (bb, _) in func.bb_iter() {
+            numbering.bbs.insert(bb, numbering.bbs.len() as u32);
+            for (inst, _) in func.inst_of_bb_iter(bb) {
+                numbering.insts.insert(inst, numbering.insts.len() as u32);
+            }
+        }
+
+        let blocks = func
+            .bb_iter()
+            .map(|(bb, block)| BlockRepr {
+                id: numbering.bb(bb),
+                insts: func
+                    .inst_of_bb_iter(bb)
+                    .map(|(idx, inst)| InstRepr {
+                        id: numbering.inst(idx),
+                        ty: inst.ty.clone(),
+                        loc: func.tac_get(idx).loc,
+                        kind: kind_repr(&inst.kind, &numbering),
+                    })
+                    .collect(),
+                branch: branch_repr(&block.branch, &numbering),
+            })
+            .collect();
+
+        FuncRepr {
+            name: func.name.clone(),
+            ty: func.ty.clone(),
+            attrs: func.attrs.clone(),
+            blocks,
+        }
+    }
+
+    fn into_func(self) -> Result<TacFunc, String> {
+        let mut func = TacFunc::new(self.name, self.ty);
+        func.attrs = self.attrs;
+        let mut editor = FuncEditor::new(&mut func);
+
+        // Instructions may refer to later ones, so every basic block and
+        // instruction is allocated before filling them
+        let mut bbs = HashMap::new();
+        let mut insts = HashMap::new();
+        for block in &self.blocks {
+            if bbs.insert(block.id, editor.new_bb()).is_some() {
+                return Err(format!("duplicate basic block id {}", block.id));
+            }
+            for inst in &block.insts {
+                let idx = editor.func.inst_new(Inst {
+                    kind: InstKind::empty_phi(),
+                    ty: Ty::unit(),
+                });
+                if insts.insert(inst.id, idx).is_some() {
+                    return Err(format!("duplicate instruction id {}", inst.id));
+                }
+            }
+        }
+        let resolver = Resolver {
+            bbs: &bbs,
+            insts: &insts,
+        };
+
+        let first = self.blocks.first().map(|block| bbs[&block.id]);
+        let mut last = None;
+        for block in self.blocks {
+            let bb = bbs[&block.id];
+            editor.set_current_bb(bb);
+            for inst in block.insts {
+                let idx = insts[&inst.id];
+                let kind = resolver.kind(inst.kind)?;
+                *editor.func.inst_get_mut(idx) = Inst { kind, ty: inst.ty };
+                editor.func.tac_get_mut(idx).loc = inst.loc;
+                editor.put_inst_after_current_place(idx);
+            }
+            editor.current_bb_mut().branch = resolver.branch(block.branch)?;
+            if let Some(last) = last {
+                editor.func.bb_set_after(last, bb);
+            }
+            last = Some(bb);
+        }
+        func.first_block = first;
+        Ok(func)
+    }
+}
+
+fn kind_repr(kind: &InstKind, numbering: &Numbering) -> KindRepr {
+    match kind {
+        InstKind::Binary(b) => KindRepr::Binary {
+            op: b.op,
+            lhs: numbering.value(b.lhs),
+            rhs: numbering.value(b.rhs),
+        },
+        InstKind::FunctionCall(call) => KindRepr::Call {
+            name: call.name.clone(),
+            params: numbering.values(&call.params),
+        },
+        InstKind::IndirectCall(call) => KindRepr::CallIndirect {
+            callee: numbering.value(call.callee),
+            params: numbering.values(&call.params),
+        },
+        InstKind::FuncRef(name) => KindRepr::Funcref(name.clone()),
+        InstKind::Assign(value) => KindRepr::Assign(numbering.value(*value)),
+        InstKind::Phi(sources) => KindRepr::Phi(
+            sources
+                .iter()
+                .map(|(&bb, &inst)| PhiSourceRepr {
+                    bb: numbering.bb(bb),
+                    inst: numbering.inst(inst),
+                })
+                .collect(),
+        ),
+        InstKind::Param(idx) => KindRepr::Param(*idx),
+        InstKind::Alloca(alloca) => KindRepr::Alloca {
+            ty: alloca.ty.clone(),
+            count: alloca.count,
+        },
+        InstKind::Load(ptr) => KindRepr::Load(numbering.value(*ptr)),
+        InstKind::Store(store) => KindRepr::Store {
+            ptr: numbering.value(store.ptr),
+            value: numbering.value(store.value),
+        },
+        InstKind::Offset(offset) => KindRepr::Offset {
+            ptr: numbering.value(offset.ptr),
+            offset: numbering.value(offset.offset),
+        },
+    }
+}
+
+fn branch_repr(branch: &Branch, numbering: &Numbering) -> BranchRepr {
+    match branch {
+        Branch::Unreachable => BranchRepr::Unreachable,
+        Branch::Return(value) => BranchRepr::Return(value.map(|v| numbering.value(v))),
+        Branch::Jump(target) => BranchRepr::Br(numbering.bb(*target)),
+        Branch::CondJump {
+            cond,
+            if_true,
+            if_false,
+        } => BranchRepr::Brif {
+            cond: numbering.value(*cond),
+            if_true: numbering.bb(*if_true),
+            if_false: numbering.bb(*if_false),
+        },
+    }
+}
+
+/// Keys of basic blocks and instructions inside a function being
+/// deserialized.
+struct Resolver<'a> {
+    bbs: &'a HashMap<u32, BBId>,
+    insts: &'a HashMap<u32, InstId>,
+}
+
+impl Resolver<'_> {
+    fn bb(&self, id: u32) -> Result<BBId, String> {
+        self.bbs
+            .get(&id)
+            .copied()
+            .ok_or_else(|| format!("no basic block with id {}", id))
+    }
+
+    fn inst(&self, id: u32) -> Result<InstId, String> {
+        self.insts
+            .get(&id)
+            .copied()
+            .ok_or_else(|| format!("no instruction with id {}", id))
+    }
+
+    fn value(&self, value: ValueRepr) -> Result<Value, String> {
+        match value {
+            ValueRepr::Imm(i) => Ok(Value::Imm(i)),
+            ValueRepr::Inst(id) => self.inst(id).map(Value::Dest),
+        }
+    }
+
+    fn values(&self, values: Vec<ValueRepr>) -> Result<Vec<Value>, String> {
+        values.into_iter().map(|v| self.value(v)).collect()
+    }
+
+    fn kind(&self, kind: KindRepr) -> Result<InstKind, String> {
+        Ok(match kind {
+            KindRepr::Binary { op, lhs, rhs } => InstKind::Binary(BinaryInst {
+                op,
+                lhs: self.value(lhs)?,
+                rhs: self.value(rhs)?,
+            }),
+            KindRepr::Call { name, params } => InstKind::FunctionCall(FunctionCall {
+                name,
+                params: self.values(params)?,
+            }),
+            KindRepr::CallIndirect { callee, params } => InstKind::IndirectCall(IndirectCall {
+                callee: self.value(callee)?,
+                params: self.values(params)?,
+            }),
+            KindRepr::Funcref(name) => InstKind::FuncRef(name),
+            KindRepr::Assign(value) => InstKind::Assign(self.value(value)?),
+            KindRepr::Phi(sources) => InstKind::Phi(
+                sources
+                    .into_iter()
+                    .map(|s| Ok((self.bb(s.bb)?, self.inst(s.inst)?)))
+                    .collect::<Result<_, String>>()?,
+            ),
+            KindRepr::Param(idx) => InstKind::Param(idx),
+            KindRepr::Alloca { ty, count } => InstKind::Alloca(AllocaInst { ty, count }),
+            KindRepr::Load(ptr) => InstKind::Load(self.value(ptr)?),
+            KindRepr::Store { ptr, value } => InstKind::Store(StoreInst {
+                ptr: self.value(ptr)?,
+                value: self.value(value)?,
+            }),
+            KindRepr::Offset { ptr, offset } => InstKind::Offset(OffsetInst {
+                ptr: self.value(ptr)?,
+                offset: self.value(offset)?,
+            }),
+        })
+    }
+
+    fn branch(&self, branch: BranchRepr) -> Result<Branch, String> {
+        Ok(match branch {
+            BranchRepr::Unreachable => Branch::Unreachable,
+            BranchRepr::Return(value) => Branch::Return(value.map(|v| self.value(v)).transpose()?),
+            BranchRepr::Br(target) => Branch::Jump(self.bb(target)?),
+            BranchRepr::Brif {
+                cond,
+                if_true,
+                if_false,
+            } => Branch::CondJump {
+                cond: self.value(cond)?,
+                if_true: self.bb(if_true)?,
+                if_false: self.bb(if_false)?,
+            },
+        })
+    }
+}
+
+impl Serialize for TacFunc {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        FuncRepr::new(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TacFunc {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        FuncRepr::deserialize(deserializer)?
+            .into_func()
+            .map_err(D::Error::custom)
+    }
+}
+
+impl Serialize for Program {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let functions = self.functions.values().collect::<Vec<_>>();
+        let imports = self.imports.iter().collect::<BTreeMap<_, _>>();
+
+        #[derive(Serialize)]
+        struct ProgramRef<'a> {
+            functions: Vec<&'a TacFunc>,
+            imports: BTreeMap<&'a SmolStr, &'a Ty>,
+        }
+        ProgramRef { functions, imports }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Program {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = ProgramRepr::deserialize(deserializer)?;
+        let mut program = Program {
+            functions: Default::default(),
+            imports: repr.imports.into_iter().collect(),
+        };
+        for func in repr.functions {
+            let name = func.name.clone();
+            if program.functions.insert(name.clone(), func).is_some() {
+                return Err(D::Error::custom(format!("duplicate function `{}`", name)));
+            }
+        }
+        Ok(program)
+    }
+}
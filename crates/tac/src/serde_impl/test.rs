@@ -0,0 +1,131 @@
+use serde_json::json;
+
+use crate::{parser::parse_program_from_string, FuncAttr, Program, SourceLoc, TacFunc, Ty};
+
+#[test]
+fn test_round_trip() {
+    let input = r"
+    (fn fib (i32) i32 (attrs pure)
+        (bb0 (
+            (%0 i32 param 0)
+            (%1 b32 le %0 1))
+            (brif %1 bb1 bb2))
+        (bb1 (
+            (%7 i32 1))
+            (br bb3))
+        (bb2 (
+            (%2 i32 sub %0 1)
+            (%3 i32 sub %0 2)
+            (%4 i32 call fib (%2))
+            (%5 (fn (i32) i32) funcref fib)
+            (%6 i32 call_indirect %5 (%3))
+            (%8 i32 add %4 %6))
+            (br bb3))
+        (bb3 (
+            (%9 i32 phi (bb1 %7) (bb2 %8)))
+            (return %9)))
+
+    (fn main () ()
+        (bb0 (
+            (%0 i32 call fib (15))
+            (%1 i32* alloca i32 2)
+            (%2 i32* offset %1 1)
+            (%3 () store %2 %0)
+            (%4 i32 load %2))
+            (return))
+        (bb1 ()
+            (unreachable)))
+    ";
+    let mut program = parse_program_from_string(input).unwrap();
+    let fib = program.functions.get_mut("fib").unwrap();
+    let first = fib.bb_get(fib.first_block.unwrap()).head.unwrap();
+    fib.tac_get_mut(first).loc = Some(SourceLoc { line: 1, col: 8 });
+    program
+        .imports
+        .insert("putint".into(), Ty::func_of(Ty::unit(), vec![Ty::int()]));
+
+    let json = serde_json::to_value(&program).unwrap();
+    assert_eq!(
+        json["imports"],
+        json!({ "putint": { "func": {
+            "return_type": "unit",
+            "params": [{ "numeric": { "kind": "int", "size": 32 } }]
+        } } })
+    );
+    assert_eq!(json["functions"][0]["name"], "fib");
+    assert_eq!(json["functions"][0]["attrs"], json!(["pure"]));
+    assert_eq!(
+        json["functions"][0]["blocks"][0]["insts"][0],
+        json!({
+            "id": 0,
+            "ty": { "numeric": { "kind": "int", "size": 32 } },
+            "loc": { "line": 1, "col": 8 },
+            "kind": { "param": 0 }
+        })
+    );
+    assert_eq!(
+        json["functions"][0]["blocks"][0]["branch"],
+        json!({ "brif": { "cond": { "inst": 1 }, "if_true": 1, "if_false": 2 } })
+    );
+
+    assert_eq!(
+        json["functions"][1]["blocks"][0]["insts"][1]["kind"],
+        json!({ "alloca": {
+            "ty": { "numeric": { "kind": "int", "size": 32 } },
+            "count": 2
+        } })
+    );
+    assert_eq!(
+        json["functions"][1]["blocks"][0]["insts"][3]["kind"],
+        json!({ "store": { "ptr": { "inst": 2 }, "value": { "inst": 0 } } })
+    );
+
+    let decoded: Program = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(decoded.imports, program.imports);
+    assert_eq!(decoded.functions.len(), program.functions.len());
+    for (name, func) in &program.functions {
+        assert_eq!(decoded.functions[name].to_string(), func.to_string());
+    }
+    assert!(decoded.functions["fib"].has_attr(FuncAttr::Pure));
+    assert_eq!(serde_json::to_value(&decoded).unwrap(), json);
+}
+
+#[test]
+fn test_deserialize_ids() {
+    // Any unique ids are accepted, and blocks keep their order
+    let func: TacFunc = serde_json::from_value(json!({
+        "name": "f",
+        "ty": { "func": { "return_type": "unit", "params": [] } },
+        "blocks": [
+            { "id": 7, "insts": [], "branch": { "br": 3 } },
+            { "id": 3, "insts": [
+                { "id": 42, "ty": "unit", "kind": { "assign": { "imm": -1 } } }
+            ], "branch": { "return": null } }
+        ]
+    }))
+    .unwrap();
+    let bbs = func.bb_iter().map(|(bb, _)| bb).collect::<Vec<_>>();
+    assert_eq!(bbs.len(), 2);
+    assert_eq!(func.first_block, Some(bbs[0]));
+    assert_eq!(func.bb_get(bbs[0]).branch, crate::Branch::Jump(bbs[1]));
+    assert_eq!(func.inst_of_bb_iter(bbs[1]).count(), 1);
+
+    let error = |blocks| {
+        serde_json::from_value::<TacFunc>(json!({
+            "name": "f",
+            "ty": "unit",
+            "blocks": blocks
+        }))
+        .unwrap_err()
+        .to_string()
+    };
+    assert!(
+        error(json!([{ "id": 0, "insts": [], "branch": { "br": 1 } }]))
+            .contains("no basic block with id 1")
+    );
+    assert!(error(json!([
+        { "id": 0, "insts": [], "branch": "unreachable" },
+        { "id": 0, "insts": [], "branch": "unreachable" }
+    ]))
+    .contains("duplicate basic block id 0"));
+}
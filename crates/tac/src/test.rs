@@ -0,0 +1,33 @@
+use crate::{parser::parse_program_from_string, InstKind};
+
+#[test]
+fn test_inst_of_bb_iter() {
+    let input = r"
+    (fn f (i32) i32
+        (bb0 (
+            (%0 i32 param 0)
+            (%1 i32 add %0 1))
+            (br bb1))
+        (bb1 (
+            (%2 i32 mul %1 2))
+            (br bb2))
+        (bb2 ()
+            (return %2)))
+    ";
+    let program = parse_program_from_string(input).unwrap();
+    let f = &program.functions["f"];
+    let bbs = f.bb_iter().map(|(bb, _)| bb).collect::<Vec<_>>();
+
+    // Every instruction is visited, including the tail of each basic block
+    let insts = |bb| {
+        f.inst_of_bb_iter(bb)
+            .map(|(idx, _)| idx)
+            .collect::<Vec<_>>()
+    };
+    let first = insts(bbs[0]);
+    assert_eq!(first.len(), 2);
+    assert_eq!(Some(first[1]), f.bb_get(bbs[0]).tail);
+    assert!(matches!(f.inst_get(first[1]).kind, InstKind::Binary(_)));
+    assert_eq!(insts(bbs[1]), vec![f.bb_get(bbs[1]).tail.unwrap()]);
+    assert!(insts(bbs[2]).is_empty());
+}
@@ -2,6 +2,9 @@
 use enum_as_inner::EnumAsInner;
 use std::sync::Arc;
 
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
 pub const PTR_SIZE: usize = 8;
 
 /// A representation of basic type that has O(1) clone and sizes no more than
@@ -10,6 +13,8 @@ pub const PTR_SIZE: usize = 8;
 /// > I know this is worse than using an external type repository, but hey you
 /// > can directly compare these!
 #[derive(Debug, Clone, PartialEq, Eq, EnumAsInner, Hash)]
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde_impl", serde(rename_all = "snake_case"))]
 pub enum Ty {
     Unit,
     Func(Arc<FuncTy>),
@@ -41,12 +46,14 @@ impl Ty {
         Ty::Ptr(Arc::new(ty))
     }
 
+    /// The number of bytes a value of this type takes in memory, or `None` if
+    /// it cannot be placed in memory.
     pub fn size(&self) -> Option<usize> {
         match self {
             Ty::Unit => Some(0),
             Ty::Func(_) => None,
             Ty::Ptr(_) => Some(PTR_SIZE),
-            Ty::Numeric(n) => Some(n.size() as usize),
+            Ty::Numeric(n) => Some((n.size() as usize).div_ceil(8)),
         }
     }
 }
@@ -58,6 +65,7 @@ impl Default for Ty {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
 pub struct NumericTy {
     pub kind: TyKind,
     pub size: u8,
@@ -78,18 +86,22 @@ impl NumericTy {
         }
     }
 
+    /// The size of this type in bits.
     pub fn size(&self) -> u8 {
         self.size
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde_impl", serde(rename_all = "snake_case"))]
 pub enum TyKind {
     Bool,
     Int,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
 pub struct FuncTy {
     pub return_type: Ty,
     pub params: Vec<Ty>,
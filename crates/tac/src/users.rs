@@ -0,0 +1,213 @@
+//! Def-use chains of instructions.
+//!
+//! Every [`TacFunc`] keeps track of the places using the value of each
+//! instruction, including conditions and return values of branches. The lists
+//! are updated by every method that mutates instructions or branches. Mutable
+//! references are given out as guards ([`TacMut`], [`InstMut`] and [`BBMut`]),
+//! which update the lists when dropped.
+
+#[cfg(test)]
+mod test;
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    ops::{Deref, DerefMut},
+};
+
+use tinyvec::TinyVec;
+
+use crate::{BBId, BasicBlock, ImplicitLinkedList, Inst, InstId, Tac, TacFunc};
+
+/// A place using the value of an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum UsePlace {
+    Inst(InstId),
+    /// The branch instruction at the end of a basic block.
+    Branch(BBId),
+}
+
+impl Display for UsePlace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UsePlace::Inst(inst) => write!(f, "{}", inst),
+            UsePlace::Branch(bb) => write!(f, "the branch of {}", bb),
+        }
+    }
+}
+
+/// The users of every instruction. A place using a value several times is
+/// counted several times.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct UseMap {
+    users: HashMap<InstId, BTreeMap<UsePlace, u32>>,
+}
+
+impl UseMap {
+    pub(crate) fn add(&mut self, value: InstId, user: UsePlace) {
+        *self
+            .users
+            .entry(value)
+            .or_default()
+            .entry(user)
+            .or_default() += 1;
+    }
+
+    pub(crate) fn remove(&mut self, value: InstId, user: UsePlace) {
+        let users = self
+            .users
+            .get_mut(&value)
+            .expect("Removing a use that does not exist");
+        let count = users
+            .get_mut(&user)
+            .expect("Removing a use that does not exist");
+        *count -= 1;
+        if *count == 0 {
+            users.remove(&user);
+        }
+        if users.is_empty() {
+            self.users.remove(&value);
+        }
+    }
+
+    pub(crate) fn users_of(&self, value: InstId) -> impl Iterator<Item = UsePlace> + '_ {
+        self.users
+            .get(&value)
+            .into_iter()
+            .flat_map(|users| users.keys().copied())
+    }
+
+    pub(crate) fn is_unused(&self, value: InstId) -> bool {
+        !self.users.contains_key(&value)
+    }
+
+    /// Returns the values whose users are different in `self` and `other`.
+    pub(crate) fn diff(&self, other: &UseMap) -> Vec<InstId> {
+        let mut values = self
+            .users
+            .iter()
+            .filter(|&(value, users)| other.users.get(value) != Some(users))
+            .map(|(&value, _)| value)
+            .collect::<Vec<_>>();
+        values.extend(
+            other
+                .users
+                .keys()
+                .filter(|value| !self.users.contains_key(value))
+                .copied(),
+        );
+        values
+    }
+
+    /// Replace the uses of `old` by `user` with uses of `new`.
+    pub(crate) fn update(
+        &mut self,
+        user: UsePlace,
+        old: impl IntoIterator<Item = InstId>,
+        new: impl IntoIterator<Item = InstId>,
+    ) {
+        old.into_iter().for_each(|value| self.remove(value, user));
+        new.into_iter().for_each(|value| self.add(value, user));
+    }
+}
+
+/// Values used by an instruction.
+type Operands = TinyVec<[InstId; 4]>;
+
+fn operands_of(inst: &Inst) -> Operands {
+    inst.kind.param_op_iter().collect()
+}
+
+/// A mutable reference to an instruction with its surrounding information,
+/// returned by [`TacFunc::tac_get_mut`].
+pub struct TacMut<'a> {
+    func: &'a mut TacFunc,
+    idx: InstId,
+    old: Operands,
+}
+
+impl<'a> TacMut<'a> {
+    pub(crate) fn new(func: &'a mut TacFunc, idx: InstId) -> TacMut<'a> {
+        let old = operands_of(&func.instructions_arena.get_item(idx).inst);
+        TacMut { func, idx, old }
+    }
+}
+
+impl Deref for TacMut<'_> {
+    type Target = Tac;
+
+    fn deref(&self) -> &Tac {
+        self.func.instructions_arena.get_item(self.idx)
+    }
+}
+
+impl DerefMut for TacMut<'_> {
+    fn deref_mut(&mut self) -> &mut Tac {
+        self.func.instructions_arena.get_item_mut(self.idx)
+    }
+}
+
+impl Drop for TacMut<'_> {
+    fn drop(&mut self) {
+        let new = operands_of(&self.inst);
+        if new != self.old {
+            let old = std::mem::take(&mut self.old);
+            self.func.users.update(UsePlace::Inst(self.idx), old, new);
+        }
+    }
+}
+
+/// A mutable reference to an instruction, returned by [`TacFunc::inst_get_mut`].
+pub struct InstMut<'a>(pub(crate) TacMut<'a>);
+
+impl Deref for InstMut<'_> {
+    type Target = Inst;
+
+    fn deref(&self) -> &Inst {
+        &self.0.inst
+    }
+}
+
+impl DerefMut for InstMut<'_> {
+    fn deref_mut(&mut self) -> &mut Inst {
+        &mut self.0.inst
+    }
+}
+
+/// A mutable reference to a basic block, returned by [`TacFunc::bb_get_mut`].
+pub struct BBMut<'a> {
+    func: &'a mut TacFunc,
+    idx: BBId,
+    old: Option<InstId>,
+}
+
+impl<'a> BBMut<'a> {
+    pub(crate) fn new(func: &'a mut TacFunc, idx: BBId) -> BBMut<'a> {
+        let old = func.basic_block_arena[idx].branch.param_op();
+        BBMut { func, idx, old }
+    }
+}
+
+impl Deref for BBMut<'_> {
+    type Target = BasicBlock;
+
+    fn deref(&self) -> &BasicBlock {
+        &self.func.basic_block_arena[self.idx]
+    }
+}
+
+impl DerefMut for BBMut<'_> {
+    fn deref_mut(&mut self) -> &mut BasicBlock {
+        &mut self.func.basic_block_arena[self.idx]
+    }
+}
+
+impl Drop for BBMut<'_> {
+    fn drop(&mut self) {
+        let new = self.branch.param_op();
+        if new != self.old {
+            let old = self.old.take();
+            self.func.users.update(UsePlace::Branch(self.idx), old, new);
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use crate::{parser::parse_program_from_string, verifier::verify_program, InstKind, UsePlace};
+
+#[test]
+fn test_users() {
+    let input = r"
+    (fn f (i32) i32
+        (bb0 (
+            (%0 i32 param 0)
+            (%1 i32 add %0 %0)
+            (%2 i32 add %1 1))
+            (brif %1 bb1 bb2))
+        (bb1 ()
+            (return %2))
+        (bb2 ()
+            (return %0)))
+    ";
+    let mut program = parse_program_from_string(input).unwrap();
+    let func = program.functions.get_mut("f").unwrap();
+    let insts = func
+        .inst_of_bb_iter(func.first_block.unwrap())
+        .map(|(idx, _)| idx)
+        .collect::<Vec<_>>();
+    let (p0, v1, v2) = (insts[0], insts[1], insts[2]);
+    let bb0 = func.first_block.unwrap();
+
+    assert_eq!(
+        func.users_of(v1).collect::<Vec<_>>(),
+        vec![UsePlace::Inst(v2), UsePlace::Branch(bb0)]
+    );
+    assert!(!func.is_unused(p0));
+
+    // Uses through mutable references are tracked
+    func.inst_get_mut(v2).kind = InstKind::Assign(p0.into());
+    assert!(!func.users_of(v1).any(|u| u == UsePlace::Inst(v2)));
+
+    func.replace_all_uses_with(v1, p0);
+    assert!(func.is_unused(v1));
+    assert_eq!(func.users_of(p0).count(), 4);
+
+    verify_program(&program).unwrap();
+}
@@ -1,13 +1,23 @@
+#[cfg(test)]
+mod test;
+
 use std::fmt::Display;
 
-pub struct ListFormatter<I>(I);
+pub struct ListFormatter<I> {
+    iter: I,
+    separator: &'static str,
+}
 
 impl<I, T> ListFormatter<I>
 where
     I: Iterator<Item = T> + Clone,
 {
     pub fn new(iter: I) -> ListFormatter<I> {
-        ListFormatter(iter)
+        Self::with_separator(iter, ", ")
+    }
+
+    pub fn with_separator(iter: I, separator: &'static str) -> ListFormatter<I> {
+        ListFormatter { iter, separator }
     }
 }
 
@@ -17,11 +27,11 @@ where
     T: Display,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut iter = self.0.clone();
+        let mut iter = self.iter.clone();
         if let Some(first) = iter.next() {
             write!(f, "{}", first)?;
             for other in iter {
-                write!(f, ", {}", other)?;
+                write!(f, "{}{}", self.separator, other)?;
             }
         }
         Ok(())
@@ -0,0 +1,30 @@
+use crate::{parser::parse_program_from_string, Ty};
+
+use super::ListFormatter;
+
+#[test]
+fn test_list_formatter() {
+    let items = [1, 2, 3];
+    assert_eq!(ListFormatter::new(items.iter()).to_string(), "1, 2, 3");
+    assert_eq!(
+        ListFormatter::with_separator(items.iter(), " ").to_string(),
+        "1 2 3"
+    );
+    assert_eq!(ListFormatter::new([0; 0].iter()).to_string(), "");
+
+    // Parameter types of functions are separated by spaces, so they parse back
+    let input = r"
+    (fn f (i32 b32) i32
+        (bb0 (
+            (%0 i32 param 0))
+            (return %0)))
+    ";
+    let program = parse_program_from_string(input).unwrap();
+    let printed = program.functions["f"].to_string();
+    assert!(printed.starts_with("(fn f (i32 b32) i32"), "{}", printed);
+    let reparsed = parse_program_from_string(&printed).unwrap();
+    assert_eq!(
+        reparsed.functions["f"].ty,
+        Ty::func_of(Ty::int(), vec![Ty::int(), Ty::bool()])
+    );
+}
@@ -0,0 +1,437 @@
+//! Checking that functions are well-formed Azuki TAC.
+//!
+//! The verifier checks the invariants every pass may rely on:
+//!
+//! - Instruction lists and the `bb` pointers of instructions agree with each
+//!   other, and every branch target exists.
+//! - The [users](crate::users) recorded for every instruction are the actual
+//!   ones.
+//! - Phi instructions sit at the head of basic blocks, with exactly one source
+//!   for every predecessor.
+//! - Every use of a value is dominated by its definition. Uses inside
+//!   unreachable basic blocks are not checked.
+//! - Operand and result types agree, and calls match the type of the callee.
+//!   Immediates may be of any type, and comparisons may result in any numeric
+//!   type.
+//! - Loads, stores and offsets go through pointers, and arithmetic is never
+//!   done on pointers; `offset` is used for that instead.
+
+#[cfg(test)]
+mod test;
+
+use std::collections::{BTreeSet, HashMap};
+
+use smol_str::SmolStr;
+
+use crate::{
+    analysis::{Cfg, DomTree},
+    err::{VerifyError, VerifyErrorKind},
+    ty::FuncTy,
+    users::UseMap,
+    BBId, BinaryOp, Branch, InstId, InstKind, Program, TacFunc, Ty, TyKind, UsePlace, Value,
+};
+
+/// Returns the types of all functions callable inside `program`, including
+/// imported ones.
+pub fn signatures(program: &Program) -> HashMap<SmolStr, Ty> {
+    let mut sigs = program.imports.clone();
+    for (name, func) in &program.functions {
+        sigs.insert(name.clone(), func.ty.clone());
+    }
+    sigs
+}
+
+/// Verify every function inside `program`.
+pub fn verify_program(program: &Program) -> Result<(), Vec<VerifyError>> {
+    let sigs = signatures(program);
+    let mut names = program.functions.keys().collect::<Vec<_>>();
+    names.sort();
+
+    let errors = names
+        .into_iter()
+        .filter_map(|name| verify_func(&program.functions[name], &sigs).err())
+        .flatten()
+        .collect::<Vec<_>>();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Verify `func`. Calls are checked against the functions inside `signatures`,
+/// see [`signatures`].
+pub fn verify_func(
+    func: &TacFunc,
+    signatures: &HashMap<SmolStr, Ty>,
+) -> Result<(), Vec<VerifyError>> {
+    let mut verifier = FuncVerifier {
+        func,
+        signatures,
+        errors: vec![],
+        cfg: Cfg::new(func),
+        position: HashMap::new(),
+        dom: DomTree::default(),
+    };
+    verifier.verify();
+
+    if verifier.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(verifier
+            .errors
+            .into_iter()
+            .map(|kind| VerifyError {
+                func: func.name.clone(),
+                kind,
+            })
+            .collect())
+    }
+}
+
+struct FuncVerifier<'a> {
+    func: &'a TacFunc,
+    signatures: &'a HashMap<SmolStr, Ty>,
+    errors: Vec<VerifyErrorKind>,
+
+    /// The control flow graph, containing basic blocks inside the function.
+    cfg: Cfg,
+    /// The basic block of every instruction and its index inside it.
+    position: HashMap<InstId, (BBId, usize)>,
+    dom: DomTree,
+}
+
+impl<'a> FuncVerifier<'a> {
+    fn verify(&mut self) {
+        if self.cfg.entry().is_none() {
+            self.errors.push(VerifyErrorKind::NoEntryBlock);
+            return;
+        }
+        let func_ty = match &self.func.ty {
+            Ty::Func(f) => Some(f.clone()),
+            ty => {
+                self.errors.push(VerifyErrorKind::NotAFuncTy(ty.clone()));
+                None
+            }
+        };
+
+        self.check_layout();
+        self.check_users();
+        self.check_branch_targets();
+        self.dom = DomTree::new(&self.cfg);
+
+        for bb in self.cfg.blocks().to_vec() {
+            self.check_phis(bb);
+            for (idx, inst) in self.func.inst_of_bb_iter(bb) {
+                let place = UsePlace::Inst(idx);
+                for value in inst.kind.param_op_iter() {
+                    self.check_dominance(place, bb, value);
+                }
+                if let Some(func_ty) = &func_ty {
+                    self.check_inst_ty(idx, func_ty);
+                }
+            }
+
+            let branch = &self.func.bb_get(bb).branch;
+            let place = UsePlace::Branch(bb);
+            match branch {
+                Branch::Return(Some(v)) | Branch::CondJump { cond: v, .. } => {
+                    if let Some(value) = v.get_inst() {
+                        self.check_dominance(place, bb, value);
+                    }
+                }
+                _ => {}
+            }
+            if let Some(func_ty) = &func_ty {
+                self.check_branch_ty(bb, func_ty);
+            }
+        }
+    }
+
+    /// Check the linked lists of basic blocks and instructions, and record the
+    /// position of every instruction.
+    fn check_layout(&mut self) {
+        for &bb in self.cfg.blocks() {
+            let block = self.func.bb_get(bb);
+            let mut prev = None;
+            let mut it = block.head;
+            let mut idx = 0;
+            while let Some(inst) = it {
+                if !self.func.inst_exists(inst) || self.position.contains_key(&inst) {
+                    self.errors.push(VerifyErrorKind::BrokenInstList(bb));
+                    break;
+                }
+                let tac = self.func.tac_get(inst);
+                if tac.prev != prev {
+                    self.errors.push(VerifyErrorKind::BrokenInstList(bb));
+                }
+                if tac.bb != bb {
+                    self.errors.push(VerifyErrorKind::WrongBlockPointer {
+                        inst,
+                        expected: bb,
+                        found: tac.bb,
+                    });
+                }
+                self.position.insert(inst, (bb, idx));
+                prev = Some(inst);
+                it = tac.next;
+                idx += 1;
+            }
+            if prev != block.tail {
+                self.errors.push(VerifyErrorKind::BrokenInstList(bb));
+            }
+        }
+    }
+
+    /// Check that the users recorded inside the function are the actual ones.
+    fn check_users(&mut self) {
+        let mut actual = UseMap::default();
+        for (idx, _, inst) in self.func.all_inst_unordered() {
+            actual.update(UsePlace::Inst(idx), None, inst.kind.param_op_iter());
+        }
+        for (bb, block) in self.func.all_bb_unordered() {
+            actual.update(UsePlace::Branch(bb), None, block.branch.param_op());
+        }
+
+        let mut wrong = actual.diff(&self.func.users);
+        wrong.sort();
+        self.errors
+            .extend(wrong.into_iter().map(VerifyErrorKind::WrongUsers));
+    }
+
+    fn check_branch_targets(&mut self) {
+        for &bb in self.cfg.blocks() {
+            for target in self.func.bb_get(bb).branch.target_iter() {
+                if !self.cfg.contains(target) {
+                    self.errors
+                        .push(VerifyErrorKind::MissingBranchTarget { bb, target });
+                }
+            }
+        }
+    }
+
+    fn check_phis(&mut self, bb: BBId) {
+        let mut at_head = true;
+        for (idx, inst) in self.func.inst_of_bb_iter(bb) {
+            let sources = match &inst.kind {
+                InstKind::Phi(sources) => sources,
+                _ => {
+                    at_head = false;
+                    continue;
+                }
+            };
+            if !at_head {
+                self.errors.push(VerifyErrorKind::PhiNotAtHead(idx));
+            }
+
+            let preds = self.cfg.preds(bb).iter().copied().collect::<BTreeSet<_>>();
+            if !sources.keys().eq(preds.iter()) {
+                self.errors.push(VerifyErrorKind::PhiSourceMismatch {
+                    inst: idx,
+                    preds: preds.into_iter().collect(),
+                    sources: sources.keys().copied().collect(),
+                });
+            }
+        }
+    }
+
+    /// Check that the definition of `value` dominates its use at `place`,
+    /// which is inside `bb`.
+    fn check_dominance(&mut self, place: UsePlace, bb: BBId, value: InstId) {
+        let (def_bb, def_idx) = match self.position.get(&value) {
+            Some(&pos) => pos,
+            None => {
+                self.errors
+                    .push(VerifyErrorKind::UndefinedValue { place, value });
+                return;
+            }
+        };
+        if !self.dom.is_reachable(bb) {
+            return;
+        }
+
+        let dominated = match place {
+            UsePlace::Inst(inst) => match &self.func.inst_get(inst).kind {
+                // Phi sources are used at the end of the corresponding predecessor
+                InstKind::Phi(sources) => {
+                    sources
+                        .iter()
+                        .filter(|&(_, &v)| v == value)
+                        .all(|(&pred, _)| {
+                            !self.dom.is_reachable(pred)
+                                || (self.dom.is_reachable(def_bb)
+                                    && self.dom.dominates(def_bb, pred))
+                        })
+                }
+                _ if def_bb == bb => def_idx < self.position[&inst].1,
+                _ => self.dom.is_reachable(def_bb) && self.dom.dominates(def_bb, bb),
+            },
+            UsePlace::Branch(_) => self.dom.is_reachable(def_bb) && self.dom.dominates(def_bb, bb),
+        };
+        if !dominated {
+            self.errors
+                .push(VerifyErrorKind::UseNotDominated { place, value });
+        }
+    }
+
+    /// Returns the type of `value`, or `None` if it is an immediate or not
+    /// defined.
+    fn value_ty(&self, value: Value) -> Option<&'a Ty> {
+        let func = self.func;
+        match value {
+            Value::Dest(inst) if func.inst_exists(inst) => Some(&func.inst_get(inst).ty),
+            _ => None,
+        }
+    }
+
+    fn expect_ty(&mut self, place: UsePlace, expected: &Ty, found: &Ty) {
+        if expected != found {
+            self.errors.push(VerifyErrorKind::TypeMismatch {
+                place,
+                expected: expected.clone(),
+                found: found.clone(),
+            });
+        }
+    }
+
+    fn expect_value_ty(&mut self, place: UsePlace, expected: &Ty, value: Value) {
+        if let Some(found) = self.value_ty(value) {
+            self.expect_ty(place, expected, found);
+        }
+    }
+
+    /// Returns the type pointed to by `ptr`, or `None` if it is unknown or
+    /// `ptr` is not a pointer.
+    fn pointee_ty(&mut self, inst: InstId, ptr: Value) -> Option<&'a Ty> {
+        match self.value_ty(ptr)? {
+            Ty::Ptr(ty) => Some(ty),
+            ty => {
+                self.errors.push(VerifyErrorKind::NotAPointer {
+                    inst,
+                    ty: ty.clone(),
+                });
+                None
+            }
+        }
+    }
+
+    fn check_call(&mut self, inst: InstId, callee: &FuncTy, params: &[Value], ret: &Ty) {
+        let place = UsePlace::Inst(inst);
+        if callee.params.len() != params.len() {
+            self.errors.push(VerifyErrorKind::WrongParamCount {
+                inst,
+                expected: callee.params.len(),
+                found: params.len(),
+            });
+        }
+        for (ty, &param) in callee.params.iter().zip(params) {
+            self.expect_value_ty(place, ty, param);
+        }
+        self.expect_ty(place, &callee.return_type, ret);
+    }
+
+    fn check_inst_ty(&mut self, idx: InstId, func_ty: &FuncTy) {
+        let inst = self.func.inst_get(idx);
+        let place = UsePlace::Inst(idx);
+        match &inst.kind {
+            InstKind::Binary(bin) => match bin.op {
+                BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div => {
+                    if let Ty::Ptr(_) = inst.ty {
+                        self.errors.push(VerifyErrorKind::PointerArithmetic(idx));
+                    }
+                    self.expect_value_ty(place, &inst.ty, bin.lhs);
+                    self.expect_value_ty(place, &inst.ty, bin.rhs);
+                }
+                BinaryOp::Lt
+                | BinaryOp::Gt
+                | BinaryOp::Le
+                | BinaryOp::Ge
+                | BinaryOp::Eq
+                | BinaryOp::Ne => {
+                    if let Some(lhs_ty) = self.value_ty(bin.lhs) {
+                        self.expect_value_ty(place, lhs_ty, bin.rhs);
+                    }
+                    if !matches!(inst.ty, Ty::Numeric(_)) {
+                        self.expect_ty(place, &Ty::bool(), &inst.ty);
+                    }
+                }
+            },
+            InstKind::FunctionCall(call) => match self.signatures.get(&call.name) {
+                Some(Ty::Func(callee)) => self.check_call(idx, callee, &call.params, &inst.ty),
+                Some(ty) => self.errors.push(VerifyErrorKind::NotCallable {
+                    inst: idx,
+                    ty: ty.clone(),
+                }),
+                None => self.errors.push(VerifyErrorKind::UnknownFunction {
+                    inst: idx,
+                    name: call.name.clone(),
+                }),
+            },
+            InstKind::IndirectCall(call) => match self.value_ty(call.callee) {
+                Some(Ty::Func(callee)) => self.check_call(idx, callee, &call.params, &inst.ty),
+                Some(ty) => self.errors.push(VerifyErrorKind::NotCallable {
+                    inst: idx,
+                    ty: ty.clone(),
+                }),
+                None => {}
+            },
+            InstKind::FuncRef(name) => match self.signatures.get(name) {
+                Some(ty) => self.expect_ty(place, ty, &inst.ty),
+                None => self.errors.push(VerifyErrorKind::UnknownFunction {
+                    inst: idx,
+                    name: name.clone(),
+                }),
+            },
+            InstKind::Assign(v) => self.expect_value_ty(place, &inst.ty, *v),
+            InstKind::Phi(sources) => {
+                for &v in sources.values() {
+                    self.expect_value_ty(place, &inst.ty, v.into());
+                }
+            }
+            InstKind::Param(i) => match func_ty.params.get(*i) {
+                Some(ty) => self.expect_ty(place, ty, &inst.ty),
+                None => self.errors.push(VerifyErrorKind::ParamOutOfRange(idx)),
+            },
+            InstKind::Alloca(alloca) => {
+                self.expect_ty(place, &Ty::ptr_of(alloca.ty.clone()), &inst.ty)
+            }
+            InstKind::Load(ptr) => {
+                if let Some(ty) = self.pointee_ty(idx, *ptr) {
+                    self.expect_ty(place, ty, &inst.ty);
+                }
+            }
+            InstKind::Store(store) => {
+                if let Some(ty) = self.pointee_ty(idx, store.ptr) {
+                    self.expect_value_ty(place, ty, store.value);
+                }
+                self.expect_ty(place, &Ty::unit(), &inst.ty);
+            }
+            InstKind::Offset(offset) => {
+                if self.pointee_ty(idx, offset.ptr).is_some() {
+                    self.expect_value_ty(place, &inst.ty, offset.ptr);
+                }
+                match self.value_ty(offset.offset) {
+                    Some(Ty::Numeric(n)) if n.kind == TyKind::Int => {}
+                    Some(ty) => self.expect_ty(place, &Ty::int(), ty),
+                    None => {}
+                }
+            }
+        }
+    }
+
+    fn check_branch_ty(&mut self, bb: BBId, func_ty: &FuncTy) {
+        let place = UsePlace::Branch(bb);
+        match &self.func.bb_get(bb).branch {
+            Branch::Return(Some(v)) => self.expect_value_ty(place, &func_ty.return_type, *v),
+            Branch::Return(None) => self.expect_ty(place, &func_ty.return_type, &Ty::unit()),
+            Branch::CondJump { cond, .. } => {
+                if let Some(ty) = self.value_ty(*cond) {
+                    if !matches!(ty, Ty::Numeric(_)) {
+                        self.expect_ty(place, &Ty::bool(), ty);
+                    }
+                }
+            }
+            Branch::Jump(_) | Branch::Unreachable => {}
+        }
+    }
+}
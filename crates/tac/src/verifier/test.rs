@@ -0,0 +1,172 @@
+use crate::{
+    err::{VerifyError, VerifyErrorKind},
+    parser::parse_program_from_string,
+};
+
+use super::verify_program;
+
+fn verify(input: &str) -> Result<(), Vec<VerifyError>> {
+    let program = parse_program_from_string(input).unwrap();
+    verify_program(&program)
+}
+
+fn verify_err(input: &str) -> Vec<VerifyErrorKind> {
+    verify(input)
+        .unwrap_err()
+        .into_iter()
+        .map(|e| e.kind)
+        .collect()
+}
+
+#[test]
+fn test_valid_program() {
+    let input = r"
+    (fn fib (i32) i32
+        (bb0 (
+            (%0 i32 param 0)
+            (%1 b32 le %0 1))
+            (brif %1 bb1 bb2))
+        (bb1 (
+            (%7 i32 1))
+            (br bb3))
+        (bb2 (
+            (%2 i32 sub %0 1)
+            (%3 i32 sub %0 2)
+            (%4 i32 call fib (%2))
+            (%5 i32 call fib (%3))
+            (%6 i32 add %4 %5))
+            (br bb3))
+        (bb3 (
+            (%8 i32 phi (bb1 %7) (bb2 %6)))
+            (return %8)))
+
+    (fn main () i32
+        (bb0 (
+            (%0 (fn (i32) i32) funcref fib)
+            (%1 i32 call_indirect %0 (15)))
+            (return %1)))
+    ";
+    verify(input).unwrap();
+}
+
+#[test]
+fn test_phi_errors() {
+    let input = r"
+    (fn f (i32) i32
+        (bb0 (
+            (%0 i32 param 0))
+            (brif %0 bb1 bb2))
+        (bb1 ()
+            (br bb2))
+        (bb2 (
+            (%1 i32 add %0 1)
+            (%2 i32 phi (bb1 %0)))
+            (return %2)))
+    ";
+    let errors = verify_err(input);
+    assert!(
+        errors
+            .iter()
+            .any(|e| matches!(e, VerifyErrorKind::PhiNotAtHead(_))),
+        "{:?}",
+        errors
+    );
+    assert!(
+        errors.iter().any(
+            |e| matches!(e, VerifyErrorKind::PhiSourceMismatch { preds, sources, .. } if preds.len() == 2 && sources.len() == 1)
+        ),
+        "{:?}",
+        errors
+    );
+}
+
+#[test]
+fn test_dominance_errors() {
+    let input = r"
+    (fn f (i32) i32
+        (bb0 (
+            (%0 i32 param 0))
+            (brif %0 bb1 bb2))
+        (bb1 (
+            (%1 i32 add %0 1))
+            (br bb2))
+        (bb2 (
+            (%2 i32 add %3 1)
+            (%3 i32 add %1 1))
+            (return %2)))
+    ";
+    let errors = verify_err(input);
+    assert_eq!(errors.len(), 2, "{:?}", errors);
+    assert!(errors
+        .iter()
+        .all(|e| matches!(e, VerifyErrorKind::UseNotDominated { .. })));
+}
+
+#[test]
+fn test_type_errors() {
+    let input = r"
+    (fn g (i32 i32) i32
+        (bb0 (
+            (%0 i32 param 0))
+            (return %0)))
+
+    (fn f () i32
+        (bb0 (
+            (%0 (fn (i32 i32) i32) funcref g)
+            (%1 i32 add %0 1)
+            (%2 i32 call g (1))
+            (%3 i32 call h ()))
+            (return %0)))
+    ";
+    let errors = verify_err(input);
+    assert!(errors.iter().any(|e| matches!(
+        e,
+        VerifyErrorKind::WrongParamCount {
+            expected: 2,
+            found: 1,
+            ..
+        }
+    )));
+    assert!(errors
+        .iter()
+        .any(|e| matches!(e, VerifyErrorKind::UnknownFunction { name, .. } if name == "h")));
+    // Once in `add` and once in `return`
+    let mismatches = errors
+        .iter()
+        .filter(|e| matches!(e, VerifyErrorKind::TypeMismatch { .. }))
+        .count();
+    assert_eq!(mismatches, 2, "{:?}", errors);
+}
+
+#[test]
+fn test_memory_errors() {
+    let input = r"
+    (fn f (i32) ()
+        (bb0 (
+            (%0 i32 param 0)
+            (%1 i32* alloca i32 2)
+            (%2 i32 load %1)
+            (%3 () store %1 %2)
+            (%4 i32* offset %1 %0)
+            (%5 b32* alloca i32)
+            (%6 i32 load %0)
+            (%7 () store %1 %1)
+            (%8 i32* add %1 1)
+            (%9 i32* offset %1 %1))
+            (return)))
+    ";
+    let errors = verify_err(input);
+    assert!(errors
+        .iter()
+        .any(|e| matches!(e, VerifyErrorKind::NotAPointer { ty, .. } if ty.to_string() == "i32")));
+    assert!(errors
+        .iter()
+        .any(|e| matches!(e, VerifyErrorKind::PointerArithmetic(_))));
+    // In `alloca`, `store` and the second `offset`
+    let mismatches = errors
+        .iter()
+        .filter(|e| matches!(e, VerifyErrorKind::TypeMismatch { .. }))
+        .count();
+    assert_eq!(mismatches, 3, "{:?}", errors);
+    assert_eq!(errors.len(), 5, "{:?}", errors);
+}
@@ -0,0 +1,83 @@
+//! Compile-time evaluation of constant expressions.
+//!
+//! Constant expressions are made of literals, unary and binary operators, `as`
+//! casts and references to other constants. Unlike the wrapping arithmetic at
+//! runtime, overflow and division by zero are reported as errors.
+
+use std::convert::TryFrom;
+
+use azuki_syntax::ast::*;
+use azuki_tac::Ty;
+
+use crate::{err::Error, resolve_ty, symbol::ScopeBuilder};
+
+/// Evaluate `expr` as a constant expression, looking up constants it refers to
+/// inside `scope`. Returns the value and the type of `expr`.
+pub fn eval(expr: &Expr, scope: &ScopeBuilder) -> Result<(i64, Ty), Error> {
+    match expr {
+        Expr::Literal(lit) => match lit.kind {
+            LiteralKind::Integer(val) => {
+                let val = i64::try_from(val).map_err(|_| Error::ConstOverflow(lit.span))?;
+                Ok((val, Ty::int()))
+            }
+            LiteralKind::Char(ch) => Ok((ch as i64, Ty::int())),
+            LiteralKind::Float(_) | LiteralKind::String(_) => Err(Error::NotConstant(lit.span)),
+        },
+        Expr::Ident(ident) => {
+            let var = scope.find(&ident.name).ok_or_else(|| Error::UnknownVar {
+                name: ident.name.clone(),
+                span: ident.span,
+            })?;
+            match var.const_val {
+                Some(val) => Ok((val, var.ty.clone())),
+                None => Err(Error::NotConstant(ident.span)),
+            }
+        }
+        Expr::Unary(e) => {
+            let (val, ty) = eval(&e.expr, scope)?;
+            let val = match e.op {
+                UnaryOp::Neg => val.checked_neg().ok_or(Error::ConstOverflow(e.span))?,
+                UnaryOp::Pos => val,
+            };
+            Ok((val, ty))
+        }
+        Expr::Binary(e) => {
+            let (lhs, lhs_ty) = eval(&e.lhs, scope)?;
+            let (rhs, rhs_ty) = eval(&e.rhs, scope)?;
+            if lhs_ty != rhs_ty {
+                return Err(Error::TypeMismatch {
+                    expected: lhs_ty,
+                    found: rhs_ty,
+                    span: e.span,
+                });
+            }
+            // Like at runtime, comparisons have the type of their operands
+            let overflow = Error::ConstOverflow(e.span);
+            let val = match e.op {
+                BinaryOp::Add => lhs.checked_add(rhs).ok_or(overflow)?,
+                BinaryOp::Sub => lhs.checked_sub(rhs).ok_or(overflow)?,
+                BinaryOp::Mul => lhs.checked_mul(rhs).ok_or(overflow)?,
+                BinaryOp::Div => {
+                    if rhs == 0 {
+                        return Err(Error::ConstDivByZero(e.span));
+                    }
+                    lhs.checked_div(rhs).ok_or(overflow)?
+                }
+                BinaryOp::Gt => (lhs > rhs) as i64,
+                BinaryOp::Lt => (lhs < rhs) as i64,
+                BinaryOp::Ge => (lhs >= rhs) as i64,
+                BinaryOp::Le => (lhs <= rhs) as i64,
+                BinaryOp::Eq => (lhs == rhs) as i64,
+                BinaryOp::Neq => (lhs != rhs) as i64,
+            };
+            Ok((val, lhs_ty))
+        }
+        Expr::As(e) => {
+            // Casts don't change values; the target type is only checked
+            resolve_ty(&e.ty)?;
+            eval(&e.val, scope)
+        }
+        Expr::Call(e) => Err(Error::NotConstant(e.span)),
+        Expr::Assign(e) => Err(Error::NotConstant(e.span)),
+    }
+}
@@ -1,12 +1,172 @@
+use std::fmt::Display;
+
+use azuki_syntax::{diag::Diagnostic, span::Span};
 use azuki_tac::Ty;
 use smol_str::SmolStr;
 
 #[derive(Debug)]
 pub enum Error {
-    UnknownType(SmolStr),
-    DuplicateVar(SmolStr),
-    UnknownVar(SmolStr),
-    InvalidLExpr(String),
-    WrongParamLength { expected: usize, found: usize },
-    TypeMismatch { expected: Ty, found: Ty },
+    UnknownType {
+        name: SmolStr,
+        span: Span,
+    },
+    DuplicateVar {
+        name: SmolStr,
+        span: Span,
+    },
+    UnknownVar {
+        name: SmolStr,
+        span: Span,
+    },
+    InvalidLExpr(Span),
+    NotCallable {
+        name: SmolStr,
+        ty: Ty,
+        span: Span,
+    },
+    WrongParamLength {
+        expected: usize,
+        found: usize,
+        span: Span,
+    },
+    TypeMismatch {
+        expected: Ty,
+        found: Ty,
+        span: Span,
+    },
+    UseBeforeInit {
+        name: SmolStr,
+        span: Span,
+    },
+    MissingReturn {
+        func: SmolStr,
+        span: Span,
+    },
+    BreakOutsideLoop(Span),
+    ContinueOutsideLoop(Span),
+    UnknownLabel {
+        name: SmolStr,
+        span: Span,
+    },
+    ShadowedLabel {
+        name: SmolStr,
+        span: Span,
+    },
+    UnknownAttribute {
+        name: SmolStr,
+        span: Span,
+    },
+    ConflictingAttributes {
+        name: SmolStr,
+        other: SmolStr,
+        span: Span,
+    },
+    /// An expression that should be constant is not.
+    NotConstant(Span),
+    ConstOverflow(Span),
+    ConstDivByZero(Span),
+    AssignToConst {
+        name: SmolStr,
+        span: Span,
+    },
+}
+
+impl Error {
+    /// The place in source code where this error happens.
+    pub fn span(&self) -> Span {
+        match self {
+            Error::UnknownType { span, .. }
+            | Error::DuplicateVar { span, .. }
+            | Error::UnknownVar { span, .. }
+            | Error::InvalidLExpr(span)
+            | Error::NotCallable { span, .. }
+            | Error::WrongParamLength { span, .. }
+            | Error::TypeMismatch { span, .. }
+            | Error::UseBeforeInit { span, .. }
+            | Error::AssignToConst { span, .. }
+            | Error::UnknownLabel { span, .. }
+            | Error::ShadowedLabel { span, .. }
+            | Error::UnknownAttribute { span, .. }
+            | Error::ConflictingAttributes { span, .. }
+            | Error::MissingReturn { span, .. }
+            | Error::BreakOutsideLoop(span)
+            | Error::ContinueOutsideLoop(span)
+            | Error::NotConstant(span)
+            | Error::ConstOverflow(span)
+            | Error::ConstDivByZero(span) => *span,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::UnknownType { name, .. } => write!(f, "unknown type `{}`", name),
+            Error::DuplicateVar { name, .. } => write!(f, "`{}` is already defined", name),
+            Error::UnknownVar { name, .. } => write!(f, "cannot find `{}` in this scope", name),
+            Error::InvalidLExpr(_) => write!(f, "invalid left-hand side of assignment"),
+            Error::NotCallable { name, ty, .. } => {
+                write!(f, "`{}` of type `{}` is not a function", name, ty)
+            }
+            Error::WrongParamLength {
+                expected, found, ..
+            } => write!(
+                f,
+                "expected {} parameter(s), found {} parameter(s)",
+                expected, found
+            ),
+            Error::TypeMismatch {
+                expected, found, ..
+            } => write!(
+                f,
+                "mismatched types: expected `{}`, found `{}`",
+                expected, found
+            ),
+            Error::UseBeforeInit { name, .. } => {
+                write!(f, "`{}` is used before being initialized", name)
+            }
+            Error::MissingReturn { func, .. } => {
+                write!(f, "function `{}` does not return on every path", func)
+            }
+            Error::BreakOutsideLoop(_) => write!(f, "`break` outside of a loop"),
+            Error::ContinueOutsideLoop(_) => write!(f, "`continue` outside of a loop"),
+            Error::UnknownLabel { name, .. } => write!(f, "use of undeclared label `'{}`", name),
+            Error::ShadowedLabel { name, .. } => {
+                write!(f, "label `'{}` shadows a label of an enclosing loop", name)
+            }
+            Error::UnknownAttribute { name, .. } => write!(f, "unknown attribute `{}`", name),
+            Error::ConflictingAttributes { name, other, .. } => {
+                write!(f, "attribute `{}` conflicts with `{}`", name, other)
+            }
+            Error::NotConstant(_) => write!(f, "expression is not constant"),
+            Error::ConstOverflow(_) => write!(f, "constant expression overflows"),
+            Error::ConstDivByZero(_) => write!(f, "division by zero in constant expression"),
+            Error::AssignToConst { name, .. } => {
+                write!(f, "cannot assign to constant `{}`", name)
+            }
+        }
+    }
+}
+
+impl From<azuki_hir::err::Error> for Error {
+    fn from(e: azuki_hir::err::Error) -> Self {
+        match e {
+            azuki_hir::err::Error::BreakOutsideLoop(span) => Error::BreakOutsideLoop(span),
+            azuki_hir::err::Error::ContinueOutsideLoop(span) => Error::ContinueOutsideLoop(span),
+            azuki_hir::err::Error::UnknownLabel { name, span } => Error::UnknownLabel {
+                name: name.into(),
+                span,
+            },
+            azuki_hir::err::Error::ShadowedLabel { name, span } => Error::ShadowedLabel {
+                name: name.into(),
+                span,
+            },
+        }
+    }
+}
+
+impl From<Error> for Diagnostic {
+    fn from(e: Error) -> Self {
+        Diagnostic::error(e.to_string(), Some(e.span()))
+    }
 }
@@ -1,50 +1,179 @@
+pub mod const_eval;
 pub mod err;
+pub mod loc;
 pub mod symbol;
 mod test;
 
-use azuki_syntax::{ast::*, visitor::AstVisitor};
+use azuki_hir as hir;
+use azuki_syntax::{
+    ast::*,
+    span::Span,
+    visitor::{walk_expr, AstVisitor},
+};
 use azuki_tac as tac;
 use err::Error;
-
-use std::{cell::RefCell, collections::HashMap, rc::Rc, todo};
+use loc::LineIndex;
+
+use bit_set::BitSet;
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    convert::TryFrom,
+    rc::Rc,
+    todo,
+};
 use symbol::{NumberingCounter, ScopeBuilder, StringInterner};
 
 use tac::{
-    builder::FuncBuilder, BBId, BinaryInst, Branch, FunctionCall, Inst, InstId, InstKind, TacFunc,
-    Ty, Value,
+    builder::FuncBuilder, BBId, BinaryInst, Branch, FuncAttr, FunctionCall, IndirectCall, Inst,
+    InstId, InstKind, SourceLoc, TacFunc, Ty, Value,
 };
 
 pub fn compile(tac: &Program) -> Result<tac::Program, Error> {
+    compile_with_imports(tac, &[])
+}
+
+/// Compile `tac`, which may call functions defined inside `imports`. The
+/// signatures of those functions are recorded in
+/// [`imports`](tac::Program::imports) of the result, and should be resolved
+/// by linking with the compiled imported programs.
+pub fn compile_with_imports(tac: &Program, imports: &[&Program]) -> Result<tac::Program, Error> {
+    compile_impl(tac, imports, None)
+}
+
+/// Like [`compile_with_imports`], and records the location of the source code
+/// producing each instruction. `source` is the source code of `tac`.
+pub fn compile_with_source(
+    tac: &Program,
+    imports: &[&Program],
+    source: &str,
+) -> Result<tac::Program, Error> {
+    compile_impl(tac, imports, Some(&LineIndex::new(source)))
+}
+
+fn compile_impl(
+    tac: &Program,
+    imports: &[&Program],
+    lines: Option<&LineIndex>,
+) -> Result<tac::Program, Error> {
     let interner = Rc::new(RefCell::new(StringInterner::new()));
     let counter = Rc::new(NumberingCounter::new(0));
     let global_scope_builder = Rc::new(RefCell::new(ScopeBuilder::new(counter, interner.clone())));
 
-    let mut funcs = HashMap::new();
+    let mut imported_funcs = HashMap::new();
+    for func in imports.iter().flat_map(|x| x.funcs.iter()) {
+        let name = func.name.name.clone();
+        let ty = func_ty_of(func)?;
+        global_scope_builder
+            .borrow_mut()
+            .insert_func(&name, ty.clone())
+            .ok_or_else(|| Error::DuplicateVar {
+                name: name.clone(),
+                span: func.name.span,
+            })?;
+        imported_funcs.insert(name, ty);
+    }
+
+    // Global constants are folded into every use
+    for decl in tac.decls.iter().filter(|decl| decl.is_const) {
+        declare_const(&mut global_scope_builder.borrow_mut(), decl)?;
+    }
+
+    let mut result = tac::Program {
+        imports: imported_funcs,
+        ..Default::default()
+    };
     for func in &tac.funcs {
         let name = func.name.name.clone();
-        let mut result = TacFunc::new_untyped(name.clone());
-        let mut compiler =
-            FuncCompiler::new(&mut result, interner.clone(), global_scope_builder.clone());
-        compiler.visit_func(func)?;
-        funcs.insert(name, result);
+        let mut tac_func = TacFunc::new_untyped(name.clone());
+        let mut compiler = FuncCompiler::new(
+            &mut tac_func,
+            interner.clone(),
+            global_scope_builder.clone(),
+        );
+        compiler.lines = lines;
+        compiler.compile_func(&hir::desugar_func(func)?)?;
+        result.functions.insert(name, tac_func);
+    }
+    Ok(result)
+}
+
+fn resolve_ty(ty: &TyDef) -> Result<Ty, Error> {
+    match ty.name.as_str() {
+        "void" => Ok(Ty::Unit),
+        "int" => Ok(Ty::int()),
+        "fn" => {
+            let params = ty
+                .params
+                .iter()
+                .flatten()
+                .map(resolve_ty)
+                .collect::<Result<Vec<_>, _>>()?;
+            let ret = match &ty.ret {
+                Some(ret) => resolve_ty(ret)?,
+                None => Ty::Unit,
+            };
+            Ok(Ty::func_of(ret, params))
+        }
+        _ => Err(Error::UnknownType {
+            name: ty.name.clone(),
+            span: ty.span,
+        }),
     }
-    Ok(tac::Program { functions: funcs })
 }
 
-struct BreakTarget {
+/// Evaluate the initializer of constant `decl` and insert it into `scope`.
+fn declare_const(scope: &mut ScopeBuilder, decl: &DeclStmt) -> Result<(), Error> {
+    let ty = resolve_ty(&decl.ty)?;
+    let expr = decl.val.as_ref().ok_or(Error::NotConstant(decl.span))?;
+    let (val, val_ty) = const_eval::eval(expr, scope)?;
+    assert_type_eq(&ty, &val_ty, expr.span())?;
+    scope
+        .insert_const(&decl.name.name, ty, val)
+        .ok_or_else(|| Error::DuplicateVar {
+            name: decl.name.name.clone(),
+            span: decl.name.span,
+        })?;
+    Ok(())
+}
+
+/// Returns the type of `func` according to its signature.
+fn func_ty_of(func: &FuncStmt) -> Result<Ty, Error> {
+    let params = func
+        .params
+        .iter()
+        .map(|param| resolve_ty(&param.ty))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Ty::func_of(resolve_ty(&func.ret_ty)?, params))
+}
+
+/// Where `break` and `continue` of a loop jump to.
+struct LoopTarget {
+    pub id: hir::LoopId,
     pub break_out: BBId,
     pub continue_in: BBId,
 }
 
 pub struct FuncCompiler<'a> {
     builder: tac::builder::FuncBuilder<'a, u32>,
-    break_targets: Vec<BreakTarget>,
+    loop_targets: Vec<LoopTarget>,
+
+    /// Variables definitely assigned at the current position, or `None` if
+    /// the current basic block is unreachable.
+    assigned: Option<BitSet>,
+
+    /// The value of `assigned` at the end of each basic block we have left.
+    end_assigned: BTreeMap<BBId, Option<BitSet>>,
 
     return_ty: Ty,
 
     interner: Rc<RefCell<StringInterner>>,
 
     scope_builder: Rc<RefCell<ScopeBuilder>>,
+
+    /// Lines of the source code, for recording source locations of
+    /// instructions. No location is recorded if this is `None`.
+    pub lines: Option<&'a LineIndex<'a>>,
 }
 
 impl<'a> FuncCompiler<'a> {
@@ -55,11 +184,24 @@ impl<'a> FuncCompiler<'a> {
     ) -> FuncCompiler<'a> {
         FuncCompiler {
             builder: FuncBuilder::new_func(func),
-            break_targets: vec![],
+            loop_targets: vec![],
+            assigned: None,
+            end_assigned: BTreeMap::new(),
             return_ty: Ty::unit(),
             interner,
             scope_builder,
+            lines: None,
+        }
+    }
+
+    /// Record `span` as the source location of the following instructions.
+    /// Returns the location recorded before, to be restored afterwards.
+    fn locate(&mut self, span: Span) -> Option<SourceLoc> {
+        let outer_loc = self.builder.loc();
+        if let Some(lines) = self.lines {
+            self.builder.set_loc(Some(lines.loc(span.start())));
         }
+        outer_loc
     }
 
     fn visit_func_param_real(
@@ -68,54 +210,82 @@ impl<'a> FuncCompiler<'a> {
         idx: usize,
     ) -> Result<(InstId, Ty), Error> {
         let ty = self.visit_ty(&param.ty)?;
-        let mut scope = self.scope_builder.borrow_mut();
-        let var = scope
+        let var_id = self
+            .scope_builder
+            .borrow_mut()
             .insert(&param.name.name, ty.clone())
-            .ok_or_else(|| Error::DuplicateVar(param.name.name.clone()))?;
+            .ok_or_else(|| Error::DuplicateVar {
+                name: param.name.name.clone(),
+                span: param.name.span,
+            })?
+            .id;
 
+        let outer_loc = self.locate(param.name.span);
         let val = self.builder.insert_after_current_place(Inst {
             kind: InstKind::Param(idx),
             ty: ty.clone(),
         });
+        self.builder.set_loc(outer_loc);
 
-        self.builder.declare_var(var.id, ty.clone());
-        self.builder.write_variable_cur(var.id, val).unwrap();
+        self.builder.declare_var(var_id, ty.clone());
+        self.builder.write_variable_cur(var_id, val).unwrap();
+        if let Some(assigned) = &mut self.assigned {
+            assigned.insert(var_id as usize);
+        }
 
         Ok((val, ty))
     }
+
+    /// Move to basic block `bb_id`, which should have all its predecessors
+    /// (apart from loop back edges) determined.
+    ///
+    /// The variables definitely assigned at the start of `bb_id` are those
+    /// assigned at the end of every reachable predecessor. A basic block with
+    /// no reachable predecessors is unreachable.
+    fn set_current_bb(&mut self, bb_id: BBId) {
+        let last_bb = self.builder.current_bb_id();
+        self.end_assigned.insert(last_bb, self.assigned.take());
+
+        let mut assigned: Option<BitSet> = None;
+        for pred in self.builder.pred_of_bb(bb_id) {
+            if let Some(Some(pred_assigned)) = self.end_assigned.get(&pred) {
+                match &mut assigned {
+                    Some(x) => x.intersect_with(pred_assigned),
+                    None => assigned = Some(pred_assigned.clone()),
+                }
+            }
+        }
+        self.assigned = assigned;
+
+        self.builder.set_current_bb(bb_id);
+    }
+
+    fn is_reachable(&self) -> bool {
+        self.assigned.is_some()
+    }
 }
 
 // This implementation is the main tac-generation part.
 //
 // I try to use the method in https://pp.ipd.kit.edu/uploads/publikationen/braun13cc.pdf
-// to directly generate SSA code from AST.
+// to directly generate SSA code from HIR.
 //
 // Notes:
 //
-// - All basic blocks that are passed from one statement visitor method into another should already
-//   have all their predecessors determined. Any statement visitor method could mark the input basic
-//   block as filled and sealed.
-impl<'a> AstVisitor for FuncCompiler<'a> {
-    type LExprResult = Result<(u32, Ty), Error>;
-
-    type ExprResult = Result<(Value, Ty), Error>;
-
-    type TyResult = Result<Ty, Error>;
-
-    type StmtResult = Result<(), Error>;
-
-    type ProgramResult = ();
-
-    type FuncResult = Result<(), Error>;
-
-    fn visit_func(&mut self, func: &FuncStmt) -> Self::FuncResult {
+// - All basic blocks that are passed from one statement lowering method into another should
+//   already have all their predecessors determined. Any statement lowering method could mark the
+//   input basic block as filled and sealed.
+impl<'a> FuncCompiler<'a> {
+    pub fn compile_func(&mut self, func: &hir::Func) -> Result<(), Error> {
         self.scope_builder.borrow_mut().add_scope();
         let initial = self.builder.new_bb();
         self.builder.set_current_bb(initial);
+        self.assigned = Some(BitSet::new());
         self.builder.func.bb_set_first(initial);
         self.builder.mark_sealed(initial);
 
         let return_ty = self.visit_ty(&func.ret_ty)?;
+        self.return_ty = return_ty.clone();
         let mut params_ty = vec![];
         for (idx, param) in func.params.iter().enumerate() {
             let (_param_op, param_ty) = self.visit_func_param_real(param, idx)?;
@@ -123,248 +293,167 @@ impl<'a> AstVisitor for FuncCompiler<'a> {
         }
         let func_ty = Ty::func_of(return_ty, params_ty);
         self.builder.set_type(func_ty.clone());
+        for attr in &func.attrs {
+            let parsed =
+                FuncAttr::from_name(&attr.name).ok_or_else(|| Error::UnknownAttribute {
+                    name: attr.name.clone(),
+                    span: attr.span,
+                })?;
+            if let Some(other) = parsed
+                .conflicting()
+                .filter(|&other| self.builder.func.has_attr(other))
+            {
+                return Err(Error::ConflictingAttributes {
+                    name: attr.name.clone(),
+                    other: other.name().into(),
+                    span: attr.span,
+                });
+            }
+            self.builder.func.attrs.insert(parsed);
+        }
 
         let func_name = &func.name.name;
         self.scope_builder
             .borrow_mut()
-            .insert_global(func_name, func_ty);
+            .insert_func(func_name, func_ty);
 
-        self.visit_block_stmt(&func.body)?;
+        self.lower_block(&func.body)?;
+
+        if self.is_reachable() {
+            // Falling off the end of a function is only allowed when it returns
+            // nothing, in which case we insert the return for it.
+            if self.return_ty != Ty::Unit {
+                return Err(Error::MissingReturn {
+                    func: func.name.name.clone(),
+                    span: func.body.span,
+                });
+            }
+            self.builder.current_bb_mut().branch = Branch::Return(None);
+        }
 
         self.builder.mark_filled(self.builder.current_bb_id());
+        self.builder.move_phis_to_head();
 
         self.scope_builder.borrow_mut().pop_scope().unwrap();
         Ok(())
     }
 
-    fn visit_ty(&mut self, _ty: &TyDef) -> Self::TyResult {
-        match _ty.name.as_str() {
-            "void" => Ok(Ty::Unit),
-            "int" => Ok(Ty::int()),
-            _ => Err(Error::UnknownType(_ty.name.clone())),
+    fn lower_block(&mut self, block: &hir::Block) -> Result<(), Error> {
+        self.scope_builder.borrow_mut().add_scope();
+        for stmt in &block.stmts {
+            self.lower_stmt(stmt)?;
         }
+        self.scope_builder.borrow_mut().pop_scope().unwrap();
+        Ok(())
     }
 
-    fn visit_literal_expr(&mut self, _expr: &LiteralExpr) -> Self::ExprResult {
-        match _expr.kind {
-            LiteralKind::Integer(val) => Ok((Value::Imm(val as i64), Ty::int())),
-            LiteralKind::Float(_) => {
-                todo!("implement float (or not)")
+    fn lower_stmt(&mut self, stmt: &hir::Stmt) -> Result<(), Error> {
+        match stmt {
+            hir::Stmt::Block(b) => self.lower_block(b),
+            hir::Stmt::Loop(l) => self.lower_loop(l),
+            hir::Stmt::If(i) => self.lower_if(i),
+            hir::Stmt::Break(target, _) => {
+                let target = self.loop_target(*target).break_out;
+                self.lower_jump(target);
+                Ok(())
             }
-            LiteralKind::String(_) => {
-                todo!("Implement String")
+            hir::Stmt::Continue(target, _) => {
+                let target = self.loop_target(*target).continue_in;
+                self.lower_jump(target);
+                Ok(())
             }
-            LiteralKind::Char(ch) => Ok((Value::Imm(ch as i64), Ty::int())),
-        }
-    }
-
-    fn visit_ident_expr(&mut self, expr: &Ident) -> Self::ExprResult {
-        let scope = self.scope_builder.borrow();
-        let var = scope
-            .find(&expr.name)
-            .ok_or_else(|| Error::UnknownVar(expr.name.clone()))?;
-        let val = self.builder.read_variable_cur(var.id).unwrap();
-        Ok((val.into(), var.ty.clone()))
-    }
-
-    fn visit_assign_expr(&mut self, expr: &AssignExpr) -> Self::ExprResult {
-        let (var_id, var_ty) = self.visit_lexpr(&expr.lhs)?;
-        let (val, val_ty) = self.visit_expr(&expr.rhs)?;
-
-        assert_type_eq(&var_ty, &val_ty)?;
-
-        let result_idx = match val {
-            Value::Dest(i) => {
-                self.builder.write_variable_cur(var_id, i).unwrap();
-                i
-            }
-            Value::Imm(i) => {
-                let target = self.builder.insert_after_current_place(Inst {
-                    kind: InstKind::Assign(Value::Imm(i)),
-                    ty: val_ty,
-                });
-                self.builder.write_variable_cur(var_id, target).unwrap();
-                target
-            }
-        };
-        Ok((result_idx.into(), Ty::unit()))
-    }
-
-    fn visit_lexpr(&mut self, expr: &Expr) -> Self::LExprResult {
-        let expr = match expr {
-            Expr::Ident(i) => i,
-            _ => return Err(Error::InvalidLExpr(format!("{:?}", &expr))),
-        };
-        let scope = self.scope_builder.borrow();
-        let var = scope
-            .find(&expr.name)
-            .ok_or_else(|| Error::UnknownVar(expr.name.clone()))?;
-        Ok((var.id, var.ty.clone()))
-    }
-
-    fn visit_binary_expr(&mut self, expr: &BinaryExpr) -> Self::ExprResult {
-        let (lhsv, lhst) = self.visit_expr(&expr.lhs)?;
-        let (rhsv, rhst) = self.visit_expr(&expr.rhs)?;
-
-        assert_type_eq(&lhst, &rhst)?;
-
-        let (op, ty) = match expr.op {
-            BinaryOp::Add => (tac::BinaryOp::Add, lhst.clone()),
-            BinaryOp::Sub => (tac::BinaryOp::Sub, lhst.clone()),
-            BinaryOp::Mul => (tac::BinaryOp::Mul, lhst.clone()),
-            BinaryOp::Div => (tac::BinaryOp::Div, lhst.clone()),
-            BinaryOp::Gt => (tac::BinaryOp::Gt, Ty::bool()),
-            BinaryOp::Lt => (tac::BinaryOp::Lt, Ty::bool()),
-            BinaryOp::Ge => (tac::BinaryOp::Ge, Ty::bool()),
-            BinaryOp::Le => (tac::BinaryOp::Le, Ty::bool()),
-            BinaryOp::Eq => (tac::BinaryOp::Eq, Ty::bool()),
-            BinaryOp::Neq => (tac::BinaryOp::Ne, Ty::bool()),
-        };
-
-        let v = self.builder.insert_after_current_place(Inst {
-            kind: InstKind::Binary(BinaryInst {
-                op,
-                lhs: lhsv,
-                rhs: rhsv,
-            }),
-            ty,
-        });
-
-        Ok((v.into(), lhst))
-    }
-
-    fn visit_unary_expr(&mut self, expr: &UnaryExpr) -> Self::ExprResult {
-        let (v, t) = self.visit_expr(&expr.expr)?;
-
-        match expr.op {
-            UnaryOp::Neg => {
-                let v = self.builder.insert_after_current_place(Inst {
-                    kind: InstKind::Binary(BinaryInst {
-                        op: tac::BinaryOp::Sub,
-                        lhs: Value::Imm(0),
-                        rhs: v,
-                    }),
-                    ty: t.clone(),
-                });
-                Ok((v.into(), t))
+            hir::Stmt::Decl(d) => self.lower_decl(d),
+            hir::Stmt::Expr(e) => {
+                self.visit_expr(e)?;
+                Ok(())
             }
-            UnaryOp::Pos => Ok((v, t)),
-        }
-    }
-
-    fn visit_call_expr(&mut self, expr: &CallExpr) -> Self::ExprResult {
-        let func_ty = self
-            .scope_builder
-            .borrow()
-            .find(&expr.func.name)
-            .ok_or_else(|| Error::UnknownVar(expr.func.name.clone()))?
-            .ty
-            .clone();
-
-        let func_ty = func_ty.as_func().unwrap();
-
-        let mut params = vec![];
-        let mut types = vec![];
-        for subexpr in &expr.params {
-            let (val, ty) = self.visit_expr(&subexpr)?;
-            params.push(val);
-            types.push(ty);
-        }
-
-        if types.len() != func_ty.params.len() {
-            return Err(Error::WrongParamLength {
-                expected: func_ty.params.len(),
-                found: types.len(),
-            });
-        }
-        for (ty, expected) in types.iter().zip(func_ty.params.iter()) {
-            assert_type_eq(ty, expected)?;
+            hir::Stmt::Return(r) => self.lower_return(r),
         }
-
-        let val = self.builder.insert_after_current_place(Inst {
-            kind: InstKind::FunctionCall(FunctionCall {
-                name: self.interner.borrow_mut().intern(&expr.func.name),
-                params,
-            }),
-            ty: func_ty.return_type.clone(),
-        });
-
-        Ok((val.into(), func_ty.return_type.clone()))
     }
 
-    fn visit_as_expr(&mut self, expr: &AsExpr) -> Self::ExprResult {
-        self.visit_expr(&expr.val)
+    fn loop_target(&self, id: hir::LoopId) -> &LoopTarget {
+        self.loop_targets
+            .iter()
+            .rev()
+            .find(|target| target.id == id)
+            .expect("Jump targets should be inside the loops we are in")
     }
 
-    fn visit_block_stmt(&mut self, stmt: &BlockStmt) -> Self::StmtResult {
-        self.scope_builder.borrow_mut().add_scope();
-        for substmt in &stmt.stmts {
-            self.visit_stmt(substmt)?;
-        }
-        self.scope_builder.borrow_mut().pop_scope().unwrap();
-        Ok(())
-    }
-
-    fn visit_while_stmt(&mut self, stmt: &WhileStmt) -> Self::StmtResult {
+    fn lower_loop(&mut self, stmt: &hir::LoopStmt) -> Result<(), Error> {
         let cur_bb = self.builder.current_bb_id();
-        let cond_bb = self.builder.new_bb();
-        self.builder.add_branch(cur_bb, cond_bb);
-        self.builder.func.bb_get_mut(cur_bb).branch = Branch::Jump(cond_bb);
+        let header_bb = self.builder.new_bb();
+        self.builder.add_branch(cur_bb, header_bb);
+        self.builder.func.bb_get_mut(cur_bb).branch = Branch::Jump(header_bb);
 
         self.builder.mark_filled(cur_bb);
 
-        self.builder.set_current_bb(cond_bb);
-        self.builder.func.bb_set_after(cur_bb, cond_bb);
+        self.set_current_bb(header_bb);
+        self.builder.func.bb_set_after(cur_bb, header_bb);
 
-        let (cond, _cond_ty) = self.visit_expr(&stmt.cond)?;
-
-        let loop_bb = self.builder.new_bb();
-        self.builder.func.bb_set_after(loop_bb, cond_bb);
         let next_bb = self.builder.new_bb();
-
-        self.break_targets.push(BreakTarget {
+        self.loop_targets.push(LoopTarget {
+            id: stmt.id,
             break_out: next_bb,
-            continue_in: cond_bb,
+            continue_in: header_bb,
         });
 
-        self.builder.mark_filled(cond_bb);
-
-        // cond_bb --> loop_bb
-        //   \---> next_bb
-        self.builder.func.bb_get_mut(cond_bb).branch = Branch::CondJump {
-            cond,
-            if_true: loop_bb,
-            if_false: next_bb,
-        };
-        self.builder.add_branch(cond_bb, loop_bb);
-        self.builder.add_branch(cond_bb, next_bb);
-        self.builder.mark_sealed(loop_bb);
-
-        self.builder.set_current_bb(loop_bb);
-        self.visit_block_stmt(&stmt.body)?;
+        self.lower_block(&stmt.body)?;
         let loop_end_bb = self.builder.current_bb_id();
 
-        self.builder.func.bb_get_mut(loop_end_bb).branch = Branch::Jump(cond_bb);
-        self.builder.add_branch(loop_end_bb, cond_bb);
+        // loop_end_bb --> header_bb
+        self.builder.func.bb_get_mut(loop_end_bb).branch = Branch::Jump(header_bb);
+        self.builder.add_branch(loop_end_bb, header_bb);
 
         self.builder.mark_filled(loop_end_bb);
-        self.builder.mark_sealed(cond_bb);
+        self.builder.mark_sealed(header_bb);
 
-        self.break_targets.pop();
+        self.loop_targets.pop();
 
+        // next_bb is only reachable by breaking out of the loop
         self.builder.func.bb_set_after(loop_end_bb, next_bb);
-        self.builder.set_current_bb(next_bb);
+        self.set_current_bb(next_bb);
         self.builder.mark_sealed(next_bb);
 
         Ok(())
     }
 
-    fn visit_if_stmt(&mut self, stmt: &IfStmt) -> Self::StmtResult {
+    /// Returns the target of `block` if it only consists of a `break` or
+    /// `continue`.
+    fn jump_target_of(&self, block: &hir::Block) -> Option<BBId> {
+        match block.stmts[..] {
+            [hir::Stmt::Break(target, _)] => Some(self.loop_target(target).break_out),
+            [hir::Stmt::Continue(target, _)] => Some(self.loop_target(target).continue_in),
+            _ => None,
+        }
+    }
+
+    fn lower_if(&mut self, stmt: &hir::IfStmt) -> Result<(), Error> {
         let expr_val = self.visit_expr(&stmt.cond)?;
         let last_bb = self.builder.current_bb_id();
 
         self.builder.mark_filled(last_bb);
 
+        // Conditional jumps, like the exit condition of `while` loops, branch
+        // to their target directly
+        let else_target = stmt
+            .else_block
+            .as_ref()
+            .and_then(|b| self.jump_target_of(b));
+        if let (true, Some(target)) = (stmt.then_block.stmts.is_empty(), else_target) {
+            let next_bb = self.builder.new_bb();
+            self.builder.func.bb_set_after(last_bb, next_bb);
+            self.builder.func.bb_get_mut(last_bb).branch = Branch::CondJump {
+                cond: expr_val.0,
+                if_true: next_bb,
+                if_false: target,
+            };
+            self.builder.add_branch(last_bb, next_bb);
+            self.builder.add_branch(last_bb, target);
+            self.builder.mark_sealed(next_bb);
+            self.set_current_bb(next_bb);
+            return Ok(());
+        }
+
         // Create if block
         let if_bb = self.builder.new_bb();
         self.builder.func.bb_set_after(last_bb, if_bb);
@@ -373,15 +462,15 @@ impl<'a> AstVisitor for FuncCompiler<'a> {
         self.builder.add_branch(last_bb, if_bb);
         self.builder.mark_sealed(if_bb);
 
-        self.builder.set_current_bb(if_bb);
-        self.visit_block_stmt(&stmt.if_block)?;
+        self.set_current_bb(if_bb);
+        self.lower_block(&stmt.then_block)?;
 
         let if_end_bb = self.builder.current_bb_id();
 
         // next_bb: The basic block after the if statement
         // Deal with else block
         let next_bb = match &stmt.else_block {
-            other @ IfElseBlock::Block(..) | other @ IfElseBlock::If(..) => {
+            Some(else_block) => {
                 let else_bb = self.builder.new_bb();
                 self.builder.func.bb_set_after(if_end_bb, else_bb);
 
@@ -395,13 +484,8 @@ impl<'a> AstVisitor for FuncCompiler<'a> {
                     if_false: else_bb,
                 };
 
-                self.builder.set_current_bb(else_bb);
-
-                match other {
-                    IfElseBlock::None => unreachable!(),
-                    IfElseBlock::If(i) => self.visit_if_stmt(&i)?,
-                    IfElseBlock::Block(b) => self.visit_block_stmt(&b)?,
-                }
+                self.set_current_bb(else_bb);
+                self.lower_block(else_block)?;
                 let else_end_bb = self.builder.current_bb_id();
 
                 let next_bb = self.builder.new_bb();
@@ -413,7 +497,7 @@ impl<'a> AstVisitor for FuncCompiler<'a> {
                 self.builder.func.bb_set_after(else_end_bb, next_bb);
                 next_bb
             }
-            azuki_syntax::ast::IfElseBlock::None => {
+            None => {
                 let next_bb = self.builder.new_bb();
                 self.builder.func.bb_set_after(if_end_bb, next_bb);
 
@@ -435,35 +519,53 @@ impl<'a> AstVisitor for FuncCompiler<'a> {
         self.builder.mark_filled(if_end_bb);
 
         self.builder.mark_sealed(next_bb);
-        self.builder.set_current_bb(next_bb);
+        self.set_current_bb(next_bb);
         Ok(())
     }
 
-    fn visit_expr_stmt(&mut self, stmt: &Expr) -> Self::StmtResult {
-        self.visit_expr(stmt)?;
-        Ok(())
+    /// End the current basic block with a jump to `target`. Code after the
+    /// jump goes into a new, unreachable basic block.
+    fn lower_jump(&mut self, target: BBId) {
+        let cur_bb = self.builder.current_bb_id();
+        self.builder.func.bb_get_mut(cur_bb).branch = Branch::Jump(target);
+        self.builder.add_branch(cur_bb, target);
+
+        self.builder.mark_filled(cur_bb);
+
+        let next_bb = self.builder.new_bb();
+        self.set_current_bb(next_bb);
+        self.builder.mark_sealed(next_bb);
+        self.builder.func.bb_set_after(cur_bb, next_bb);
     }
 
-    fn visit_decl_stmt(&mut self, stmt: &DeclStmt) -> Self::StmtResult {
+    fn lower_decl(&mut self, stmt: &DeclStmt) -> Result<(), Error> {
+        if stmt.is_const {
+            return declare_const(&mut self.scope_builder.borrow_mut(), stmt);
+        }
         let ty = self.visit_ty(&stmt.ty)?;
         let var_id = self
             .scope_builder
             .borrow_mut()
             .insert(&stmt.name.name, ty.clone())
-            .ok_or_else(|| Error::DuplicateVar(stmt.name.name.clone()))?
+            .ok_or_else(|| Error::DuplicateVar {
+                name: stmt.name.name.clone(),
+                span: stmt.name.span,
+            })?
             .id;
         self.builder.declare_var(var_id, ty);
 
         if let Some(expr) = &stmt.val {
+            let outer_loc = self.locate(stmt.span);
             let (inst, _) = self.visit_assign_expr(&AssignExpr {
                 span: stmt.span,
-                allow_assign_const: stmt.is_const,
+                allow_assign_const: false,
                 lhs: Rc::new(Expr::Ident(Ident {
                     span: stmt.span,
                     name: stmt.name.name.clone(),
                 })),
                 rhs: expr.clone(),
             })?;
+            self.builder.set_loc(outer_loc);
             self.builder
                 .write_variable_cur(var_id, inst.get_inst().unwrap())
                 .unwrap();
@@ -472,9 +574,9 @@ impl<'a> AstVisitor for FuncCompiler<'a> {
         Ok(())
     }
 
-    fn visit_return_stmt(&mut self, stmt: &ReturnStmt) -> Self::StmtResult {
+    fn lower_return(&mut self, stmt: &ReturnStmt) -> Result<(), Error> {
         let val = if let Some(val) = &stmt.val {
-            Some(self.visit_expr(&val)?)
+            Some(self.visit_expr(val)?)
         } else {
             None
         };
@@ -485,57 +587,254 @@ impl<'a> AstVisitor for FuncCompiler<'a> {
         self.builder.mark_filled(self.builder.current_bb_id());
 
         let next_bb = self.builder.new_bb();
-        self.builder.set_current_bb(next_bb);
+        self.set_current_bb(next_bb);
         self.builder.mark_sealed(next_bb);
         self.builder.func.bb_set_after(curr_bb, next_bb);
 
         Ok(())
     }
+}
 
-    fn visit_break_stmt(&mut self, _span: azuki_syntax::span::Span) -> Self::StmtResult {
-        let break_target = self.break_targets.last().unwrap().break_out;
+// Expressions are shared with the syntax tree, and are lowered by visiting it.
+impl<'a> AstVisitor for FuncCompiler<'a> {
+    type LExprResult = Result<(u32, Ty), Error>;
 
-        let cur_bb = self.builder.current_bb_id();
-        self.builder.func.bb_get_mut(cur_bb).branch = Branch::Jump(break_target);
-        self.builder.add_branch(cur_bb, break_target);
+    type ExprResult = Result<(Value, Ty), Error>;
 
-        self.builder.mark_filled(cur_bb);
+    type TyResult = Result<Ty, Error>;
 
-        let next_bb = self.builder.new_bb();
-        self.builder.set_current_bb(next_bb);
-        self.builder.mark_sealed(next_bb);
-        self.builder.func.bb_set_after(cur_bb, next_bb);
+    type StmtResult = Result<(), Error>;
 
-        Ok(())
+    type ProgramResult = ();
+
+    type FuncResult = Result<(), Error>;
+
+    fn visit_ty(&mut self, _ty: &TyDef) -> Self::TyResult {
+        resolve_ty(_ty)
     }
 
-    fn visit_continue_stmt(&mut self, _span: azuki_syntax::span::Span) -> Self::StmtResult {
-        let continue_target = self.break_targets.last().unwrap().continue_in;
+    fn visit_expr(&mut self, expr: &Expr) -> Self::ExprResult {
+        // Instructions are located at the innermost expression producing them
+        let outer_loc = self.locate(expr.span());
+        let res = walk_expr(self, expr);
+        self.builder.set_loc(outer_loc);
+        res
+    }
 
-        let cur_bb = self.builder.current_bb_id();
-        self.builder.func.bb_get_mut(cur_bb).branch = Branch::Jump(continue_target);
-        self.builder.add_branch(cur_bb, continue_target);
+    fn visit_literal_expr(&mut self, _expr: &LiteralExpr) -> Self::ExprResult {
+        match _expr.kind {
+            LiteralKind::Integer(val) => {
+                let val = i64::try_from(val).map_err(|_| Error::ConstOverflow(_expr.span))?;
+                Ok((Value::Imm(val), Ty::int()))
+            }
+            LiteralKind::Float(_) => {
+                todo!("implement float (or not)")
+            }
+            LiteralKind::String(_) => {
+                todo!("Implement String")
+            }
+            LiteralKind::Char(ch) => Ok((Value::Imm(ch as i64), Ty::int())),
+        }
+    }
 
-        self.builder.mark_filled(cur_bb);
+    fn visit_ident_expr(&mut self, expr: &Ident) -> Self::ExprResult {
+        let scope = self.scope_builder.borrow();
+        let var = scope.find(&expr.name).ok_or_else(|| Error::UnknownVar {
+            name: expr.name.clone(),
+            span: expr.span,
+        })?;
+        if let Some(val) = var.const_val {
+            return Ok((Value::Imm(val), var.ty.clone()));
+        }
+        // Every variable counts as assigned in unreachable code
+        let is_assigned = match &self.assigned {
+            Some(assigned) => assigned.contains(var.id as usize),
+            None => true,
+        };
+        if !var.is_func && !is_assigned {
+            return Err(Error::UseBeforeInit {
+                name: expr.name.clone(),
+                span: expr.span,
+            });
+        }
+        if var.is_func {
+            // Functions used as values are referenced by name
+            let val = self.builder.insert_after_current_place(Inst {
+                kind: InstKind::FuncRef(self.interner.borrow_mut().intern(&expr.name)),
+                ty: var.ty.clone(),
+            });
+            return Ok((val.into(), var.ty.clone()));
+        }
+        let val = self.builder.read_variable_cur(var.id).unwrap();
+        Ok((val.into(), var.ty.clone()))
+    }
 
-        let next_bb = self.builder.new_bb();
-        self.builder.set_current_bb(next_bb);
-        self.builder.mark_sealed(next_bb);
-        self.builder.func.bb_set_after(cur_bb, next_bb);
+    fn visit_assign_expr(&mut self, expr: &AssignExpr) -> Self::ExprResult {
+        let (var_id, var_ty) = self.visit_lexpr(&expr.lhs)?;
+        let (val, val_ty) = self.visit_expr(&expr.rhs)?;
 
-        Ok(())
+        assert_type_eq(&var_ty, &val_ty, expr.rhs.span())?;
+        if let Some(assigned) = &mut self.assigned {
+            assigned.insert(var_id as usize);
+        }
+
+        let result_idx = match val {
+            Value::Dest(i) => {
+                self.builder.write_variable_cur(var_id, i).unwrap();
+                i
+            }
+            Value::Imm(i) => {
+                let target = self.builder.insert_after_current_place(Inst {
+                    kind: InstKind::Assign(Value::Imm(i)),
+                    ty: val_ty,
+                });
+                self.builder.write_variable_cur(var_id, target).unwrap();
+                target
+            }
+        };
+        Ok((result_idx.into(), Ty::unit()))
     }
 
-    fn visit_empty_stmt(&mut self, _span: azuki_syntax::span::Span) -> Self::StmtResult {
-        Ok(())
+    fn visit_lexpr(&mut self, expr: &Expr) -> Self::LExprResult {
+        let expr = match expr {
+            Expr::Ident(i) => i,
+            _ => return Err(Error::InvalidLExpr(expr.span())),
+        };
+        let scope = self.scope_builder.borrow();
+        let var = scope.find(&expr.name).ok_or_else(|| Error::UnknownVar {
+            name: expr.name.clone(),
+            span: expr.span,
+        })?;
+        if var.const_val.is_some() {
+            return Err(Error::AssignToConst {
+                name: expr.name.clone(),
+                span: expr.span,
+            });
+        }
+        Ok((var.id, var.ty.clone()))
+    }
+
+    fn visit_binary_expr(&mut self, expr: &BinaryExpr) -> Self::ExprResult {
+        let (lhsv, lhst) = self.visit_expr(&expr.lhs)?;
+        let (rhsv, rhst) = self.visit_expr(&expr.rhs)?;
+
+        assert_type_eq(&lhst, &rhst, expr.span)?;
+
+        // Comparisons result in integers in C0
+        let (op, ty) = match expr.op {
+            BinaryOp::Add => (tac::BinaryOp::Add, lhst.clone()),
+            BinaryOp::Sub => (tac::BinaryOp::Sub, lhst.clone()),
+            BinaryOp::Mul => (tac::BinaryOp::Mul, lhst.clone()),
+            BinaryOp::Div => (tac::BinaryOp::Div, lhst.clone()),
+            BinaryOp::Gt => (tac::BinaryOp::Gt, Ty::int()),
+            BinaryOp::Lt => (tac::BinaryOp::Lt, Ty::int()),
+            BinaryOp::Ge => (tac::BinaryOp::Ge, Ty::int()),
+            BinaryOp::Le => (tac::BinaryOp::Le, Ty::int()),
+            BinaryOp::Eq => (tac::BinaryOp::Eq, Ty::int()),
+            BinaryOp::Neq => (tac::BinaryOp::Ne, Ty::int()),
+        };
+
+        let v = self.builder.insert_after_current_place(Inst {
+            kind: InstKind::Binary(BinaryInst {
+                op,
+                lhs: lhsv,
+                rhs: rhsv,
+            }),
+            ty: ty.clone(),
+        });
+
+        Ok((v.into(), ty))
+    }
+
+    fn visit_unary_expr(&mut self, expr: &UnaryExpr) -> Self::ExprResult {
+        let (v, t) = self.visit_expr(&expr.expr)?;
+
+        match expr.op {
+            UnaryOp::Neg => {
+                let v = self.builder.insert_after_current_place(Inst {
+                    kind: InstKind::Binary(BinaryInst {
+                        op: tac::BinaryOp::Sub,
+                        lhs: Value::Imm(0),
+                        rhs: v,
+                    }),
+                    ty: t.clone(),
+                });
+                Ok((v.into(), t))
+            }
+            UnaryOp::Pos => Ok((v, t)),
+        }
+    }
+
+    fn visit_call_expr(&mut self, expr: &CallExpr) -> Self::ExprResult {
+        let (is_func, func_ty) = {
+            let scope = self.scope_builder.borrow();
+            let var = scope
+                .find(&expr.func.name)
+                .ok_or_else(|| Error::UnknownVar {
+                    name: expr.func.name.clone(),
+                    span: expr.func.span,
+                })?;
+            (var.is_func, var.ty.clone())
+        };
+
+        let func_ty = func_ty.as_func().ok_or_else(|| Error::NotCallable {
+            name: expr.func.name.clone(),
+            ty: func_ty.clone(),
+            span: expr.func.span,
+        })?;
+
+        // Calling a variable of function type results in an indirect call
+        let callee = if is_func {
+            None
+        } else {
+            Some(self.visit_ident_expr(&expr.func)?.0)
+        };
+
+        let mut params = vec![];
+        let mut types = vec![];
+        for subexpr in &expr.params {
+            let (val, ty) = self.visit_expr(&subexpr)?;
+            params.push(val);
+            types.push(ty);
+        }
+
+        if types.len() != func_ty.params.len() {
+            return Err(Error::WrongParamLength {
+                expected: func_ty.params.len(),
+                found: types.len(),
+                span: expr.span,
+            });
+        }
+        for ((ty, expected), subexpr) in types.iter().zip(func_ty.params.iter()).zip(&expr.params) {
+            assert_type_eq(expected, ty, subexpr.span())?;
+        }
+
+        let kind = match callee {
+            Some(callee) => InstKind::IndirectCall(IndirectCall { callee, params }),
+            None => InstKind::FunctionCall(FunctionCall {
+                name: self.interner.borrow_mut().intern(&expr.func.name),
+                params,
+            }),
+        };
+        let val = self.builder.insert_after_current_place(Inst {
+            kind,
+            ty: func_ty.return_type.clone(),
+        });
+
+        Ok((val.into(), func_ty.return_type.clone()))
+    }
+
+    fn visit_as_expr(&mut self, expr: &AsExpr) -> Self::ExprResult {
+        self.visit_expr(&expr.val)
     }
 }
 
-fn assert_type_eq(lhs: &Ty, rhs: &Ty) -> Result<(), err::Error> {
+fn assert_type_eq(lhs: &Ty, rhs: &Ty, span: Span) -> Result<(), err::Error> {
     if lhs != rhs {
         return Err(Error::TypeMismatch {
             expected: lhs.clone(),
             found: rhs.clone(),
+            span,
         });
     }
     Ok(())
@@ -0,0 +1,37 @@
+//! Mapping positions in source code into line and column numbers.
+
+use azuki_tac::SourceLoc;
+
+/// Finds the line and column of byte indices inside a source file.
+pub struct LineIndex<'src> {
+    source: &'src str,
+    /// The byte index of the start of every line.
+    line_starts: Vec<usize>,
+}
+
+impl<'src> LineIndex<'src> {
+    pub fn new(source: &'src str) -> LineIndex<'src> {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+        LineIndex {
+            source,
+            line_starts,
+        }
+    }
+
+    /// Returns the location of byte index `idx`. Columns are counted in
+    /// characters, like in diagnostics.
+    pub fn loc(&self, idx: usize) -> SourceLoc {
+        let idx = idx.min(self.source.len());
+        let line = match self.line_starts.binary_search(&idx) {
+            Ok(line) => line,
+            Err(next_line) => next_line - 1,
+        };
+        let col = self.source[self.line_starts[line]..idx].chars().count();
+        SourceLoc {
+            line: line as u32 + 1,
+            col: col as u32 + 1,
+        }
+    }
+}
@@ -93,10 +93,15 @@ impl NumberingCounter {
 pub struct Variable {
     /// Whether if this variable is a global variable
     pub is_global: bool,
+    /// Whether if this variable names a function declaration instead of a
+    /// variable holding a value
+    pub is_func: bool,
     /// The unique global ID of this variable
     pub id: u32,
     /// The type of this variable
     pub ty: Ty,
+    /// The value of this variable if it's a constant
+    pub const_val: Option<i64>,
 }
 
 pub struct ScopeBuilder {
@@ -136,8 +141,26 @@ impl ScopeBuilder {
         let var_id = self.counter.next();
         let variable = Variable {
             is_global: self.is_top_scope_global(),
+            is_func: false,
             id: var_id,
             ty,
+            const_val: None,
+        };
+
+        let scope = self.top_scope_mut();
+        scope.insert(interned_name, variable)
+    }
+
+    /// Insert a constant with given name, type and value into this scope.
+    pub fn insert_const(&mut self, name: &SmolStr, ty: Ty, val: i64) -> Option<&Variable> {
+        let interned_name = self.interner.borrow_mut().intern(name);
+        let var_id = self.counter.next();
+        let variable = Variable {
+            is_global: self.is_top_scope_global(),
+            is_func: false,
+            id: var_id,
+            ty,
+            const_val: Some(val),
         };
 
         let scope = self.top_scope_mut();
@@ -149,8 +172,26 @@ impl ScopeBuilder {
         let var_id = self.counter.next();
         let variable = Variable {
             is_global: true,
+            is_func: false,
+            id: var_id,
+            ty,
+            const_val: None,
+        };
+
+        let scope = self.global_scope_mut();
+        scope.insert(interned_name, variable)
+    }
+
+    /// Insert a function with given name and type into the global scope.
+    pub fn insert_func(&mut self, name: &SmolStr, ty: Ty) -> Option<&Variable> {
+        let interned_name = self.interner.borrow_mut().intern(name);
+        let var_id = self.counter.next();
+        let variable = Variable {
+            is_global: true,
+            is_func: true,
             id: var_id,
             ty,
+            const_val: None,
         };
 
         let scope = self.global_scope_mut();
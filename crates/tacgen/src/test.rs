@@ -1,8 +1,27 @@
 #![cfg(test)]
 
+use azuki_interp::Interpreter;
 use azuki_syntax::parse;
+use azuki_tac::{parser::parse_program_from_string, FuncAttr};
 use azuki_tacvm::Vm;
 
+use crate::err::Error;
+
+/// Run `func` inside `input` with both the AST interpreter and the VM, and
+/// assert they give the same result.
+fn assert_same_result(input: &str, func: &str, params: Vec<i64>) -> Option<i64> {
+    let program = parse(input).unwrap();
+    let expected = Interpreter::new(&program)
+        .run_func(func, params.clone())
+        .unwrap();
+
+    let result = crate::compile(&program).unwrap();
+    let mut vm = Vm::new(&result);
+    let found = vm.run_func(func, params);
+    assert_eq!(expected, found, "AST interpreter and VM disagree");
+    found
+}
+
 #[test]
 fn test_basic_func_generation() {
     let input = r"
@@ -23,3 +42,294 @@ fn test_basic_func_generation() {
     let mut vm = Vm::new(&result);
     assert_eq!(vm.run_func("fib", vec![5]), Some(8));
 }
+
+#[test]
+fn test_loop_generation() {
+    let input = r"
+    fn main() -> int {
+        let i: int = 0;
+        let s: int = 0;
+        while i < 10 {
+            i = i + 1;
+            if i == 3 {
+                continue;
+            }
+            if i == 8 {
+                break;
+            }
+            s = s + i;
+        }
+        return s;
+    }
+    ";
+    let program = parse(input).unwrap();
+    let result = crate::compile(&program).unwrap();
+    eprintln!("{}", result.functions["main"]);
+
+    let mut vm = Vm::new(&result);
+    assert_eq!(vm.run_func("main", vec![]), Some(25));
+}
+
+#[test]
+fn test_indirect_call() {
+    let input = r"
+    fn square(x: int) -> int {
+        return x * x;
+    }
+    fn twice(f: fn(int) -> int, x: int) -> int {
+        return f(f(x));
+    }
+    fn main() -> int {
+        let f: fn(int) -> int = square;
+        return twice(f, 3);
+    }
+    ";
+    let program = parse(input).unwrap();
+    let result = crate::compile(&program).unwrap();
+    eprintln!("{}", result.functions["twice"]);
+
+    let mut vm = Vm::new(&result);
+    assert_eq!(vm.run_func("main", vec![]), Some(81));
+}
+
+#[test]
+fn test_void_func_implicit_return() {
+    let input = r"
+    fn nothing(n: int) -> void {
+        if n > 0 {
+            return;
+        }
+    }
+    fn main() -> int {
+        nothing(1);
+        nothing(0);
+        return 1;
+    }
+    ";
+    let program = parse(input).unwrap();
+    let result = crate::compile(&program).unwrap();
+
+    let mut vm = Vm::new(&result);
+    assert_eq!(vm.run_func("main", vec![]), Some(1));
+}
+
+#[test]
+fn test_flow_sensitive_errors() {
+    let missing_return = r"
+    fn f(n: int) -> int {
+        if n > 0 {
+            return 1;
+        }
+    }
+    ";
+    let program = parse(missing_return).unwrap();
+    assert!(matches!(
+        crate::compile(&program),
+        Err(Error::MissingReturn { .. })
+    ));
+
+    let unreachable = r"
+    fn f(n: int) -> int {
+        return n;
+        n = 1;
+    }
+    ";
+    // Unreachable code is only linted against
+    let program = parse(unreachable).unwrap();
+    crate::compile(&program).unwrap();
+
+    // ... but is still checked
+    let unknown_var_after_return = r"
+    fn main() -> int {
+        return 1;
+        let y: int = z;
+    }
+    ";
+    let program = parse(unknown_var_after_return).unwrap();
+    assert!(matches!(
+        crate::compile(&program),
+        Err(Error::UnknownVar { .. })
+    ));
+
+    let wrong_call_after_return = r"
+    fn f(n: int) -> int {
+        return n;
+        f(n, n);
+    }
+    ";
+    let program = parse(wrong_call_after_return).unwrap();
+    assert!(matches!(
+        crate::compile(&program),
+        Err(Error::WrongParamLength { .. })
+    ));
+
+    let use_before_init = r"
+    fn f(n: int) -> int {
+        let r: int;
+        if n > 0 {
+            r = 1;
+        }
+        return r;
+    }
+    ";
+    let program = parse(use_before_init).unwrap();
+    assert!(matches!(
+        crate::compile(&program),
+        Err(Error::UseBeforeInit { .. })
+    ));
+
+    let break_outside_loop = r"
+    fn f() -> void {
+        break;
+    }
+    ";
+    let program = parse(break_outside_loop).unwrap();
+    assert!(matches!(
+        crate::compile(&program),
+        Err(Error::BreakOutsideLoop(_))
+    ));
+}
+
+#[test]
+fn test_compile_and_link_imports() {
+    let lib = parse(
+        r"
+    fn double(x: int) -> int {
+        return x + x;
+    }
+    ",
+    )
+    .unwrap();
+    let main = parse(
+        r#"
+    import "lib.c0";
+    fn main() -> int {
+        return double(21);
+    }
+    "#,
+    )
+    .unwrap();
+    assert_eq!(main.imports[0].path, "lib.c0");
+
+    let lib_tac = crate::compile(&lib).unwrap();
+    let main_tac = crate::compile_with_imports(&main, &[&lib]).unwrap();
+    assert!(main_tac.imports.contains_key("double"));
+
+    let missing = azuki_tac::linker::link(vec![main_tac.clone()]);
+    assert!(matches!(
+        missing,
+        Err(azuki_tac::err::LinkError::MissingSymbol(name)) if name == "double"
+    ));
+
+    let program = azuki_tac::linker::link(vec![main_tac, lib_tac]).unwrap();
+    let mut vm = Vm::new(&program);
+    assert_eq!(vm.run_func("main", vec![]), Some(42));
+}
+
+#[test]
+fn test_differential() {
+    let input = r"
+    fn gcd(a: int, b: int) -> int {
+        while b != 0 {
+            let t: int = b;
+            b = a - a / b * b;
+            a = t;
+        }
+        return a;
+    }
+    fn apply(f: fn(int, int) -> int, a: int, b: int) -> int {
+        return f(a, b);
+    }
+    fn apply_gcd() -> int {
+        return apply(gcd, 12, 18);
+    }
+    fn sum_odd(n: int) -> int {
+        let i: int = 0;
+        let s: int = 0;
+        while 1 {
+            i = i + 1;
+            if i > n {
+                break;
+            }
+            if i / 2 * 2 == i {
+                continue;
+            } else {
+                s = s + i;
+            }
+        }
+        return s;
+    }
+    ";
+    assert_eq!(assert_same_result(input, "gcd", vec![84, 36]), Some(12));
+    assert_eq!(assert_same_result(input, "sum_odd", vec![10]), Some(25));
+    assert_eq!(assert_same_result(input, "sum_odd", vec![-1]), Some(0));
+    assert_eq!(assert_same_result(input, "apply_gcd", vec![]), Some(6));
+}
+
+#[test]
+fn test_const_eval() {
+    let input = r"
+    const SIZE: int = 4 * 8;
+    const HALF: int = SIZE / 2 - -1 as int;
+    fn main() -> int {
+        const LOCAL: int = HALF + 'a';
+        return LOCAL;
+    }
+    ";
+    let program = parse(input).unwrap();
+    let result = crate::compile(&program).unwrap();
+    // Constants are folded into an immediate
+    let main = result.functions["main"].to_string();
+    assert!(main.contains("(return 114)"), "{}", main);
+    assert_eq!(assert_same_result(input, "main", vec![]), Some(114));
+
+    let errors = [
+        "fn f(n: int) -> int { const C: int = n + 1; return C; }",
+        "const C: int = 9223372036854775807 + 1;",
+        "const C: int = 1 / (2 - 2);",
+        "fn f() -> int { const C: int = 1; C = 2; return C; }",
+        "const C: int = 9223372036854775808;",
+        "fn f() -> int { return 0xffffffffffffffff; }",
+    ];
+    let results = errors
+        .iter()
+        .map(|input| crate::compile(&parse(input).unwrap()).unwrap_err())
+        .collect::<Vec<_>>();
+    assert!(matches!(results[0], Error::NotConstant(_)));
+    assert!(matches!(results[1], Error::ConstOverflow(_)));
+    assert!(matches!(results[2], Error::ConstDivByZero(_)));
+    assert!(matches!(results[3], Error::AssignToConst { .. }));
+    assert!(matches!(results[4], Error::ConstOverflow(_)));
+    assert!(matches!(results[5], Error::ConstOverflow(_)));
+}
+
+#[test]
+fn test_func_attrs() {
+    let input = r"
+    #[inline]
+    fn sq(x: int) -> int { return x * x; }
+    #[cold, noinline] #[pure]
+    fn main() -> int { return sq(3); }
+    ";
+    let program = parse(input).unwrap();
+    let result = crate::compile(&program).unwrap();
+    let main = &result.functions["main"];
+    assert_eq!(
+        main.attrs.iter().copied().collect::<Vec<_>>(),
+        [FuncAttr::NoInline, FuncAttr::Pure, FuncAttr::Cold]
+    );
+    assert!(result.functions["sq"].has_attr(FuncAttr::Inline));
+
+    // Attributes survive printing and parsing TAC
+    let parsed = parse_program_from_string(&result.to_string()).unwrap();
+    assert_eq!(parsed.functions["main"].attrs, main.attrs);
+
+    let res = crate::compile(&parse("#[fast] fn f() -> void {}").unwrap());
+    assert!(matches!(res, Err(Error::UnknownAttribute { name, .. }) if name == "fast"));
+
+    let res = crate::compile(&parse("#[inline] #[noinline] fn f() -> void {}").unwrap());
+    assert!(matches!(
+        res,
+        Err(Error::ConflictingAttributes { name, other, .. }) if name == "noinline" && other == "inline"
+    ));
+}
@@ -0,0 +1,68 @@
+use std::fmt::Display;
+
+use azuki_tac::SourceLoc;
+use smol_str::SmolStr;
+
+/// A fault happening when running a program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fault {
+    DivByZero,
+    /// A value is used before being computed, e.g. a phi from a basic block
+    /// that is not its source.
+    UndefinedValue,
+    /// An `unreachable` branch is reached.
+    Unreachable,
+    UnknownFunction(SmolStr),
+    /// An indirect call to a value that is not a function.
+    NotAFunction(i64),
+    /// A load or store at an address outside allocated memory.
+    InvalidAddress(i64),
+    /// Stack slots take more memory than the stack has.
+    StackOverflow,
+    /// More instructions and branches are run than the step limit allows.
+    StepLimitExceeded,
+}
+
+impl Display for Fault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Fault::DivByZero => write!(f, "division by zero"),
+            Fault::UndefinedValue => write!(f, "use of an undefined value"),
+            Fault::Unreachable => write!(f, "reached unreachable code"),
+            Fault::UnknownFunction(name) => write!(f, "function `{}` does not exist", name),
+            Fault::NotAFunction(val) => write!(f, "called value {} is not a function", val),
+            Fault::InvalidAddress(addr) => write!(f, "invalid memory access at {:#x}", addr),
+            Fault::StackOverflow => write!(f, "stack overflow"),
+            Fault::StepLimitExceeded => write!(f, "step limit exceeded"),
+        }
+    }
+}
+
+/// A function being run when a fault happens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackFrame {
+    pub func: SmolStr,
+    /// The source location of the instruction being run, if known.
+    pub loc: Option<SourceLoc>,
+}
+
+/// A fault with the call stack where it happens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeError {
+    pub fault: Fault,
+    /// The call stack, innermost frame first.
+    pub backtrace: Vec<StackFrame>,
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "runtime error: {}", self.fault)?;
+        for frame in &self.backtrace {
+            write!(f, "\n    at {}", frame.func)?;
+            if let Some(loc) = frame.loc {
+                write!(f, " ({})", loc)?;
+            }
+        }
+        Ok(())
+    }
+}
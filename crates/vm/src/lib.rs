@@ -1,16 +1,27 @@
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
-use azuki_tac::{BBId, BinaryInst, Inst, InstId, Program, TacFunc, Value};
+use azuki_tac::{BBId, BinaryInst, Inst, InstId, Program, SourceLoc, TacFunc, Ty, Value};
+use err::{Fault, RuntimeError, StackFrame};
 use inspector::Inspector;
+use memory::Memory;
 
+pub mod err;
 pub mod inspector;
+pub mod memory;
 mod test;
-pub mod value;
 
 pub struct Vm<'src> {
     program: &'src Program,
+    /// Functions of this program, indexed by their function value.
+    func_table: Vec<&'src TacFunc>,
+    /// Maps function names to their function value.
+    func_ids: HashMap<&'src str, i64>,
     stack: Vec<Frame<'src>>,
+    memory: Memory,
     inspectors: Vec<Rc<RefCell<dyn Inspector>>>,
+    /// The maximum number of instructions and branches to execute, if any.
+    step_limit: Option<u64>,
+    steps: u64,
 }
 
 pub struct Frame<'f> {
@@ -58,6 +69,14 @@ impl<'f> Frame<'f> {
         }
     }
 
+    /// The source location of the instruction being run, if known.
+    pub fn loc(&self) -> Option<SourceLoc> {
+        match self.instruction {
+            CurrInst::Instruction(i) => self.func.inst_loc(i),
+            CurrInst::Jump => None,
+        }
+    }
+
     /// Get a reference to the frame's vars.
     pub fn vars(&self) -> &HashMap<InstId, i64> {
         &self.vars
@@ -81,33 +100,105 @@ impl From<Option<InstId>> for CurrInst {
 enum JumpAction {
     Goto(BBId),
     Return(Option<Value>),
-    Error,
 }
 
 impl<'src> Vm<'src> {
     pub fn new(program: &'src Program) -> Vm<'src> {
+        let mut func_table = program.functions.values().collect::<Vec<_>>();
+        func_table.sort_by(|a, b| a.name.cmp(&b.name));
+        let func_ids = func_table
+            .iter()
+            .enumerate()
+            .map(|(idx, f)| (f.name.as_str(), idx as i64))
+            .collect();
+
         Vm {
             program,
+            func_table,
+            func_ids,
             stack: Vec::new(),
+            memory: Memory::default(),
             inspectors: Vec::new(),
+            step_limit: None,
+            steps: 0,
         }
     }
 
+    /// Limit the number of instructions and branches executed by this VM.
+    /// Exceeding the limit faults with [`Fault::StepLimitExceeded`]. This is
+    /// useful for running programs that may not terminate.
+    pub fn set_step_limit(&mut self, limit: Option<u64>) {
+        self.step_limit = limit;
+    }
+
+    /// Get a reference to the memory of this VM.
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
     pub fn add_inspector_boxed(&mut self, inspector: Rc<RefCell<dyn Inspector>>) {
         self.inspectors.push(inspector);
     }
 
+    /// Run the function `name`.
+    ///
+    /// # Panics
+    ///
+    /// Panics with the error message if the program faults. See
+    /// [`try_run_func`](Self::try_run_func) for handling faults.
     pub fn run_func(&mut self, name: &str, params: Vec<i64>) -> Option<i64> {
+        match self.try_run_func(name, params) {
+            Ok(res) => res,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Run the function `name`. Returns the fault and the call stack at that
+    /// time if the program faults.
+    pub fn try_run_func(
+        &mut self,
+        name: &str,
+        params: Vec<i64>,
+    ) -> Result<Option<i64>, RuntimeError> {
         let func = self
             .program
             .functions
             .get(name)
-            .expect("Function does not exist");
+            .ok_or_else(|| self.fault(Fault::UnknownFunction(name.into())))?;
+
+        self.run_tac_func(func, params)
+    }
 
+    /// Returns the function value of the function with the given name.
+    pub fn func_value(&self, name: &str) -> Option<i64> {
+        self.func_ids.get(name).cloned()
+    }
+
+    /// Create an error of `fault` happening at the current position.
+    fn fault(&self, fault: Fault) -> RuntimeError {
+        let backtrace = self
+            .stack
+            .iter()
+            .rev()
+            .map(|frame| StackFrame {
+                func: frame.func.name.clone(),
+                loc: frame.loc(),
+            })
+            .collect();
+        RuntimeError { fault, backtrace }
+    }
+
+    fn run_tac_func(
+        &mut self,
+        func: &'src TacFunc,
+        params: Vec<i64>,
+    ) -> Result<Option<i64>, RuntimeError> {
         self.inspectors
             .iter_mut()
             .for_each(|i| i.borrow_mut().before_call(&params, func));
 
+        // Stack slots of the function are freed when it returns
+        let top = self.memory.top();
         self.stack.push(Frame {
             func,
             instruction: func
@@ -120,33 +211,38 @@ impl<'src> Vm<'src> {
             bb: func.starting_block().unwrap(),
         });
 
-        let ret = self.run_till_return()?;
+        let ret = self.run_till_return();
 
         self.stack.pop();
-        Some(ret)
+        self.memory.free(top);
+        ret
     }
 
-    fn run_till_return(&mut self) -> Option<i64> {
+    fn run_till_return(&mut self) -> Result<Option<i64>, RuntimeError> {
         assert!(!self.stack.is_empty());
         loop {
+            self.steps += 1;
+            if matches!(self.step_limit, Some(limit) if self.steps > limit) {
+                return Err(self.fault(Fault::StepLimitExceeded));
+            }
             let last = self.stack.last_mut().unwrap();
             match last.instruction {
                 CurrInst::Instruction(i) => {
                     let next = last.func.tac_get(i).next;
-                    self.run_inst_in_curr_func(i);
+                    self.run_inst_in_curr_func(i)?;
                     let last = self.stack.last_mut().unwrap();
                     last.instruction = next.into();
                 }
                 CurrInst::Jump => {
-                    if let Some(value) = self.run_jump_inst() {
-                        return value;
+                    if let Some(value) = self.run_jump_inst()? {
+                        return Ok(value);
                     }
                 }
             }
         }
     }
 
-    fn run_inst_in_curr_func(&mut self, idx: InstId) {
+    fn run_inst_in_curr_func(&mut self, idx: InstId) -> Result<(), RuntimeError> {
         assert!(!self.stack.is_empty());
 
         let last = self.stack.last().unwrap();
@@ -157,38 +253,119 @@ impl<'src> Vm<'src> {
             .for_each(|i| i.borrow_mut().before_inst(&inst.inst, last));
 
         let res = match &inst.inst.kind {
-            azuki_tac::InstKind::Binary(bin) => self.run_binary_inst(last, bin),
+            azuki_tac::InstKind::Binary(bin) => {
+                Some(self.run_binary_inst(last, bin).map_err(|f| self.fault(f))?)
+            }
             azuki_tac::InstKind::FunctionCall(func) => {
-                let params = func
-                    .params
-                    .iter()
-                    .map(|x| last.eval(*x))
-                    .collect::<Option<Vec<_>>>()
-                    .unwrap();
-
-                self.run_func(&func.name, params)
+                let params = self.eval_params(last, &func.params)?;
+                self.try_run_func(&func.name, params)?
+            }
+            azuki_tac::InstKind::IndirectCall(call) => {
+                let callee = last
+                    .eval(call.callee)
+                    .ok_or_else(|| self.fault(Fault::UndefinedValue))?;
+                let params = self.eval_params(last, &call.params)?;
+
+                let func = *self
+                    .func_table
+                    .get(callee as usize)
+                    .ok_or_else(|| self.fault(Fault::NotAFunction(callee)))?;
+                self.run_tac_func(func, params)?
             }
+            azuki_tac::InstKind::FuncRef(name) => Some(
+                self.func_value(name)
+                    .ok_or_else(|| self.fault(Fault::UnknownFunction(name.clone())))?,
+            ),
             azuki_tac::InstKind::Assign(v) => last.eval(*v),
             azuki_tac::InstKind::Phi(sources) => {
                 let last_bb = last.last_bb;
                 sources.get(&last_bb).and_then(|&val| last.eval(val.into()))
             }
             azuki_tac::InstKind::Param(i) => last.params.get(*i).cloned(),
-            // azuki_tac::InstKind::Dead => None,
+            azuki_tac::InstKind::Alloca(alloca) => {
+                let size = memory::size_of(&alloca.ty)
+                    .checked_mul(alloca.count)
+                    .ok_or_else(|| self.fault(Fault::StackOverflow))?;
+                let align = memory::align_of(&alloca.ty);
+                Some(self.memory.alloc(size, align).map_err(|f| self.fault(f))?)
+            }
+            azuki_tac::InstKind::Load(ptr) => {
+                let addr = last
+                    .eval(*ptr)
+                    .ok_or_else(|| self.fault(Fault::UndefinedValue))?;
+                let size = memory::size_of(&inst.inst.ty);
+                Some(self.memory.load(addr, size).map_err(|f| self.fault(f))?)
+            }
+            azuki_tac::InstKind::Store(store) => {
+                let addr = last
+                    .eval(store.ptr)
+                    .ok_or_else(|| self.fault(Fault::UndefinedValue))?;
+                let value = last
+                    .eval(store.value)
+                    .ok_or_else(|| self.fault(Fault::UndefinedValue))?;
+                let size = match Self::value_ty(last.func, store.ptr) {
+                    Some(Ty::Ptr(ty)) => memory::size_of(ty),
+                    _ => Self::value_ty(last.func, store.value)
+                        .map_or(azuki_tac::ty::PTR_SIZE, memory::size_of),
+                };
+                self.memory
+                    .store(addr, size, value)
+                    .map_err(|f| self.fault(f))?;
+                None
+            }
+            azuki_tac::InstKind::Offset(offset) => {
+                let addr = last
+                    .eval(offset.ptr)
+                    .ok_or_else(|| self.fault(Fault::UndefinedValue))?;
+                let offset = last
+                    .eval(offset.offset)
+                    .ok_or_else(|| self.fault(Fault::UndefinedValue))?;
+                let size = match &inst.inst.ty {
+                    Ty::Ptr(ty) => memory::size_of(ty),
+                    _ => 1,
+                };
+                Some(addr.wrapping_add(offset.wrapping_mul(size as i64)))
+            }
+        };
+
+        // Calls to functions returning nothing still produce a (unit) value
+        let res = if inst.inst.ty == Ty::Unit {
+            res.unwrap_or(0)
+        } else {
+            res.ok_or_else(|| self.fault(Fault::UndefinedValue))?
         };
 
         let last = self.stack.last_mut().unwrap();
-        last.vars.insert(idx, res.unwrap());
+        last.vars.insert(idx, res);
+        Ok(())
+    }
+
+    /// The type of `value`, or `None` if it is an immediate.
+    fn value_ty(func: &TacFunc, value: Value) -> Option<&Ty> {
+        value.get_inst().map(|inst| &func.inst_get(inst).ty)
     }
 
-    fn run_binary_inst(&self, frame: &Frame, inst: &BinaryInst) -> Option<i64> {
-        let lhs = frame.eval(inst.lhs)?;
-        let rhs = frame.eval(inst.rhs)?;
+    fn eval_params(&self, frame: &Frame, params: &[Value]) -> Result<Vec<i64>, RuntimeError> {
+        params
+            .iter()
+            .map(|x| frame.eval(*x))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| self.fault(Fault::UndefinedValue))
+    }
+
+    fn run_binary_inst(&self, frame: &Frame, inst: &BinaryInst) -> Result<i64, Fault> {
+        let lhs = frame.eval(inst.lhs).ok_or(Fault::UndefinedValue)?;
+        let rhs = frame.eval(inst.rhs).ok_or(Fault::UndefinedValue)?;
         let res = match inst.op {
-            azuki_tac::BinaryOp::Add => lhs + rhs,
-            azuki_tac::BinaryOp::Sub => lhs - rhs,
-            azuki_tac::BinaryOp::Mul => lhs * rhs,
-            azuki_tac::BinaryOp::Div => lhs.checked_div(rhs)?,
+            azuki_tac::BinaryOp::Add => lhs.wrapping_add(rhs),
+            azuki_tac::BinaryOp::Sub => lhs.wrapping_sub(rhs),
+            azuki_tac::BinaryOp::Mul => lhs.wrapping_mul(rhs),
+            azuki_tac::BinaryOp::Div => {
+                if rhs == 0 {
+                    return Err(Fault::DivByZero);
+                }
+                lhs.wrapping_div(rhs)
+            }
             azuki_tac::BinaryOp::Lt => (lhs < rhs) as i64,
             azuki_tac::BinaryOp::Gt => (lhs > rhs) as i64,
             azuki_tac::BinaryOp::Le => (lhs <= rhs) as i64,
@@ -196,19 +373,20 @@ impl<'src> Vm<'src> {
             azuki_tac::BinaryOp::Eq => (lhs == rhs) as i64,
             azuki_tac::BinaryOp::Ne => (lhs != rhs) as i64,
         };
-        Some(res)
+        Ok(res)
     }
 
-    fn run_jump_inst(&mut self) -> Option<Option<i64>> {
+    fn run_jump_inst(&mut self) -> Result<Option<Option<i64>>, RuntimeError> {
         let last = self.stack.last_mut().unwrap();
-
         last.last_bb = last.bb;
+
+        let last = self.stack.last().unwrap();
         let inst = &last.func.bb_get(last.bb).branch;
         self.inspectors
             .iter_mut()
             .for_each(|i| i.borrow_mut().before_branch(inst, last));
 
-        let mut action = match inst {
+        let action = match inst {
             azuki_tac::Branch::Return(v) => JumpAction::Return(*v),
             azuki_tac::Branch::Jump(target) => JumpAction::Goto(*target),
             azuki_tac::Branch::CondJump {
@@ -216,28 +394,33 @@ impl<'src> Vm<'src> {
                 if_true,
                 if_false,
             } => {
-                if last.eval(*cond).map_or(false, |x| x != 0) {
+                let cond = last
+                    .eval(*cond)
+                    .ok_or_else(|| self.fault(Fault::UndefinedValue))?;
+                if cond != 0 {
                     JumpAction::Goto(*if_true)
                 } else {
                     JumpAction::Goto(*if_false)
                 }
             }
-            azuki_tac::Branch::Unreachable => {
-                panic!("Met unreachable branch")
-            }
+            azuki_tac::Branch::Unreachable => return Err(self.fault(Fault::Unreachable)),
         };
         match action {
-            JumpAction::Goto(bb) => last.move_to(bb),
+            JumpAction::Goto(bb) => self.stack.last_mut().unwrap().move_to(bb),
             JumpAction::Return(v) => {
                 self.inspectors
                     .iter_mut()
-                    .for_each(|i| i.borrow_mut().before_ret(&last));
-                return Some(v.and_then(|v| last.eval(v)));
-            }
-            JumpAction::Error => {
-                panic!("Error")
+                    .for_each(|i| i.borrow_mut().before_ret(last));
+                let val = match v {
+                    Some(v) => Some(
+                        last.eval(v)
+                            .ok_or_else(|| self.fault(Fault::UndefinedValue))?,
+                    ),
+                    None => None,
+                };
+                return Ok(Some(val));
             }
         }
-        None
+        Ok(None)
     }
 }
@@ -0,0 +1,111 @@
+//! Byte-addressed memory of the VM.
+//!
+//! Stack slots of all frames live in a single stack growing upwards, and every
+//! frame frees its slots when it returns. Pointers are plain addresses into
+//! this stack, so they can be stored and compared like any other value. The
+//! first few bytes are never allocated, making 0 an invalid address usable as
+//! null.
+
+use std::{convert::TryFrom, ops::Range};
+
+use azuki_tac::{ty::PTR_SIZE, Ty};
+
+use crate::err::Fault;
+
+/// The number of bytes at the start of memory that are never allocated.
+const RESERVED: usize = 16;
+
+/// The default maximum size of the stack in bytes.
+pub const DEFAULT_STACK_SIZE: usize = 8 << 20;
+
+#[derive(Debug, Clone)]
+pub struct Memory {
+    bytes: Vec<u8>,
+    limit: usize,
+}
+
+impl Memory {
+    /// Create a memory whose stack holds at most `limit` bytes.
+    pub fn new(limit: usize) -> Memory {
+        Memory {
+            bytes: vec![0; RESERVED],
+            limit,
+        }
+    }
+
+    /// The address where the next allocation starts. Passing it to
+    /// [`free`](Self::free) later frees everything allocated since.
+    pub fn top(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Allocate `size` zeroed bytes aligned to `align`, and return their
+    /// address.
+    pub fn alloc(&mut self, size: usize, align: usize) -> Result<i64, Fault> {
+        let align = align.max(1);
+        let start = self.bytes.len().div_ceil(align) * align;
+        let end = start
+            .checked_add(size)
+            .filter(|&end| end <= self.limit)
+            .ok_or(Fault::StackOverflow)?;
+        self.bytes.resize(end, 0);
+        Ok(start as i64)
+    }
+
+    /// Free everything allocated after `top`.
+    pub fn free(&mut self, top: usize) {
+        self.bytes.truncate(top.max(RESERVED));
+    }
+
+    fn range(&self, addr: i64, size: usize) -> Result<Range<usize>, Fault> {
+        usize::try_from(addr)
+            .ok()
+            .filter(|&start| start >= RESERVED)
+            .and_then(|start| Some(start..start.checked_add(size)?))
+            .filter(|range| range.end <= self.bytes.len())
+            .ok_or(Fault::InvalidAddress(addr))
+    }
+
+    /// Read the `size`-byte little-endian integer at `addr`, sign-extended.
+    /// Only the lowest 8 bytes are read if `size` is larger.
+    pub fn load(&self, addr: i64, size: usize) -> Result<i64, Fault> {
+        let range = self.range(addr, size)?;
+        let size = size.min(8);
+        if size == 0 {
+            return Ok(0);
+        }
+        let mut buf = [0u8; 8];
+        buf[..size].copy_from_slice(&self.bytes[range.start..range.start + size]);
+        let shift = 64 - 8 * size as u32;
+        Ok(i64::from_le_bytes(buf) << shift >> shift)
+    }
+
+    /// Write the lowest `size` bytes of `value` to `addr`, in little endian.
+    /// Bytes beyond the lowest 8 are filled with the sign of `value`.
+    pub fn store(&mut self, addr: i64, size: usize, value: i64) -> Result<(), Fault> {
+        let range = self.range(addr, size)?;
+        let fill = if value < 0 { 0xff } else { 0 };
+        let bytes = value.to_le_bytes();
+        for (idx, byte) in self.bytes[range].iter_mut().enumerate() {
+            *byte = bytes.get(idx).cloned().unwrap_or(fill);
+        }
+        Ok(())
+    }
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Memory::new(DEFAULT_STACK_SIZE)
+    }
+}
+
+/// The number of bytes a value of `ty` takes in memory. Function values are
+/// indices into the function table, and are stored like pointers.
+pub fn size_of(ty: &Ty) -> usize {
+    ty.size().unwrap_or(PTR_SIZE)
+}
+
+/// The alignment of values of `ty` in memory.
+pub fn align_of(ty: &Ty) -> usize {
+    size_of(ty).next_power_of_two().min(PTR_SIZE)
+}
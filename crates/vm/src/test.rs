@@ -1,5 +1,5 @@
 #![cfg(test)]
-use crate::Vm;
+use crate::{err::Fault, Vm};
 
 use azuki_tac::parser::parse_program_from_string;
 
@@ -52,3 +52,106 @@ fn run_a_plus_b() {
     let run_fib = vm.run_func("add", vec![1, 2]);
     assert_eq!(run_fib, Some(3));
 }
+
+#[test]
+fn run_indirect_call() {
+    let input = r"
+    (fn inc (i32) i32
+        (bb0 (
+            (%0 i32 param 0)
+            (%1 i32 add %0 1))
+            (return %1)))
+
+    (fn main () i32
+        (bb0 (
+            (%0 (fn (i32) i32) funcref inc)
+            (%1 i32 call_indirect %0 (41)))
+            (return %1)))
+    ";
+    let result = parse_program_from_string(input).unwrap();
+    let mut vm = Vm::new(&result);
+    assert_eq!(vm.run_func("main", vec![]), Some(42));
+}
+
+#[test]
+fn run_fault_backtrace() {
+    let input = r"fn div(a: int, b: int) -> int {
+    return a / b;
+}
+
+fn main(x: int) -> int {
+    return div(x, x - 3);
+}
+";
+    let program = azuki_syntax::parse(input).unwrap();
+    let result = azuki_tacgen::compile_with_source(&program, &[], input).unwrap();
+
+    // Locations are printed as comments, which are skipped when parsing
+    let printed = result.functions["div"].to_string();
+    assert!(printed.contains(" ; 2:12"), "{}", printed);
+    parse_program_from_string(&printed).unwrap();
+
+    let mut vm = Vm::new(&result);
+    assert_eq!(vm.try_run_func("main", vec![4]), Ok(Some(4)));
+
+    let err = vm.try_run_func("main", vec![3]).unwrap_err();
+    assert_eq!(err.fault, Fault::DivByZero);
+    let frames = err
+        .backtrace
+        .iter()
+        .map(|frame| (frame.func.as_str(), frame.loc.map(|loc| loc.line)))
+        .collect::<Vec<_>>();
+    assert_eq!(frames, vec![("div", Some(2)), ("main", Some(6))]);
+}
+
+#[test]
+fn run_memory() {
+    let input = r"
+    (fn set (i32* i32) ()
+        (bb0 (
+            (%0 i32* param 0)
+            (%1 i32 param 1)
+            (%2 () store %0 %1))
+            (return)))
+
+    (fn main () i32
+        (bb0 (
+            (%0 i32* alloca i32 4)
+            (%1 i32* offset %0 3)
+            (%2 () call set (%1 -7))
+            (%3 i32* offset %1 -2)
+            (%4 () store %3 40)
+            (%5 i32 load %1)
+            (%6 i32 load %3)
+            (%7 i32 add %5 %6)
+            (%8 i32 load %0))
+            (brif %8 bb1 bb2))
+        (bb1 ()
+            (unreachable))
+        (bb2 ()
+            (return %7)))
+
+    (fn null () i32
+        (bb0 (
+            (%0 i32 load 0))
+            (return %0)))
+
+    (fn overflow () ()
+        (bb0 (
+            (%0 i64* alloca i64 100000000))
+            (return)))
+    ";
+    let result = parse_program_from_string(input).unwrap();
+    azuki_tac::verifier::verify_program(&result).unwrap();
+
+    let mut vm = Vm::new(&result);
+    let top = vm.memory().top();
+    assert_eq!(vm.run_func("main", vec![]), Some(33));
+    // Stack slots are freed on return
+    assert_eq!(vm.memory().top(), top);
+
+    let err = vm.try_run_func("null", vec![]).unwrap_err();
+    assert_eq!(err.fault, Fault::InvalidAddress(0));
+    let err = vm.try_run_func("overflow", vec![]).unwrap_err();
+    assert_eq!(err.fault, Fault::StackOverflow);
+}
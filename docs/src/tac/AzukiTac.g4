@@ -47,6 +47,12 @@ IF: 'if';
 UNREACHABLE: 'unreachable';
 RETURN: 'return';
 CALL: 'call';
+CALL_INDIRECT: 'call_indirect';
+FUNCREF: 'funcref';
+ALLOCA: 'alloca';
+LOAD: 'load';
+STORE: 'store';
+OFFSET: 'offset';
 
 // misc
 LINEFEED: '\n';
@@ -68,15 +74,32 @@ binary_inst: value binary_op value;
 
 fn_param_list: (value (',' value)*)?;
 fn_call_inst: CALL GlobalVariable '(' fn_param_list ')';
+fn_call_indirect_inst: CALL_INDIRECT value '(' fn_param_list ')';
+fn_ref_inst: FUNCREF GlobalVariable;
 
 phi_source: '(' Variable ',' BasicBlock ')';
 phi_inst: PHI '[' (phi_source (',' phi_source)*)? ']';
 
 val_inst: value;
 
+alloca_inst: ALLOCA ty Number?;
+load_inst: LOAD value;
+store_inst: STORE value ',' value;
+offset_inst: OFFSET value ',' value;
+
 variable: Variable;
 inst_lhs: ty variable | DiscardVariable;
-inst_rhs: binary_inst | phi_inst | val_inst | fn_call_inst;
+inst_rhs:
+	binary_inst
+	| phi_inst
+	| val_inst
+	| fn_call_inst
+	| fn_call_indirect_inst
+	| fn_ref_inst
+	| alloca_inst
+	| load_inst
+	| store_inst
+	| offset_inst;
 inst: inst_lhs '=' ty inst_rhs LINEFEED;
 
 unreachable_inst: UNREACHABLE;
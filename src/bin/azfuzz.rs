@@ -0,0 +1,57 @@
+use std::process::exit;
+
+use azuki_gen::{generate, harness::check_program, GenConfig};
+use clap::Clap;
+
+/// Generate random programs, and check that the AST interpreter, the VM and
+/// the optimized VM agree on their results.
+#[derive(Clap, Debug)]
+struct Opt {
+    /// The seed of the first program.
+    #[clap(long, default_value = "0")]
+    seed: u64,
+
+    /// The number of programs to check.
+    #[clap(long, default_value = "100")]
+    count: u64,
+
+    /// Print every generated program, not only failing ones.
+    #[clap(long)]
+    print: bool,
+
+    /// Keep going after finding a failing program.
+    #[clap(long = "keep-going")]
+    keep_going: bool,
+}
+
+fn main() {
+    let opt = Opt::parse();
+    let config = GenConfig::default();
+
+    // Panics are reported as failures by the harness
+    std::panic::set_hook(Box::new(|_| {}));
+
+    let mut failures = 0;
+    for seed in opt.seed..opt.seed + opt.count {
+        let program = generate(seed, &config);
+        if opt.print {
+            println!("// seed {}\n{}", seed, program);
+        }
+        if let Err(e) = check_program(&program) {
+            failures += 1;
+            println!("// seed {} failed: {:?}", seed, e);
+            if !opt.print {
+                println!("{}", program);
+            }
+            if !opt.keep_going {
+                break;
+            }
+        }
+    }
+
+    if failures > 0 {
+        eprintln!("{} program(s) failed", failures);
+        exit(1);
+    }
+    eprintln!("All {} programs passed", opt.count);
+}
@@ -0,0 +1,15 @@
+//! The C0 language server, speaking LSP over stdin and stdout.
+
+use std::process::exit;
+
+use lsp_server::Connection;
+
+fn main() {
+    let (connection, io_threads) = Connection::stdio();
+    if let Err(e) = azuki_lsp::run(&connection) {
+        eprintln!("azls: {}", e);
+        exit(1);
+    }
+    drop(connection);
+    io_threads.join().expect("Failed to join IO threads");
+}
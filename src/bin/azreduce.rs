@@ -0,0 +1,110 @@
+use std::{path::PathBuf, process::exit};
+
+use azuki_gen::harness::{check_program, check_tac, Failure};
+use azuki_reduce::{reduce, Reduce, Stats};
+use clap::Clap;
+
+/// Reduce a program that crashes the compiler or is miscompiled into a
+/// minimal reproducer.
+///
+/// A program is interesting if checking it with the fuzzing harness finds a
+/// bug of the kind specified in the options, or any bug if none is specified.
+#[derive(Clap, Debug)]
+struct Opt {
+    /// The program to reduce.
+    file: PathBuf,
+
+    /// Treat the input as a TAC program instead of C0 source code.
+    #[clap(long)]
+    tac: bool,
+
+    /// Only keep programs panicking or faulting in the VM with a message
+    /// containing this string.
+    #[clap(long)]
+    panic: Option<String>,
+
+    /// Only keep programs whose results differ between execution paths.
+    #[clap(long)]
+    mismatch: bool,
+
+    /// The file to write the reduced program into. Defaults to stdout.
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+}
+
+impl Opt {
+    fn is_interesting(&self, res: Result<Option<i64>, Failure>) -> bool {
+        match res {
+            Err(Failure::Panic { message, .. }) if self.panic.is_some() => {
+                message.contains(self.panic.as_deref().unwrap())
+            }
+            Err(Failure::Fault { error, .. }) if self.panic.is_some() => error
+                .fault
+                .to_string()
+                .contains(self.panic.as_deref().unwrap()),
+            Err(Failure::Mismatch { .. }) if self.mismatch => true,
+            Err(e) if self.panic.is_none() && !self.mismatch => e.is_bug(),
+            _ => false,
+        }
+    }
+}
+
+fn run<T: Reduce + ToString>(
+    opt: &Opt,
+    program: T,
+    check: impl Fn(&T) -> Result<Option<i64>, Failure>,
+) -> (String, Stats) {
+    if !opt.is_interesting(check(&program)) {
+        eprintln!("The input program is not interesting, nothing to reduce.");
+        exit(1);
+    }
+    let (program, stats) = reduce(program, |p| opt.is_interesting(check(p)));
+    (program.to_string(), stats)
+}
+
+fn main() {
+    let opt = Opt::parse();
+    let input = match std::fs::read_to_string(&opt.file) {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Failed to open file {}: {}", &opt.file.to_string_lossy(), e);
+            exit(1);
+        }
+    };
+
+    // Panics are reported as failures by the harness
+    std::panic::set_hook(Box::new(|_| {}));
+
+    let (output, stats) = if opt.tac {
+        let program = match azuki_tac::parser::parse_program_from_string(&input) {
+            Ok(p) => p,
+            Err(e) => {
+                eprintln!("Input is not a valid Azuki TAC file.");
+                eprintln!();
+                eprintln!("{:?}", e);
+                exit(2);
+            }
+        };
+        run(&opt, program, check_tac)
+    } else {
+        let program = match azuki_syntax::parse(&input) {
+            Ok(p) => p,
+            Err(e) => {
+                let file_name = opt.file.to_string_lossy();
+                let diag = azuki_syntax::diag::Diagnostic::from(e);
+                eprintln!("{}", diag.display(&file_name, &input));
+                exit(2);
+            }
+        };
+        run(&opt, program, check_program)
+    };
+
+    eprintln!(
+        "Reduced with {} of {} tested variants",
+        stats.reductions, stats.tests
+    );
+    match &opt.output {
+        Some(path) => std::fs::write(path, output).expect("Failed to write to output file"),
+        None => print!("{}", output),
+    }
+}
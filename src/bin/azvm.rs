@@ -1,6 +1,6 @@
 use std::{cell::RefCell, path::PathBuf, process::exit, rc::Rc};
 
-use azuki_tac::parser::parse_program_from_string;
+use azuki_tac::{binary, parser::parse_program_from_string};
 use azuki_tacvm::{inspector::Inspector, Vm};
 use clap::Clap;
 
@@ -25,20 +25,37 @@ struct Opt {
 
 fn main() {
     let opt = Opt::parse();
-    let program = match std::fs::read_to_string(&opt.file) {
+    let program = match std::fs::read(&opt.file) {
         Ok(p) => p,
         Err(e) => {
             eprintln!("Failed to open file {}: {}", &opt.file.to_string_lossy(), e);
             exit(1);
         }
     };
-    let program = match parse_program_from_string(&program) {
-        Ok(p) => p,
-        Err(e) => {
-            eprintln!("Input is not a valid Azuki TAC file.");
-            eprintln!();
-            eprintln!("{:?}", e);
-            exit(2);
+    let program = if binary::is_binary(&program) {
+        match binary::read_program(&program) {
+            Ok(p) => p,
+            Err(e) => {
+                eprintln!("Input is not a valid binary Azuki TAC file: {}", e);
+                exit(2);
+            }
+        }
+    } else {
+        let parsed = std::str::from_utf8(&program)
+            .ok()
+            .map(parse_program_from_string);
+        match parsed {
+            Some(Ok(p)) => p,
+            Some(Err(e)) => {
+                eprintln!("Input is not a valid Azuki TAC file.");
+                eprintln!();
+                eprintln!("{:?}", e);
+                exit(2);
+            }
+            None => {
+                eprintln!("Input is not a valid Azuki TAC file.");
+                exit(2);
+            }
         }
     };
     let mut vm = Vm::new(&program);
@@ -51,11 +68,16 @@ fn main() {
         None
     };
 
-    vm.run_func(&opt.entry_point, opt.entry_params);
+    let res = vm.try_run_func(&opt.entry_point, opt.entry_params);
 
     if let Some(inst_cnt) = inst_cnt {
         eprintln!("azvm::instruction_cnt={}", inst_cnt.borrow().0);
     }
+
+    if let Err(e) = res {
+        eprintln!("{}", e);
+        exit(3);
+    }
 }
 
 struct InstCounter(usize);
@@ -64,6 +86,7 @@ impl Inspector for InstCounter {
     fn before_inst(&mut self, inst: &azuki_tac::Inst, _frame: &azuki_tacvm::Frame) {
         match &inst.kind {
             azuki_tac::InstKind::FunctionCall(c) => self.0 += (c.params.len() + 1) * 2,
+            azuki_tac::InstKind::IndirectCall(c) => self.0 += (c.params.len() + 2) * 2,
             _ => self.0 += 1,
         }
     }
@@ -1,18 +1,28 @@
 use std::io::{stdout, Write};
 
-use azuki_opt::{
-    branching_simplify::BranchingSimplify, const_folding::ConstFolding,
-    dead_code_eliminator::DeadCodeEliminator,
+use azuki_interp::Interpreter;
+use azuki_lint::{LintLevel, LintRegistry};
+use azuki_syntax::{
+    diag::Diagnostic,
+    feature::{Feature, Features},
+    lexer::spanned_lexer,
+    parse_with_features,
+    parser::err::{ParseError, ParseErrorKind},
+    Token,
+};
+use azuki_tac::{
+    binary,
+    formatter::dot::{self, DotOptions},
+    optimizer::{dump::DumpOptions, schedule::Schedule},
 };
-use azuki_syntax::{lexer::lexer, parse};
-use azuki_tac::optimizer::sanity_checker::SanityChecker;
 use azuki_tacvm::Vm;
 use clap::Clap;
-use opt::Action;
+use opt::{Action, Emit};
 use tracing::{info, trace, warn};
 use tracing_subscriber::fmt::format::FmtSpan;
 
 mod opt;
+mod sources;
 
 fn main() {
     let opt = opt::Opt::parse();
@@ -24,8 +34,8 @@ fn main() {
         .without_time()
         .init();
 
-    let file = opt.file;
-    let input = std::fs::read_to_string(file).expect("Unable to read input file");
+    let file_name = opt.file.to_string_lossy().into_owned();
+    let input = std::fs::read_to_string(&opt.file).expect("Unable to read input file");
 
     let mut output: Box<dyn Write> = match opt.out_file {
         Some(file) => Box::new(
@@ -37,89 +47,194 @@ fn main() {
         None => Box::new(stdout()),
     };
 
+    let mut features = match opt.level {
+        Some(level) => Features::at_level(level),
+        None => Features::all(),
+    };
+    for &feature in &opt.enable {
+        features.enable(feature);
+    }
+    for &feature in &opt.disable {
+        features.disable(feature);
+    }
+
     if opt.action == Action::Lex {
         // lex file
-        let lexer = lexer(&input);
-        lexer.for_each(|token| {
-            writeln!(output, "{}", token).expect("Failed to write to output file")
-        });
+        for (token, span) in spanned_lexer(&input) {
+            if let Token::LexError(e) = token {
+                let diag = Diagnostic::error(e.to_string(), Some(span));
+                eprintln!("{}", diag.display(&file_name, &input));
+            } else if let Some(feature) =
+                Feature::of_token(&token).filter(|&f| !features.is_enabled(f))
+            {
+                let kind = ParseErrorKind::FeatureNotAvailable {
+                    feature,
+                    level: features.level(),
+                };
+                let diag = Diagnostic::from(ParseError::new_span(kind, span));
+                eprintln!("{}", diag.display(&file_name, &input));
+            } else {
+                writeln!(output, "{}", token).expect("Failed to write to output file")
+            }
+        }
         return;
     }
 
-    let program = match parse(&input) {
+    let program = match parse_with_features(&input, &features) {
         Ok(p) => p,
         Err(e) => {
-            // TODO: Error display
-            println!("{:?}", e);
+            eprintln!("{}", Diagnostic::from(e).display(&file_name, &input));
             return;
         }
     };
 
+    let mut lints = LintRegistry::with_default_lints();
+    let levels = [
+        (&opt.allow, LintLevel::Allow),
+        (&opt.warn, LintLevel::Warn),
+        (&opt.deny, LintLevel::Deny),
+    ];
+    for (names, level) in levels.iter() {
+        for name in names.iter() {
+            if !lints.set_level(name, *level) {
+                warn!("Cannot find lint `{}`", name);
+            }
+        }
+    }
+
+    let sources = match sources::load_sources(&opt.file, input, program, &features) {
+        Some(sources) => sources,
+        None => return,
+    };
+
+    let mut has_lint_error = false;
+    for source in &sources {
+        let diagnostics = lints.check_program(&source.program);
+        for diag in &diagnostics {
+            eprintln!("{}", diag.display(&source.name, &source.input));
+        }
+        has_lint_error |= diagnostics.iter().any(|x| x.is_error());
+    }
+    if has_lint_error {
+        return;
+    }
+
     if opt.action == Action::Parse {
         // TODO: output parse result
         return;
     }
 
+    if opt.action == Action::Interpret {
+        info!("Running program in AST interpreter");
+        let mut interpreter = Interpreter::with_programs(sources.iter().map(|x| &x.program));
+        let entry = opt.entry_point.as_deref().unwrap_or("main");
+        match interpreter.run_func(entry, opt.params.clone()) {
+            Ok(Some(res)) => println!("{}", res),
+            Ok(None) => {}
+            Err(e) => eprintln!("runtime error: {}", e),
+        }
+        return;
+    }
+
     info!("Generating IR");
-    let mut program = match azuki_tacgen::compile(&program) {
+    let mut programs = vec![];
+    for source in &sources {
+        let imports = source
+            .imports
+            .iter()
+            .map(|&idx| &sources[idx].program)
+            .collect::<Vec<_>>();
+        match azuki_tacgen::compile_with_source(&source.program, &imports, &source.input) {
+            Ok(p) => programs.push(p),
+            Err(e) => {
+                let diag = Diagnostic::from(e);
+                eprintln!("{}", diag.display(&source.name, &source.input));
+                return;
+            }
+        }
+    }
+
+    let mut program = match azuki_tac::linker::link(programs) {
         Ok(p) => p,
         Err(e) => {
-            eprintln!("{:?}", e);
+            eprintln!("error: {}", e);
             return;
         }
     };
 
-    let mut pipeline = azuki_tac::optimizer::Pipeline::new();
-
-    pipeline.add_func_optimizer(SanityChecker::default());
-    pipeline.add_func_optimizer(DeadCodeEliminator::default());
-    pipeline.add_func_optimizer(BranchingSimplify);
-    pipeline.add_func_optimizer(ConstFolding::new());
+    let mut pipeline = azuki_opt::default_pipeline();
+    pipeline.set_verify_each(opt.verify_each);
+    let dump_options = DumpOptions {
+        print_before: opt.print_before.iter().cloned().collect(),
+        print_after: opt.print_after.iter().cloned().collect(),
+        print_after_all: opt.print_after_all,
+        print_changed: opt.print_changed,
+        diff: opt.print_diff,
+        dump_dir: opt.dump_dir.clone(),
+    };
+    if let Err(e) = pipeline.set_dump_options(dump_options) {
+        eprintln!("error: cannot create dump directory: {}", e);
+        return;
+    }
 
-    let optimizations = opt
-        .optimization
-        .clone()
-        .unwrap_or_else(|| default_opts().iter().map(|x| x.to_string()).collect());
+    let schedule = match &opt.optimization {
+        Some(passes) => match passes.join(",").parse::<Schedule>() {
+            Ok(schedule) => schedule,
+            Err(e) => {
+                eprintln!("error: invalid optimization passes: {}", e);
+                return;
+            }
+        },
+        None => opt.opt_level.schedule(),
+    };
 
-    for optimization in optimizations {
-        info!("Running pass `{}`", optimization);
-        if !pipeline.run_pass(&mut program, &optimization) {
-            warn!("Cannot find pass `{}`", optimization);
-        }
+    info!("Running passes `{}`", schedule);
+    if let Err(e) = pipeline.run(&mut program, &schedule) {
+        eprintln!("error: {}", e);
+        return;
     }
 
     if opt.action == Action::Compile {
         info!("Writing IR into desired output");
 
-        let func_list = program
-            .functions
-            .drain()
-            .map(|(_, x)| x)
-            .collect::<Vec<_>>();
+        match opt.emit {
+            Emit::Binary => {
+                output
+                    .write_all(&binary::write_program(&program))
+                    .expect("Failed to write to output file");
+                return;
+            }
+            Emit::Json => {
+                serde_json::to_writer_pretty(&mut output, &program)
+                    .expect("Failed to write to output file");
+                writeln!(output).unwrap();
+                return;
+            }
+            Emit::Dot => {
+                let options = DotOptions {
+                    dfg: opt.dot_dfg,
+                    dom_tree: opt.dot_dom_tree,
+                };
+                write!(output, "{}", dot::program_to_dot(&program, &options))
+                    .expect("Failed to write to output file");
+                return;
+            }
+            Emit::Tac => {}
+        }
 
-        func_list.iter().for_each(|function| {
+        for function in program.functions.values() {
             writeln!(output, "{}", function).expect("Failed to write to output file");
             writeln!(output).unwrap();
-        });
+        }
     } else if opt.action == Action::Run {
         info!("Running program in VM");
         let mut vm = Vm::new(&program);
         let entry = opt.entry_point.as_deref().unwrap_or("main");
         let params = opt.params.clone();
-        let res = vm.run_func(entry, params);
-        if let Some(res) = res {
-            println!("{}", res);
+        match vm.try_run_func(entry, params) {
+            Ok(Some(res)) => println!("{}", res),
+            Ok(None) => {}
+            Err(e) => eprintln!("{}", e),
         }
     }
 }
-
-fn default_opts() -> &'static [&'static str] {
-    &[
-        "sanity-check",
-        "const-folding",
-        "branching-simplify",
-        "dead-code-eliminator",
-        "branching-simplify",
-        "dead-code-eliminator",
-    ]
-}
@@ -1,5 +1,7 @@
 use std::{path::PathBuf, str::FromStr};
 
+use azuki_opt::OptLevel;
+use azuki_syntax::feature::Feature;
 use clap::Clap;
 
 /// Options
@@ -12,7 +14,7 @@ pub struct Opt {
     #[clap(short, long = "out")]
     pub out_file: Option<PathBuf>,
 
-    /// The action to perform. Accepts: lex, parse, compile, run
+    /// The action to perform. Accepts: lex, parse, compile, run, interpret
     #[clap(
         short = 'd',
         long = "do",
@@ -22,16 +24,93 @@ pub struct Opt {
     )]
     pub action: Action,
 
-    /// The optimization passes to perform.
+    /// The format of the compiled program. Accepts: tac, binary, json, dot
+    #[clap(long, default_value = "tac", env = "AZUKI_EMIT")]
+    pub emit: Emit,
+
+    /// Draw data flow edges when emitting DOT graphs.
+    #[clap(long)]
+    pub dot_dfg: bool,
+
+    /// Draw the dominator tree when emitting DOT graphs.
+    #[clap(long)]
+    pub dot_dom_tree: bool,
+
+    /// The optimization passes to perform, overriding `-O`. Passes are
+    /// separated by commas, and passes inside `fixpoint(...)` run until the
+    /// program no longer changes.
     #[clap(long = "opt", env = "AZUKI_OPT")]
     pub optimization: Option<Vec<String>>,
 
+    /// The optimization level. Accepts: 0, 1, 2, s
+    #[clap(
+        short = 'O',
+        long = "opt-level",
+        default_value = "2",
+        env = "AZUKI_OPT_LEVEL"
+    )]
+    pub opt_level: OptLevel,
+
+    /// Verify the program after every optimization pass, for debugging passes.
+    #[clap(long)]
+    pub verify_each: bool,
+
+    /// Print functions before these optimization passes.
+    #[clap(long)]
+    pub print_before: Vec<String>,
+
+    /// Print functions after these optimization passes.
+    #[clap(long)]
+    pub print_after: Vec<String>,
+
+    /// Print functions after every optimization pass.
+    #[clap(long)]
+    pub print_after_all: bool,
+
+    /// Print functions changed by each optimization pass.
+    #[clap(long)]
+    pub print_changed: bool,
+
+    /// Print changes by each optimization pass as unified diffs.
+    #[clap(long)]
+    pub print_diff: bool,
+
+    /// Write printed functions into files inside this directory instead of
+    /// stderr.
+    #[clap(long)]
+    pub dump_dir: Option<PathBuf>,
+
     #[clap(long)]
     pub entry_point: Option<String>,
 
     #[clap(long)]
     pub params: Vec<i64>,
 
+    /// The language level, i.e. the lab whose language features are enabled.
+    /// Omit to enable every feature.
+    #[clap(long, env = "AZUKI_LEVEL")]
+    pub level: Option<u32>,
+
+    /// Language features to enable on top of the language level.
+    #[clap(long = "enable")]
+    pub enable: Vec<Feature>,
+
+    /// Language features to disable.
+    #[clap(long = "disable")]
+    pub disable: Vec<Feature>,
+
+    /// Lints to allow.
+    #[clap(short = 'A', long = "allow")]
+    pub allow: Vec<String>,
+
+    /// Lints to report as warnings.
+    #[clap(short = 'W', long = "warn")]
+    pub warn: Vec<String>,
+
+    /// Lints to report as errors.
+    #[clap(short = 'D', long = "deny")]
+    pub deny: Vec<String>,
+
     #[clap(long = "log", default_value = "warn", env = "AZUKI_LOG")]
     pub log_level: tracing_subscriber::filter::LevelFilter,
 }
@@ -42,6 +121,7 @@ pub enum Action {
     Parse,
     Run,
     Compile,
+    Interpret,
 }
 
 impl FromStr for Action {
@@ -53,7 +133,39 @@ impl FromStr for Action {
             "parse" => Self::Parse,
             "run" => Self::Run,
             "compile" => Self::Compile,
-            _ => return Err(format!("Expected lex, parse, run, got {}", s)),
+            "interpret" => Self::Interpret,
+            _ => {
+                return Err(format!(
+                    "Expected lex, parse, run, compile, interpret, got {}",
+                    s
+                ))
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Emit {
+    /// Text representation of TAC.
+    Tac,
+    /// Binary encoding of TAC, see [`azuki_tac::binary`].
+    Binary,
+    /// JSON representation of TAC, see [`azuki_tac::serde_impl`].
+    Json,
+    /// Graphviz graph of every function, see [`azuki_tac::formatter::dot`].
+    Dot,
+}
+
+impl FromStr for Emit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "tac" => Self::Tac,
+            "binary" => Self::Binary,
+            "json" => Self::Json,
+            "dot" => Self::Dot,
+            _ => return Err(format!("Expected tac, binary, json, dot, got {}", s)),
         })
     }
 }
@@ -0,0 +1,94 @@
+//! Loading source files imported by the main source file.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use azuki_syntax::{ast::Program, diag::Diagnostic, feature::Features, parse_with_features};
+
+/// A parsed source file.
+pub struct SourceFile {
+    pub name: String,
+    pub input: String,
+    pub program: Program,
+    /// Indices of files directly imported by this file.
+    pub imports: Vec<usize>,
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_owned())
+}
+
+/// Load and parse all files imported by the file at `path`, directly or
+/// indirectly. The file at `path` itself is the first file returned.
+///
+/// Imported files are parsed with the same `features` as the main file.
+/// Errors are printed to stderr, in which case `None` is returned.
+pub fn load_sources(
+    path: &Path,
+    input: String,
+    program: Program,
+    features: &Features,
+) -> Option<Vec<SourceFile>> {
+    let mut files = vec![SourceFile {
+        name: path.to_string_lossy().into_owned(),
+        input,
+        program,
+        imports: vec![],
+    }];
+    let mut paths = vec![path.to_owned()];
+    let mut ids = HashMap::new();
+    ids.insert(normalize(path), 0);
+
+    // `files` grows as we go, so every file is visited exactly once.
+    let mut idx = 0;
+    while idx < files.len() {
+        let dir = paths[idx].parent().map(Path::to_owned).unwrap_or_default();
+        let mut imports = vec![];
+        let file_imports = files[idx].program.imports.clone();
+        for import in &file_imports {
+            let import_path = dir.join(&import.path);
+            let normalized = normalize(&import_path);
+            if let Some(&id) = ids.get(&normalized) {
+                imports.push(id);
+                continue;
+            }
+
+            let file = &files[idx];
+            let input = match std::fs::read_to_string(&import_path) {
+                Ok(input) => input,
+                Err(e) => {
+                    let diag = Diagnostic::error(
+                        format!("cannot read `{}`: {}", import_path.display(), e),
+                        Some(import.span),
+                    );
+                    eprintln!("{}", diag.display(&file.name, &file.input));
+                    return None;
+                }
+            };
+            let name = import_path.to_string_lossy().into_owned();
+            let program = match parse_with_features(&input, features) {
+                Ok(p) => p,
+                Err(e) => {
+                    eprintln!("{}", Diagnostic::from(e).display(&name, &input));
+                    return None;
+                }
+            };
+
+            let id = files.len();
+            ids.insert(normalized, id);
+            imports.push(id);
+            paths.push(import_path);
+            files.push(SourceFile {
+                name,
+                input,
+                program,
+                imports: vec![],
+            });
+        }
+        files[idx].imports = imports;
+        idx += 1;
+    }
+    Some(files)
+}